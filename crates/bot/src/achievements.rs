@@ -0,0 +1,162 @@
+//! Achievements that are unlocked by reaching thresholds defined in the config, like sending
+//! 1000 messages. They're checked whenever something that counts towards them happens.
+
+use color_eyre::Result;
+
+/// How many achievements `!achievements` lists.
+const MAX_LISTED: usize = 10;
+
+/// How long the banner for an unlocked achievement is shown in the terminal, in seconds.
+const BANNER_DURATION: u64 = 8;
+
+/// A milestone achievement, from the config.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AchievementConfig {
+    pub name: String,
+    pub description: String,
+    pub metric: Metric,
+    /// The achievement is unlocked when the metric reaches this.
+    pub threshold: i64,
+}
+
+/// Something about a mate that achievements can be based on.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// Chat messages sent.
+    Messages,
+    /// Times they've `!arrived`.
+    Arrivals,
+    /// Rubber chickens squeezed in chicken runs.
+    Chickens,
+    /// Bits cheered.
+    Bits,
+}
+
+impl crate::bot::Bot {
+    /// Save an achievement and show it off in the terminal.
+    pub async fn award_achievement(
+        &self,
+        username: &str,
+        name: &str,
+        achievement: crate::database::Achievement,
+    ) -> Result<()> {
+        self.db.add_achievement(achievement).await?;
+        let result = self
+            .send_to_tattoy(&tattoy_twitch_tombh_plugin::BotMessage::Achievement {
+                username: username.to_owned(),
+                name: name.to_owned(),
+                duration: BANNER_DURATION,
+            })
+            .await;
+        if let Err(error) = result {
+            tracing::error!("Showing achievement in Tattoy: {error:?}");
+        }
+
+        Ok(())
+    }
+
+    /// Award any achievements for the given metric that the mate has now reached.
+    pub async fn check_achievements(
+        &self,
+        user_id: &str,
+        username: &str,
+        metric: Metric,
+    ) -> Result<()> {
+        let candidates = self
+            .config
+            .read()
+            .await
+            .achievement
+            .iter()
+            .filter(|achievement| achievement.metric == metric)
+            .cloned()
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return Ok(());
+        }
+
+        let mate = self.db.get_mate(user_id, username).await?;
+        let value = self.db.metric(metric, user_id, mate.id).await?;
+        for candidate in candidates {
+            if value < candidate.threshold
+                || self.db.has_milestone(mate.id, &candidate.name).await?
+            {
+                continue;
+            }
+
+            tracing::info!("{username} unlocked the {} achievement", candidate.name);
+            let achievement = crate::database::Achievement {
+                achiever: mate.id,
+                kind: crate::database::AchievementKind::Milestone,
+                data: serde_json::json!({
+                    "name": candidate.name,
+                    "value": value,
+                }),
+                timestamp: chrono::Utc::now(),
+            };
+            self.award_achievement(username, &candidate.name, achievement)
+                .await?;
+
+            let message = format!(
+                "🏆 {username} unlocked {}: {}",
+                candidate.name, candidate.description
+            );
+            self.send_message(message.as_str()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// `!achievements [user]`, defaults to the chatter.
+    pub async fn achievements(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let username = arguments
+            .map(|args| args.trim().trim_start_matches('@'))
+            .filter(|user| !user.is_empty())
+            .unwrap_or(payload.chatter_user_name.as_str());
+        let Some(mate) = self.db.find_mate(username).await? else {
+            let message = format!("{username} hasn't unlocked any achievements yet");
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        };
+
+        let mut unlocked = Vec::new();
+        for (kind, data) in self.db.get_achievements(mate.id).await? {
+            let data: serde_json::Value = serde_json::from_str(&data).unwrap_or_default();
+            let name = match kind.as_str() {
+                "Milestone" => data["name"].as_str().unwrap_or("Milestone").to_owned(),
+                "EarliestBird" => format!(
+                    "Earliest bird of {}",
+                    data["month"].as_str().unwrap_or("a month")
+                ),
+                "ChickenRun" => "Chicken run".to_owned(),
+                "LastChickenStanding" => "Last chicken standing".to_owned(),
+                _ => kind,
+            };
+            if !unlocked.contains(&name) {
+                unlocked.push(name);
+            }
+        }
+
+        let message = if unlocked.is_empty() {
+            format!("{} hasn't unlocked any achievements yet", mate.name)
+        } else {
+            let extra = unlocked.len().saturating_sub(MAX_LISTED);
+            unlocked.truncate(MAX_LISTED);
+            let mut list = unlocked.join(", ");
+            if extra > 0 {
+                list.push_str(&format!(" and {extra} more"));
+            }
+            format!("🏆 {}: {list}", mate.name)
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+}
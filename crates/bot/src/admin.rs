@@ -0,0 +1,98 @@
+//! Administering the running bot from another terminal, eg: `tbhbot ctl pause`. It's for when
+//! chat itself is the thing that's broken, so chat commands and whispers can't be used. The CLI
+//! sends the command over the control socket, so only the user running the bot can use it.
+
+use color_eyre::Result;
+use std::sync::atomic::Ordering;
+
+/// What an admin command is called on the control socket.
+pub const CONTROL_COMMAND: &str = "admin";
+
+#[derive(clap::Subcommand, serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+pub enum AdminCommand {
+    /// Send a message to chat, as the bot
+    Say { message: String },
+    /// Ignore chat commands until resumed
+    Pause,
+    /// Start handling chat commands again
+    Resume,
+    /// Load the config from disk again
+    Reload,
+    /// Show what the bot is up to
+    Stats,
+}
+
+/// Send an admin command to the running bot, returning its reply.
+pub async fn send(command: &AdminCommand) -> Result<String> {
+    let line = format!("{CONTROL_COMMAND} {}", serde_json::to_string(command)?);
+    crate::killswitch::send_control_line(&line).await
+}
+
+impl crate::bot::Bot {
+    /// Handle an admin command from the control socket, returning the reply to send back.
+    pub async fn admin(&self, json: &str) -> String {
+        match self.run_admin_command(json).await {
+            Ok(reply) => reply,
+            Err(error) => {
+                tracing::error!("Admin command: {error:?}");
+                format!("Failed: {error}")
+            }
+        }
+    }
+
+    async fn run_admin_command(&self, json: &str) -> Result<String> {
+        let command: AdminCommand = serde_json::from_str(json)?;
+        // The database might be what's broken, so it shouldn't stop the command.
+        if let Err(error) = self
+            .db
+            .audit("control socket", "admin", None, Some(json))
+            .await
+        {
+            tracing::error!("Auditing admin command: {error:?}");
+        }
+
+        let reply = match command {
+            AdminCommand::Say { message } => {
+                self.send_message(message.trim()).await?;
+                "Sent".to_owned()
+            }
+            AdminCommand::Pause => {
+                self.is_paused.store(true, Ordering::Relaxed);
+                "Paused, chat commands will be ignored".to_owned()
+            }
+            AdminCommand::Resume => {
+                self.is_paused.store(false, Ordering::Relaxed);
+                "Resumed".to_owned()
+            }
+            AdminCommand::Reload => {
+                self.reload_config().await?;
+                "Reloaded config".to_owned()
+            }
+            AdminCommand::Stats => self.admin_stats().await?,
+        };
+        Ok(reply)
+    }
+
+    /// A one line summary of the bot's state, and of chat since the stream started, or for the
+    /// past hour when not live.
+    async fn admin_stats(&self) -> Result<String> {
+        let stream = self.db.current_stream().await?;
+        let since = stream.as_ref().map_or_else(
+            || chrono::Utc::now() - chrono::Duration::hours(1),
+            |stream| stream.started_at,
+        );
+        let summary = self.db.summarise_since(since).await?;
+        Ok(format!(
+            "Live: {}, paused: {}, !osd: {}, log-only: {}, since {}: {} messages from {} \
+             chatters, {} follows",
+            stream.is_some(),
+            self.is_paused.load(Ordering::Relaxed),
+            self.is_osd_enabled.load(Ordering::Relaxed),
+            crate::killswitch::is_log_only(),
+            since.format("%H:%M"),
+            summary.messages,
+            summary.unique_chatters,
+            summary.follows
+        ))
+    }
+}
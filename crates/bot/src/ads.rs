@@ -0,0 +1,60 @@
+use color_eyre::Result;
+use twitch_api::helix;
+
+impl crate::bot::Bot {
+    /// Warn chat that ads are running and show a countdown on screen until they finish.
+    pub async fn ad_break(
+        &self,
+        payload: &crate::eventsub::channel::ChannelAdBreakBeginV1Payload,
+    ) -> Result<()> {
+        tracing::info!("Ad break: {payload:?}");
+        let duration = u64::try_from(payload.duration_seconds)?;
+        let message = format!(
+            "📺 Ads are running for {duration} seconds, stretch your legs! We'll be right back."
+        );
+        self.send_announcement(message.as_str(), helix::chat::AnnouncementColor::Orange)
+            .await?;
+
+        tokio::spawn(async move {
+            for remaining in (1..=duration).rev() {
+                let result = Self::onscreen_popup_replacing(
+                    format!(" \nAd break: {remaining}s"),
+                    "twitch-ad-break",
+                    "ad-break",
+                );
+                if let Err(error) = result {
+                    tracing::error!("Ad break countdown: {error:?}");
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// `!snooze` pushes the next scheduled ad back.
+    pub async fn snooze(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let token = self.token.lock().await.clone();
+        let request = helix::ads::SnoozeNextAdRequest::broadcaster_id(crate::BROADCASTER_ID);
+        let response = self
+            .client
+            .req_post(request, helix::EmptyBody, &token)
+            .await?;
+        let Some(snooze) = response.data.first() else {
+            return Ok(());
+        };
+
+        let message = format!("Ad snoozed 😴 {} snoozes left", snooze.snooze_count);
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+}
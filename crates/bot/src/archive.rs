@@ -0,0 +1,62 @@
+//! Every night, move old chat messages out of the database into compressed JSONL archives, one
+//! per day. Daily counts for each chatter are kept in the database so that stats still work.
+
+use color_eyre::Result;
+use std::io::Write as _;
+
+/// How often to look for messages to archive.
+const ARCHIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// Messages from this many of the most recent days are kept in the database, so that things
+/// like end of stream summaries still have them.
+const HOT_DAYS: u64 = 2;
+
+impl crate::bot::Bot {
+    pub async fn archiver(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(ARCHIVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.archive_messages().await {
+                tracing::error!("Archiving messages: {error:?}");
+            }
+        }
+    }
+
+    async fn archive_messages(&self) -> Result<()> {
+        let directory = crate::paths::state_directory().join("archive");
+        std::fs::create_dir_all(&directory)?;
+
+        let cutoff = chrono::Utc::now().date_naive() - chrono::Days::new(HOT_DAYS);
+        for day in self.db.message_days_before(cutoff).await? {
+            let path = directory.join(format!("messages-{day}.jsonl.gz"));
+            self.db
+                .archive_messages_on(&day, |messages| write_archive(&path, messages))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Add messages to a day's archive.
+fn write_archive(
+    path: &std::path::Path,
+    messages: &[crate::database::ArchivedMessage],
+) -> Result<()> {
+    tracing::info!("Archiving {} messages to {path:?}", messages.len());
+
+    // Appending keeps any earlier archive of the same day, gzip allows several compressed
+    // streams one after the other in the same file.
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    for message in messages {
+        serde_json::to_writer(&mut encoder, message)?;
+        encoder.write_all(b"\n")?;
+    }
+    encoder.finish()?.sync_all()?;
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+//! The earliest bird leaderboard. Mates get points for how early they `!arrived` each stream,
+//! the first to arrive getting the most. The leaderboard resets every month and the winner
+//! gets an achievement.
+
+use chrono::Datelike as _;
+use color_eyre::Result;
+
+/// Points for arriving first, each later arrival gets one less.
+const FIRST_ARRIVAL_POINTS: i64 = 10;
+
+/// Everyone who arrives gets at least this many points.
+const MINIMUM_ARRIVAL_POINTS: i64 = 1;
+
+/// How many mates `!earlybirds` shows.
+const LEADERBOARD_LENGTH: u32 = 5;
+
+/// Arrival streaks that get announced, with flair that gets more over the top the longer the
+/// streak.
+const STREAK_MILESTONES: &[(i64, &str)] = &[
+    (3, "🔥"),
+    (5, "🔥🔥"),
+    (10, "🔥🔥🔥"),
+    (25, "🌟🔥🌟"),
+    (50, "👑🔥👑"),
+    (100, "🏆👑🔥👑🏆"),
+];
+
+/// The last month that had its earliest bird crowned.
+const LAST_CROWNED_SETTING: &str = "earliest_bird.last_crowned";
+
+/// The start of the month that the given time is in.
+pub fn start_of_month(time: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+    time.date_naive()
+        .with_day(1)
+        .unwrap_or_default()
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc()
+}
+
+impl crate::bot::Bot {
+    /// Record a mate's arrival in the current stream, returning their position and points. Does
+    /// nothing if the stream isn't live or they've already arrived.
+    pub async fn record_arrival(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<Option<(i64, i64)>> {
+        let Some(stream) = self.db.current_stream().await? else {
+            return Ok(None);
+        };
+
+        let Some((position, points)) = self
+            .db
+            .add_arrival(
+                stream.id,
+                payload.chatter_user_id.as_str(),
+                payload.chatter_user_name.as_str(),
+                FIRST_ARRIVAL_POINTS,
+                MINIMUM_ARRIVAL_POINTS,
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        self.check_achievements(
+            payload.chatter_user_id.as_str(),
+            payload.chatter_user_name.as_str(),
+            crate::achievements::Metric::Arrivals,
+        )
+        .await?;
+        self.update_arrival_streak(payload, stream.id).await?;
+        Ok(Some((position, points)))
+    }
+
+    /// Continue the mate's streak if they arrived in the previous stream, otherwise start a new
+    /// one. Milestones are announced in chat.
+    async fn update_arrival_streak(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        stream_id: i64,
+    ) -> Result<()> {
+        let username = payload.chatter_user_name.as_str();
+        let mate = self
+            .db
+            .get_mate(payload.chatter_user_id.as_str(), username)
+            .await?;
+        let previous = self.db.previous_stream_id(stream_id).await?;
+        let streak = if previous.is_some() && mate.last_arrival_stream == previous {
+            mate.arrival_streak + 1
+        } else {
+            1
+        };
+        self.db
+            .set_arrival_streak(mate.id, stream_id, streak)
+            .await?;
+
+        let Some((_, flair)) = STREAK_MILESTONES
+            .iter()
+            .find(|(milestone, _)| *milestone == streak)
+        else {
+            return Ok(());
+        };
+        tracing::info!("{username} is on an arrival streak of {streak}");
+        let mut message =
+            format!("{flair} {username} has arrived {streak} streams in a row! {flair}");
+        if streak > mate.best_arrival_streak && mate.best_arrival_streak >= STREAK_MILESTONES[0].0 {
+            message.push_str(" A new personal best!");
+        }
+        self.send_message(message.as_str()).await
+    }
+
+    /// `!earlybirds`
+    pub async fn early_birds(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let now = chrono::Utc::now();
+        let birds = self
+            .db
+            .earliest_birds(start_of_month(now), now, LEADERBOARD_LENGTH)
+            .await?;
+        if birds.is_empty() {
+            self.send_message_reply(&payload.message_id, "Nobody has !arrived yet this month")
+                .await?;
+            return Ok(());
+        }
+
+        let message = birds
+            .iter()
+            .enumerate()
+            .map(|(index, (_, username, points))| format!("{}. {username} ({points})", index + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.send_message_reply(
+            &payload.message_id,
+            format!("🐦 Earliest birds this month: {message}").as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Give last month's earliest bird their achievement, if they haven't already got it.
+    pub async fn crown_earliest_bird(&self) -> Result<()> {
+        let this_month = start_of_month(chrono::Utc::now());
+        let last_month = start_of_month(this_month - chrono::TimeDelta::days(1));
+        let month_name = last_month.format("%Y-%m").to_string();
+        if self.db.get_setting(LAST_CROWNED_SETTING).await?.as_deref() == Some(&month_name) {
+            return Ok(());
+        }
+
+        let winner = self
+            .db
+            .earliest_birds(last_month, this_month, 1)
+            .await?
+            .into_iter()
+            .next();
+        if let Some((user_id, username, points)) = winner {
+            tracing::info!("Earliest bird of {month_name}: {username} ({points} points)");
+            let mate = self.db.get_mate(&user_id, &username).await?;
+            let achievement = crate::database::Achievement {
+                achiever: mate.id,
+                kind: crate::database::AchievementKind::EarliestBird,
+                data: serde_json::json!({
+                    "month": month_name,
+                    "points": points,
+                }),
+                timestamp: chrono::Utc::now(),
+            };
+            let name = format!("Earliest bird of {}", last_month.format("%B"));
+            self.award_achievement(&username, &name, achievement)
+                .await?;
+
+            let message = format!(
+                "🐦 {username} was the earliest bird of {} with {points} points!",
+                last_month.format("%B")
+            );
+            self.send_announcement(
+                message.as_str(),
+                twitch_api::helix::chat::AnnouncementColor::Green,
+            )
+            .await?;
+        }
+
+        self.db
+            .set_setting(LAST_CROWNED_SETTING, &month_name)
+            .await?;
+        Ok(())
+    }
+}
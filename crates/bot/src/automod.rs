@@ -0,0 +1,128 @@
+use color_eyre::Result;
+use twitch_api::helix;
+
+const USAGE: &str = "Usage: !am allow|deny [n]";
+
+/// AutoMod denies held messages that nobody deals with after a few minutes, so ones older than
+/// this are forgotten.
+const HELD_MESSAGE_LIFETIME: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// A message that AutoMod is holding for review.
+#[derive(Debug, Clone)]
+pub struct HeldMessage {
+    pub message_id: twitch_api::types::MsgId,
+    pub username: String,
+    pub text: String,
+}
+
+/// The messages that AutoMod is holding. Each one keeps the number it was shown with, so that
+/// `!am allow 2` still means the same message after others have been dealt with.
+#[derive(Debug, Default)]
+pub struct HeldMessages {
+    /// Each message's number, when it was held, and the message.
+    messages: Vec<(usize, std::time::Instant, HeldMessage)>,
+}
+
+impl HeldMessages {
+    fn prune_expired(&mut self) {
+        self.messages
+            .retain(|(_, held_at, _)| held_at.elapsed() < HELD_MESSAGE_LIFETIME);
+    }
+
+    /// Add a message, returning its number. Numbers start again from 1 once there aren't any
+    /// messages waiting.
+    fn add(&mut self, held: HeldMessage) -> usize {
+        self.prune_expired();
+        let number = self.messages.last().map_or(1, |(number, _, _)| number + 1);
+        self.messages
+            .push((number, std::time::Instant::now(), held));
+        number
+    }
+
+    /// A message by its number, or the most recent one.
+    fn get(&mut self, maybe_number: Option<usize>) -> Option<(usize, HeldMessage)> {
+        self.prune_expired();
+        let found = match maybe_number {
+            Some(wanted) => self
+                .messages
+                .iter()
+                .find(|(number, _, _)| *number == wanted),
+            None => self.messages.last(),
+        };
+        found.map(|(number, _, held)| (*number, held.clone()))
+    }
+
+    fn remove(&mut self, number: usize) {
+        self.messages.retain(|(other, _, _)| *other != number);
+    }
+}
+
+impl crate::bot::Bot {
+    /// Show a message held by AutoMod on screen so that it can be allowed or denied from chat.
+    pub async fn automod_hold(&self, held: HeldMessage) -> Result<()> {
+        tracing::info!("AutoMod held message: {held:?}");
+        let number = self.held_automod_messages.lock().await.add(held.clone());
+
+        let message = format!(
+            " \nAutoMod [{number}] {}: {}\n!am allow|deny {number}",
+            held.username, held.text
+        );
+        Self::onscreen_popup(message, "twitch-automod")?;
+        Ok(())
+    }
+
+    /// `!am allow [n]` or `!am deny [n]`. Without a number the most recently held message is
+    /// used.
+    pub async fn automod(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let mut parts = arguments.unwrap_or_default().split_whitespace();
+        let (action, verb) = match parts.next() {
+            Some("allow") => (helix::moderation::AutoModAction::Allow, "Allowed"),
+            Some("deny") => (helix::moderation::AutoModAction::Deny, "Denied"),
+            _ => return self.send_message_reply(&payload.message_id, USAGE).await,
+        };
+        let maybe_number = match (parts.next().map(str::parse::<usize>), parts.next()) {
+            (None, None) => None,
+            (Some(Ok(number)), None) => Some(number),
+            _ => return self.send_message_reply(&payload.message_id, USAGE).await,
+        };
+
+        let maybe_held = self.held_automod_messages.lock().await.get(maybe_number);
+        let Some((number, held)) = maybe_held else {
+            self.send_message_reply(&payload.message_id, "No such held message")
+                .await?;
+            return Ok(());
+        };
+
+        let token = self.token.lock().await.clone();
+        let request = helix::moderation::ManageHeldAutoModMessagesRequest::new();
+        let body = helix::moderation::ManageHeldAutoModMessagesBody::new(
+            crate::BOT_ID,
+            held.message_id.clone(),
+            action,
+        );
+        if let Err(error) = self.client.req_post(request, body, &token).await {
+            tracing::error!("AutoMod decision for {held:?}: {error:?}");
+            let message = format!(
+                "Couldn't deal with {}'s message, it might have been already",
+                held.username
+            );
+            return self
+                .send_message_reply(&payload.message_id, message.as_str())
+                .await;
+        }
+        tracing::info!("AutoMod decision made for: {held:?}");
+        self.held_automod_messages.lock().await.remove(number);
+
+        let message = format!("{verb} {}'s message", held.username);
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+}
@@ -0,0 +1,108 @@
+use color_eyre::Result;
+use futures::TryStreamExt as _;
+use twitch_api::helix;
+
+/// Don't announce follows from longer ago than this, it'd be weird to welcome people hours
+/// later.
+const MAX_BACKFILL: chrono::TimeDelta = chrono::TimeDelta::minutes(60);
+
+/// Why events were missed.
+#[derive(Debug, Clone, Copy)]
+enum Absence {
+    /// The bot crashed.
+    Down,
+    /// The bot's connection to Twitch was lost.
+    Disconnected,
+}
+
+impl Absence {
+    const fn describe(self) -> &'static str {
+        match self {
+            Self::Down => "down",
+            Self::Disconnected => "disconnected from Twitch",
+        }
+    }
+}
+
+impl crate::bot::Bot {
+    /// Look for events that were missed whilst the bot was down and announce them all together.
+    ///
+    /// Only follows are backfilled, Helix doesn't say when subscriptions happened so there's no
+    /// way to tell which ones were missed.
+    pub async fn backfill(&self) -> Result<()> {
+        let Some(crash) = self.recovered_from else {
+            return Ok(());
+        };
+        self.backfill_since(crash.timestamp, Absence::Down).await
+    }
+
+    /// Backfill whatever was missed whilst the connection to Twitch was being reestablished,
+    /// each time that it is.
+    pub async fn reconnection_backfiller(
+        &self,
+        mut reconnected: tokio::sync::mpsc::UnboundedReceiver<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<()> {
+        while let Some(dropped_at) = reconnected.recv().await {
+            if let Err(error) = self.backfill_since(dropped_at, Absence::Disconnected).await {
+                tracing::error!("Backfilling events missed whilst reconnecting: {error:?}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn backfill_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+        absence: Absence,
+    ) -> Result<()> {
+        let since = since.max(chrono::Utc::now() - MAX_BACKFILL);
+
+        let token = self.token.lock().await.clone();
+        let request =
+            helix::channels::GetChannelFollowersRequest::broadcaster_id(crate::BROADCASTER_ID)
+                .first(100);
+        let followers: Vec<helix::channels::Follower> = helix::make_stream(
+            request,
+            &token,
+            &self.client,
+            std::collections::VecDeque::from,
+        )
+        .try_take_while(|follower| {
+            let is_recent = chrono::DateTime::parse_from_rfc3339(follower.followed_at.as_str())
+                .is_ok_and(|followed_at| followed_at > since);
+            futures::future::ready(Ok(is_recent))
+        })
+        .try_collect()
+        .await?;
+
+        if followers.is_empty() {
+            return Ok(());
+        }
+        for follower in &followers {
+            self.db
+                .add_follow(follower.user_id.as_str(), follower.user_name.as_str())
+                .await?;
+        }
+
+        let names = followers
+            .iter()
+            .map(|follower| follower.user_name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let absence = absence.describe();
+        tracing::info!("Missed follows whilst {absence}: {names}");
+        self.play_alert(
+            crate::themes::AlertKind::Follow,
+            format!("Whilst the bot was {absence}...\nWelcome {names} ❤️"),
+            &[("{user}", &names)],
+        )
+        .await?;
+        self.send_message(
+            format!("Whilst I was {absence} we got some new followers! Welcome {names} ❤️")
+                .as_str(),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+//! Regular snapshots of the database, so that a corrupted file doesn't lose everything.
+//!
+//! The schedule carries on from the latest backup, rather than from when the bot started, so
+//! that the bot restarting over and over doesn't replace all the good backups.
+
+use color_eyre::Result;
+
+/// Backups are named with this prefix and the time they were made.
+const BACKUP_PREFIX: &str = "tbhbot-";
+
+/// The format of the time in backups' names.
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    /// Where to put backups. Defaults to `backups` in the bot's state directory.
+    pub directory: Option<std::path::PathBuf>,
+    pub interval_hours: u64,
+    /// How many backups to keep, the oldest ones are deleted.
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: None,
+            interval_hours: 24,
+            keep: 7,
+        }
+    }
+}
+
+impl crate::bot::Bot {
+    /// Back up the database on the configured schedule.
+    pub async fn backuper(&self) -> Result<()> {
+        loop {
+            let config = self.config.read().await.database.backup.clone();
+            let interval = std::time::Duration::from_secs(config.interval_hours.max(1) * 60 * 60);
+            let mut wait = interval;
+            if config.enabled {
+                let directory = backup_directory(&config);
+                match latest_backup_age(&directory) {
+                    Some(age) if age < interval => {
+                        tracing::debug!("The latest database backup is only {age:?} old");
+                        wait = interval - age;
+                    }
+                    _ => {
+                        if let Err(error) = self.backup_database(&config, &directory).await {
+                            tracing::error!("Backing up the database: {error:?}");
+                        }
+                    }
+                }
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn backup_database(
+        &self,
+        config: &BackupConfig,
+        directory: &std::path::Path,
+    ) -> Result<()> {
+        std::fs::create_dir_all(directory)?;
+
+        let timestamp = chrono::Utc::now().format(TIMESTAMP_FORMAT);
+        let path = directory.join(format!("{BACKUP_PREFIX}{timestamp}.db"));
+        tracing::info!("Backing up the database to {path:?}");
+        self.db.backup_to(&path).await?;
+
+        let backups = backups_in(directory)?;
+        let excess = backups.len().saturating_sub(config.keep.max(1));
+        for old in backups.iter().take(excess) {
+            tracing::info!("Removing old database backup {old:?}");
+            std::fs::remove_file(old)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn backup_directory(config: &BackupConfig) -> std::path::PathBuf {
+    config
+        .directory
+        .clone()
+        .unwrap_or_else(|| crate::paths::state_directory().join("backups"))
+}
+
+/// Every backup in the directory, oldest first.
+fn backups_in(directory: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut backups = std::fs::read_dir(directory)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(".db"))
+        })
+        .collect::<Vec<_>>();
+    // The timestamps in the names sort oldest first.
+    backups.sort();
+    Ok(backups)
+}
+
+/// How long ago the latest backup was made, going by its name. `None` if there aren't any.
+fn latest_backup_age(directory: &std::path::Path) -> Option<std::time::Duration> {
+    let latest = backups_in(directory).ok()?.pop()?;
+    let timestamp = latest
+        .file_name()?
+        .to_str()?
+        .strip_prefix(BACKUP_PREFIX)?
+        .strip_suffix(".db")?;
+    let made_at = chrono::NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc();
+    (chrono::Utc::now() - made_at).to_std().ok()
+}
@@ -15,26 +15,99 @@ pub struct Bot {
     pub opts: crate::Cli,
     pub client: HelixClient<'static, reqwest::Client>,
     pub token: Arc<Mutex<twitch_oauth2::UserToken>>,
-    pub config: crate::Config,
+    pub config: Arc<tokio::sync::RwLock<crate::config::Config>>,
     pub broadcaster: twitch_api::types::UserId,
     pub tattoy_socket: Arc<Mutex<tokio::net::UnixStream>>,
+    /// Chat messages waiting to be sent, see `outbox.rs`.
+    pub outbox: tokio::sync::mpsc::UnboundedSender<crate::outbox::Outgoing>,
+    /// Mates whose notes have already been shown to mods since the bot started.
+    pub noted_mates_seen: Mutex<std::collections::HashSet<String>>,
+    /// Messages held by AutoMod, see `automod.rs`.
+    pub held_automod_messages: Mutex<crate::automod::HeldMessages>,
+    /// Whether chat commands are being ignored, see `whispers.rs`.
+    pub is_paused: std::sync::atomic::AtomicBool,
+    /// Whether `!osd` shows popups.
+    pub is_osd_enabled: std::sync::atomic::AtomicBool,
+    /// The token waiting to confirm a shutdown, see `killswitch.rs`.
+    pub kill_switch_token: Mutex<Option<String>>,
+    /// The countdown to an outgoing raid, or the task that started it.
+    pub pending_raid: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// The Pomodoro timer, see `pomodoro.rs`.
+    pub pomodoro: Mutex<Option<crate::pomodoro::Pomodoro>>,
+    /// The countdown started with `!timer`, see `timers.rs`.
+    pub timer: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// The raffle taking entries, see `raffles.rs`.
+    pub raffle: Mutex<Option<crate::raffles::Raffle>>,
+    /// The time-boxed challenge that's running, if any.
+    pub challenge: Mutex<Option<crate::challenges::Challenge>>,
+    /// Title suggestions from chat, and the vote on them.
+    pub title_vote: Mutex<crate::titles::TitleVote>,
+    /// The alert theme picked with `!theme`, overriding the automatic choice.
+    pub chosen_theme: Mutex<Option<String>>,
+    /// Cached follow dates, keyed by user ID.
+    pub follow_cache: Mutex<std::collections::HashMap<String, crate::followage::CachedFollow>>,
+    /// Cached account creation dates, keyed by user ID.
+    pub account_created_cache:
+        Mutex<std::collections::HashMap<String, chrono::DateTime<chrono::Utc>>>,
+    /// The websocket session of each conduit shard, see `conduits.rs`.
+    pub conduit_sessions: Arc<Mutex<std::collections::HashMap<String, String>>>,
+    /// Commands from WebAssembly plugins, see `plugins.rs`.
+    pub plugins: Vec<Arc<crate::plugins::Plugin>>,
+    /// Events for the parts of the bot that subscribe to them, see `bus.rs`.
+    pub bus: tokio::sync::broadcast::Sender<crate::bus::BusEvent>,
+    /// Alerts for the OBS overlay, see `overlay.rs`.
+    pub overlay: tokio::sync::broadcast::Sender<crate::overlay::OverlayAlert>,
+    /// When each command with a cooldown was last used.
+    pub command_cooldowns: Mutex<std::collections::HashMap<String, std::time::Instant>>,
+    /// Details of the crash that the bot is recovering from, if any.
+    pub recovered_from: Option<crate::Crash>,
 }
 
 impl Bot {
-    pub async fn start(&self) -> Result<(), eyre::Report> {
+    pub async fn start(
+        &self,
+        mut outbox: tokio::sync::mpsc::UnboundedReceiver<crate::outbox::Outgoing>,
+    ) -> Result<(), eyre::Report> {
+        if let Err(error) = self.sync_rewards().await {
+            tracing::error!("Syncing channel point rewards: {error:?}");
+        }
+        if let Err(error) = self.announce_recovery().await {
+            tracing::error!("Announcing recovery: {error:?}");
+        }
+        if let Err(error) = self.backfill().await {
+            tracing::error!("Backfilling missed events: {error:?}");
+        }
+        if let Err(error) = self.announce_new_version().await {
+            tracing::error!("Announcing new version: {error:?}");
+        }
+        if let Err(error) = self.show_todos().await {
+            tracing::error!("Showing todos: {error:?}");
+        }
+        if let Err(error) = self.announce_startup().await {
+            tracing::error!("Announcing startup: {error:?}");
+        }
+
         let connect_url = match self.opts.ws_server.clone() {
             Some(uri) => uri,
             None => twitch_api::TWITCH_EVENTSUB_WEBSOCKET_URL.clone(),
         };
 
+        // Reconnecting to Twitch can miss events, so they're backfilled.
+        let (reconnections, reconnected) = tokio::sync::mpsc::unbounded_channel();
+
         // To make a connection to the chat we need to use a websocket connection.
         // This is a wrapper for the websocket connection that handles the reconnects and handles all messages from eventsub.
         let websocket = crate::websocket::ChatWebsocketClient {
             session_id: None,
+            keepalive_timeout: None,
             token: self.token.clone(),
             client: self.client.clone(),
-            connect_url,
+            connect_url: connect_url.clone(),
+            reconnect_url: None,
             chats: vec![self.broadcaster.clone()],
+            shard: None,
+            dropped_at: None,
+            reconnections: Some(reconnections.clone()),
         };
         let token_refresher = async move {
             let token = self.token.clone();
@@ -46,34 +119,202 @@ impl Bot {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
             loop {
                 interval.tick().await;
-                let mut token = token.lock().await;
-                if token.expires_in() < std::time::Duration::from_secs(60) {
-                    token
-                        .refresh_token(&self.client)
-                        .await
-                        .wrap_err("couldn't refresh token")?;
+                let refreshed = {
+                    let mut token = token.lock().await;
+                    if token.expires_in() < std::time::Duration::from_secs(60) {
+                        token
+                            .refresh_token(&self.client)
+                            .await
+                            .map_err(crate::supervisor::fatal_if_bad_credentials)
+                            .wrap_err("couldn't refresh token")?;
+                        Some(token.clone())
+                    } else {
+                        None
+                    }
+                };
+                if let Some(refreshed) = refreshed {
+                    // Encrypting is slow on purpose, and the keyring blocks, so saving is done
+                    // on its own thread and without holding up everything else that needs the
+                    // token.
+                    let config = self.config.read().await.tokens.clone();
+                    let saving = tokio::task::spawn_blocking(move || {
+                        crate::tokens::save_tokens(&refreshed, &config)
+                    });
+                    if let Err(error) = saving.await.unwrap_or_else(|error| Err(error.into())) {
+                        tracing::error!("Saving refreshed tokens: {error:?}");
+                    }
                 }
                 token
+                    .lock()
+                    .await
                     .validate_token(&client)
                     .await
+                    .map_err(crate::supervisor::fatal_if_bad_credentials)
                     .wrap_err("couldn't validate token")?;
             }
             #[allow(unreachable_code)]
             Ok(())
         };
-        let eventer = websocket.run(|event, timestamp| async {
-            let result = self.handle_event(event, timestamp).await;
-            if let Err(error) = result {
-                tracing::error!("Handling event: {error:?}");
+        // Events are queued rather than handled straight away, so that the dispatcher can
+        // handle them concurrently.
+        let (events, event_queue) = tokio::sync::mpsc::unbounded_channel();
+        let on_event = |event, timestamp, raw| {
+            let events = events.clone();
+            async move {
+                self.record_event(&raw, &timestamp).await;
+                self.forward_to_webhooks(raw).await;
+                events
+                    .send(crate::dispatcher::QueuedEvent { event, timestamp })
+                    .map_err(|error| eyre::eyre!("Event queue closed: {error}"))?;
+                Ok(())
             }
-            Ok(())
+        };
+        let eventer = async {
+            match self.opts.transport {
+                crate::Transport::Websocket => websocket.run(on_event).await,
+                crate::Transport::Conduit => {
+                    self.conduit_eventer(connect_url, &reconnections, on_event)
+                        .await
+                }
+                crate::Transport::Webhook => {
+                    let server = crate::eventsub_webhook::EventsubWebhookServer {
+                        config: self.config.read().await.eventsub_webhook.clone(),
+                        client: self.client.clone(),
+                    };
+                    server.run(on_event).await
+                }
+            }
+        };
+
+        let tipper = async {
+            if let Err(error) = self.tipper().await {
+                tracing::error!("Tips: {error:?}");
+            }
+            Ok::<(), eyre::Report>(())
+        };
+
+        let youtube_mirror = async {
+            if let Err(error) = self.youtube_mirror().await {
+                tracing::error!("YouTube chat mirror: {error:?}");
+            }
+            Ok::<(), eyre::Report>(())
+        };
+
+        let dashboard = async {
+            if let Err(error) = self.dashboard().await {
+                tracing::error!("Dashboard: {error:?}");
+            }
+            Ok::<(), eyre::Report>(())
+        };
+
+        let overlay = async {
+            if let Err(error) = self.overlay().await {
+                tracing::error!("Overlay: {error:?}");
+            }
+            Ok::<(), eyre::Report>(())
+        };
+
+        let http_api = async {
+            if let Err(error) = self.http_api().await {
+                tracing::error!("HTTP API: {error:?}");
+            }
+            Ok::<(), eyre::Report>(())
+        };
+
+        let captioner = async {
+            if let Err(error) = self.captioner().await {
+                tracing::error!("Captions: {error:?}");
+            }
+            Ok::<(), eyre::Report>(())
+        };
+
+        // Set once every event has been handled, so that nothing more will be published.
+        let (published_all, is_published_all) = tokio::sync::watch::channel(false);
+        // Subscribing before any events come in, so that none are missed.
+        let alerts = self.subscriber("Alerts", self.bus.subscribe(), is_published_all, |event| {
+            self.alerts_subscriber(event)
         });
+        let dispatcher = async {
+            self.dispatcher(event_queue).await?;
+            // Sending only fails when the subscribers have already stopped, which is fine.
+            let _ = published_all.send(true);
+            Ok::<(), eyre::Report>(())
+        };
+
+        // Handling events is kept apart from where they come from, so that stopping can stop new
+        // events while still finishing the ones that have already arrived.
+        let handlers = async {
+            tokio::try_join!(dispatcher, alerts)?;
+            Ok::<(), eyre::Report>(())
+        };
+        let sources = async {
+            tokio::try_join!(
+                eventer,
+                token_refresher,
+                self.role_syncer(),
+                captioner,
+                self.outboxer(&mut outbox),
+                self.hourly_stats(),
+                self.emote_syncer(),
+                self.archiver(),
+                self.backuper(),
+                self.controller(),
+                tipper,
+                self.reminderer(),
+                youtube_mirror,
+                dashboard,
+                http_api,
+                overlay,
+                self.config_watcher(),
+                self.reconnection_backfiller(reconnected),
+                self.watchdog()
+            )
+        };
+        crate::watchdog::notify_ready();
+        let mut handlers = std::pin::pin!(handlers);
+        // Stopping drops the event sources, and everything else that's running in the
+        // background, so that they're cancelled before tidying up.
+        tokio::select! {
+            result = sources => {
+                result?;
+                return Ok(());
+            }
+            result = &mut handlers => {
+                result?;
+                return Ok(());
+            }
+            result = crate::shutdown::signal() => result?,
+        }
 
-        futures::future::try_join(eventer, token_refresher).await?;
+        // Nothing can queue events any more, so closing the queue lets the dispatcher finish the
+        // events that are already queued, then the bus subscribers finish what was published.
+        // Only then is it safe to close the database.
+        drop(events);
+        match tokio::time::timeout(crate::shutdown::DRAIN_TIMEOUT, handlers).await {
+            Ok(Ok(())) => (),
+            Ok(Err(error)) => tracing::error!("Finishing queued events: {error:?}"),
+            Err(_) => tracing::warn!("Gave up finishing queued events"),
+        }
+        self.shut_down(&mut outbox).await;
+        // Returning would have the supervisor start the bot again.
+        std::process::exit(0);
+    }
+
+    /// Let chat know that the bot just recovered from a crash. Clean starts aren't announced.
+    async fn announce_recovery(&self) -> Result<()> {
+        let Some(crash) = self.recovered_from else {
+            return Ok(());
+        };
+
+        Self::onscreen_popup(
+            format!(" \nBot recovered from a {} error", crash.category),
+            "twitch-bot-recovered",
+        )?;
+        self.send_message("🤖 rebooted, sorry about that").await?;
         Ok(())
     }
 
-    async fn handle_event(
+    pub async fn handle_event(
         &self,
         event: Event,
         timestamp: twitch_api::types::Timestamp,
@@ -85,14 +326,28 @@ impl Bot {
                 message: Message::Notification(payload),
                 ..
             }) => {
-                println!(
-                    "[{}] {}: {}",
-                    timestamp, payload.chatter_user_name, payload.message.text
-                );
-
-                self.db.save_message(&payload, timestamp).await?;
+                self.print_chat_line(&payload, &timestamp).await?;
+                let message = crate::bus::ChatMessage::new(&payload, timestamp)?;
+                self.db.save_message(&message).await?;
+                self.publish(crate::bus::BusEvent::ChatMessage(message));
+                // Keeps mates' names up to date when they rename.
+                self.db
+                    .get_mate(
+                        payload.chatter_user_id.as_str(),
+                        payload.chatter_user_name.as_str(),
+                    )
+                    .await?;
+                self.check_achievements(
+                    payload.chatter_user_id.as_str(),
+                    payload.chatter_user_name.as_str(),
+                    crate::achievements::Metric::Messages,
+                )
+                .await?;
+                if let Err(error) = self.show_notes(&payload).await {
+                    tracing::error!("Showing notes: {error:?}");
+                }
 
-                if let Some(original) = payload.message.text.strip_prefix("!") {
+                if let Some(original) = Self::without_reply_mention(&payload).strip_prefix("!") {
                     let mut split_whitespace = original.split_whitespace();
 
                     let command = split_whitespace.next().unwrap();
@@ -101,6 +356,8 @@ impl Bot {
                     let arguments = maybe_more.map(|more| more.1);
 
                     self.command(&payload, command, arguments).await?;
+                } else {
+                    self.pattern_responder(&payload).await?;
                 }
             }
             // The `channel.chat.notification` subscription type sends a notification
@@ -126,22 +383,64 @@ impl Bot {
             Event::ChannelFollowV2(Payload {
                 message: Message::Notification(payload),
                 ..
-            }) => self.new_follower(&payload)?,
+            }) => {
+                self.db
+                    .add_follow(payload.user_id.as_str(), payload.user_name.as_str())
+                    .await?;
+                self.publish(crate::bus::BusEvent::Follow(crate::bus::Follow {
+                    user_id: payload.user_id.to_string(),
+                    username: payload.user_name.to_string(),
+                }));
+            }
             Event::ChannelRaidV1(Payload {
                 message: Message::Notification(payload),
                 ..
-            }) => self.incoming_raid(&payload)?,
+            }) => self.publish(crate::bus::BusEvent::Raid(crate::bus::Raid {
+                user_id: payload.from_broadcaster_user_id.to_string(),
+                username: payload.from_broadcaster_user_name.to_string(),
+                login: payload.from_broadcaster_user_login.to_string(),
+                viewers: payload.viewers,
+            })),
 
-            Event::AutomodMessageHoldV1(payload) => Self::log_event(&payload),
-            Event::AutomodMessageHoldV2(payload) => Self::log_event(&payload),
+            Event::AutomodMessageHoldV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => {
+                self.automod_hold(crate::automod::HeldMessage {
+                    message_id: payload.message_id,
+                    username: payload.user_name.to_string(),
+                    text: payload.message.text,
+                })
+                .await?;
+            }
+            Event::AutomodMessageHoldV2(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => {
+                self.automod_hold(crate::automod::HeldMessage {
+                    message_id: payload.message_id,
+                    username: payload.user_name.to_string(),
+                    text: payload.message.text,
+                })
+                .await?;
+            }
             Event::AutomodMessageUpdateV1(payload) => Self::log_event(&payload),
             Event::AutomodMessageUpdateV2(payload) => Self::log_event(&payload),
             Event::AutomodSettingsUpdateV1(payload) => Self::log_event(&payload),
             Event::AutomodTermsUpdateV1(payload) => Self::log_event(&payload),
-            Event::ChannelAdBreakBeginV1(payload) => Self::log_event(&payload),
+            Event::ChannelAdBreakBeginV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.ad_break(&payload).await?,
             Event::ChannelChatClearV1(payload) => Self::log_event(&payload),
-            Event::ChannelChatClearUserMessagesV1(payload) => Self::log_event(&payload),
-            Event::ChannelChatMessageDeleteV1(payload) => Self::log_event(&payload),
+            Event::ChannelChatClearUserMessagesV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.user_messages_cleared(&payload).await?,
+            Event::ChannelChatMessageDeleteV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.message_deleted(&payload).await?,
             Event::ChannelChatUserMessageHoldV1(payload) => Self::log_event(&payload),
             Event::ChannelChatUserMessageUpdateV1(payload) => Self::log_event(&payload),
             Event::ChannelChatSettingsUpdateV1(payload) => Self::log_event(&payload),
@@ -150,8 +449,25 @@ impl Bot {
             Event::ChannelCharityCampaignStartV1(payload) => Self::log_event(&payload),
             Event::ChannelCharityCampaignStopV1(payload) => Self::log_event(&payload),
             Event::ChannelUpdateV2(payload) => Self::log_event(&payload),
-            Event::ChannelSubscribeV1(payload) => Self::log_event(&payload),
-            Event::ChannelCheerV1(payload) => Self::log_event(&payload),
+            Event::ChannelSubscribeV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.publish(crate::bus::BusEvent::Subscription(
+                crate::bus::Subscription {
+                    user_id: payload.user_id.to_string(),
+                    username: payload.user_name.to_string(),
+                    tier: match payload.tier {
+                        twitch_api::types::SubscriptionTier::Tier2 => 2,
+                        twitch_api::types::SubscriptionTier::Tier3 => 3,
+                        _ => 1,
+                    },
+                    is_gift: payload.is_gift,
+                },
+            )),
+            Event::ChannelCheerV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.cheer(&payload).await?,
             Event::ChannelBanV1(payload) => Self::log_event(&payload),
             Event::ChannelUnbanV1(payload) => Self::log_event(&payload),
             Event::ChannelUnbanRequestCreateV1(payload) => Self::log_event(&payload),
@@ -166,13 +482,31 @@ impl Bot {
             Event::ChannelPointsCustomRewardAddV1(payload) => Self::log_event(&payload),
             Event::ChannelPointsCustomRewardUpdateV1(payload) => Self::log_event(&payload),
             Event::ChannelPointsCustomRewardRemoveV1(payload) => Self::log_event(&payload),
-            Event::ChannelPointsCustomRewardRedemptionAddV1(payload) => Self::log_event(&payload),
+            Event::ChannelPointsCustomRewardRedemptionAddV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => {
+                let redemption = crate::bus::Redemption {
+                    id: payload.id.to_string(),
+                    reward_id: payload.reward.id.to_string(),
+                    reward: payload.reward.title.clone(),
+                    user_id: payload.user_id.to_string(),
+                    username: payload.user_name.to_string(),
+                    input: payload.user_input.clone(),
+                };
+                // Viewers paid for it, so it's not left to the bus, which could miss it.
+                self.redemption(&redemption).await?;
+                self.publish(crate::bus::BusEvent::Redemption(redemption));
+            }
             Event::ChannelPointsCustomRewardRedemptionUpdateV1(payload) => {
                 Self::log_event(&payload)
             }
             Event::ChannelPollBeginV1(payload) => Self::log_event(&payload),
             Event::ChannelPollProgressV1(payload) => Self::log_event(&payload),
-            Event::ChannelPollEndV1(payload) => Self::log_event(&payload),
+            Event::ChannelPollEndV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.poll_ended(&payload).await?,
             Event::ChannelPredictionBeginV1(payload) => Self::log_event(&payload),
             Event::ChannelPredictionProgressV1(payload) => Self::log_event(&payload),
             Event::ChannelPredictionLockV1(payload) => Self::log_event(&payload),
@@ -193,16 +527,31 @@ impl Bot {
             Event::ChannelHypeTrainProgressV1(payload) => Self::log_event(&payload),
             Event::ChannelHypeTrainEndV1(payload) => Self::log_event(&payload),
             Event::ChannelModerateV1(payload) => Self::log_event(&payload),
-            Event::ChannelModerateV2(payload) => Self::log_event(&payload),
+            Event::ChannelModerateV2(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.channel_moderated(&payload).await?,
             Event::ChannelModeratorAddV1(payload) => Self::log_event(&payload),
             Event::ChannelModeratorRemoveV1(payload) => Self::log_event(&payload),
-            Event::ConduitShardDisabledV1(payload) => Self::log_event(&payload),
-            Event::StreamOnlineV1(payload) => Self::log_event(&payload),
-            Event::StreamOfflineV1(payload) => Self::log_event(&payload),
+            Event::ConduitShardDisabledV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.conduit_shard_disabled(&payload).await?,
+            Event::StreamOnlineV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.stream_online(&payload).await?,
+            Event::StreamOfflineV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.stream_offline(&payload).await?,
             Event::UserUpdateV1(payload) => Self::log_event(&payload),
             Event::UserAuthorizationGrantV1(payload) => Self::log_event(&payload),
             Event::UserAuthorizationRevokeV1(payload) => Self::log_event(&payload),
-            Event::UserWhisperMessageV1(payload) => Self::log_event(&payload),
+            Event::UserWhisperMessageV1(Payload {
+                message: Message::Notification(payload),
+                ..
+            }) => self.whisper(&payload).await?,
             Event::ChannelSubscriptionEndV1(payload) => Self::log_event(&payload),
             Event::ChannelSubscriptionGiftV1(payload) => Self::log_event(&payload),
             Event::ChannelSubscriptionMessageV1(payload) => Self::log_event(&payload),
@@ -222,17 +571,43 @@ impl Bot {
         rest: Option<&str>,
     ) -> Result<(), eyre::Report> {
         tracing::info!("Command: {}", command);
-        let username = payload.chatter_user_name.as_str();
-
-        match command {
-            "arrive" | "arrives" | "arrived" => self.arrived(payload, username).await?,
-            "chirp" => self.chirp(payload, username, None).await?,
-            "osd" => self.osd(payload, rest).await?,
-            "tty" => self.tattoy(payload, rest).await?,
-            _ => self.text_responder(command, payload).await?,
+
+        if self.is_paused.load(std::sync::atomic::Ordering::Relaxed) {
+            tracing::info!("Bot is paused, ignoring command");
+            return Ok(());
         }
 
-        Ok(())
+        let registry = self.command_registry().await;
+        let Some(chat_command) = registry.find(command) else {
+            return Ok(());
+        };
+        if !self
+            .passes_follow_gate(payload, chat_command.name())
+            .await?
+        {
+            return Ok(());
+        }
+        if !self.may_run(payload, chat_command).await? {
+            return Ok(());
+        }
+
+        chat_command.execute(self, payload, rest).await
+    }
+
+    pub async fn send_message(&self, message: &str) -> Result<()> {
+        self.queue_message(crate::outbox::Outgoing::Message(message.to_owned()))
+    }
+
+    /// Send a highlighted announcement to chat, so it stands out from normal messages.
+    pub async fn send_announcement(
+        &self,
+        message: &str,
+        colour: twitch_api::helix::chat::AnnouncementColor,
+    ) -> Result<()> {
+        self.queue_message(crate::outbox::Outgoing::Announcement {
+            message: message.to_owned(),
+            colour,
+        })
     }
 
     pub async fn send_message_reply(
@@ -240,21 +615,89 @@ impl Bot {
         parent_message_id: &twitch_api::types::MsgId,
         message: &str,
     ) -> Result<()> {
+        self.queue_message(crate::outbox::Outgoing::Reply {
+            parent_message_id: parent_message_id.clone(),
+            message: message.to_owned(),
+        })
+    }
+
+    pub async fn send_whisper(&self, to_user_id: &str, message: &str) -> Result<()> {
+        if crate::killswitch::stays_local() {
+            tracing::info!("Log-only, not whispering: {message}");
+            return Ok(());
+        }
         let token = self.token.lock().await.clone();
         self.client
-            .send_chat_message_reply(
-                crate::BROADCASTER_ID,
-                crate::BOT_ID,
-                parent_message_id,
-                message,
-                &token,
-            )
+            .send_whisper(crate::BOT_ID, to_user_id, message, &token)
             .await?;
 
         Ok(())
     }
 
+    /// Whether the chatter is the broadcaster.
+    pub fn is_broadcaster(payload: &eventsub::channel::ChannelChatMessageV1Payload) -> bool {
+        payload.chatter_user_id.as_str() == crate::BROADCASTER_ID
+    }
+
+    /// Whether the chatter is a moderator. The broadcaster counts as a moderator. Falls back to
+    /// the cached moderator list in case the badges are missing.
+    pub async fn is_moderator(
+        &self,
+        payload: &eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<bool> {
+        let has_badge = payload
+            .badges
+            .iter()
+            .any(|badge| badge.set_id.as_str() == "moderator");
+        if Self::is_broadcaster(payload) || has_badge {
+            return Ok(true);
+        }
+        self.user_is_moderator(payload.chatter_user_id.as_str())
+            .await
+    }
+
+    /// Play a sound file in the background. Relative paths are in the sounds directory.
+    pub fn play_sound<P: AsRef<std::path::Path>>(path: P) -> Result<Option<std::process::Child>> {
+        let path = crate::paths::sound(path);
+        if crate::killswitch::is_log_only() {
+            tracing::info!("Log-only, not playing: {path:?}");
+            return Ok(None);
+        }
+        if crate::quiet_hours::is_quiet_now() {
+            tracing::info!("Quiet hours, not playing: {path:?}");
+            return Ok(None);
+        }
+        let process = std::process::Command::new("mpv")
+            .arg("--volume=50")
+            .arg(path)
+            .spawn()?;
+        Ok(Some(process))
+    }
+
+    /// Show a popup in a particular colour, if one is given.
+    pub fn onscreen_popup_coloured(
+        message: String,
+        category: &str,
+        colour: Option<&str>,
+    ) -> Result<()> {
+        if crate::killswitch::is_log_only() {
+            tracing::info!("Log-only, not showing popup: {message}");
+            return Ok(());
+        }
+        let mut command = std::process::Command::new("notify-send");
+        command.arg(format!("--category={}", category));
+        if let Some(colour) = colour {
+            command.arg(format!("--hint=string:bgcolor:{colour}"));
+        }
+        command.arg(message).spawn()?;
+        Ok(())
+    }
+
     pub fn onscreen_popup(message: String, category: &str) -> Result<()> {
+        if crate::killswitch::is_log_only() {
+            tracing::info!("Log-only, not showing popup: {message}");
+            return Ok(());
+        }
         std::process::Command::new("notify-send")
             .arg(format!("--category={}", category))
             .arg(message)
@@ -262,32 +705,61 @@ impl Bot {
         Ok(())
     }
 
-    fn new_follower(&self, payload: &eventsub::channel::ChannelFollowV2Payload) -> Result<()> {
-        tracing::info!("New follower: {payload:?}");
-        let message = format!(" \nWelcome {} ❤️", payload.user_name);
-        Self::onscreen_popup(message, "twitch-new-follower")?;
-
-        let path = "/home/streamer/Documents/great_scott.mp3";
-        std::process::Command::new("mpv")
-            .arg("--volume=50")
-            .arg(path)
+    /// Show a popup that replaces any previous popup with the same tag, rather than stacking
+    /// up. Useful for things like countdowns.
+    pub fn onscreen_popup_replacing(message: String, category: &str, tag: &str) -> Result<()> {
+        if crate::killswitch::is_log_only() {
+            tracing::info!("Log-only, not showing popup: {message}");
+            return Ok(());
+        }
+        std::process::Command::new("notify-send")
+            .arg(format!("--category={}", category))
+            .arg(format!("--hint=string:x-dunst-stack-tag:{tag}"))
+            .arg(message)
             .spawn()?;
         Ok(())
     }
 
-    fn incoming_raid(&self, payload: &eventsub::channel::ChannelRaidV1Payload) -> Result<()> {
-        tracing::info!("Raid: {payload:?}");
-        let message = format!(
-            " \n{} RAIDERS FROM {}!",
-            payload.viewers, payload.from_broadcaster_user_name
-        );
-        Self::onscreen_popup(message, "twitch-raid")?;
+    pub async fn new_follower(&self, follow: &crate::bus::Follow) -> Result<()> {
+        tracing::info!("New follower: {follow:?}");
+        let user = follow.username.as_str();
+        self.play_alert(
+            crate::themes::AlertKind::Follow,
+            format!("Welcome {user} ❤️"),
+            &[("{user}", user)],
+        )
+        .await
+    }
 
-        let path = "/home/streamer/Documents/hand_of_god.mp3";
-        std::process::Command::new("mpv")
-            .arg("--volume=50")
-            .arg(path)
-            .spawn()?;
-        Ok(())
+    pub async fn incoming_raid(&self, raid: &crate::bus::Raid) -> Result<()> {
+        tracing::info!("Raid: {raid:?}");
+        let user = raid.username.as_str();
+        let viewers = raid.viewers.to_string();
+        self.play_alert(
+            crate::themes::AlertKind::Raid,
+            format!("{viewers} RAIDERS FROM {user}!"),
+            &[("{user}", user), ("{viewers}", &viewers)],
+        )
+        .await
+    }
+
+    pub async fn new_subscriber(&self, subscription: &crate::bus::Subscription) -> Result<()> {
+        tracing::info!("New subscriber: {subscription:?}");
+        if subscription.is_gift {
+            return Ok(());
+        }
+        let tier = subscription.tier.to_string();
+        let user = subscription.username.as_str();
+        let default_message = if subscription.tier == 1 {
+            format!("{user} subscribed! 🎉")
+        } else {
+            format!("{user} subscribed at tier {tier}! 🎉")
+        };
+        self.play_alert(
+            crate::themes::AlertKind::Subscribe,
+            default_message,
+            &[("{user}", user), ("{tier}", &tier)],
+        )
+        .await
     }
 }
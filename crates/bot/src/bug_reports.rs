@@ -0,0 +1,153 @@
+//! `!bug <description>` lets chat report the bot misbehaving the moment they see it. Reports
+//! are saved with the bot's most recent logs, and can optionally be opened as GitHub issues.
+
+use color_eyre::Result;
+use twitch_oauth2::TwitchToken as _;
+
+/// How many lines of logs to keep, and attach to reports.
+const RECENT_LOG_LINES: usize = 50;
+
+/// How many reports each mate can make in an hour, so that chat can't flood the table.
+const MAX_REPORTS_PER_HOUR: i64 = 3;
+
+/// Descriptions longer than this are cut short.
+const MAX_DESCRIPTION_LENGTH: usize = 300;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// The bot's latest log lines. This is global because logs are written from anywhere.
+static RECENT_LOGS: std::sync::Mutex<std::collections::VecDeque<String>> =
+    std::sync::Mutex::new(std::collections::VecDeque::new());
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct BugReportConfig {
+    /// The repo to open reports as issues in, eg: "tombh/tombh_twitch_bot". Needs a token in
+    /// the `[github]` config. No issues are opened when it's not set.
+    pub github_repo: Option<String>,
+}
+
+/// A log writer that keeps the latest lines in memory, for attaching to bug reports.
+pub struct RecentLogs;
+
+impl std::io::Write for RecentLogs {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buffer);
+        if let Ok(mut logs) = RECENT_LOGS.lock() {
+            for line in text.lines() {
+                logs.push_back(line.to_owned());
+            }
+            while logs.len() > RECENT_LOG_LINES {
+                logs.pop_front();
+            }
+        }
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Replace anything secret in the text.
+fn scrub(text: &str, secrets: &[String]) -> String {
+    let mut scrubbed = text.to_owned();
+    for secret in secrets.iter().filter(|secret| secret.len() >= 4) {
+        scrubbed = scrubbed.replace(secret.as_str(), REDACTED);
+    }
+
+    // Catch tokens that the bot doesn't know about, like ones in request headers.
+    let mut is_token_next = false;
+    let mut words = Vec::new();
+    for word in scrubbed.split(' ') {
+        words.push(if is_token_next { REDACTED } else { word });
+        let lowercase = word.to_lowercase();
+        is_token_next = lowercase.ends_with("bearer") || lowercase.ends_with("oauth");
+    }
+    words.join(" ")
+}
+
+impl crate::bot::Bot {
+    /// `!bug <description>` saves a bug report about the bot, with its recent logs.
+    pub async fn bug(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let user_id = payload.chatter_user_id.as_str();
+        let username = payload.chatter_user_name.as_str();
+        let Some(description) = arguments.map(str::trim).filter(|text| !text.is_empty()) else {
+            return self
+                .send_message_reply(
+                    &payload.message_id,
+                    "Usage: !bug <what went wrong>, eg: !bug !tty didn't show my emote",
+                )
+                .await;
+        };
+        let description = description
+            .chars()
+            .take(MAX_DESCRIPTION_LENGTH)
+            .collect::<String>();
+
+        if self.db.count_recent_bug_reports(user_id).await? >= MAX_REPORTS_PER_HOUR {
+            return self
+                .send_message_reply(
+                    &payload.message_id,
+                    "Thanks, but you've sent a few reports already, they'll be looked at soon",
+                )
+                .await;
+        }
+
+        let secrets = self.secrets().await;
+        let logs = RECENT_LOGS
+            .lock()
+            .map(|logs| logs.iter().cloned().collect::<Vec<_>>().join("\n"))
+            .unwrap_or_default();
+        let logs = scrub(&logs, &secrets);
+        let description = scrub(&description, &secrets);
+        let id = self
+            .db
+            .add_bug_report(user_id, username, &description, &logs)
+            .await?;
+        tracing::info!("Bug report #{id} from {username}: {description}");
+
+        let repo = self.config.read().await.bug_reports.github_repo.clone();
+        if let Some(repo) = repo {
+            let title = format!("Bug report from {username}: {description}");
+            let body = format!(
+                "Reported in chat by {username}:\n\n> {description}\n\n\
+                Latest logs:\n\n```\n{logs}\n```\n"
+            );
+            match self.create_github_issue(&repo, &title, &body).await {
+                Ok(url) => self.db.set_bug_report_issue(id, &url).await?,
+                Err(error) => tracing::error!("Opening GitHub issue for bug #{id}: {error:?}"),
+            }
+        }
+
+        let message = format!("Thanks! Saved as bug #{id} 🐛");
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+
+    /// Everything secret that the bot knows, so that it can be kept out of bug reports.
+    async fn secrets(&self) -> Vec<String> {
+        let mut secrets = Vec::new();
+        {
+            let token = self.token.lock().await;
+            secrets.push(token.token().secret().to_owned());
+            if let Some(refresh_token) = &token.refresh_token {
+                secrets.push(refresh_token.secret().to_owned());
+            }
+        }
+        if let Ok(client_secret) = std::env::var("CLIENTSECRET") {
+            secrets.push(client_secret);
+        }
+
+        let config = self.config.read().await;
+        secrets.extend(config.github.token.clone());
+        secrets.push(config.youtube.api_key.clone());
+        secrets.push(config.tips.kofi.verification_token.clone());
+        secrets.push(config.tips.streamelements.token.clone());
+        secrets
+    }
+}
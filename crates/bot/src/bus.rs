@@ -0,0 +1,188 @@
+//! The event bus. `handle_event` publishes the Twitch events that other parts of the bot care
+//! about, in a simpler form than EventSub's payloads, and each part subscribes to the bus on its
+//! own. So slow alerts don't hold up chat, and a new part of the bot doesn't need to be wired
+//! into `handle_event`.
+//!
+//! Subscribers that fall too far behind miss events rather than holding up the bus. So anything
+//! that mustn't be lost, like saving chat messages and follows or running redemptions, is done in
+//! `handle_event` itself before the event is published.
+
+use color_eyre::Result;
+
+/// How many events a slow subscriber can fall behind before it misses some.
+pub const CAPACITY: usize = 256;
+
+/// An event on the bus.
+#[derive(Debug, Clone)]
+pub enum BusEvent {
+    ChatMessage(ChatMessage),
+    Follow(Follow),
+    Raid(Raid),
+    Subscription(Subscription),
+    Redemption(Redemption),
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub user_id: String,
+    pub username: String,
+    pub text: String,
+    pub message_id: String,
+    /// The message type as JSON, eg: `"text"`.
+    pub kind: String,
+    /// The message being replied to, if it's a reply.
+    pub parent_message_id: Option<String>,
+    /// The first message of the thread that's being replied to.
+    pub thread_message_id: Option<String>,
+    /// The ID and code of each emote in the message.
+    pub emotes: Vec<(String, String)>,
+    pub timestamp: twitch_api::types::Timestamp,
+}
+
+impl ChatMessage {
+    pub fn new(
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        timestamp: twitch_api::types::Timestamp,
+    ) -> Result<Self> {
+        let emotes = payload
+            .message
+            .fragments
+            .iter()
+            .filter_map(|fragment| match fragment {
+                twitch_api::eventsub::channel::chat::Fragment::Emote { text, emote, .. } => {
+                    Some((emote.id.to_string(), text.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(Self {
+            user_id: payload.chatter_user_id.to_string(),
+            username: payload.chatter_user_name.to_string(),
+            text: payload.message.text.clone(),
+            message_id: payload.message_id.to_string(),
+            kind: serde_json::to_string(&payload.message_type)?,
+            parent_message_id: payload
+                .reply
+                .as_ref()
+                .map(|reply| reply.parent_message_id.to_string()),
+            thread_message_id: payload
+                .reply
+                .as_ref()
+                .map(|reply| reply.thread_message_id.to_string()),
+            emotes,
+            timestamp,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Follow {
+    pub user_id: String,
+    pub username: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Raid {
+    pub user_id: String,
+    pub username: String,
+    pub login: String,
+    pub viewers: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    pub user_id: String,
+    pub username: String,
+    /// 1, 2 or 3.
+    pub tier: u8,
+    pub is_gift: bool,
+}
+
+/// A channel point reward being redeemed.
+#[derive(Debug, Clone)]
+pub struct Redemption {
+    /// The ID of the redemption itself.
+    pub id: String,
+    pub reward_id: String,
+    /// The title of the reward.
+    pub reward: String,
+    pub user_id: String,
+    pub username: String,
+    /// What the viewer entered, if the reward asks for it.
+    pub input: String,
+}
+
+impl crate::bot::Bot {
+    /// Send an event to everything that's subscribed to the bus.
+    pub fn publish(&self, event: BusEvent) {
+        // Sending only fails when nothing is subscribed, which is fine.
+        let _ = self.bus.send(event);
+    }
+
+    /// Pass every event on the bus to `handler`. Errors are logged rather than returned, so that
+    /// one failing event doesn't stop the subscriber.
+    ///
+    /// Once `is_published_all` is set, the events that have already been published are handled
+    /// and then the subscriber stops, so that stopping the bot doesn't lose them.
+    pub async fn subscriber<F, Fut>(
+        &self,
+        name: &str,
+        mut receiver: tokio::sync::broadcast::Receiver<BusEvent>,
+        mut is_published_all: tokio::sync::watch::Receiver<bool>,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(BusEvent) -> Fut,
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+
+        loop {
+            let received = tokio::select! {
+                received = receiver.recv() => received,
+                _ = is_published_all.wait_for(|is_published_all| *is_published_all) => break,
+            };
+            match received {
+                Ok(event) => {
+                    if let Err(error) = handler(event).await {
+                        tracing::error!("{name}: {error:?}");
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("{name} fell behind and missed {missed} events");
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+
+        loop {
+            match receiver.try_recv() {
+                Ok(event) => {
+                    if let Err(error) = handler(event).await {
+                        tracing::error!("{name}: {error:?}");
+                    }
+                }
+                Err(TryRecvError::Lagged(missed)) => {
+                    tracing::warn!("{name} fell behind and missed {missed} events");
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+
+    /// Show alerts for follows, raids and subscriptions.
+    pub async fn alerts_subscriber(&self, event: BusEvent) -> Result<()> {
+        match event {
+            BusEvent::Follow(follow) => self.new_follower(&follow).await,
+            BusEvent::Raid(raid) => {
+                self.incoming_raid(&raid).await?;
+                if let Err(error) = self.raid_emote_rain(&raid).await {
+                    tracing::error!("Raid emote rain: {error:?}");
+                }
+                self.raid_shoutout(&raid).await
+            }
+            BusEvent::Subscription(subscription) => self.new_subscriber(&subscription).await,
+            BusEvent::ChatMessage(_) | BusEvent::Redemption(_) => Ok(()),
+        }
+    }
+}
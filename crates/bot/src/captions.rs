@@ -0,0 +1,157 @@
+//! Optional live captions of the streamer's microphone, for accessibility.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+use tokio::io::AsyncBufReadExt as _;
+
+/// The longest caption summary that we'll post to chat.
+const MAX_SUMMARY_LENGTH: usize = 400;
+
+/// How many recent caption lines to show on screen at once.
+const ROLLING_LINES: usize = 3;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct CaptionsConfig {
+    pub enabled: bool,
+    /// The speech-to-text program and its arguments. It should print a line of text to STDOUT
+    /// for each thing that is said. Eg, whisper.cpp's `whisper-stream -m model.bin`.
+    pub command: Vec<String>,
+    /// Show rolling captions on screen.
+    pub onscreen: bool,
+    /// Post what was said to chat every this many seconds.
+    pub chat_summary_interval: Option<u64>,
+}
+
+/// Something that turns speech into lines of text.
+#[allow(async_fn_in_trait)]
+pub trait SpeechToText {
+    /// Wait for the next line of speech. `None` means there won't be any more.
+    async fn next_line(&mut self) -> Result<Option<String>>;
+}
+
+/// Speech-to-text from a subprocess, like whisper.cpp, that prints text to STDOUT.
+pub struct Subprocess {
+    /// Kept so that the process is killed when we're dropped.
+    _child: tokio::process::Child,
+    lines: tokio::io::Lines<tokio::io::BufReader<tokio::process::ChildStdout>>,
+}
+
+impl Subprocess {
+    pub fn spawn(command: &[String]) -> Result<Self> {
+        let (program, arguments) = command
+            .split_first()
+            .context("No speech-to-text command configured")?;
+        let mut child = tokio::process::Command::new(program)
+            .args(arguments)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("No STDOUT for speech-to-text")?;
+
+        Ok(Self {
+            _child: child,
+            lines: tokio::io::BufReader::new(stdout).lines(),
+        })
+    }
+}
+
+impl SpeechToText for Subprocess {
+    async fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            let Some(line) = self.lines.next_line().await? else {
+                return Ok(None);
+            };
+            if let Some(caption) = clean_caption(&line) {
+                return Ok(Some(caption));
+            }
+        }
+    }
+}
+
+/// Remove terminal escape codes, timestamps and non-speech markers like `[BLANK_AUDIO]` from
+/// whisper.cpp-style output.
+fn clean_caption(line: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut characters = line.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '\x1b' => {
+                for next in characters.by_ref() {
+                    if next.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            '[' | '(' => {
+                let closing = if character == '[' { ']' } else { ')' };
+                for next in characters.by_ref() {
+                    if next == closing {
+                        break;
+                    }
+                }
+            }
+            _ => text.push(character),
+        }
+    }
+
+    let caption = text.trim();
+    (!caption.is_empty()).then(|| caption.to_owned())
+}
+
+impl crate::bot::Bot {
+    /// Caption the microphone, if captioning is enabled.
+    pub async fn captioner(&self) -> Result<()> {
+        let config = self.config.read().await.captions.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+
+        let mut transcriber = Subprocess::spawn(&config.command)?;
+        let mut recent = std::collections::VecDeque::new();
+        let mut unsummarised = Vec::new();
+        let mut last_summary = std::time::Instant::now();
+
+        while let Some(caption) = transcriber.next_line().await? {
+            tracing::debug!("Caption: {caption}");
+
+            if config.onscreen {
+                recent.push_back(caption.clone());
+                if recent.len() > ROLLING_LINES {
+                    recent.pop_front();
+                }
+                let lines = recent.iter().cloned().collect::<Vec<_>>().join("\n");
+                Self::onscreen_popup_replacing(
+                    format!(" \n{lines}"),
+                    "twitch-captions",
+                    "captions",
+                )?;
+            }
+
+            if let Some(interval) = config.chat_summary_interval {
+                unsummarised.push(caption);
+                if last_summary.elapsed() >= std::time::Duration::from_secs(interval) {
+                    let mut summary = format!("🎙️ {}", unsummarised.join(" "));
+                    if summary.len() > MAX_SUMMARY_LENGTH {
+                        let mut end = MAX_SUMMARY_LENGTH;
+                        while !summary.is_char_boundary(end) {
+                            end -= 1;
+                        }
+                        summary.truncate(end);
+                        summary.push('…');
+                    }
+                    self.send_message(summary.as_str()).await?;
+                    unsummarised.clear();
+                    last_summary = std::time::Instant::now();
+                }
+            }
+        }
+
+        tracing::warn!("Speech-to-text stopped");
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+//! Time-boxed coding challenges. Starting one shows a countdown on screen, puts the challenge
+//! in the stream title and opens a prediction on whether it'll be done in time.
+
+use color_eyre::Result;
+use twitch_api::helix;
+
+const USAGE: &str = "Usage: !challenge \"implement X\" 45m, then !challenge done|fail|cancel";
+
+/// How long chat gets to make their predictions, in seconds.
+const PREDICTION_WINDOW: i64 = 120;
+
+/// Twitch's limit on prediction titles.
+const MAX_PREDICTION_TITLE_LENGTH: usize = 45;
+
+/// Twitch's limit on stream titles, in characters.
+const MAX_STREAM_TITLE_LENGTH: usize = 140;
+
+/// A challenge that's in progress.
+pub struct Challenge {
+    description: String,
+    /// The stream title from before the challenge, so that it can be put back.
+    original_title: String,
+    prediction_id: String,
+    /// The IDs of the success and failure prediction outcomes.
+    outcome_ids: (String, String),
+    countdown: tokio::task::JoinHandle<()>,
+}
+
+/// How a challenge ended.
+#[derive(Clone, Copy)]
+enum Ending {
+    Done,
+    Fail,
+    Cancel,
+}
+
+impl crate::bot::Bot {
+    /// `!challenge "implement X" 45m` starts a challenge. `!challenge done|fail|cancel` ends it.
+    pub async fn challenge(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let mut parts = crate::utils::split_arguments(arguments.unwrap_or_default());
+        let ending = match parts.first().map(String::as_str) {
+            Some("done") => Some(Ending::Done),
+            Some("fail") => Some(Ending::Fail),
+            Some("cancel") => Some(Ending::Cancel),
+            _ => None,
+        };
+        if let Some(ending) = ending {
+            return self.end_challenge(payload, ending).await;
+        }
+
+        let maybe_duration = parts
+            .pop()
+            .and_then(|duration| crate::utils::parse_duration(&duration));
+        let (Some(duration), [description]) = (maybe_duration, parts.as_slice()) else {
+            self.send_message_reply(&payload.message_id, USAGE).await?;
+            return Ok(());
+        };
+        if self.challenge.lock().await.is_some() {
+            self.send_message_reply(&payload.message_id, "There's already a challenge running")
+                .await?;
+            return Ok(());
+        }
+
+        let challenge_title = format!("⏱️ Challenge: {description}");
+        let title_length = challenge_title.chars().count();
+        if title_length > MAX_STREAM_TITLE_LENGTH {
+            let message = format!(
+                "That's too long for the stream title, it needs to be {} characters shorter",
+                title_length - MAX_STREAM_TITLE_LENGTH
+            );
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        }
+
+        let token = self.token.lock().await.clone();
+        let original_title = self
+            .client
+            .get_channel_from_id(crate::BROADCASTER_ID, &token)
+            .await?
+            .map(|channel| channel.title)
+            .unwrap_or_default();
+
+        // The prediction is opened first, because it's the part that's most likely to fail, eg:
+        // when one is already running. Then a failure doesn't leave the title changed.
+        let (prediction_id, outcome_ids) = self.open_challenge_prediction(duration).await?;
+
+        // As much of the original title is kept as fits.
+        let mut title = format!("{challenge_title} | {original_title}");
+        if title.chars().count() > MAX_STREAM_TITLE_LENGTH {
+            title = title.chars().take(MAX_STREAM_TITLE_LENGTH).collect();
+        }
+        if let Err(error) = self.set_title(&title).await {
+            tracing::error!("Setting the challenge title: {error:?}");
+        }
+
+        let challenge_description = description.clone();
+        let countdown = tokio::spawn(async move {
+            for remaining in (0..=duration.as_secs()).rev() {
+                let text = if remaining == 0 {
+                    format!(" \n{challenge_description}\nTime's up!")
+                } else {
+                    format!(
+                        " \n{challenge_description}\n{:02}:{:02}",
+                        remaining / 60,
+                        remaining % 60
+                    )
+                };
+                let result = Self::onscreen_popup_replacing(text, "twitch-challenge", "challenge");
+                if let Err(error) = result {
+                    tracing::error!("Challenge countdown: {error:?}");
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        });
+
+        *self.challenge.lock().await = Some(Challenge {
+            description: description.clone(),
+            original_title,
+            prediction_id,
+            outcome_ids,
+            countdown,
+        });
+        tracing::info!("Started challenge: {description}");
+
+        let message = format!(
+            "⏱️ Challenge started: {description}! Place your predictions, there's {}s to vote",
+            PREDICTION_WINDOW
+        );
+        self.send_announcement(message.as_str(), helix::chat::AnnouncementColor::Blue)
+            .await?;
+        Ok(())
+    }
+
+    /// Open a prediction for whether the challenge will be done in time, returning the
+    /// prediction's ID and the IDs of its success and failure outcomes.
+    async fn open_challenge_prediction(
+        &self,
+        duration: std::time::Duration,
+    ) -> Result<(String, (String, String))> {
+        let minutes = duration.as_secs().div_ceil(60);
+        let mut title = format!("Will Tom finish in {minutes} minutes?");
+        title.truncate(MAX_PREDICTION_TITLE_LENGTH);
+        let outcomes = vec![
+            helix::predictions::NewPredictionOutcome::new("Yes, easy"),
+            helix::predictions::NewPredictionOutcome::new("No way"),
+        ];
+        let body = helix::predictions::CreatePredictionBody::new(
+            crate::BROADCASTER_ID,
+            title.as_str(),
+            outcomes,
+            PREDICTION_WINDOW,
+        );
+        let token = self.token.lock().await.clone();
+        let prediction = self
+            .client
+            .req_post(
+                helix::predictions::CreatePredictionRequest::new(),
+                body,
+                &token,
+            )
+            .await?
+            .data;
+
+        let mut outcome_ids = prediction
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.id.to_string());
+        let (Some(success), Some(failure)) = (outcome_ids.next(), outcome_ids.next()) else {
+            eyre::bail!("Prediction is missing outcomes: {prediction:?}");
+        };
+
+        Ok((prediction.id.to_string(), (success, failure)))
+    }
+
+    async fn end_challenge(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        ending: Ending,
+    ) -> Result<()> {
+        let Some(challenge) = self.challenge.lock().await.take() else {
+            self.send_message_reply(&payload.message_id, "There's no challenge running")
+                .await?;
+            return Ok(());
+        };
+        challenge.countdown.abort();
+
+        let (status, winner, text, message) = match ending {
+            Ending::Done => (
+                helix::predictions::PredictionStatus::Resolved,
+                Some(challenge.outcome_ids.0),
+                "Challenge complete! 🎉",
+                format!("🎉 Challenge complete: {}!", challenge.description),
+            ),
+            Ending::Fail => (
+                helix::predictions::PredictionStatus::Resolved,
+                Some(challenge.outcome_ids.1),
+                "Challenge failed 💀",
+                format!("💀 Challenge failed: {}", challenge.description),
+            ),
+            Ending::Cancel => (
+                helix::predictions::PredictionStatus::Canceled,
+                None,
+                "Challenge cancelled",
+                format!("🛑 Challenge cancelled: {}", challenge.description),
+            ),
+        };
+
+        let mut body = helix::predictions::EndPredictionBody::new(
+            crate::BROADCASTER_ID,
+            challenge.prediction_id.as_str(),
+            status,
+        );
+        body.winning_outcome_id = winner.map(Into::into);
+        let token = self.token.lock().await.clone();
+        let result = self
+            .client
+            .req_patch(
+                helix::predictions::EndPredictionRequest::new(),
+                body,
+                &token,
+            )
+            .await;
+        if let Err(error) = result {
+            tracing::error!("Ending challenge prediction: {error:?}");
+        }
+
+        self.set_title(&challenge.original_title).await?;
+        Self::onscreen_popup_replacing(
+            format!(" \n{}\n{text}", challenge.description),
+            "twitch-challenge",
+            "challenge",
+        )?;
+        self.send_announcement(message.as_str(), helix::chat::AnnouncementColor::Blue)
+            .await?;
+        Ok(())
+    }
+
+    /// Change the stream's title.
+    pub async fn set_title(&self, title: &str) -> Result<()> {
+        let mut body = helix::channels::ModifyChannelInformationBody::new();
+        body.title(title);
+        let token = self.token.lock().await.clone();
+        self.client
+            .req_patch(
+                helix::channels::ModifyChannelInformationRequest::broadcaster_id(
+                    crate::BROADCASTER_ID,
+                ),
+                body,
+                &token,
+            )
+            .await?;
+
+        Ok(())
+    }
+}
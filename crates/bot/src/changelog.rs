@@ -0,0 +1,88 @@
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+
+const CHANGELOG_TOML: &str = include_str!("../../../CHANGELOG.toml");
+
+/// The setting that remembers which version of the bot last ran.
+const LAST_VERSION_SETTING: &str = "last_version";
+
+/// The most releases `!changelog` shows. It shows fewer when their changes don't fit in one
+/// chat message.
+const RELEASES_SHOWN: usize = 2;
+
+#[derive(serde_derive::Deserialize, Debug)]
+pub struct Changelog {
+    pub release: Vec<Release>,
+}
+
+#[derive(serde_derive::Deserialize, Debug)]
+pub struct Release {
+    pub version: String,
+    pub date: String,
+    pub changes: Vec<String>,
+}
+
+impl Changelog {
+    pub fn load() -> Result<Self> {
+        Ok(toml::from_str(CHANGELOG_TOML)?)
+    }
+
+    pub fn latest(&self) -> Result<&Release> {
+        self.release.first().context("The changelog is empty")
+    }
+}
+
+impl crate::bot::Bot {
+    /// `!changelog`. The newest changes, as many as fit in a chat message.
+    pub async fn changelog(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let changelog = Changelog::load()?;
+        let mut message = String::new();
+        'releases: for release in changelog.release.iter().take(RELEASES_SHOWN) {
+            let separator = if message.is_empty() { "" } else { " | " };
+            for (index, change) in release.changes.iter().enumerate() {
+                let addition = if index == 0 {
+                    format!("{separator}v{}: {change}", release.version)
+                } else {
+                    format!(", {change}")
+                };
+                let length = message.chars().count() + addition.chars().count();
+                if length > crate::chat_commands::MAX_MESSAGE_LENGTH {
+                    break 'releases;
+                }
+                message.push_str(&addition);
+            }
+        }
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// Show what's new on screen when the bot is running a new version.
+    pub async fn announce_new_version(&self) -> Result<()> {
+        let changelog = Changelog::load()?;
+        let latest = changelog.latest()?;
+        let last_version = self.db.get_setting(LAST_VERSION_SETTING).await?;
+        if last_version.as_deref() == Some(latest.version.as_str()) {
+            return Ok(());
+        }
+
+        tracing::info!("Now running bot version {}", latest.version);
+        let changes = latest
+            .changes
+            .iter()
+            .map(|change| format!("• {change}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self::onscreen_popup(
+            format!(" \nBot updated to v{}\n{changes}", latest.version),
+            "twitch-bot-updated",
+        )?;
+        self.db
+            .set_setting(LAST_VERSION_SETTING, &latest.version)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,846 @@
+//! Chat commands, like `!chirp`. Built-in commands and the text commands from the config are all
+//! a `ChatCommand`, found by their name or an alias in the `Registry`. So they share the same
+//! permission checks, cooldowns and `!help`, and new kinds of command only have to implement the
+//! trait.
+//!
+//! A command's entry in the config overrides its own settings, eg: a `cooldown` for `!chirp`.
+
+use color_eyre::Result;
+use futures::future::LocalBoxFuture;
+
+type Payload = crate::eventsub::channel::ChannelChatMessageV1Payload;
+
+/// The most characters that Twitch allows in a chat message.
+pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// Something that chat can run with `!name`.
+pub trait ChatCommand {
+    /// The command word, eg: `chirp` for `!chirp`.
+    fn name(&self) -> &str;
+
+    /// Other command words that run the command.
+    fn aliases(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// A short description, for `!help`.
+    fn help(&self) -> &str {
+        ""
+    }
+
+    /// Who can use the command.
+    fn permission(&self) -> crate::config::Permission {
+        crate::config::Permission::Everyone
+    }
+
+    /// How long before anybody can use the command again.
+    fn cooldown(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    /// A sound to play whenever the command is used.
+    fn sound(&self) -> Option<&std::path::Path> {
+        None
+    }
+
+    /// Run the command. The `arguments` are everything after the command word.
+    fn execute<'a>(
+        &'a self,
+        bot: &'a crate::bot::Bot,
+        payload: &'a Payload,
+        arguments: Option<&'a str>,
+    ) -> LocalBoxFuture<'a, Result<()>>;
+
+    /// Whether `!word` runs the command.
+    fn is_called(&self, word: &str) -> bool {
+        self.name() == word || self.aliases().contains(&word)
+    }
+}
+
+/// How a built-in command is run.
+type Handler =
+    for<'a> fn(&'a crate::bot::Bot, &'a Payload, Option<&'a str>) -> LocalBoxFuture<'a, Result<()>>;
+
+/// A command that's part of the bot.
+pub struct Builtin {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    help: &'static str,
+    handler: Handler,
+}
+
+impl Builtin {
+    const fn new(name: &'static str, help: &'static str, handler: Handler) -> Self {
+        Self {
+            name,
+            aliases: &[],
+            help,
+            handler,
+        }
+    }
+
+    const fn with_aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+}
+
+impl ChatCommand for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        self.aliases.to_vec()
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn execute<'a>(
+        &'a self,
+        bot: &'a crate::bot::Bot,
+        payload: &'a Payload,
+        arguments: Option<&'a str>,
+    ) -> LocalBoxFuture<'a, Result<()>> {
+        (self.handler)(bot, payload, arguments)
+    }
+}
+
+/// A command from the config that replies with some text.
+pub struct TextCommand {
+    settings: crate::config::Command,
+}
+
+impl TextCommand {
+    /// Whether any of the command's regex triggers match a message.
+    fn is_match(&self, text: &str) -> bool {
+        self.settings.trigger.iter().any(|trigger| {
+            matches!(trigger, crate::config::Trigger::Pattern(pattern) if pattern.is_match(text))
+        })
+    }
+
+    /// Every command word and alias in a config entry, its name is the first one.
+    fn words_of(settings: &crate::config::Command) -> Vec<&str> {
+        settings
+            .trigger
+            .iter()
+            .filter_map(|trigger| match trigger {
+                crate::config::Trigger::Word(word) => Some(word.as_str()),
+                crate::config::Trigger::Pattern(_) => None,
+            })
+            .chain(settings.aliases.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+impl ChatCommand for TextCommand {
+    /// Commands that only have regex triggers are named after their first one, so that they
+    /// still have their own cooldown.
+    fn name(&self) -> &str {
+        self.settings
+            .name()
+            .or_else(|| {
+                self.settings
+                    .trigger
+                    .iter()
+                    .find_map(|trigger| match trigger {
+                        crate::config::Trigger::Pattern(pattern) => Some(pattern.as_str()),
+                        crate::config::Trigger::Word(_) => None,
+                    })
+            })
+            .unwrap_or_default()
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        Self::words_of(&self.settings).into_iter().skip(1).collect()
+    }
+
+    fn help(&self) -> &str {
+        self.settings.response.as_deref().unwrap_or_default()
+    }
+
+    fn permission(&self) -> crate::config::Permission {
+        self.settings.permission
+    }
+
+    fn cooldown(&self) -> Option<std::time::Duration> {
+        self.settings.cooldown.map(std::time::Duration::from_secs)
+    }
+
+    fn enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    fn sound(&self) -> Option<&std::path::Path> {
+        self.settings.sound.as_deref()
+    }
+
+    fn execute<'a>(
+        &'a self,
+        bot: &'a crate::bot::Bot,
+        payload: &'a Payload,
+        _arguments: Option<&'a str>,
+    ) -> LocalBoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let Some(response) = &self.settings.response else {
+                return Ok(());
+            };
+            let response = response.replace("{user}", payload.chatter_user_name.as_str());
+            bot.send_message_reply(&payload.message_id, response.as_str())
+                .await
+        })
+    }
+}
+
+/// A built-in command with its settings from the config.
+struct Configured {
+    command: Builtin,
+    settings: crate::config::Command,
+}
+
+impl ChatCommand for Configured {
+    fn name(&self) -> &str {
+        self.command.name()
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        let mut aliases = self.command.aliases();
+        let configured = TextCommand::words_of(&self.settings);
+        aliases.extend(configured.into_iter().filter(|word| *word != self.name()));
+        aliases
+    }
+
+    fn help(&self) -> &str {
+        self.command.help()
+    }
+
+    fn permission(&self) -> crate::config::Permission {
+        self.settings.permission
+    }
+
+    fn cooldown(&self) -> Option<std::time::Duration> {
+        self.settings.cooldown.map(std::time::Duration::from_secs)
+    }
+
+    fn enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    fn sound(&self) -> Option<&std::path::Path> {
+        self.settings.sound.as_deref()
+    }
+
+    fn execute<'a>(
+        &'a self,
+        bot: &'a crate::bot::Bot,
+        payload: &'a Payload,
+        arguments: Option<&'a str>,
+    ) -> LocalBoxFuture<'a, Result<()>> {
+        self.command.execute(bot, payload, arguments)
+    }
+}
+
+/// Every command that chat can run.
+#[derive(Default)]
+pub struct Registry {
+    commands: Vec<Box<dyn ChatCommand>>,
+    /// The text commands that have regex triggers, which are run by any message that matches
+    /// rather than by `!word`.
+    patterns: Vec<TextCommand>,
+}
+
+impl Registry {
+    /// The built-in commands, with their settings and the text commands from the config.
+    pub fn new(config: &[crate::config::Command]) -> Self {
+        let mut registry = Self::default();
+        let mut settings: Vec<_> = config.iter().collect();
+        for builtin in builtins() {
+            // Entries with a response are text commands of their own, even when they share a
+            // word with a built-in command.
+            let maybe_position = settings.iter().position(|entry| {
+                entry.response.is_none()
+                    && TextCommand::words_of(entry)
+                        .into_iter()
+                        .any(|word| builtin.is_called(word))
+            });
+            match maybe_position {
+                Some(position) => registry.register(Configured {
+                    command: builtin,
+                    settings: settings.remove(position).clone(),
+                }),
+                None => registry.register(builtin),
+            }
+        }
+        for entry in settings {
+            if entry.response.is_none() {
+                continue;
+            }
+            if entry
+                .trigger
+                .iter()
+                .any(|trigger| matches!(trigger, crate::config::Trigger::Pattern(_)))
+            {
+                registry.patterns.push(TextCommand {
+                    settings: entry.clone(),
+                });
+            }
+            if entry.name().is_some() {
+                registry.register(TextCommand {
+                    settings: entry.clone(),
+                });
+            }
+        }
+        registry
+    }
+
+    /// Add a command. Commands added first win when two have the same name.
+    pub fn register(&mut self, command: impl ChatCommand + 'static) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// The command that `!word` runs.
+    pub fn find(&self, word: &str) -> Option<&dyn ChatCommand> {
+        self.commands
+            .iter()
+            .find(|command| command.is_called(word))
+            .map(AsRef::as_ref)
+    }
+
+    /// The first enabled command with a regex trigger that matches a message.
+    pub fn matching(&self, text: &str) -> Option<&dyn ChatCommand> {
+        self.patterns
+            .iter()
+            .find(|command| command.enabled() && command.is_match(text))
+            .map(|command| command as &dyn ChatCommand)
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &dyn ChatCommand> {
+        self.commands.iter().map(AsRef::as_ref)
+    }
+}
+
+/// The commands that are part of the bot.
+fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::new(
+            "achievements",
+            "Lists the achievements someone has unlocked",
+            |bot, payload, arguments| Box::pin(bot.achievements(payload, arguments)),
+        ),
+        Builtin::new(
+            "am",
+            "Allows or denies a message held by AutoMod",
+            |bot, payload, arguments| Box::pin(bot.automod(payload, arguments)),
+        ),
+        Builtin::new("arrived", "Plays your arrival sound", |bot, payload, _| {
+            Box::pin(bot.arrived(payload, payload.chatter_user_name.as_str()))
+        })
+        .with_aliases(&["arrive", "arrives"]),
+        Builtin::new(
+            "bot",
+            "Shows who the bot's token is for",
+            |bot, payload, arguments| Box::pin(bot.bot_command(payload, arguments)),
+        ),
+        Builtin::new(
+            "bug",
+            "Reports the bot misbehaving",
+            |bot, payload, arguments| Box::pin(bot.bug(payload, arguments)),
+        ),
+        Builtin::new(
+            "challenge",
+            "Starts or ends a time-boxed challenge",
+            |bot, payload, arguments| Box::pin(bot.challenge(payload, arguments)),
+        ),
+        Builtin::new(
+            "changelog",
+            "Shows what's new with the bot",
+            |bot, payload, _| Box::pin(bot.changelog(payload)),
+        ),
+        Builtin::new(
+            "chirp",
+            "Plays a chirp, or maybe a chicken",
+            |bot, payload, _| {
+                Box::pin(bot.chirp(payload, payload.chatter_user_name.as_str(), None))
+            },
+        ),
+        Builtin::new("config", "Changes the config", |bot, payload, arguments| {
+            Box::pin(bot.config_command(payload, arguments))
+        }),
+        Builtin::new(
+            "commit",
+            "Shows the latest commit in the repo",
+            |bot, payload, _| Box::pin(bot.commit(payload)),
+        ),
+        Builtin::new(
+            "duck",
+            "Asks the rubber duck a question",
+            |bot, payload, arguments| Box::pin(bot.duck(payload, arguments)),
+        ),
+        Builtin::new(
+            "earlybirds",
+            "Shows who has arrived earliest this month",
+            |bot, payload, _| Box::pin(bot.early_birds(payload)),
+        ),
+        Builtin::new(
+            "emotehistory",
+            "Shows the past emotes of the stream",
+            |bot, payload, _| Box::pin(bot.emote_history(payload)),
+        ),
+        Builtin::new(
+            "guest",
+            "Manages co-hosts for collab streams",
+            |bot, payload, arguments| Box::pin(bot.guest(payload, arguments)),
+        ),
+        Builtin::new(
+            "help",
+            "Lists the commands, or describes one",
+            |bot, payload, arguments| Box::pin(bot.help(payload, arguments)),
+        ),
+        Builtin::new(
+            "intro",
+            "Sets the intro shown when you arrive",
+            |bot, payload, arguments| Box::pin(bot.intro(payload, arguments)),
+        ),
+        Builtin::new(
+            "issue",
+            "Shows an issue in the repo",
+            |bot, payload, arguments| Box::pin(bot.issue(payload, arguments)),
+        ),
+        Builtin::new("join", "Enters the raffle", |bot, payload, _| {
+            Box::pin(bot.join_raffle(payload))
+        }),
+        Builtin::new(
+            "note",
+            "Keeps a private note about a mate",
+            |bot, payload, arguments| Box::pin(bot.note(payload, arguments)),
+        ),
+        Builtin::new(
+            "osd",
+            "Shows some text on screen",
+            |bot, payload, arguments| Box::pin(bot.osd(payload, arguments)),
+        ),
+        Builtin::new("poll", "Starts a Twitch poll", |bot, payload, arguments| {
+            Box::pin(bot.poll(payload, arguments))
+        }),
+        Builtin::new(
+            "pomo",
+            "Runs a Pomodoro focus timer",
+            |bot, payload, arguments| Box::pin(bot.pomodoro(payload, arguments)),
+        ),
+        Builtin::new(
+            "pr",
+            "Shows a pull request in the repo",
+            |bot, payload, arguments| Box::pin(bot.pull_request(payload, arguments)),
+        ),
+        Builtin::new(
+            "pronouns",
+            "Sets your pronouns",
+            |bot, payload, arguments| Box::pin(bot.pronouns(payload, arguments)),
+        ),
+        Builtin::new(
+            "quote",
+            "Shows a quote, or adds one",
+            |bot, payload, arguments| Box::pin(bot.quote(payload, arguments)),
+        ),
+        Builtin::new("raffle", "Runs a raffle", |bot, payload, arguments| {
+            Box::pin(bot.raffle(payload, arguments))
+        }),
+        Builtin::new(
+            "raid",
+            "Counts down and then raids a channel",
+            |bot, payload, arguments| Box::pin(bot.raid(payload, arguments)),
+        ),
+        Builtin::new(
+            "remind",
+            "Reminds you of something later",
+            |bot, payload, arguments| Box::pin(bot.remind(payload, arguments)),
+        ),
+        Builtin::new(
+            "repo",
+            "Links to the repo being worked on",
+            |bot, payload, arguments| Box::pin(bot.repo(payload, arguments)),
+        ),
+        Builtin::new("snooze", "Snoozes the next ad break", |bot, payload, _| {
+            Box::pin(bot.snooze(payload))
+        }),
+        Builtin::new(
+            "sql",
+            "Runs a read-only query on the database",
+            |bot, payload, arguments| Box::pin(bot.sql(payload, arguments)),
+        ),
+        Builtin::new(
+            "stats",
+            "Shows someone's chat stats",
+            |bot, payload, arguments| Box::pin(bot.stats(payload, arguments)),
+        ),
+        Builtin::new(
+            "suggesttitle",
+            "Suggests a stream title",
+            |bot, payload, arguments| Box::pin(bot.suggest_title(payload, arguments)),
+        ),
+        Builtin::new(
+            "testfollow",
+            "Previews the follow alert",
+            |bot, payload, _| Box::pin(bot.test_follow(payload)),
+        ),
+        Builtin::new(
+            "testraid",
+            "Previews the raid alert",
+            |bot, payload, arguments| Box::pin(bot.test_raid(payload, arguments)),
+        ),
+        Builtin::new(
+            "testsub",
+            "Previews the sub alert",
+            |bot, payload, arguments| Box::pin(bot.test_sub(payload, arguments)),
+        ),
+        Builtin::new(
+            "theme",
+            "Shows or changes the alert theme",
+            |bot, payload, arguments| Box::pin(bot.theme_command(payload, arguments)),
+        ),
+        Builtin::new(
+            "time",
+            "Shows the time for someone, or in a timezone",
+            |bot, payload, arguments| Box::pin(bot.time(payload, arguments)),
+        ),
+        Builtin::new(
+            "timer",
+            "Shows a countdown on screen",
+            |bot, payload, arguments| Box::pin(bot.timer(payload, arguments)),
+        ),
+        Builtin::new(
+            "titlevote",
+            "Votes on the suggested stream titles",
+            |bot, payload, arguments| Box::pin(bot.title_vote(payload, arguments)),
+        ),
+        Builtin::new(
+            "today",
+            "Shows what the stream is about",
+            |bot, payload, arguments| Box::pin(bot.today(payload, arguments)),
+        ),
+        Builtin::new(
+            "todo",
+            "Manages the todo list on stream",
+            |bot, payload, arguments| Box::pin(bot.todo(payload, arguments)),
+        ),
+        Builtin::new(
+            "topcheers",
+            "Shows the biggest cheerers",
+            |bot, payload, _| Box::pin(bot.top_cheers(payload)),
+        ),
+        Builtin::new(
+            "tty",
+            "Renders an emote in Tom's terminal",
+            |bot, payload, arguments| Box::pin(bot.tattoy(payload, arguments)),
+        ),
+        Builtin::new("tz", "Sets your timezone", |bot, payload, arguments| {
+            Box::pin(bot.timezone(payload, arguments))
+        }),
+        Builtin::new(
+            "whois",
+            "Shows details about an account",
+            |bot, payload, arguments| Box::pin(bot.whois(payload, arguments)),
+        ),
+    ]
+}
+
+impl crate::bot::Bot {
+    /// Every command, with the current config.
+    pub async fn command_registry(&self) -> Registry {
+        let mut registry = Registry::new(&self.config.read().await.command);
+        for plugin in &self.plugins {
+            for spec in &plugin.commands {
+                registry.register(crate::plugins::PluginCommand {
+                    plugin: plugin.clone(),
+                    spec: spec.clone(),
+                });
+            }
+        }
+        registry
+    }
+
+    /// Whether the chatter can use the command right now. A command that's allowed starts its
+    /// cooldown and plays its sound.
+    pub async fn may_run(&self, payload: &Payload, command: &dyn ChatCommand) -> Result<bool> {
+        let name = command.name();
+        if !command.enabled() {
+            tracing::info!("!{name} is disabled, ignoring");
+            return Ok(false);
+        }
+
+        let is_permitted = match command.permission() {
+            crate::config::Permission::Everyone => true,
+            crate::config::Permission::Follower => {
+                self.is_moderator(payload).await?
+                    || self
+                        .followed_at(payload.chatter_user_id.as_str())
+                        .await?
+                        .is_some()
+            }
+            crate::config::Permission::Moderator => self.is_moderator(payload).await?,
+            crate::config::Permission::Broadcaster => Self::is_broadcaster(payload),
+        };
+        if !is_permitted {
+            tracing::info!(
+                "{} doesn't have permission for !{name}",
+                payload.chatter_user_name
+            );
+            return Ok(false);
+        }
+
+        if let Some(cooldown) = command.cooldown() {
+            let is_guest = self
+                .db
+                .has_role(payload.chatter_user_id.as_str(), crate::roles::Role::Guest)
+                .await?;
+            let mut cooldowns = self.command_cooldowns.lock().await;
+            if !start_cooldown(&mut cooldowns, name, cooldown, is_guest) {
+                tracing::info!("!{name} is cooling down, ignoring");
+                return Ok(false);
+            }
+        }
+
+        if let Some(sound) = command.sound() {
+            if !self.are_sounds_muted().await {
+                Self::play_sound(sound)?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// `!help` lists the commands, `!help chirp` describes one.
+    pub async fn help(&self, payload: &Payload, arguments: Option<&str>) -> Result<()> {
+        let registry = self.command_registry().await;
+        let maybe_word = arguments
+            .map(|arguments| arguments.trim().trim_start_matches('!'))
+            .filter(|word| !word.is_empty());
+
+        let message = match maybe_word {
+            None => {
+                let mut message = "Commands:".to_owned();
+                for command in registry.commands().filter(|command| command.enabled()) {
+                    let name = format!(" !{}", command.name());
+                    if message.len() + name.len() > MAX_MESSAGE_LENGTH {
+                        break;
+                    }
+                    message.push_str(&name);
+                }
+                message
+            }
+            Some(word) => match registry.find(word) {
+                Some(command) => describe(command),
+                None => format!("There's no !{word} command"),
+            },
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+}
+
+/// Whether a command has finished cooling down, starting its cooldown again if it has. Guests
+/// skip cooldowns, and don't start them for everyone else either.
+fn start_cooldown(
+    cooldowns: &mut std::collections::HashMap<String, std::time::Instant>,
+    name: &str,
+    cooldown: std::time::Duration,
+    is_guest: bool,
+) -> bool {
+    if is_guest {
+        return true;
+    }
+    if cooldowns
+        .get(name)
+        .is_some_and(|last_used| last_used.elapsed() < cooldown)
+    {
+        return false;
+    }
+    cooldowns.insert(name.to_owned(), std::time::Instant::now());
+    true
+}
+
+/// A command's help, with its aliases and settings.
+fn describe(command: &dyn ChatCommand) -> String {
+    let mut details = Vec::new();
+    let aliases = command.aliases();
+    if !aliases.is_empty() {
+        let aliases = aliases
+            .iter()
+            .map(|alias| format!("!{alias}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        details.push(format!("also {aliases}"));
+    }
+    match command.permission() {
+        crate::config::Permission::Everyone => (),
+        crate::config::Permission::Follower => details.push("followers only".to_owned()),
+        crate::config::Permission::Moderator => details.push("mods only".to_owned()),
+        crate::config::Permission::Broadcaster => details.push("broadcaster only".to_owned()),
+    }
+    if let Some(cooldown) = command.cooldown() {
+        details.push(format!("{}s cooldown", cooldown.as_secs()));
+    }
+
+    let description = format!("!{}: {}", command.name(), command.help());
+    if details.is_empty() {
+        description
+    } else {
+        format!("{description} ({})", details.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(triggers: &[&str], response: Option<&str>) -> crate::config::Command {
+        crate::config::Command {
+            trigger: triggers
+                .iter()
+                .map(|trigger| crate::config::Trigger::try_from((*trigger).to_owned()).unwrap())
+                .collect(),
+            response: response.map(ToOwned::to_owned),
+            aliases: Vec::new(),
+            cooldown: None,
+            permission: crate::config::Permission::Everyone,
+            enabled: true,
+            sound: None,
+        }
+    }
+
+    #[test]
+    fn finds_builtins_by_name_and_alias() {
+        let registry = Registry::new(&[]);
+        assert_eq!(registry.find("arrived").unwrap().name(), "arrived");
+        assert_eq!(registry.find("arrive").unwrap().name(), "arrived");
+        assert!(registry.find("nope").is_none());
+    }
+
+    #[test]
+    fn finds_text_commands_by_any_word() {
+        let registry = Registry::new(&[entry(&["hello", "hi"], Some("Hello {user}!"))]);
+        let command = registry.find("hi").unwrap();
+        assert_eq!(command.name(), "hello");
+        assert_eq!(command.help(), "Hello {user}!");
+    }
+
+    #[test]
+    fn config_settings_apply_to_builtins() {
+        let mut chirp = entry(&["chirp"], None);
+        chirp.aliases = vec!["tweet".to_owned()];
+        chirp.cooldown = Some(30);
+        let registry = Registry::new(&[chirp]);
+
+        let command = registry.find("tweet").unwrap();
+        assert_eq!(command.name(), "chirp");
+        assert_eq!(command.cooldown(), Some(std::time::Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn config_words_that_are_builtin_aliases_configure_the_builtin() {
+        let mut arrive = entry(&["arrive"], None);
+        arrive.permission = crate::config::Permission::Follower;
+        let registry = Registry::new(&[arrive]);
+
+        let command = registry.find("arrived").unwrap();
+        assert!(matches!(
+            command.permission(),
+            crate::config::Permission::Follower
+        ));
+    }
+
+    #[test]
+    fn text_commands_dont_shadow_builtins() {
+        let registry = Registry::new(&[entry(&["chirp"], Some("Not a chirp"))]);
+        let command = registry.find("chirp").unwrap();
+        assert_eq!(command.help(), "Plays a chirp, or maybe a chicken");
+    }
+
+    #[test]
+    fn text_commands_sharing_a_word_with_a_builtin_keep_their_response() {
+        let registry =
+            Registry::new(&[entry(&["dotfiles", "editor", "chirp"], Some("My dotfiles"))]);
+        assert_eq!(registry.find("dotfiles").unwrap().help(), "My dotfiles");
+        assert_eq!(registry.find("editor").unwrap().help(), "My dotfiles");
+        assert_eq!(
+            registry.find("chirp").unwrap().help(),
+            "Plays a chirp, or maybe a chicken"
+        );
+    }
+
+    #[test]
+    fn earlier_commands_win() {
+        let registry = Registry::new(&[
+            entry(&["lurk"], Some("First")),
+            entry(&["lurk"], Some("Second")),
+        ]);
+        assert_eq!(registry.find("lurk").unwrap().help(), "First");
+    }
+
+    #[test]
+    fn pattern_triggers_match_messages_not_words() {
+        let mut disabled = entry(&["/(?i)good/"], Some("Disabled"));
+        disabled.enabled = false;
+        let registry = Registry::new(&[
+            disabled,
+            entry(&["/(?i)good morning/"], Some("Morning {user}!")),
+        ]);
+
+        let command = registry.matching("Good morning everyone").unwrap();
+        assert_eq!(command.name(), "(?i)good morning");
+        assert_eq!(command.help(), "Morning {user}!");
+        assert!(registry.matching("Good evening").is_none());
+        assert!(registry.find("(?i)good morning").is_none());
+    }
+
+    #[test]
+    fn describes_commands_without_settings() {
+        let registry = Registry::new(&[]);
+        assert_eq!(
+            describe(registry.find("help").unwrap()),
+            "!help: Lists the commands, or describes one"
+        );
+    }
+
+    #[test]
+    fn describes_commands_with_their_settings() {
+        let mut hello = entry(&["hello", "hi"], Some("Hello!"));
+        hello.aliases = vec!["hey".to_owned()];
+        hello.permission = crate::config::Permission::Moderator;
+        hello.cooldown = Some(30);
+        let registry = Registry::new(&[hello]);
+
+        assert_eq!(
+            describe(registry.find("hello").unwrap()),
+            "!hello: Hello! (also !hi, !hey, mods only, 30s cooldown)"
+        );
+    }
+
+    #[test]
+    fn cooldowns_stop_commands_until_theyve_passed() {
+        let mut cooldowns = std::collections::HashMap::new();
+        let cooldown = std::time::Duration::from_secs(30);
+        assert!(start_cooldown(&mut cooldowns, "chirp", cooldown, false));
+        assert!(!start_cooldown(&mut cooldowns, "chirp", cooldown, false));
+        assert!(start_cooldown(&mut cooldowns, "lurk", cooldown, false));
+    }
+
+    #[test]
+    fn guests_skip_cooldowns() {
+        let mut cooldowns = std::collections::HashMap::new();
+        let cooldown = std::time::Duration::from_secs(30);
+        assert!(start_cooldown(&mut cooldowns, "chirp", cooldown, false));
+        assert!(start_cooldown(&mut cooldowns, "chirp", cooldown, true));
+
+        let mut cooldowns = std::collections::HashMap::new();
+        assert!(start_cooldown(&mut cooldowns, "chirp", cooldown, true));
+        assert!(start_cooldown(&mut cooldowns, "chirp", cooldown, false));
+    }
+}
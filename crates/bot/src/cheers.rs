@@ -0,0 +1,102 @@
+use color_eyre::Result;
+
+/// How many cheerers to show in `!topcheers`.
+const LEADERBOARD_SIZE: u32 = 5;
+
+/// An alert style for cheers of at least `minimum` bits. The tier with the highest matching
+/// minimum is used.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CheerTier {
+    pub minimum: i64,
+    /// `{user}` and `{bits}` are replaced in the message.
+    pub message: String,
+    /// The notification category, so the popup can be styled differently per tier.
+    #[serde(default = "default_cheer_category")]
+    pub category: String,
+    pub sound: Option<String>,
+}
+
+fn default_cheer_category() -> String {
+    "twitch-cheer".to_owned()
+}
+
+impl crate::bot::Bot {
+    pub async fn cheer(
+        &self,
+        payload: &crate::eventsub::channel::ChannelCheerV1Payload,
+    ) -> Result<()> {
+        tracing::info!("Cheer: {payload:?}");
+        let username = match &payload.user_name {
+            Some(name) if !payload.is_anonymous => name.as_str(),
+            _ => "Anonymous",
+        };
+        let user_id = payload
+            .user_id
+            .as_ref()
+            .filter(|_| !payload.is_anonymous)
+            .map(|id| id.as_str());
+
+        self.db
+            .add_cheer(user_id, username, payload.bits, &payload.message)
+            .await?;
+        if let Some(user_id) = user_id {
+            self.check_achievements(user_id, username, crate::achievements::Metric::Bits)
+                .await?;
+        }
+
+        let Some(tier) = self
+            .config
+            .read()
+            .await
+            .cheer_tier
+            .iter()
+            .filter(|tier| payload.bits >= tier.minimum)
+            .max_by_key(|tier| tier.minimum)
+            .cloned()
+        else {
+            return Ok(());
+        };
+
+        let message = tier
+            .message
+            .replace("{user}", username)
+            .replace("{bits}", &payload.bits.to_string());
+        self.overlay_alert("cheer", &message, None);
+        if self.wants_alert_popups().await {
+            Self::onscreen_popup(format!(" \n{message}"), &tier.category)?;
+        }
+        if let Some(sound) = &tier.sound {
+            Self::play_sound(sound)?;
+        }
+
+        Ok(())
+    }
+
+    /// `!topcheers` shows the mates that have cheered the most bits.
+    pub async fn top_cheers(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let leaders = self.db.top_cheerers(LEADERBOARD_SIZE).await?;
+        if leaders.is_empty() {
+            self.send_message_reply(&payload.message_id, "Nobody has cheered yet!")
+                .await?;
+            return Ok(());
+        }
+
+        let message = leaders
+            .iter()
+            .enumerate()
+            .map(|(position, (username, bits))| format!("{}. {username} ({bits})", position + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.send_message_reply(
+            &payload.message_id,
+            format!("Top cheers: {message}").as_str(),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
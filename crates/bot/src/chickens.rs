@@ -0,0 +1,54 @@
+//! Tallies of the rubber chickens that each mate gets from `!chirp` during a stream. At the end
+//! of the stream whoever got the most is crowned the last chicken standing.
+
+use color_eyre::Result;
+
+impl crate::bot::Bot {
+    /// Record a run of chickens towards the current stream's tally.
+    pub async fn record_chickens(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        chickens: u16,
+    ) -> Result<()> {
+        let stream_id = self.db.current_stream().await?.map(|stream| stream.id);
+        self.db
+            .add_chickens(
+                stream_id,
+                payload.chatter_user_id.as_str(),
+                payload.chatter_user_name.as_str(),
+                chickens,
+            )
+            .await
+    }
+
+    /// Give the achievement to whoever got the most chickens in the stream that just ended.
+    pub async fn crown_last_chicken_standing(
+        &self,
+        stream: Option<&crate::database::Stream>,
+    ) -> Result<()> {
+        let Some(stream) = stream else {
+            return Ok(());
+        };
+        let Some((user_id, username, chickens)) = self.db.top_chicken(stream.id).await? else {
+            return Ok(());
+        };
+        tracing::info!("Last chicken standing: {username} ({chickens} chickens)");
+
+        let mate = self.db.get_mate(&user_id, &username).await?;
+        let achievement = crate::database::Achievement {
+            achiever: mate.id,
+            kind: crate::database::AchievementKind::LastChickenStanding,
+            data: serde_json::json!({
+                "stream": stream.id,
+                "chickens": chickens,
+            }),
+            timestamp: chrono::Utc::now(),
+        };
+        self.award_achievement(&username, "Last Chicken Standing", achievement)
+            .await?;
+        let message = format!(
+            "🐔 The last chicken standing is {username}, with {chickens} rubber chickens! 🐔"
+        );
+        self.send_message(message.as_str()).await
+    }
+}
@@ -0,0 +1,123 @@
+//! Work out what kind of chat message something is, eg whether it mentions the streamer or asks
+//! a question. Used to highlight lines in the terminal chat log.
+
+use color_eyre::Result;
+
+/// Words that usually start a question, even when there's no question mark.
+const QUESTION_WORDS: &[&str] = &[
+    "how", "what", "why", "when", "where", "who", "which", "is", "are", "can", "could", "does",
+    "do", "did", "should", "would", "will", "has", "have",
+];
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct HighlightConfig {
+    /// Names that count as mentioning the streamer, case insensitive.
+    pub names: Vec<String>,
+    /// Terminal colour for mentions: black, red, green, yellow, blue, magenta, cyan or white.
+    pub mention_colour: String,
+    /// Terminal colour for questions.
+    pub question_colour: String,
+    /// A soft sound to play for highlighted messages.
+    pub sound: Option<std::path::PathBuf>,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            names: vec!["tombh".to_owned(), "tom".to_owned()],
+            mention_colour: "yellow".to_owned(),
+            question_colour: "cyan".to_owned(),
+            sound: None,
+        }
+    }
+}
+
+/// What kind of message a chat message is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Classification {
+    Mention,
+    Question,
+    Normal,
+}
+
+/// Classify a chat message. Mentions win over questions.
+pub fn classify(text: &str, names: &[String]) -> Classification {
+    let text = text.trim();
+    if text.starts_with('!') {
+        return Classification::Normal;
+    }
+
+    let words = text
+        .split(|character: char| !character.is_alphanumeric() && character != '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    let is_mention = names
+        .iter()
+        .any(|name| words.contains(&name.to_lowercase()));
+    if is_mention {
+        return Classification::Mention;
+    }
+
+    let starts_with_question_word = words
+        .first()
+        .is_some_and(|word| QUESTION_WORDS.contains(&word.as_str()));
+    if text.ends_with('?') || (starts_with_question_word && words.len() > 2) {
+        return Classification::Question;
+    }
+
+    Classification::Normal
+}
+
+/// The ANSI escape code for a named terminal colour.
+fn ansi_colour(name: &str) -> Option<&'static str> {
+    let code = match name {
+        "black" => "\x1b[1;30m",
+        "red" => "\x1b[1;31m",
+        "green" => "\x1b[1;32m",
+        "yellow" => "\x1b[1;33m",
+        "blue" => "\x1b[1;34m",
+        "magenta" => "\x1b[1;35m",
+        "cyan" => "\x1b[1;36m",
+        "white" => "\x1b[1;37m",
+        _ => return None,
+    };
+    Some(code)
+}
+
+impl crate::bot::Bot {
+    /// Print a chat message to the terminal, highlighting mentions and questions.
+    pub async fn print_chat_line(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        timestamp: &twitch_api::types::Timestamp,
+    ) -> Result<()> {
+        let mut line = format!(
+            "[{}] {}: {}",
+            timestamp, payload.chatter_user_name, payload.message.text
+        );
+        if let Some(context) = self.reply_context(payload).await? {
+            line = format!("{line}  ({context})");
+        }
+        let config = self.config.read().await.highlight.clone();
+        let colour_name = match classify(&payload.message.text, &config.names) {
+            Classification::Mention => &config.mention_colour,
+            Classification::Question => &config.question_colour,
+            Classification::Normal => {
+                println!("{line}");
+                return Ok(());
+            }
+        };
+
+        match ansi_colour(colour_name) {
+            Some(colour) => println!("{colour}{line}\x1b[0m"),
+            None => println!("{line}"),
+        }
+        if let Some(sound) = config.sound {
+            Self::play_sound(sound)?;
+        }
+
+        Ok(())
+    }
+}
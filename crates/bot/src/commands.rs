@@ -3,29 +3,70 @@ use eyre::ContextCompat as _;
 use rand::{seq::IteratorRandom as _, Rng as _};
 use tokio::io::AsyncWriteExt;
 
+/// How much bigger premium `!tty` emotes are.
+const PREMIUM_TTY_SCALE: f32 = 2.0;
+
+/// How long premium `!tty` emotes are shown for, in seconds.
+const PREMIUM_TTY_DURATION: u64 = 30;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct TtyConfig {
+    /// Render `!tty` emotes behind every match of the text, rather than only the first.
+    pub every_match: bool,
+    /// The most matches to render an emote behind, so the whole terminal isn't covered.
+    pub max_matches: usize,
+    /// Whether `!tty` emotes grow into place when they appear.
+    pub pop_in: bool,
+}
+
+impl Default for TtyConfig {
+    fn default() -> Self {
+        Self {
+            every_match: false,
+            max_matches: 5,
+            pop_in: true,
+        }
+    }
+}
+
 impl crate::bot::Bot {
-    pub async fn text_responder(
+    /// The response to one of the simple text commands from the config, if there's one for
+    /// the command.
+    pub async fn command_response(&self, command: &str, username: &str) -> Option<String> {
+        self.config
+            .read()
+            .await
+            .command
+            .iter()
+            .find(|c| c.enabled && c.is_called(command))
+            .and_then(|c| c.response.as_ref())
+            .map(|response| response.replace("{user}", username))
+    }
+
+    /// Reply to messages that match a text command's regex trigger. They're run like any other
+    /// command, so their permission, cooldown and sound apply. The bot's own messages are
+    /// ignored, so that a response can't trigger itself.
+    pub async fn pattern_responder(
         &self,
-        command: &str,
         message: &crate::eventsub::channel::ChannelChatMessageV1Payload,
     ) -> Result<()> {
-        if let Some(response) = self
-            .config
-            .command
-            .iter()
-            .find(|c| c.trigger.contains(&command.to_string()))
-        {
-            self.send_message_reply(
-                &message.message_id,
-                response
-                    .response
-                    .replace("{user}", message.chatter_user_name.as_str())
-                    .as_str(),
-            )
-            .await?;
+        if message.chatter_user_id.as_str() == crate::BOT_ID {
+            return Ok(());
+        }
+        if self.is_paused.load(std::sync::atomic::Ordering::Relaxed) {
+            return Ok(());
         }
 
-        Ok(())
+        let registry = self.command_registry().await;
+        let Some(command) = registry.matching(message.message.text.as_str()) else {
+            return Ok(());
+        };
+        if !self.may_run(message, command).await? {
+            return Ok(());
+        }
+
+        command.execute(self, message, None).await
     }
 
     pub async fn arrived(
@@ -33,9 +74,16 @@ impl crate::bot::Bot {
         payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
         username: &str,
     ) -> Result<()> {
-        let mate = self.db.get_mate(username).await?;
+        let mate = self
+            .db
+            .get_mate(payload.chatter_user_id.as_str(), username)
+            .await?;
         let elapsed = chrono::Utc::now() - mate.last_played;
-        if elapsed.num_hours() < 12 {
+        let is_guest = self
+            .db
+            .has_role(payload.chatter_user_id.as_str(), crate::roles::Role::Guest)
+            .await?;
+        if elapsed.num_hours() < 12 && !is_guest {
             tracing::info!("Not playing {username}'s sound. {elapsed} to go.");
             let message = format!("You're already here {username}!");
             self.send_message_reply(&payload.message_id, message.as_str())
@@ -43,24 +91,36 @@ impl crate::bot::Bot {
             return Ok(());
         }
 
-        let path = format!("/home/streamer/Documents/arrivals/{username}-arrived.mp3");
-        if !std::path::Path::new(&path).exists() {
-            let message = "You don't have an arrival sound yet, type \"!sounds\" to find out how.";
-            self.send_message_reply(&payload.message_id, message)
+        let placing = self
+            .record_arrival(payload)
+            .await?
+            .map(|(position, points)| format!(" (#{position}, +{points} points)"))
+            .unwrap_or_default();
+
+        let path = crate::paths::sound(format!("arrivals/{username}-arrived.mp3"));
+        if !path.exists() {
+            let message = format!(
+                "You don't have an arrival sound yet, type \"!sounds\" to find out how.{placing}"
+            );
+            self.send_message_reply(&payload.message_id, message.as_str())
                 .await?;
             return Ok(());
         }
 
-        std::process::Command::new("mpv")
-            .arg("--volume=50")
-            .arg(path)
-            .spawn()?;
+        if !self.are_sounds_muted().await {
+            Self::play_sound(path)?;
+        }
 
-        let message = format!("{username} has arrived 📣");
+        let intro = mate
+            .intro
+            .as_ref()
+            .map(|intro| format!(" {intro}"))
+            .unwrap_or_default();
+        let message = format!("{} has arrived 📣{intro}{placing}", mate.display_name());
         self.send_message_reply(&payload.message_id, message.as_str())
             .await?;
 
-        self.db.set_last_played(username).await?;
+        self.db.set_last_played(mate.id).await?;
         Ok(())
     }
 
@@ -70,18 +130,22 @@ impl crate::bot::Bot {
         username: &str,
         depth: Option<u16>,
     ) -> Result<()> {
+        if depth.is_none() && self.are_sounds_muted().await {
+            self.send_message_reply(&payload.message_id, "🍅 Shh, it's focus time")
+                .await?;
+            return Ok(());
+        }
+
         let chicken_chance = 0.05;
         let mut rng = rand::rng();
         let mut repeats = depth.unwrap_or_default();
 
-        let directory = "/home/streamer/Documents/chirps";
-        let chirps = std::fs::read_dir(directory)?;
+        let chirps = std::fs::read_dir(crate::paths::sound("chirps"))?;
         let mut sound = chirps.choose(&mut rng).context("No chirp found")??.path();
-        let chicken_path = "/home/streamer/Documents/rubber-chicken.mp3";
 
         let is_chicken = rng.random_bool(chicken_chance);
         if is_chicken {
-            sound = std::path::Path::new(chicken_path).to_path_buf();
+            sound = crate::paths::sound("rubber-chicken.mp3");
             repeats += 1;
         }
 
@@ -90,14 +154,13 @@ impl crate::bot::Bot {
             return Ok(());
         }
 
-        let mut process = std::process::Command::new("mpv")
-            .arg("--volume=50")
-            .arg(sound)
-            .spawn()?;
+        let process = Self::play_sound(sound)?;
 
         if is_chicken {
             if rng.random_bool(chicken_chance) {
-                process.wait()?;
+                if let Some(mut process) = process {
+                    process.wait()?;
+                }
                 std::boxed::Box::pin(self.chirp(payload, username, Some(repeats))).await?;
                 return Ok(());
             } else {
@@ -114,11 +177,15 @@ impl crate::bot::Bot {
         payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
     ) -> Result<()> {
         tracing::info!("{username} got {repeats} chickens");
+        self.record_chickens(payload, repeats).await?;
         if repeats > 1 {
             let message = format!("Wooooah that's {repeats} rubber chickens!");
             self.send_message_reply(&payload.message_id, message.as_str())
                 .await?;
-            let mate = self.db.get_mate(username).await?;
+            let mate = self
+                .db
+                .get_mate(payload.chatter_user_id.as_str(), username)
+                .await?;
             let achievement = crate::database::Achievement {
                 achiever: mate.id,
                 kind: crate::database::AchievementKind::ChickenRun,
@@ -127,7 +194,14 @@ impl crate::bot::Bot {
                 }),
                 timestamp: chrono::Utc::now(),
             };
-            self.db.add_achievement(achievement).await?;
+            let name = format!("a {repeats} chicken run");
+            self.award_achievement(username, &name, achievement).await?;
+            self.check_achievements(
+                payload.chatter_user_id.as_str(),
+                username,
+                crate::achievements::Metric::Chickens,
+            )
+            .await?;
         }
         Ok(())
     }
@@ -137,6 +211,12 @@ impl crate::bot::Bot {
         payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
         arguments: Option<&str>,
     ) -> Result<()> {
+        if !self
+            .is_osd_enabled
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            return Ok(());
+        }
         if let Some(text) = arguments {
             let safe_text = text
                 .chars()
@@ -154,31 +234,302 @@ impl crate::bot::Bot {
         arguments: Option<&str>,
     ) -> Result<()> {
         tracing::info!("Tattoy command: {arguments:?}");
-        if let Some(text) = arguments {
-            let mut parts: Vec<&str> = text.split_whitespace().collect();
-            let Some(emote) = parts.pop() else {
-                return Ok(());
-            };
-            let regexish = text.replace(emote, "").trim().to_owned();
-            let safe_regexish = regexish
-                .chars()
-                .filter(|c| c.is_alphanumeric() || [' ', '?'].contains(c))
-                .collect::<String>();
-            let message = tattoy_twitch_tombh_plugin::BotMessage {
-                username: payload.chatter_user_name.clone().into(),
-                regexish: safe_regexish,
-                emote: emote.into(),
-            };
-            let mut json = serde_json::to_string(&message)?;
-            json.push('\n');
-            tracing::info!("Sending message to Tattoy: {json}");
+        let Some(text) = arguments else {
+            return Ok(());
+        };
+        let username = payload.chatter_user_name.as_str();
+        let (first_word, rest) = text
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((text.trim(), ""));
+        if first_word == "default" {
+            return self.tattoy_default(payload, rest.trim()).await;
+        }
+
+        let mate = self
+            .db
+            .get_mate(payload.chatter_user_id.as_str(), username)
+            .await?;
+        let ends_with_emote = matches!(
+            payload.message.fragments.last(),
+            Some(twitch_api::eventsub::channel::chat::Fragment::Emote { .. })
+        );
+        // `!tty @prompt LUL` renders the emote at an anchor rather than behind some text.
+        let mut text = text;
+        let mut anchor = None;
+        if let Some((first, rest)) = text.trim().split_once(' ') {
+            if let Some(name) = first.strip_prefix('@') {
+                match name.parse::<tattoy_twitch_tombh_plugin::Anchor>() {
+                    Ok(parsed) => {
+                        anchor = Some(parsed);
+                        text = rest;
+                    }
+                    Err(error) => {
+                        let message =
+                            format!("{error}, use @top-left, @status-bar, @prompt or @<row>,<col>");
+                        self.send_message_reply(&payload.message_id, message.as_str())
+                            .await?;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        let (regexish, emote) = match mate.tty_emote {
+            Some(default) if !ends_with_emote => (text.to_owned(), default),
+            _ => {
+                let mut parts: Vec<&str> = text.split_whitespace().collect();
+                let Some(emote) = parts.pop() else {
+                    return Ok(());
+                };
+                (text.replace(emote, ""), emote.to_owned())
+            }
+        };
+        self.send_tattoy_emote(username, &regexish, &emote, mate.tty_premium, anchor)
+            .await?;
+        Ok(())
+    }
+
+    /// `!tty default LUL` sets the emote that's used when `!tty` isn't given one.
+    /// `!tty default none` clears it. Either way, a premium default from a redemption stays
+    /// premium.
+    async fn tattoy_default(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        emote: &str,
+    ) -> Result<()> {
+        let mate = self
+            .db
+            .get_mate(
+                payload.chatter_user_id.as_str(),
+                payload.chatter_user_name.as_str(),
+            )
+            .await?;
+        let message = match emote {
+            "" => match mate.tty_emote {
+                Some(emote) => format!("Your default !tty emote is {emote}"),
+                None => "You don't have a default !tty emote, set one with !tty default <emote>"
+                    .to_owned(),
+            },
+            "none" => {
+                self.db
+                    .set_tty_default(mate.id, None, mate.tty_premium)
+                    .await?;
+                "Your default !tty emote has been cleared".to_owned()
+            }
+            _ => {
+                self.db
+                    .set_tty_default(mate.id, Some(emote), mate.tty_premium)
+                    .await?;
+                format!("Your default !tty emote is now {emote}")
+            }
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// Ask the Tattoy plugin to render an emote behind some text in the terminal, or at an
+    /// anchor. Premium emotes are bigger and stay around for longer.
+    pub async fn send_tattoy_emote(
+        &self,
+        username: &str,
+        regexish: &str,
+        emote: &str,
+        is_premium: bool,
+        anchor: Option<tattoy_twitch_tombh_plugin::Anchor>,
+    ) -> Result<()> {
+        // Enough for simple regexes, like `(?i)\bfoo\b`, but no repetition counts, which can
+        // make for huge patterns.
+        let safe_regexish = regexish
+            .trim()
+            .chars()
+            .filter(|c| {
+                c.is_alphanumeric()
+                    || [
+                        ' ', '?', '.', '*', '+', '|', '(', ')', '[', ']', '^', '$', '\\', '-',
+                    ]
+                    .contains(c)
+            })
+            .collect::<String>();
+        let config = self.config.read().await.tty.clone();
+        let message = tattoy_twitch_tombh_plugin::BotMessage::Emote {
+            username: username.into(),
+            regexish: safe_regexish,
+            emote: emote.into(),
+            scale: is_premium.then_some(PREMIUM_TTY_SCALE),
+            duration: is_premium.then_some(PREMIUM_TTY_DURATION),
+            anchor,
+            pinned: false,
+            every_match: config.every_match.then_some(config.max_matches),
+            pop_in: config.pop_in,
+        };
+        self.send_to_tattoy(&message).await
+    }
+
+    /// Send a message to the Tattoy plugin.
+    pub async fn send_to_tattoy(
+        &self,
+        message: &tattoy_twitch_tombh_plugin::BotMessage,
+    ) -> Result<()> {
+        let mut json = serde_json::to_string(message)?;
+        if crate::killswitch::is_log_only() {
+            tracing::info!("Log-only, not sending to Tattoy: {json}");
+            return Ok(());
+        }
+        json.push('\n');
+        tracing::info!("Sending message to Tattoy: {json}");
+        let mut socket = self.tattoy_socket.lock().await;
+        if let Err(error) = socket.write_all(json.as_ref()).await {
+            // Tattoy restarting closes the socket. The plugin starts without any emotes, so
+            // they're sent again before the message, which might need them.
+            tracing::warn!("Reconnecting to Tattoy: {error}");
+            *socket =
+                tokio::net::UnixStream::connect(tattoy_twitch_tombh_plugin::SOCKET_PATH).await?;
+            drop(socket);
+            if !matches!(
+                message,
+                tattoy_twitch_tombh_plugin::BotMessage::Emotes { .. }
+            ) {
+                if let Err(error) = Box::pin(self.sync_emotes()).await {
+                    tracing::error!("Syncing emotes with Tattoy: {error:?}");
+                }
+            }
             self.tattoy_socket
                 .lock()
                 .await
                 .write_all(json.as_ref())
                 .await?;
-            tracing::info!("Message sent");
+        }
+        tracing::info!("Message sent");
+        Ok(())
+    }
+
+    /// `!note @user some text`. Moderators can keep private notes about mates. The notes are
+    /// only ever shown to moderators, never in public chat.
+    pub async fn note(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !self.is_moderator(payload).await? {
+            return Ok(());
+        }
+
+        let Some((user, text)) = arguments.and_then(|args| args.trim().split_once(' ')) else {
+            self.send_message_reply(&payload.message_id, "Usage: !note @user some text")
+                .await?;
+            return Ok(());
+        };
+        let user = user.trim_start_matches('@');
+
+        let Some(mate) = self.db.find_mate(user).await? else {
+            let message = format!("I haven't met {user} yet");
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        };
+
+        self.db
+            .add_note(
+                mate.id,
+                payload.chatter_user_id.as_str(),
+                payload.chatter_user_name.as_str(),
+                text.trim(),
+            )
+            .await?;
+        self.send_message_reply(&payload.message_id, "Noted 📝")
+            .await?;
+
+        Ok(())
+    }
+
+    /// The first time a mate with notes chats, show their notes to the broadcaster and whisper
+    /// them to the moderators that wrote them.
+    pub async fn show_notes(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let username = payload.chatter_user_name.as_str();
+        if !self
+            .noted_mates_seen
+            .lock()
+            .await
+            .insert(username.to_owned())
+        {
+            return Ok(());
+        }
+
+        let Some(mate) = self.db.find_mate(username).await? else {
+            return Ok(());
+        };
+        let notes = self.db.get_notes(mate.id).await?;
+        if notes.is_empty() {
+            return Ok(());
+        }
+
+        let summary = notes
+            .iter()
+            .map(|note| format!("• {} ({})", note.text, note.author_name))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self::onscreen_popup(format!(" \nNotes on {username}:\n{summary}"), "twitch-note")?;
+
+        for note in notes {
+            if note.author_id == crate::BOT_ID {
+                continue;
+            }
+            let message = format!("Your note on {username}: {}", note.text);
+            // Authors can have whispers blocked, which shouldn't stop the other notes.
+            if let Err(error) = self.send_whisper(&note.author_id, message.as_str()).await {
+                tracing::error!("Whispering a note to {}: {error:?}", note.author_name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `!config set command.discord.response "Join us!"`. Changes a config value and saves it
+    /// back to the config file on disk.
+    pub async fn config_command(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let maybe_setting = arguments
+            .and_then(|args| args.trim().strip_prefix("set "))
+            .and_then(|setting| setting.trim().split_once(char::is_whitespace));
+        let Some((key, value)) = maybe_setting else {
+            self.send_message_reply(&payload.message_id, "Usage: !config set <key> <value>")
+                .await?;
+            return Ok(());
         };
+
+        let path = match crate::config::Config::set_value(&self.opts, key, value.trim()) {
+            Ok(path) => path,
+            Err(error) => {
+                tracing::warn!("Setting config `{key}`: {error:?}");
+                let message = format!("Couldn't set {key}: {error}");
+                self.send_message_reply(&payload.message_id, message.as_str())
+                    .await?;
+                return Ok(());
+            }
+        };
+        tracing::info!("Config `{key}` set in {path:?}");
+
+        let message = match self.reload_config().await {
+            Ok(()) => format!("Updated {key} ✅"),
+            Err(error) => {
+                tracing::error!("Reloading config after setting `{key}`: {error:?}");
+                format!("Saved {key}, but couldn't reload the config: {error}")
+            }
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
         Ok(())
     }
 }
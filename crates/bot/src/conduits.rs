@@ -0,0 +1,197 @@
+//! Receiving events through an EventSub conduit, with `--transport conduit`. Subscriptions are
+//! made once, to the conduit, rather than to a websocket session, and the conduit shares the
+//! events between its shards. Each shard is its own websocket, so there's no cap on how many
+//! subscriptions there can be, and a shard disconnecting doesn't lose the others' events.
+//!
+//! When Twitch disables a shard, it's given its websocket's session again. If the websocket is
+//! dead, its keepalive timeout reconnects it and the new session is given to the shard instead.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+use futures::TryStreamExt as _;
+use twitch_api::{eventsub, types};
+use twitch_oauth2::TwitchToken as _;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConduitConfig {
+    /// How many websockets to spread the events over.
+    pub shards: usize,
+}
+
+impl Default for ConduitConfig {
+    fn default() -> Self {
+        Self { shards: 2 }
+    }
+}
+
+/// One of the conduit's shards, that a websocket gives its session to.
+#[derive(Debug, Clone)]
+pub struct ConduitShard {
+    pub conduit_id: String,
+    pub id: String,
+    /// The websocket session of every shard, keyed by shard ID.
+    pub sessions: std::sync::Arc<tokio::sync::Mutex<std::collections::HashMap<String, String>>>,
+}
+
+impl ConduitShard {
+    /// Send the shard's events to a websocket session.
+    pub async fn assign(
+        &self,
+        client: &twitch_api::HelixClient<'static, reqwest::Client>,
+        session_id: &str,
+    ) -> Result<()> {
+        let token = crate::tokens::app_access_token(client).await?;
+        let shard = eventsub::Shard::new(
+            self.id.clone(),
+            eventsub::Transport::websocket(session_id.to_owned()),
+        );
+        client
+            .update_conduit_shards(self.conduit_id.clone(), vec![shard], &token)
+            .await?;
+        self.sessions
+            .lock()
+            .await
+            .insert(self.id.clone(), session_id.to_owned());
+        tracing::info!("Conduit shard {} is on session {session_id}", self.id);
+        Ok(())
+    }
+}
+
+impl crate::bot::Bot {
+    /// Receive events from a conduit, with a websocket for each of its shards.
+    pub async fn conduit_eventer<Fut>(
+        &self,
+        connect_url: url::Url,
+        reconnections: &tokio::sync::mpsc::UnboundedSender<chrono::DateTime<chrono::Utc>>,
+        event_fn: impl FnMut(eventsub::Event, types::Timestamp, serde_json::Value) -> Fut + Copy,
+    ) -> Result<()>
+    where
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        let shard_count = self.config.read().await.conduit.shards.max(1);
+        let conduit_id = self.conduit(shard_count).await?;
+        self.subscribe_conduit(&conduit_id).await?;
+
+        let websockets = (0..shard_count).map(|shard| {
+            crate::websocket::ChatWebsocketClient {
+                session_id: None,
+                keepalive_timeout: None,
+                token: self.token.clone(),
+                client: self.client.clone(),
+                connect_url: connect_url.clone(),
+                reconnect_url: None,
+                chats: vec![self.broadcaster.clone()],
+                shard: Some(ConduitShard {
+                    conduit_id: conduit_id.clone(),
+                    id: shard.to_string(),
+                    sessions: self.conduit_sessions.clone(),
+                }),
+                dropped_at: None,
+                reconnections: Some(reconnections.clone()),
+            }
+            .run(event_fn)
+        });
+        futures::future::try_join_all(websockets).await?;
+        Ok(())
+    }
+
+    /// The ID of the bot's conduit, made with the right number of shards. Twitch only allows a
+    /// few conduits per app, so an existing one is reused.
+    async fn conduit(&self, shard_count: usize) -> Result<String> {
+        let token = crate::tokens::app_access_token(&self.client).await?;
+        let shard_count = shard_count.try_into()?;
+        let conduit = match self.client.get_conduits(&token).await?.into_iter().next() {
+            Some(conduit) if conduit.shard_count == shard_count => conduit,
+            Some(conduit) => {
+                self.client
+                    .update_conduit(conduit.id, shard_count, &token)
+                    .await?
+            }
+            None => self.client.create_conduit(shard_count, &token).await?,
+        };
+        tracing::info!(
+            "Using conduit {} with {} shards",
+            conduit.id,
+            conduit.shard_count
+        );
+        Ok(conduit.id.to_string())
+    }
+
+    /// Subscribe the conduit to every event, unless it already is. Subscriptions to a conduit
+    /// last between restarts.
+    async fn subscribe_conduit(&self, conduit_id: &str) -> Result<()> {
+        let token = crate::tokens::app_access_token(&self.client).await?;
+        let existing: Vec<_> =
+            self.client
+                .get_eventsub_subscriptions(Some(eventsub::Status::Enabled), None, None, &token)
+                .map_ok(|response| {
+                    futures::stream::iter(
+                        response
+                            .subscriptions
+                            .into_iter()
+                            .filter(|subscription| {
+                                subscription.transport.as_conduit().is_some_and(|conduit| {
+                                    conduit.conduit_id.as_str() == conduit_id
+                                })
+                            })
+                            .map(Ok::<_, eyre::Report>),
+                    )
+                })
+                .try_flatten()
+                .try_collect()
+                .await?;
+        if !existing.is_empty() {
+            return Ok(());
+        }
+
+        let transport = eventsub::Transport::conduit(conduit_id);
+        crate::subscriptions::create_subscriptions(
+            &self.client,
+            transport.clone(),
+            &types::UserId::new(crate::BOT_ID.to_owned()),
+            &token,
+        )
+        .await?;
+        self.client
+            .create_eventsub_subscription(
+                eventsub::conduit::ConduitShardDisabledV1::client_id(token.client_id().clone()),
+                transport,
+                &token,
+            )
+            .await?;
+        tracing::info!("Subscribed conduit {conduit_id} to events");
+        Ok(())
+    }
+
+    /// Give a disabled shard its websocket's session again.
+    pub async fn conduit_shard_disabled(
+        &self,
+        payload: &eventsub::conduit::ConduitShardDisabledV1Payload,
+    ) -> Result<()> {
+        let shard_id = payload.shard_id.to_string();
+        tracing::warn!(
+            "Conduit shard {shard_id} was disabled: {:?}",
+            payload.status
+        );
+        let session_id = self
+            .conduit_sessions
+            .lock()
+            .await
+            .get(&shard_id)
+            .cloned()
+            .context("No websocket session for the disabled shard")?;
+        let shard = ConduitShard {
+            conduit_id: payload.conduit_id.to_string(),
+            id: shard_id,
+            sessions: self.conduit_sessions.clone(),
+        };
+        if let Err(error) = shard.assign(&self.client, &session_id).await {
+            tracing::warn!(
+                "Couldn't reassign the shard, it'll get a new session when its websocket \
+                 reconnects: {error:?}"
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,499 @@
+use color_eyre::Result;
+use eyre::WrapErr as _;
+use figment::{
+    providers::{Env, Format as _, Serialized, Toml},
+    Figment,
+};
+
+/// The prefix for ENV vars that override config values. Nested keys are separated by a double
+/// underscore, eg: `TBHBOT_MIN_FOLLOW_HOURS__CHIRP=0`.
+const ENV_PREFIX: &str = "TBHBOT_";
+
+/// How long to wait for more changes to the config files before reloading, editors often
+/// write a file in more than one go.
+const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Other config files to merge in, relative to the main config file. Globs are supported,
+    /// eg: `include = ["commands/*.toml"]`. Lists like `[[command]]` are combined rather than
+    /// replaced. Included files can't include other files.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub database: crate::database::DatabaseConfig,
+    #[serde(default)]
+    pub command: Vec<Command>,
+    #[serde(default)]
+    pub redemption: Vec<crate::redemptions::Redemption>,
+    #[serde(default)]
+    pub reward: Vec<crate::rewards::Reward>,
+    /// Alert themes, by name. See `themes::Theme`.
+    #[serde(default)]
+    pub theme: std::collections::HashMap<String, crate::themes::Theme>,
+    #[serde(default)]
+    pub stream_summary: crate::streams::SummaryConfig,
+    #[serde(default)]
+    pub captions: crate::captions::CaptionsConfig,
+    #[serde(default)]
+    pub highlight: crate::classifier::HighlightConfig,
+    #[serde(default)]
+    pub hourly_stats: crate::stats::HourlyStatsConfig,
+    #[serde(default)]
+    pub crosspost: crate::crossposts::CrosspostConfig,
+    #[serde(default)]
+    pub tty: crate::commands::TtyConfig,
+    #[serde(default)]
+    pub emote_rain: crate::emotes::EmoteRainConfig,
+    #[serde(default)]
+    pub discord: crate::discord::DiscordConfig,
+    #[serde(default)]
+    pub cheer_tier: Vec<crate::cheers::CheerTier>,
+    #[serde(default)]
+    pub tips: crate::tips::TipsConfig,
+    #[serde(default)]
+    pub pomodoro: crate::pomodoro::PomodoroConfig,
+    #[serde(default)]
+    pub youtube: crate::youtube::YoutubeConfig,
+    #[serde(default)]
+    pub presence: crate::presence::PresenceConfig,
+    #[serde(default)]
+    pub timer: crate::timers::TimerConfig,
+    #[serde(default)]
+    pub github: crate::github::GithubConfig,
+    #[serde(default)]
+    pub raffle: crate::raffles::RaffleConfig,
+    #[serde(default)]
+    pub quiet_hours: crate::quiet_hours::QuietHoursConfig,
+    #[serde(default)]
+    pub bug_reports: crate::bug_reports::BugReportConfig,
+    #[serde(default)]
+    pub tokens: crate::tokens::TokensConfig,
+    #[serde(default)]
+    pub eventsub_webhook: crate::eventsub_webhook::EventsubWebhookConfig,
+    #[serde(default)]
+    pub conduit: crate::conduits::ConduitConfig,
+    #[serde(default)]
+    pub event_log: crate::recordings::EventLogConfig,
+    #[serde(default)]
+    pub plugins: crate::plugins::PluginsConfig,
+    #[serde(default)]
+    pub dashboard: crate::dashboard::DashboardConfig,
+    #[serde(default)]
+    pub http_api: crate::http_api::HttpApiConfig,
+    #[serde(default)]
+    pub overlay: crate::overlay::OverlayConfig,
+    #[serde(default)]
+    pub achievement: Vec<crate::achievements::AchievementConfig>,
+    #[serde(default)]
+    pub webhook: Vec<crate::webhooks::Webhook>,
+    /// The minimum number of hours a viewer must have followed for before they can use the
+    /// given command, eg `chirp = 24`.
+    #[serde(default)]
+    pub min_follow_hours: std::collections::HashMap<String, u64>,
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Command {
+    pub trigger: Vec<Trigger>,
+    /// What to reply with. Without a response the entry only holds the settings for the
+    /// built-in command of the same name, eg: a `cooldown` for `!chirp`.
+    pub response: Option<String>,
+    /// Other command words that run the command, eg: `aliases = ["tweet"]` for `!chirp`.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Seconds before anybody can use the command again.
+    pub cooldown: Option<u64>,
+    /// Who can use the command.
+    #[serde(default)]
+    pub permission: Permission,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// A sound to play whenever the command is used.
+    pub sound: Option<std::path::PathBuf>,
+}
+
+const fn default_enabled() -> bool {
+    true
+}
+
+impl Command {
+    /// The command's own name, its first command word.
+    pub fn name(&self) -> Option<&str> {
+        self.trigger.iter().find_map(|trigger| match trigger {
+            Trigger::Word(word) => Some(word.as_str()),
+            Trigger::Pattern(_) => None,
+        })
+    }
+
+    /// Whether `!command` runs this command, either through a trigger or an alias.
+    pub fn is_called(&self, command: &str) -> bool {
+        self.aliases.iter().any(|alias| alias == command)
+            || self
+                .trigger
+                .iter()
+                .any(|trigger| matches!(trigger, Trigger::Word(word) if word == command))
+    }
+}
+
+/// Who can use a command. Each level includes the ones above it, so moderators can use
+/// follower commands and the broadcaster can use everything.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    #[default]
+    Everyone,
+    Follower,
+    Moderator,
+    Broadcaster,
+}
+
+/// What makes a text command respond.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub enum Trigger {
+    /// A command word, eg: `lurk` for `!lurk`.
+    Word(String),
+    /// A regex, written between slashes, eg: `/(?i)good morning/`. It's matched against the
+    /// whole of any message that isn't a `!command`.
+    Pattern(regex::Regex),
+}
+
+impl TryFrom<String> for Trigger {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let maybe_pattern = text
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+            .filter(|pattern| !pattern.is_empty());
+        match maybe_pattern {
+            Some(pattern) => regex::Regex::new(pattern)
+                .map(Self::Pattern)
+                .map_err(|error| format!("Bad regex in command trigger `{text}`: {error}")),
+            None => Ok(Self::Word(text)),
+        }
+    }
+}
+
+impl From<Trigger> for String {
+    fn from(trigger: Trigger) -> Self {
+        match trigger {
+            Trigger::Word(word) => word,
+            Trigger::Pattern(pattern) => format!("/{}/", pattern.as_str()),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from its layers, each one overriding the last:
+    ///   1. The built-in defaults.
+    ///   2. The `config.toml` file, and any files it includes.
+    ///   3. `TBHBOT_*` ENV vars.
+    ///   4. `--set key=value` CLI arguments.
+    pub fn load(cli: &crate::Cli) -> Result<Self> {
+        Self::load_with(cli, None)
+    }
+
+    /// Load the config, but with `edited` standing in for what's on disk for one of its files.
+    /// It's how edits are checked before they're saved.
+    fn load_with(cli: &crate::Cli, edited: Option<(&std::path::Path, &str)>) -> Result<Self> {
+        let path = Self::path(cli);
+        let file = |file: &std::path::Path| match edited {
+            Some((edited_path, contents)) if edited_path == file => Toml::string(contents),
+            _ => Toml::file_exact(file),
+        };
+
+        let mut figment = Figment::from(Serialized::defaults(Self::default())).merge(file(&path));
+        for included in Self::included_files(&path)? {
+            tracing::debug!("Including config file: {included:?}");
+            figment = figment.admerge(file(&included));
+        }
+        figment = figment.merge(Env::prefixed(ENV_PREFIX).split("__"));
+
+        for setting in &cli.set {
+            let Some((key, value)) = setting.split_once('=') else {
+                eyre::bail!("Config override `{setting}` should be in the form `key=value`");
+            };
+            figment = figment.merge(Toml::string(&format!("{} = {}", key.trim(), value.trim())));
+        }
+
+        figment
+            .extract()
+            .wrap_err_with(|| format!("Failed to load config from {}", path.display()))
+    }
+
+    /// The path to the main config file.
+    pub fn path(cli: &crate::Cli) -> std::path::PathBuf {
+        cli.config
+            .clone()
+            .unwrap_or_else(|| crate::workspace_dir().join("config.toml"))
+    }
+
+    /// Change a value in the config files on disk, preserving their comments and formatting.
+    /// Returns the path of the file that was changed.
+    ///
+    /// Commands are addressed by one of their triggers, eg: `command.discord.response`. The
+    /// command is edited in whichever file defines it, or added to the main config file if it
+    /// doesn't exist yet. Any other key, eg: `min_follow_hours.chirp`, is set in the main
+    /// config file.
+    ///
+    /// The value is parsed as TOML, falling back to a plain string. The whole config is loaded
+    /// with the change before it's saved, so that a bad value can't stop the bot from starting.
+    pub fn set_value(cli: &crate::Cli, key: &str, value: &str) -> Result<std::path::PathBuf> {
+        let value = value
+            .parse::<toml_edit::Value>()
+            .unwrap_or_else(|_| value.into());
+        let (path, document) = Self::edit_document(cli, key, value)?;
+
+        let contents = document.to_string();
+        Self::load_with(cli, Some((&path, &contents)))
+            .wrap_err_with(|| format!("Not saving `{key}`, the config would be broken"))?;
+        Self::write_document(&path, &document)?;
+        Ok(path)
+    }
+
+    /// Make the change for `set_value()` to the file that it belongs in, without saving it.
+    fn edit_document(
+        cli: &crate::Cli,
+        key: &str,
+        value: toml_edit::Value,
+    ) -> Result<(std::path::PathBuf, toml_edit::DocumentMut)> {
+        let main_path = Self::path(cli);
+
+        if let Some(rest) = key.strip_prefix("command.") {
+            let Some((trigger, field)) = rest.split_once('.') else {
+                eyre::bail!("Command keys look like: command.<trigger>.<field>");
+            };
+
+            let mut files = vec![main_path.clone()];
+            files.append(&mut Self::included_files(&main_path)?);
+            for path in files {
+                let mut document = Self::read_document(&path)?;
+                let Some(commands) = document
+                    .get_mut("command")
+                    .and_then(|item| item.as_array_of_tables_mut())
+                else {
+                    continue;
+                };
+                let Some(command) = commands.iter_mut().find(|command| {
+                    command
+                        .get("trigger")
+                        .and_then(|triggers| triggers.as_array())
+                        .is_some_and(|triggers| {
+                            triggers.iter().any(|t| t.as_str() == Some(trigger))
+                        })
+                }) else {
+                    continue;
+                };
+
+                command[field] = toml_edit::Item::Value(value);
+                return Ok((path, document));
+            }
+
+            let mut document = Self::read_document(&main_path)?;
+            let mut command = toml_edit::Table::new();
+            command["trigger"] = toml_edit::value(toml_edit::Array::from_iter([trigger]));
+            command[field] = toml_edit::Item::Value(value);
+            if document.get("command").is_none() {
+                document["command"] =
+                    toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new());
+            }
+            document["command"]
+                .as_array_of_tables_mut()
+                .ok_or_else(|| eyre::eyre!("`command` in the config isn't a list"))?
+                .push(command);
+            return Ok((main_path, document));
+        }
+
+        let mut document = Self::read_document(&main_path)?;
+        let parts = key.split('.').collect::<Vec<_>>();
+        let Some((last, parents)) = parts.split_last() else {
+            eyre::bail!("No config key given");
+        };
+        let mut table: &mut dyn toml_edit::TableLike = document.as_table_mut();
+        for part in parents {
+            if !table.contains_key(part) {
+                table.insert(part, toml_edit::Item::Table(toml_edit::Table::new()));
+            }
+            table = table
+                .get_mut(part)
+                .and_then(toml_edit::Item::as_table_like_mut)
+                .ok_or_else(|| eyre::eyre!("`{part}` in `{key}` isn't a table"))?;
+        }
+        table.insert(last, toml_edit::Item::Value(value));
+        Ok((main_path, document))
+    }
+
+    fn read_document(path: &std::path::Path) -> Result<toml_edit::DocumentMut> {
+        let contents = std::fs::read_to_string(path)?;
+        contents
+            .parse()
+            .wrap_err_with(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Write via a temporary file so that a crash can't leave a half-written config.
+    fn write_document(path: &std::path::Path, document: &toml_edit::DocumentMut) -> Result<()> {
+        let temporary = path.with_extension("toml.tmp");
+        std::fs::write(&temporary, document.to_string())?;
+        std::fs::rename(temporary, path)?;
+        Ok(())
+    }
+
+    /// Resolve the `include` globs of the given config file.
+    fn included_files(path: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+        let includes: Vec<String> = Figment::from(Toml::file_exact(path))
+            .extract_inner("include")
+            .unwrap_or_default();
+        let directory = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+
+        let mut files = Vec::new();
+        for include in includes {
+            let pattern = directory.join(&include);
+            let mut matches = glob::glob(&pattern.to_string_lossy())
+                .wrap_err_with(|| format!("Bad config include pattern: {include}"))?
+                .collect::<Result<Vec<_>, _>>()?;
+            if matches.is_empty() {
+                tracing::warn!("Config include `{include}` didn't match any files");
+            }
+            matches.sort();
+            files.append(&mut matches);
+        }
+
+        Ok(files)
+    }
+
+    /// The directories to watch for changes to the config files, and whether to watch them
+    /// recursively. Directories are watched rather than the files themselves, because editors
+    /// often save by replacing a file, and so that new files matching an `include` are noticed.
+    fn watched_directories(
+        cli: &crate::Cli,
+    ) -> std::collections::BTreeMap<std::path::PathBuf, bool> {
+        let path = Self::path(cli);
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => std::path::PathBuf::from("."),
+        };
+        let includes: Vec<String> = Figment::from(Toml::file_exact(&path))
+            .extract_inner("include")
+            .unwrap_or_default();
+
+        let mut directories = std::collections::BTreeMap::from([(directory.clone(), false)]);
+        for include in includes {
+            let pattern = directory.join(&include);
+            let Some(parent) = pattern.parent() else {
+                continue;
+            };
+            // Globs in the directory part, like `commands/**/*.toml`, can match files in any
+            // of the directories under the part before them.
+            let fixed = parent
+                .components()
+                .take_while(|part| !part.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+                .collect::<std::path::PathBuf>();
+            let is_recursive = fixed != parent;
+            let recursive = directories.entry(fixed).or_default();
+            *recursive |= is_recursive;
+        }
+
+        directories
+    }
+
+    /// The config file and all the files it includes, with when they were last modified.
+    /// Files that can't be read are left out, so that deleting a file counts as a change.
+    fn modified_times(cli: &crate::Cli) -> Vec<(std::path::PathBuf, std::time::SystemTime)> {
+        let path = Self::path(cli);
+        let mut files = vec![path.clone()];
+        files.append(&mut Self::included_files(&path).unwrap_or_default());
+        files
+            .into_iter()
+            .filter_map(|file| {
+                let modified = std::fs::metadata(&file).and_then(|meta| meta.modified());
+                modified.ok().map(|modified| (file, modified))
+            })
+            .collect()
+    }
+}
+
+impl crate::bot::Bot {
+    /// Load the config from disk again and swap it in.
+    pub async fn reload_config(&self) -> Result<()> {
+        let config = Config::load(&self.opts)?;
+        crate::quiet_hours::set_quiet_hours(&config.quiet_hours);
+        *self.config.write().await = config;
+        Ok(())
+    }
+
+    /// Reload the config whenever its files change, so that things like new text commands
+    /// work without restarting the bot. A config with mistakes in it is logged and ignored.
+    ///
+    /// Some settings, like for tips and YouTube, are only read when the bot starts, so they
+    /// still need a restart.
+    pub async fn config_watcher(&self) -> Result<()> {
+        let (changes, mut changed) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                // Sending only fails when the bot is stopping, which is fine.
+                let _ = changes.send(event);
+            })?;
+        let mut watched = std::collections::BTreeMap::new();
+        watch_config_directories(&mut watcher, &mut watched, &self.opts);
+
+        // Other files in the watched directories change too, so only a change to the
+        // modification times of the config files themselves reloads the config.
+        let mut previous = Config::modified_times(&self.opts);
+        while let Some(event) = changed.recv().await {
+            if let Err(error) = event {
+                tracing::warn!("Watching the config files: {error:?}");
+                continue;
+            }
+            tokio::time::sleep(WATCH_DEBOUNCE).await;
+            while changed.try_recv().is_ok() {}
+
+            let current = Config::modified_times(&self.opts);
+            if current == previous {
+                continue;
+            }
+            previous = current;
+
+            match self.reload_config().await {
+                Ok(()) => tracing::info!("Reloaded config"),
+                Err(error) => tracing::error!("Reloading config, keeping the old one: {error:?}"),
+            }
+            // The config's `include`s might have changed.
+            watch_config_directories(&mut watcher, &mut watched, &self.opts);
+        }
+
+        Ok(())
+    }
+}
+
+/// Start watching any config directories that aren't already being watched. A directory that
+/// can't be watched, like an `include` of one that doesn't exist yet, is tried again the next
+/// time that the config is reloaded.
+fn watch_config_directories(
+    watcher: &mut impl notify::Watcher,
+    watched: &mut std::collections::BTreeMap<std::path::PathBuf, bool>,
+    cli: &crate::Cli,
+) {
+    for (directory, is_recursive) in Config::watched_directories(cli) {
+        if watched.get(&directory) == Some(&is_recursive) {
+            continue;
+        }
+        let mode = if is_recursive {
+            notify::RecursiveMode::Recursive
+        } else {
+            notify::RecursiveMode::NonRecursive
+        };
+        match watcher.watch(&directory, mode) {
+            Ok(()) => {
+                watched.insert(directory, is_recursive);
+            }
+            Err(error) => tracing::warn!(
+                "Can't watch {} for config changes: {error:?}",
+                directory.display()
+            ),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Cross-post go-live announcements, and optionally end-of-stream summaries, to Mastodon and
+//! Bluesky.
+
+use color_eyre::Result;
+
+/// Where Bluesky posts are made.
+const BLUESKY_SERVICE: &str = "https://bsky.social";
+
+/// When the last go-live announcement was cross-posted.
+const LAST_GO_LIVE_SETTING: &str = "crossposted_go_live_at";
+
+/// When the last stream summary was cross-posted.
+const LAST_SUMMARY_SETTING: &str = "crossposted_summary_at";
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct CrosspostConfig {
+    /// The post. `{title}`, `{category}` and `{link}` are replaced with the stream's details.
+    pub template: String,
+    /// A post for the end of the stream. `{messages}`, `{chatters}`, `{follows}` and
+    /// `{top_emote}` are replaced with the stream's stats. Nothing is posted if unset.
+    pub summary_template: Option<String>,
+    /// Don't post the same kind of post again within this many minutes, so that a stream
+    /// dropping and coming back doesn't spam followers.
+    pub min_gap_minutes: i64,
+    /// Only log and show on screen what would be posted, without actually posting anything.
+    pub dry_run: bool,
+    pub mastodon: MastodonConfig,
+    pub bluesky: BlueskyConfig,
+}
+
+impl Default for CrosspostConfig {
+    fn default() -> Self {
+        Self {
+            template: "🔴 Live now: {title} ({category}) {link}".to_owned(),
+            summary_template: None,
+            min_gap_minutes: 120,
+            dry_run: false,
+            mastodon: MastodonConfig::default(),
+            bluesky: BlueskyConfig::default(),
+        }
+    }
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct MastodonConfig {
+    pub enabled: bool,
+    /// The URL of the Mastodon server, eg: `https://mastodon.social`.
+    pub instance: String,
+    /// An access token with the `write:statuses` scope.
+    pub token: String,
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct BlueskyConfig {
+    pub enabled: bool,
+    /// The account's handle, eg: `tombh.bsky.social`.
+    pub handle: String,
+    /// An app password, made in Bluesky's settings.
+    pub app_password: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlueskySession {
+    access_jwt: String,
+    did: String,
+}
+
+impl crate::bot::Bot {
+    /// Let the world know that the stream has started.
+    pub async fn crosspost_go_live(
+        &self,
+        payload: &crate::eventsub::stream::StreamOnlineV1Payload,
+    ) -> Result<()> {
+        let config = self.config.read().await.crosspost.clone();
+        if !config.mastodon.enabled && !config.bluesky.enabled {
+            return Ok(());
+        }
+
+        let token = self.token.lock().await.clone();
+        let channel = self
+            .client
+            .get_channel_from_id(&payload.broadcaster_user_id, &token)
+            .await?;
+        let (title, category) = channel
+            .map(|channel| (channel.title, channel.game_name.to_string()))
+            .unwrap_or_default();
+        let link = format!("https://twitch.tv/{}", payload.broadcaster_user_login);
+        let post = config
+            .template
+            .replace("{title}", &title)
+            .replace("{category}", &category)
+            .replace("{link}", &link);
+
+        self.crosspost(&config, &post, Some(&link), LAST_GO_LIVE_SETTING)
+            .await
+    }
+
+    /// Let the world know how the stream went.
+    pub async fn crosspost_summary(&self, summary: &crate::database::StreamSummary) -> Result<()> {
+        let config = self.config.read().await.crosspost.clone();
+        if !config.mastodon.enabled && !config.bluesky.enabled {
+            return Ok(());
+        }
+        let Some(template) = &config.summary_template else {
+            return Ok(());
+        };
+
+        let top_emote = summary
+            .top_emotes
+            .first()
+            .map(|(emote, _)| emote.clone())
+            .unwrap_or_default();
+        let post = template
+            .replace("{messages}", &summary.messages.to_string())
+            .replace("{chatters}", &summary.unique_chatters.to_string())
+            .replace("{follows}", &summary.follows.to_string())
+            .replace("{top_emote}", &top_emote);
+
+        self.crosspost(&config, &post, None, LAST_SUMMARY_SETTING)
+            .await
+    }
+
+    /// Post to every enabled service, unless the same kind of post, as identified by its
+    /// setting, was made too recently.
+    async fn crosspost(
+        &self,
+        config: &CrosspostConfig,
+        post: &str,
+        link: Option<&str>,
+        setting: &str,
+    ) -> Result<()> {
+        let now = chrono::Utc::now();
+        if let Some(last_posted_at) = self
+            .posted_recently(setting, config.min_gap_minutes)
+            .await?
+        {
+            tracing::info!("Not cross-posting, the last one was at {last_posted_at}: {post}");
+            return Ok(());
+        }
+
+        if crate::killswitch::stays_local() {
+            tracing::info!("Log-only, not cross-posting: {post}");
+            return Ok(());
+        }
+        if config.dry_run {
+            tracing::info!("Would have cross-posted: {post}");
+            Self::onscreen_popup(
+                format!(" \nCross-post preview:\n{post}"),
+                "twitch-crosspost",
+            )?;
+            return Ok(());
+        }
+
+        // Saved before posting, so that a post that fails part way isn't retried over and over.
+        self.db.set_setting(setting, &now.to_rfc3339()).await?;
+
+        let http = reqwest::Client::new();
+        if config.mastodon.enabled {
+            if let Err(error) = Self::post_to_mastodon(&http, &config.mastodon, post).await {
+                tracing::error!("Posting to Mastodon: {error:?}");
+            }
+        }
+        if config.bluesky.enabled {
+            if let Err(error) = Self::post_to_bluesky(&http, &config.bluesky, post, link).await {
+                tracing::error!("Posting to Bluesky: {error:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// When a post was last made, as saved in `setting`, if it was within the past
+    /// `min_gap_minutes`.
+    pub async fn posted_recently(
+        &self,
+        setting: &str,
+        min_gap_minutes: i64,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        let last_posted_at = self
+            .db
+            .get_setting(setting)
+            .await?
+            .and_then(|value| chrono::DateTime::parse_from_rfc3339(&value).ok())
+            .map(|last_posted_at| last_posted_at.to_utc());
+        Ok(last_posted_at.filter(|last_posted_at| {
+            chrono::Utc::now() - *last_posted_at < chrono::Duration::minutes(min_gap_minutes)
+        }))
+    }
+
+    async fn post_to_mastodon(
+        http: &reqwest::Client,
+        config: &MastodonConfig,
+        post: &str,
+    ) -> Result<()> {
+        let url = format!("{}/api/v1/statuses", config.instance.trim_end_matches('/'));
+        http.post(url)
+            .bearer_auth(&config.token)
+            .json(&serde_json::json!({ "status": post }))
+            .send()
+            .await?
+            .error_for_status()?;
+        tracing::info!("Posted to Mastodon: {post}");
+
+        Ok(())
+    }
+
+    async fn post_to_bluesky(
+        http: &reqwest::Client,
+        config: &BlueskyConfig,
+        post: &str,
+        link: Option<&str>,
+    ) -> Result<()> {
+        let session: BlueskySession = http
+            .post(format!(
+                "{BLUESKY_SERVICE}/xrpc/com.atproto.server.createSession"
+            ))
+            .json(&serde_json::json!({
+                "identifier": config.handle,
+                "password": config.app_password,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Bluesky doesn't turn URLs into links by itself, they have to be marked up with byte
+        // offsets.
+        let mut record = serde_json::json!({
+            "$type": "app.bsky.feed.post",
+            "text": post,
+            "createdAt": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        });
+        if let Some((start, link)) = link.and_then(|link| Some((post.find(link)?, link))) {
+            record["facets"] = serde_json::json!([{
+                "index": { "byteStart": start, "byteEnd": start + link.len() },
+                "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": link }],
+            }]);
+        }
+
+        http.post(format!(
+            "{BLUESKY_SERVICE}/xrpc/com.atproto.repo.createRecord"
+        ))
+        .bearer_auth(session.access_jwt)
+        .json(&serde_json::json!({
+            "repo": session.did,
+            "collection": "app.bsky.feed.post",
+            "record": record,
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+        tracing::info!("Posted to Bluesky: {post}");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+//! A web page for keeping an eye on the bot during a stream, without having to SSH into the box.
+//! It shows recent chat, which commands get used, the cooldowns that are running and the
+//! earliest birds leaderboard, and has buttons for testing the alerts.
+//!
+//! Every request needs the configured token, either as `Authorization: Bearer <token>` or as a
+//! cookie. Opening `/?token=<token>` once sets the cookie. Requests are also only allowed for the
+//! dashboard's own host names, so that other websites can't reach it by pointing their own domain
+//! at it. Buttons only work from the dashboard itself, so that other websites can't press them.
+
+use axum::response::IntoResponse as _;
+use color_eyre::Result;
+use std::fmt::Write as _;
+
+/// How many chat messages to show.
+const RECENT_MESSAGES: u32 = 30;
+
+/// How many commands to show the usage of.
+const COMMAND_USAGE: u32 = 15;
+
+/// How many earliest birds to show.
+const LEADERBOARD_LENGTH: u32 = 10;
+
+/// How often the page refreshes itself, in seconds.
+const REFRESH_SECONDS: u32 = 10;
+
+/// The cookie that holds the token, once it's been given in the URL.
+const COOKIE: &str = "tbhbot_dashboard";
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct DashboardConfig {
+    pub enabled: bool,
+    /// The address to serve the dashboard on.
+    pub listen: String,
+    /// What requests have to send to be allowed, at least 16 characters. It's a secret, so it's
+    /// set with `TBHBOT_DASHBOARD__TOKEN` rather than in the config file.
+    pub token: String,
+    /// Other names the dashboard is reached by, with their port, eg: `streambox.lan:8089`.
+    /// `listen` is always allowed.
+    pub hosts: Vec<String>,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:8089".to_owned(),
+            token: String::new(),
+            hosts: Vec::new(),
+        }
+    }
+}
+
+/// What the web server asks the bot for.
+enum Request {
+    Page(tokio::sync::oneshot::Sender<String>),
+    TestAlert(String),
+}
+
+/// Shared by the web server's requests.
+struct DashboardState {
+    token: String,
+    /// The `Host` headers that requests are allowed to have.
+    hosts: Vec<String>,
+    requests: tokio::sync::mpsc::UnboundedSender<Request>,
+}
+
+/// The token, when it's given in the URL.
+#[derive(serde_derive::Deserialize)]
+struct Login {
+    token: Option<String>,
+}
+
+impl crate::bot::Bot {
+    /// Serve the dashboard, if it's enabled.
+    pub async fn dashboard(&self) -> Result<()> {
+        let config = self.config.read().await.dashboard.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+
+        if config.token.len() < 16 {
+            eyre::bail!("The dashboard token should be at least 16 characters long");
+        }
+
+        let (requests, mut queue) = tokio::sync::mpsc::unbounded_channel();
+        let mut hosts = config.hosts.clone();
+        hosts.push(config.listen.clone());
+        let state = std::sync::Arc::new(DashboardState {
+            token: config.token.clone(),
+            hosts,
+            requests,
+        });
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(page))
+            .route("/test/{kind}", axum::routing::post(test_alert))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind(&config.listen).await?;
+        tracing::info!("Serving the dashboard on http://{}", config.listen);
+
+        let server = async {
+            axum::serve(listener, app).await?;
+            Ok::<(), eyre::Report>(())
+        };
+        let handler = async {
+            while let Some(request) = queue.recv().await {
+                match request {
+                    Request::Page(reply) => {
+                        let html = match self.dashboard_page().await {
+                            Ok(html) => html,
+                            Err(error) => {
+                                tracing::error!("Dashboard page: {error:?}");
+                                format!("<p>Error: {}</p>", escape(&error.to_string()))
+                            }
+                        };
+                        let _ = reply.send(html);
+                    }
+                    Request::TestAlert(kind) => {
+                        let result = match kind.as_str() {
+                            "follow" => self.new_follower(&crate::previews::made_up_follow()).await,
+                            "raid" => self.incoming_raid(&crate::previews::made_up_raid(10)).await,
+                            "sub" => {
+                                self.new_subscriber(&crate::previews::made_up_subscription(1))
+                                    .await
+                            }
+                            _ => Ok(()),
+                        };
+                        if let Err(error) = result {
+                            tracing::error!("Dashboard test alert: {error:?}");
+                        }
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        tokio::try_join!(server, handler)?;
+        Ok(())
+    }
+
+    async fn dashboard_page(&self) -> Result<String> {
+        let mut html = format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+             <meta http-equiv=\"refresh\" content=\"{REFRESH_SECONDS}\">\
+             <title>tbhbot</title></head><body><h1>tbhbot</h1>"
+        );
+
+        html.push_str("<h2>Test alerts</h2>");
+        for kind in ["follow", "raid", "sub"] {
+            write!(
+                html,
+                "<form method=\"post\" action=\"/test/{kind}\" style=\"display: inline\">\
+                 <button>{kind}</button></form> "
+            )?;
+        }
+
+        html.push_str("<h2>Chat</h2><table>");
+        for (timestamp, username, text) in self.db.recent_messages(RECENT_MESSAGES).await? {
+            write!(
+                html,
+                "<tr><td>{}</td><td><b>{}</b></td><td>{}</td></tr>",
+                escape(&timestamp),
+                escape(&username),
+                escape(&text)
+            )?;
+        }
+        html.push_str("</table>");
+
+        html.push_str("<h2>Command usage</h2><table>");
+        for (command, uses) in self.db.command_usage(COMMAND_USAGE).await? {
+            write!(
+                html,
+                "<tr><td>{}</td><td>{uses}</td></tr>",
+                escape(&command)
+            )?;
+        }
+        html.push_str("</table>");
+
+        html.push_str("<h2>Cooldowns</h2><table>");
+        let registry = self.command_registry().await;
+        for (name, last_used) in self.command_cooldowns.lock().await.iter() {
+            let Some(cooldown) = registry.find(name).and_then(|command| command.cooldown()) else {
+                continue;
+            };
+            let remaining = cooldown.saturating_sub(last_used.elapsed());
+            if !remaining.is_zero() {
+                write!(
+                    html,
+                    "<tr><td>!{}</td><td>{}s</td></tr>",
+                    escape(name),
+                    remaining.as_secs()
+                )?;
+            }
+        }
+        html.push_str("</table>");
+
+        html.push_str("<h2>Earliest birds this month</h2><ol>");
+        let now = chrono::Utc::now();
+        let birds = self
+            .db
+            .earliest_birds(
+                crate::arrivals::start_of_month(now),
+                now,
+                LEADERBOARD_LENGTH,
+            )
+            .await?;
+        for (_, username, points) in birds {
+            write!(html, "<li>{} ({points})</li>", escape(&username))?;
+        }
+        html.push_str("</ol></body></html>");
+
+        Ok(html)
+    }
+}
+
+async fn page(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<DashboardState>>,
+    axum::extract::Query(login): axum::extract::Query<Login>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    if let Some(token) = login.token {
+        if !is_allowed_host(&state, &headers)
+            || !crate::http_api::is_same_token(&token, &state.token)
+        {
+            tracing::warn!("Dashboard login with the wrong token or host");
+            return axum::http::StatusCode::UNAUTHORIZED.into_response();
+        }
+        // Redirecting takes the token out of the address bar and the browser's history.
+        let cookie = format!("{COOKIE}={token}; Path=/; HttpOnly; SameSite=Strict");
+        return (
+            [(axum::http::header::SET_COOKIE, cookie)],
+            axum::response::Redirect::to("/"),
+        )
+            .into_response();
+    }
+    if let Err(response) = authorise(&state, &headers) {
+        return response;
+    }
+
+    let (reply, html) = tokio::sync::oneshot::channel();
+    if state.requests.send(Request::Page(reply)).is_err() {
+        return axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    match html.await {
+        Ok(html) => axum::response::Html(html).into_response(),
+        Err(_) => axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+async fn test_alert(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<DashboardState>>,
+    axum::extract::Path(kind): axum::extract::Path<String>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    if let Err(response) = authorise(&state, &headers) {
+        return response;
+    }
+    if !is_same_origin(&headers) {
+        tracing::warn!("Dashboard request from another website");
+        return axum::http::StatusCode::FORBIDDEN.into_response();
+    }
+    if state.requests.send(Request::TestAlert(kind)).is_err() {
+        return axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    axum::response::Redirect::to("/").into_response()
+}
+
+/// Check that a request is for one of the dashboard's own hosts, and has the token.
+fn authorise(
+    state: &DashboardState,
+    headers: &axum::http::HeaderMap,
+) -> Result<(), axum::response::Response> {
+    if !is_allowed_host(state, headers) {
+        tracing::warn!("Dashboard request for another host");
+        return Err(axum::http::StatusCode::FORBIDDEN.into_response());
+    }
+    let has_cookie = headers
+        .get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix(&format!("{COOKIE}=")))
+        .any(|token| crate::http_api::is_same_token(token, &state.token));
+    if !has_cookie && !crate::http_api::has_bearer_token(headers, &state.token) {
+        tracing::warn!("Dashboard request without the right token");
+        return Err(axum::http::StatusCode::UNAUTHORIZED.into_response());
+    }
+    Ok(())
+}
+
+/// Whether a request's `Host` is one of the dashboard's own. Checking it stops DNS rebinding,
+/// where another website points its own domain at the dashboard's address.
+fn is_allowed_host(state: &DashboardState, headers: &axum::http::HeaderMap) -> bool {
+    headers
+        .get(axum::http::header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|host| {
+            state
+                .hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host))
+        })
+}
+
+/// Whether a request came from the dashboard's own page.
+fn is_same_origin(headers: &axum::http::HeaderMap) -> bool {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value: &axum::http::HeaderValue| value.to_str().ok())
+    };
+    match (
+        header(axum::http::header::ORIGIN),
+        header(axum::http::header::HOST),
+    ) {
+        (Some(origin), Some(host)) => origin == format!("http://{host}"),
+        _ => false,
+    }
+}
+
+/// Make text safe to put in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
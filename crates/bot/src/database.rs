@@ -1,19 +1,61 @@
 use color_eyre::Result;
+use eyre::WrapErr as _;
 use sqlx::Executor as _;
 
-// TODO: make a CLI arg for this
-const DB_PATH: &str = "tbhbot.db";
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct DatabaseConfig {
+    /// The SQLite database file. Relative paths are relative to the data directory. It's
+    /// created if it doesn't exist.
+    pub path: std::path::PathBuf,
+    /// Use SQLite's write-ahead log, so that reads don't block writes.
+    pub wal: bool,
+    pub max_connections: u32,
+    pub backup: crate::backups::BackupConfig,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "tbhbot.db".into(),
+            wal: true,
+            max_connections: 5,
+            backup: crate::backups::BackupConfig::default(),
+        }
+    }
+}
 
 #[derive(Debug, sqlx::FromRow)]
 pub struct Mate {
     pub id: i32,
+    /// Their current name, see `mate_name` for the names they've had before.
     pub name: String,
+    /// Only missing for mates that haven't been seen since IDs started being recorded.
+    pub twitch_user_id: Option<String>,
     pub last_played: chrono::DateTime<chrono::Utc>,
+    /// The emote used for `!tty` when they don't give one.
+    pub tty_emote: Option<String>,
+    /// Whether their default `!tty` emote was bought with channel points.
+    pub tty_premium: bool,
+    pub pronouns: Option<String>,
+    /// A short intro about themselves, see `profiles.rs`.
+    pub intro: Option<String>,
+    /// How many streams in a row they've arrived for, see `arrivals.rs`.
+    pub arrival_streak: i64,
+    pub best_arrival_streak: i64,
+    pub last_arrival_stream: Option<i64>,
+    /// The IANA name of their timezone, see `timezones.rs`.
+    pub timezone: Option<String>,
 }
 
 #[derive(Debug, sqlx::Type)]
 pub enum AchievementKind {
     ChickenRun,
+    EarliestBird,
+    /// Whoever got the most rubber chickens in a stream, see `chickens.rs`.
+    LastChickenStanding,
+    /// One of the achievements from the config, see `achievements.rs`.
+    Milestone,
 }
 
 #[derive(Debug, sqlx::FromRow)]
@@ -32,59 +74,476 @@ pub struct Achievement {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug, sqlx::FromRow)]
+pub struct Note {
+    pub id: i64,
+    pub mate: i32,
+    pub author_id: String,
+    pub author_name: String,
+    pub text: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Duck {
+    pub id: i64,
+    pub stream: Option<i64>,
+    pub twitch_user_id: String,
+    pub username: String,
+    pub question: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub answered_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A chat message, as saved in the database.
+#[derive(Debug, sqlx::FromRow)]
+pub struct SavedMessage {
+    pub message_id: String,
+    pub twitch_user_id: String,
+    pub username: String,
+    pub text: String,
+    /// The message that this one was a reply to.
+    pub parent_message_id: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Reminder {
+    pub id: i64,
+    /// The name of who set the reminder.
+    pub author: String,
+    /// The name of who is being reminded.
+    pub target: String,
+    pub text: String,
+    /// The message that set the reminder, which the reminder replies to.
+    pub message_id: Option<String>,
+    /// Whether it's shown on screen rather than in chat.
+    pub is_onscreen: bool,
+    pub due_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Stream {
+    pub id: i64,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// What the stream is about, see `today.rs`.
+    pub today: Option<String>,
+    /// The go-live message posted to Discord, see `discord.rs`.
+    pub discord_message_id: Option<String>,
+}
+
+/// Stats about what happened during a stream.
+#[derive(Debug, Default)]
+pub struct StreamSummary {
+    pub unique_chatters: i64,
+    pub messages: i64,
+    pub follows: i64,
+    /// Emote names with how many times they were used, most used first.
+    pub top_emotes: Vec<(String, i64)>,
+    pub chicken_runs: i64,
+    pub chirps: i64,
+}
+
+/// A chat message as it's written to the archives.
+#[derive(Debug, sqlx::FromRow, serde_derive::Serialize)]
+pub struct ArchivedMessage {
+    pub twitch_user_id: String,
+    pub timestamp: String,
+    pub username: Option<String>,
+    pub text: Option<String>,
+    pub kind: Option<String>,
+    pub message_id: Option<String>,
+    pub deleted_at: Option<String>,
+    pub deleted_by: Option<String>,
+}
+
+/// A chatter's all-time stats.
+#[derive(Debug)]
+pub struct UserStats {
+    pub username: String,
+    pub messages: i64,
+    pub first_seen: chrono::DateTime<chrono::Utc>,
+    pub favourite_emote: Option<String>,
+    pub best_chicken_run: Option<i64>,
+}
+
+/// Results of an ad-hoc query, see `Database::read_only_query`.
+#[derive(Debug)]
+pub struct QueryResults {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Whether there were more rows than were returned.
+    pub is_truncated: bool,
+}
+
 pub struct Database {
     connection: sqlx::SqlitePool,
+    /// A connection that can't change anything, for running ad-hoc queries from chat.
+    read_only_connection: sqlx::SqlitePool,
+    /// A scratch copy of the database for dry runs, made before the first one, see
+    /// `killswitch::dry_run()`.
+    dry_run_connection: tokio::sync::OnceCell<sqlx::SqlitePool>,
 }
 
 impl Database {
-    pub async fn new() -> Result<Self> {
+    /// Connect to the database, creating it if needed, and bring its schema up to date.
+    ///
+    /// The database used to be kept next to the config file, in `legacy_directory`. If there's
+    /// one there and not in the data directory, it's moved, rather than starting a new empty one.
+    pub async fn new(
+        config: &DatabaseConfig,
+        data_directory: &std::path::Path,
+        legacy_directory: &std::path::Path,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(data_directory)?;
+        let path = data_directory.join(&config.path);
+        Self::move_legacy_database(&legacy_directory.join(&config.path), &path)?;
+        tracing::debug!("Using database: {path:?}");
+        let journal_mode = if config.wal {
+            sqlx::sqlite::SqliteJournalMode::Wal
+        } else {
+            sqlx::sqlite::SqliteJournalMode::Delete
+        };
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .journal_mode(journal_mode);
+        let connection = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect_with(options)
+            .await?;
+        let read_only_options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(&path)
+            .read_only(true);
+        let read_only_connection = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(read_only_options)
+            .await?;
         let db = Self {
-            connection: sqlx::SqlitePool::connect(format!("sqlite://{}", DB_PATH).as_str()).await?,
+            connection,
+            read_only_connection,
+            dry_run_connection: tokio::sync::OnceCell::new(),
         };
+        sqlx::migrate!("../../migrations")
+            .run(&db.connection)
+            .await?;
 
         Ok(db)
     }
 
-    pub async fn get_mate(&self, username: &str) -> Result<Mate> {
-        self.connection
-            .execute(
-                sqlx::query(
-                    "
-                    INSERT INTO mate(name, last_played) VALUES (?, datetime('now', '-1 year'))
-                    ON CONFLICT(name) DO NOTHING;
-                    ",
+    /// The connection for queries, which is the scratch copy during dry runs, so that simulated
+    /// and replayed events can't change the real database.
+    fn pool(&self) -> &sqlx::SqlitePool {
+        if !crate::killswitch::is_dry_run() {
+            return &self.connection;
+        }
+        // The copy is made before any dry run starts, but if it hasn't been then nothing can be
+        // written.
+        self.dry_run_connection
+            .get()
+            .unwrap_or(&self.read_only_connection)
+    }
+
+    /// Make the scratch copy of the database for dry runs, if it hasn't been made already. It's
+    /// a snapshot, so it doesn't see anything that happens after the first dry run.
+    pub async fn prepare_dry_run(&self) -> Result<()> {
+        self.dry_run_connection
+            .get_or_try_init(|| async {
+                let path = crate::paths::state_directory().join("dry-run.db");
+                if path.exists() {
+                    std::fs::remove_file(&path)?;
+                }
+                self.backup_to(&path).await?;
+                let options = sqlx::sqlite::SqliteConnectOptions::new().filename(&path);
+                let pool = sqlx::sqlite::SqlitePoolOptions::new()
+                    .max_connections(1)
+                    .connect_with(options)
+                    .await?;
+                Ok::<_, eyre::Report>(pool)
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Move the database from where it used to be kept, along with its WAL files. If it can't
+    /// be moved then the bot doesn't start, rather than carrying on with an empty database.
+    fn move_legacy_database(legacy: &std::path::Path, path: &std::path::Path) -> Result<()> {
+        if path.exists() || !legacy.exists() || legacy == path {
+            return Ok(());
+        }
+
+        tracing::info!("Moving the database from {legacy:?} to {path:?}");
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        for suffix in ["", "-wal", "-shm"] {
+            let mut from = legacy.as_os_str().to_owned();
+            from.push(suffix);
+            let mut to = path.as_os_str().to_owned();
+            to.push(suffix);
+            if !std::path::Path::new(&from).exists() {
+                continue;
+            }
+            // Renaming doesn't work across filesystems.
+            std::fs::rename(&from, &to)
+                .or_else(|_| std::fs::copy(&from, &to).and_then(|_| std::fs::remove_file(&from)))
+                .wrap_err_with(|| {
+                    format!(
+                        "Couldn't move the database from {} to {}, please move it by hand",
+                        legacy.display(),
+                        path.display()
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Get a mate by their Twitch user ID, creating them if they're new. If they've renamed
+    /// since they were last seen then their name is updated and added to their name history.
+    /// Mates from before IDs were recorded are matched by name.
+    pub async fn get_mate(&self, twitch_user_id: &str, username: &str) -> Result<Mate> {
+        let mut transaction = self.pool().begin().await?;
+        let existing: Option<Mate> = sqlx::query_as("SELECT * FROM mate WHERE twitch_user_id = ?")
+            .bind(twitch_user_id)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        match existing {
+            Some(mate) if mate.name == username => return Ok(mate),
+            Some(mate) => {
+                tracing::info!("{} has renamed to {username}", mate.name);
+                Self::free_name(&mut transaction, username).await?;
+                sqlx::query("UPDATE mate SET name = ? WHERE id = ?")
+                    .bind(username)
+                    .bind(mate.id)
+                    .execute(&mut *transaction)
+                    .await?;
+                Self::add_mate_name(&mut transaction, mate.id, username).await?;
+            }
+            None => {
+                let is_claimed = sqlx::query(
+                    "UPDATE mate SET twitch_user_id = ? WHERE name = ? AND twitch_user_id IS NULL",
                 )
-                .bind(username),
-            )
+                .bind(twitch_user_id)
+                .bind(username)
+                .execute(&mut *transaction)
+                .await?
+                .rows_affected()
+                    > 0;
+                if !is_claimed {
+                    Self::free_name(&mut transaction, username).await?;
+                    let mate_id: i32 = sqlx::query_scalar(
+                        "
+                        INSERT INTO mate(twitch_user_id, name, last_played)
+                        VALUES (?, ?, datetime('now', '-1 year'))
+                        RETURNING id
+                        ",
+                    )
+                    .bind(twitch_user_id)
+                    .bind(username)
+                    .fetch_one(&mut *transaction)
+                    .await?;
+                    Self::add_mate_name(&mut transaction, mate_id, username).await?;
+                }
+            }
+        }
+
+        let mate = sqlx::query_as("SELECT * FROM mate WHERE twitch_user_id = ?")
+            .bind(twitch_user_id)
+            .fetch_one(&mut *transaction)
             .await?;
+        transaction.commit().await?;
+
+        Ok(mate)
+    }
 
-        let mate = sqlx::query_as("SELECT * FROM mate WHERE name = ?")
+    /// Names only belong to one Twitch account at a time, so if another mate still has the
+    /// name then they must have renamed since they were last seen. They're given a placeholder
+    /// name until they're next seen.
+    async fn free_name(connection: &mut sqlx::SqliteConnection, username: &str) -> Result<()> {
+        sqlx::query("UPDATE mate SET name = name || '~' || id WHERE name = ?")
             .bind(username)
-            .fetch_one(&self.connection)
+            .execute(connection)
             .await?;
 
+        Ok(())
+    }
+
+    async fn add_mate_name(
+        connection: &mut sqlx::SqliteConnection,
+        mate_id: i32,
+        username: &str,
+    ) -> Result<()> {
+        sqlx::query("INSERT INTO mate_name(mate, name) VALUES (?, ?)")
+            .bind(mate_id)
+            .bind(username)
+            .execute(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Look up a mate without creating them. Names are matched case-insensitively because
+    /// people rarely type usernames with the right capitalisation. Names that mates used to
+    /// have are matched too, preferring whoever has the name now.
+    pub async fn find_mate(&self, username: &str) -> Result<Option<Mate>> {
+        let mate = sqlx::query_as(
+            "
+            SELECT mate.* FROM mate
+            LEFT JOIN mate_name ON mate_name.mate = mate.id
+            WHERE mate.name = ?1 COLLATE NOCASE OR mate_name.name = ?1 COLLATE NOCASE
+            ORDER BY mate.name = ?1 COLLATE NOCASE DESC, mate_name.timestamp DESC
+            LIMIT 1
+            ",
+        )
+        .bind(username)
+        .fetch_optional(self.pool())
+        .await?;
+
         Ok(mate)
     }
 
-    pub async fn set_last_played(&self, username: &str) -> Result<()> {
-        self.connection
+    pub async fn set_last_played(&self, mate_id: i32) -> Result<()> {
+        self.pool()
             .execute(
                 sqlx::query(
                     "
                     UPDATE mate SET last_played = ?
-                    WHERE name = ?
+                    WHERE id = ?
                     ",
                 )
                 .bind(chrono::offset::Utc::now())
-                .bind(username),
+                .bind(mate_id),
             )
             .await?;
 
         Ok(())
     }
 
+    /// A mate's achievements, oldest first, as their kind and their data as JSON.
+    pub async fn get_achievements(&self, mate_id: i32) -> Result<Vec<(String, String)>> {
+        let achievements = sqlx::query_as(
+            "
+            SELECT achievement, COALESCE(data, '{}') FROM achievement
+            WHERE achiever = ?
+            ORDER BY timestamp
+            ",
+        )
+        .bind(mate_id)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(achievements)
+    }
+
+    /// Whether a mate has already unlocked the named achievement from the config.
+    pub async fn has_milestone(&self, mate_id: i32, name: &str) -> Result<bool> {
+        let (count,): (i64,) = sqlx::query_as(
+            "
+            SELECT COUNT(*) FROM achievement
+            WHERE achiever = ? AND achievement = 'Milestone' AND json_extract(data, '$.name') = ?
+            ",
+        )
+        .bind(mate_id)
+        .bind(name)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// A mate's all-time total for an achievement metric.
+    pub async fn metric(
+        &self,
+        metric: crate::achievements::Metric,
+        user_id: &str,
+        mate_id: i32,
+    ) -> Result<i64> {
+        use crate::achievements::Metric;
+
+        let (query, key) = match metric {
+            Metric::Messages => (
+                "
+                SELECT
+                    (SELECT COUNT(*) FROM message WHERE twitch_user_id = ?1)
+                    + (SELECT COALESCE(SUM(messages), 0) FROM message_aggregate
+                       WHERE twitch_user_id = ?1)
+                ",
+                user_id.to_owned(),
+            ),
+            Metric::Arrivals => (
+                "SELECT COUNT(*) FROM arrival WHERE twitch_user_id = ?",
+                user_id.to_owned(),
+            ),
+            Metric::Chickens => (
+                "
+                SELECT COALESCE(SUM(json_extract(data, '$.repeats')), 0) FROM achievement
+                WHERE achievement = 'ChickenRun' AND achiever = ?
+                ",
+                mate_id.to_string(),
+            ),
+            Metric::Bits => (
+                "SELECT COALESCE(SUM(bits), 0) FROM cheer WHERE twitch_user_id = ?",
+                user_id.to_owned(),
+            ),
+        };
+        let (value,): (i64,) = sqlx::query_as(query)
+            .bind(key)
+            .fetch_one(self.pool())
+            .await?;
+
+        Ok(value)
+    }
+
+    /// Set, or with `None` clear, a mate's default `!tty` emote.
+    pub async fn set_tty_default(
+        &self,
+        mate_id: i32,
+        emote: Option<&str>,
+        is_premium: bool,
+    ) -> Result<()> {
+        sqlx::query("UPDATE mate SET tty_emote = ?, tty_premium = ? WHERE id = ?")
+            .bind(emote)
+            .bind(is_premium)
+            .bind(mate_id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_pronouns(&self, mate_id: i32, pronouns: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE mate SET pronouns = ? WHERE id = ?")
+            .bind(pronouns)
+            .bind(mate_id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_intro(&self, mate_id: i32, intro: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE mate SET intro = ? WHERE id = ?")
+            .bind(intro)
+            .bind(mate_id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_timezone(&self, mate_id: i32, timezone: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE mate SET timezone = ? WHERE id = ?")
+            .bind(timezone)
+            .bind(mate_id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn add_achievement(&self, achievement: Achievement) -> Result<()> {
-        self.connection
+        self.pool()
             .execute(
                 sqlx::query(
                     "
@@ -101,26 +560,1269 @@ impl Database {
         Ok(())
     }
 
-    pub async fn save_message(
+    /// Look up a saved message by its Twitch message ID.
+    pub async fn get_message(&self, message_id: &str) -> Result<Option<SavedMessage>> {
+        let message = sqlx::query_as(
+            "
+            SELECT
+                message_id,
+                CAST(twitch_user_id AS TEXT) AS twitch_user_id,
+                username,
+                text,
+                parent_message_id
+            FROM message
+            WHERE message_id = ?
+            ",
+        )
+        .bind(message_id)
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(message)
+    }
+
+    /// Save a message from another platform's chat, eg YouTube.
+    pub async fn save_platform_message(
+        &self,
+        platform: &str,
+        user_id: &str,
+        username: &str,
+        text: &str,
+        message_id: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO message(platform, twitch_user_id, username, text, kind, message_id)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(platform)
+        .bind(user_id)
+        .bind(username)
+        .bind(text)
+        // Twitch's message types are saved as JSON.
+        .bind(serde_json::to_string("text")?)
+        .bind(message_id)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn save_message(&self, message: &crate::bus::ChatMessage) -> Result<()> {
+        self.pool()
+            .execute(
+                sqlx::query(
+                    "
+                    INSERT INTO message(
+                        twitch_user_id, timestamp, username, text, kind, message_id,
+                        parent_message_id, thread_message_id
+                    )
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?);
+                    ",
+                )
+                .bind(&message.user_id)
+                .bind(message.timestamp.as_str())
+                .bind(&message.username)
+                .bind(&message.text)
+                .bind(&message.kind)
+                .bind(&message.message_id)
+                .bind(&message.parent_message_id)
+                .bind(&message.thread_message_id),
+            )
+            .await?;
+
+        for (emote_id, name) in &message.emotes {
+            sqlx::query("INSERT INTO emote(emote_id, name, twitch_user_id) VALUES (?, ?, ?)")
+                .bind(emote_id)
+                .bind(name)
+                .bind(&message.user_id)
+                .execute(self.pool())
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn add_note(
+        &self,
+        mate_id: i32,
+        author_id: &str,
+        author_name: &str,
+        text: &str,
+    ) -> Result<()> {
+        self.pool()
+            .execute(
+                sqlx::query(
+                    "
+                    INSERT INTO note(mate, author_id, author_name, text)
+                    VALUES (?, ?, ?, ?);
+                    ",
+                )
+                .bind(mate_id)
+                .bind(author_id)
+                .bind(author_name)
+                .bind(text),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_notes(&self, mate_id: i32) -> Result<Vec<Note>> {
+        let notes = sqlx::query_as("SELECT * FROM note WHERE mate = ? ORDER BY timestamp")
+            .bind(mate_id)
+            .fetch_all(self.pool())
+            .await?;
+
+        Ok(notes)
+    }
+
+    /// Replace every cached user with the given role.
+    pub async fn replace_roles(
+        &self,
+        role: crate::roles::Role,
+        users: &[(String, String)],
+    ) -> Result<()> {
+        let mut transaction = self.pool().begin().await?;
+        sqlx::query("DELETE FROM role WHERE role = ?")
+            .bind(role)
+            .execute(&mut *transaction)
+            .await?;
+        for (user_id, username) in users {
+            sqlx::query("INSERT INTO role(twitch_user_id, username, role) VALUES (?, ?, ?)")
+                .bind(user_id)
+                .bind(username)
+                .bind(role)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn has_role(&self, user_id: &str, role: crate::roles::Role) -> Result<bool> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT twitch_user_id FROM role WHERE twitch_user_id = ? AND role = ?")
+                .bind(user_id)
+                .bind(role)
+                .fetch_optional(self.pool())
+                .await?;
+
+        Ok(row.is_some())
+    }
+
+    pub async fn add_cheer(
         &self,
-        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
-        timestamp: twitch_api::types::Timestamp,
+        user_id: Option<&str>,
+        username: &str,
+        bits: i64,
+        message: &str,
     ) -> Result<()> {
-        self.connection
+        self.pool()
             .execute(
                 sqlx::query(
                     "
-                    INSERT INTO message(twitch_user_id, timestamp, username, text, kind)
-                    VALUES (?, ?, ?, ?, ?);
+                    INSERT INTO cheer(twitch_user_id, username, bits, message)
+                    VALUES (?, ?, ?, ?);
                     ",
                 )
-                .bind(payload.chatter_user_id.as_str())
-                .bind(timestamp.as_str())
-                .bind(payload.chatter_user_name.as_str())
-                .bind(payload.message.text.clone())
-                .bind(serde_json::to_string(&payload.message_type)?),
+                .bind(user_id)
+                .bind(username)
+                .bind(bits)
+                .bind(message),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// The latest chat messages, newest first, as their timestamp, username and text.
+    pub async fn recent_messages(&self, limit: u32) -> Result<Vec<(String, String, String)>> {
+        let messages = sqlx::query_as(
+            "
+            SELECT timestamp, COALESCE(username, ''), COALESCE(text, '') FROM message
+            ORDER BY rowid DESC
+            LIMIT ?
+            ",
+        )
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// How many times each `!command` has been used, most used first. Archived days are counted
+    /// from their aggregates.
+    pub async fn command_usage(&self, limit: u32) -> Result<Vec<(String, i64)>> {
+        let usage = sqlx::query_as(
+            "
+            SELECT command, SUM(uses) AS total FROM (
+                SELECT
+                    CASE WHEN instr(text, ' ') > 0 THEN substr(text, 1, instr(text, ' ') - 1)
+                    ELSE text END AS command,
+                    COUNT(*) AS uses
+                FROM message
+                WHERE text LIKE '!%'
+                GROUP BY command
+                UNION ALL
+                SELECT command, SUM(uses) FROM command_aggregate
+                GROUP BY command
+            )
+            GROUP BY command
+            ORDER BY total DESC
+            LIMIT ?
+            ",
+        )
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// The total bits cheered per user, highest first. Anonymous cheers aren't included.
+    pub async fn top_cheerers(&self, limit: u32) -> Result<Vec<(String, i64)>> {
+        let leaders = sqlx::query_as(
+            "
+            SELECT username, SUM(bits) AS total FROM cheer
+            WHERE twitch_user_id IS NOT NULL
+            GROUP BY twitch_user_id
+            ORDER BY total DESC
+            LIMIT ?
+            ",
+        )
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(leaders)
+    }
+
+    pub async fn add_follow(&self, user_id: &str, username: &str) -> Result<()> {
+        sqlx::query("INSERT INTO follow(twitch_user_id, username) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(username)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn start_stream(&self, started_at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        sqlx::query("INSERT INTO stream(started_at) VALUES (?)")
+            .bind(started_at)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// The stream that's currently live, if any.
+    pub async fn current_stream(&self) -> Result<Option<Stream>> {
+        let stream = sqlx::query_as(
+            "SELECT * FROM stream WHERE ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+        )
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(stream)
+    }
+
+    pub async fn set_stream_today(&self, stream_id: i64, today: &str) -> Result<()> {
+        sqlx::query("UPDATE stream SET today = ? WHERE id = ?")
+            .bind(today)
+            .bind(stream_id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_stream_discord_message(&self, stream_id: i64, message_id: &str) -> Result<()> {
+        sqlx::query("UPDATE stream SET discord_message_id = ? WHERE id = ?")
+            .bind(message_id)
+            .bind(stream_id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// The ID of the stream before the given one, if there was one.
+    pub async fn previous_stream_id(&self, stream_id: i64) -> Result<Option<i64>> {
+        let id = sqlx::query_scalar("SELECT id FROM stream WHERE id < ? ORDER BY id DESC LIMIT 1")
+            .bind(stream_id)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(id)
+    }
+
+    /// Mark the current stream as finished, returning it.
+    pub async fn end_stream(&self) -> Result<Option<Stream>> {
+        let Some(mut stream) = self.current_stream().await? else {
+            return Ok(None);
+        };
+        let now = chrono::Utc::now();
+        sqlx::query("UPDATE stream SET ended_at = ? WHERE id = ?")
+            .bind(now)
+            .bind(stream.id)
+            .execute(self.pool())
+            .await?;
+        stream.ended_at = Some(now);
+
+        Ok(Some(stream))
+    }
+
+    /// Summarise everything that happened since the given time.
+    pub async fn summarise_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<StreamSummary> {
+        let (messages, unique_chatters): (i64, i64) = sqlx::query_as(
+            "
+            SELECT COUNT(*), COUNT(DISTINCT twitch_user_id) FROM message
+            WHERE datetime(timestamp) >= datetime(?)
+            ",
+        )
+        .bind(since)
+        .fetch_one(self.pool())
+        .await?;
+
+        let (follows,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM follow WHERE datetime(timestamp) >= datetime(?)")
+                .bind(since)
+                .fetch_one(self.pool())
+                .await?;
+
+        let top_emotes = sqlx::query_as(
+            "
+            SELECT name, COUNT(*) AS uses FROM emote
+            WHERE datetime(timestamp) >= datetime(?)
+            GROUP BY emote_id
+            ORDER BY uses DESC
+            LIMIT 3
+            ",
+        )
+        .bind(since)
+        .fetch_all(self.pool())
+        .await?;
+
+        let (chicken_runs,): (i64,) = sqlx::query_as(
+            "
+            SELECT COUNT(*) FROM achievement
+            WHERE achievement = 'ChickenRun' AND datetime(timestamp) >= datetime(?)
+            ",
+        )
+        .bind(since)
+        .fetch_one(self.pool())
+        .await?;
+
+        let (chirps,): (i64,) = sqlx::query_as(
+            "
+            SELECT COUNT(*) FROM message
+            WHERE text LIKE '!chirp%' AND datetime(timestamp) >= datetime(?)
+            ",
+        )
+        .bind(since)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(StreamSummary {
+            unique_chatters,
+            messages,
+            follows,
+            top_emotes,
+            chicken_runs,
+            chirps,
+        })
+    }
+
+    pub async fn add_role(
+        &self,
+        user_id: &str,
+        username: &str,
+        role: crate::roles::Role,
+    ) -> Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO role(twitch_user_id, username, role) VALUES (?, ?, ?)
+            ON CONFLICT(twitch_user_id, role) DO NOTHING
+            ",
+        )
+        .bind(user_id)
+        .bind(username)
+        .bind(role)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_role(&self, user_id: &str, role: crate::roles::Role) -> Result<()> {
+        sqlx::query("DELETE FROM role WHERE twitch_user_id = ? AND role = ?")
+            .bind(user_id)
+            .bind(role)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// The names of everybody with the given role.
+    pub async fn users_with_role(&self, role: crate::roles::Role) -> Result<Vec<String>> {
+        let users: Vec<(String,)> =
+            sqlx::query_as("SELECT username FROM role WHERE role = ? ORDER BY username")
+                .bind(role)
+                .fetch_all(self.pool())
+                .await?;
+
+        Ok(users.into_iter().map(|(username,)| username).collect())
+    }
+
+    /// Record a privileged action.
+    pub async fn audit(
+        &self,
+        actor: &str,
+        action: &str,
+        target: Option<&str>,
+        details: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query("INSERT INTO audit(actor, action, target, details) VALUES (?, ?, ?, ?)")
+            .bind(actor)
+            .bind(action)
+            .bind(target)
+            .bind(details)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Close the database, waiting for queries that are still running.
+    pub async fn close(&self) {
+        self.connection.close().await;
+        self.read_only_connection.close().await;
+        if let Some(dry_run_connection) = self.dry_run_connection.get() {
+            dry_run_connection.close().await;
+        }
+    }
+
+    pub async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let value: Option<(String,)> = sqlx::query_as("SELECT value FROM setting WHERE key = ?")
+            .bind(key)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(value.map(|(value,)| value))
+    }
+
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO setting(key, value) VALUES (?, ?)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value
+            ",
+        )
+        .bind(key)
+        .bind(value)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Everything that a plugin has stored, keyed by its own keys.
+    pub async fn get_plugin_storage(
+        &self,
+        plugin: &str,
+    ) -> Result<std::collections::HashMap<String, String>> {
+        let values: Vec<(String, String)> =
+            sqlx::query_as("SELECT key, value FROM plugin_storage WHERE plugin = ?")
+                .bind(plugin)
+                .fetch_all(self.pool())
+                .await?;
+
+        Ok(values.into_iter().collect())
+    }
+
+    pub async fn set_plugin_value(&self, plugin: &str, key: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO plugin_storage(plugin, key, value) VALUES (?, ?, ?)
+            ON CONFLICT(plugin, key) DO UPDATE SET value = excluded.value
+            ",
+        )
+        .bind(plugin)
+        .bind(key)
+        .bind(value)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// The most used emote since the given time, with its ID, name and number of uses.
+    pub async fn top_emote_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<(String, String, i64)>> {
+        let emote = sqlx::query_as(
+            "
+            SELECT emote_id, name, COUNT(*) AS uses FROM emote
+            WHERE datetime(timestamp) >= datetime(?)
+            GROUP BY emote_id
+            ORDER BY uses DESC
+            LIMIT 1
+            ",
+        )
+        .bind(since)
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(emote)
+    }
+
+    pub async fn add_emote_of_the_stream(
+        &self,
+        stream_id: Option<i64>,
+        emote_id: &str,
+        name: &str,
+        uses: i64,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO emote_of_the_stream(stream, emote_id, name, uses) VALUES (?, ?, ?, ?)",
+        )
+        .bind(stream_id)
+        .bind(emote_id)
+        .bind(name)
+        .bind(uses)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Past emotes of the stream, newest first.
+    pub async fn emote_history(
+        &self,
+        limit: u32,
+    ) -> Result<Vec<(String, i64, chrono::DateTime<chrono::Utc>)>> {
+        let history = sqlx::query_as(
+            "
+            SELECT name, uses, timestamp FROM emote_of_the_stream
+            ORDER BY timestamp DESC
+            LIMIT ?
+            ",
+        )
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(history)
+    }
+
+    /// Record that someone arrived during a stream, returning their position and points.
+    /// Returns `None` if they'd already arrived.
+    ///
+    /// The position is counted in the same statement as the insert, so that two mates arriving
+    /// at the same time can't both be first.
+    pub async fn add_arrival(
+        &self,
+        stream_id: i64,
+        user_id: &str,
+        username: &str,
+        first_points: i64,
+        minimum_points: i64,
+    ) -> Result<Option<(i64, i64)>> {
+        let arrival = sqlx::query_as(
+            "
+            INSERT INTO arrival(stream, twitch_user_id, username, position, points)
+            SELECT ?1, ?2, ?3, COUNT(*) + 1, MAX(?4 - COUNT(*), ?5)
+            FROM arrival WHERE stream = ?1
+            ON CONFLICT(stream, twitch_user_id) DO NOTHING
+            RETURNING position, points
+            ",
+        )
+        .bind(stream_id)
+        .bind(user_id)
+        .bind(username)
+        .bind(first_points)
+        .bind(minimum_points)
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(arrival)
+    }
+
+    /// The mates with the most arrival points between the given times, most points first, as
+    /// their ID, name and points.
+    pub async fn earliest_birds(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        until: chrono::DateTime<chrono::Utc>,
+        limit: u32,
+    ) -> Result<Vec<(String, String, i64)>> {
+        let birds = sqlx::query_as(
+            "
+            SELECT twitch_user_id, username, SUM(points) AS total FROM arrival
+            WHERE datetime(timestamp) >= datetime(?) AND datetime(timestamp) < datetime(?)
+            GROUP BY twitch_user_id
+            ORDER BY total DESC
+            LIMIT ?
+            ",
+        )
+        .bind(from)
+        .bind(until)
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(birds)
+    }
+
+    /// Queue a rubber duck question, returning its ID.
+    pub async fn add_duck(
+        &self,
+        stream_id: Option<i64>,
+        user_id: &str,
+        username: &str,
+        question: &str,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO duck(stream, twitch_user_id, username, question) VALUES (?, ?, ?, ?)",
+        )
+        .bind(stream_id)
+        .bind(user_id)
+        .bind(username)
+        .bind(question)
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Unanswered rubber duck questions from the given stream, oldest first.
+    pub async fn open_ducks(&self, stream_id: Option<i64>) -> Result<Vec<Duck>> {
+        let ducks = sqlx::query_as(
+            "SELECT * FROM duck WHERE stream IS ? AND answered_at IS NULL ORDER BY id",
+        )
+        .bind(stream_id)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(ducks)
+    }
+
+    /// Archive an answered rubber duck question. Returns `false` if there was no such open
+    /// question.
+    pub async fn answer_duck(&self, id: i64) -> Result<bool> {
+        let result =
+            sqlx::query("UPDATE duck SET answered_at = ? WHERE id = ? AND answered_at IS NULL")
+                .bind(chrono::Utc::now())
+                .bind(id)
+                .execute(self.pool())
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Flag a message as deleted. The moderator who deleted it is kept if it's already known.
+    pub async fn mark_message_deleted(
+        &self,
+        message_id: &str,
+        deleted_by: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "
+            UPDATE message
+            SET deleted_at = COALESCE(deleted_at, ?), deleted_by = COALESCE(?, deleted_by)
+            WHERE message_id = ?
+            ",
+        )
+        .bind(chrono::Utc::now())
+        .bind(deleted_by)
+        .bind(message_id)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Flag all of someone's messages since the given time as deleted.
+    pub async fn mark_user_messages_deleted(
+        &self,
+        user_id: &str,
+        since: chrono::DateTime<chrono::Utc>,
+        deleted_by: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "
+            UPDATE message
+            SET deleted_at = COALESCE(deleted_at, ?), deleted_by = COALESCE(?, deleted_by)
+            WHERE twitch_user_id = ? AND datetime(timestamp) >= datetime(?)
+            ",
+        )
+        .bind(chrono::Utc::now())
+        .bind(deleted_by)
+        .bind(user_id)
+        .bind(since)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// All-time stats for a chatter, if they've ever chatted.
+    pub async fn user_stats(&self, username: &str) -> Result<Option<UserStats>> {
+        let maybe_messages: Option<(String, String, i64, chrono::DateTime<chrono::Utc>)> =
+            sqlx::query_as(
+                "
+                SELECT twitch_user_id, username, SUM(messages) AS total, MIN(first_seen) FROM (
+                    SELECT
+                        CAST(twitch_user_id AS TEXT) AS twitch_user_id, username,
+                        COUNT(*) AS messages, MIN(timestamp) AS first_seen
+                    FROM message
+                    WHERE username = ? COLLATE NOCASE
+                    GROUP BY twitch_user_id
+                    UNION ALL
+                    SELECT twitch_user_id, username, messages, first_seen FROM message_aggregate
+                    WHERE username = ? COLLATE NOCASE
+                )
+                GROUP BY twitch_user_id
+                ORDER BY total DESC
+                LIMIT 1
+                ",
             )
+            .bind(username)
+            .bind(username)
+            .fetch_optional(self.pool())
             .await?;
+        let Some((user_id, username, messages, first_seen)) = maybe_messages else {
+            return Ok(None);
+        };
+
+        let favourite_emote: Option<(String,)> = sqlx::query_as(
+            "
+            SELECT name FROM emote
+            WHERE twitch_user_id = ?
+            GROUP BY emote_id
+            ORDER BY COUNT(*) DESC
+            LIMIT 1
+            ",
+        )
+        .bind(&user_id)
+        .fetch_optional(self.pool())
+        .await?;
+
+        let (best_chicken_run,): (Option<i64>,) = sqlx::query_as(
+            "
+            SELECT MAX(json_extract(achievement.data, '$.repeats')) FROM achievement
+            JOIN mate ON mate.id = achievement.achiever
+            WHERE achievement.achievement = 'ChickenRun' AND mate.name = ? COLLATE NOCASE
+            ",
+        )
+        .bind(&username)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(Some(UserStats {
+            username,
+            messages,
+            first_seen,
+            favourite_emote: favourite_emote.map(|(name,)| name),
+            best_chicken_run,
+        }))
+    }
+
+    /// Days, before the given one, that still have messages in the database, oldest first.
+    pub async fn message_days_before(&self, day: chrono::NaiveDate) -> Result<Vec<String>> {
+        let days: Vec<(String,)> = sqlx::query_as(
+            "
+            SELECT DISTINCT date(timestamp) AS day FROM message
+            WHERE date(timestamp) < ?
+            ORDER BY day
+            ",
+        )
+        .bind(day.to_string())
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(days.into_iter().map(|(day,)| day).collect())
+    }
+
+    /// Replace a day's messages with daily counts for each chatter, and for each `!command`.
+    /// The messages themselves, in the order they were sent, are given to `archive` to keep.
+    ///
+    /// It's all one transaction, and `archive` is called last, so that if anything fails
+    /// nothing changes and archiving the day again doesn't archive any message twice.
+    pub async fn archive_messages_on<F>(&self, day: &str, archive: F) -> Result<()>
+    where
+        F: FnOnce(&[ArchivedMessage]) -> Result<()>,
+    {
+        let mut transaction = self.pool().begin().await?;
+        let messages: Vec<ArchivedMessage> = sqlx::query_as(
+            "
+            SELECT
+                CAST(twitch_user_id AS TEXT) AS twitch_user_id, timestamp, username, text, kind,
+                message_id, deleted_at, deleted_by
+            FROM message
+            WHERE date(timestamp) = ?
+            ORDER BY timestamp
+            ",
+        )
+        .bind(day)
+        .fetch_all(&mut *transaction)
+        .await?;
+        sqlx::query(
+            "
+            INSERT INTO message_aggregate(
+                day, twitch_user_id, username, messages, chirps, first_seen, last_seen
+            )
+            SELECT
+                date(timestamp), CAST(twitch_user_id AS TEXT), COALESCE(username, ''), COUNT(*),
+                SUM(text LIKE '!chirp%'), MIN(timestamp), MAX(timestamp)
+            FROM message
+            WHERE date(timestamp) = ?
+            GROUP BY twitch_user_id
+            ON CONFLICT(day, twitch_user_id) DO UPDATE SET
+                messages = messages + excluded.messages,
+                chirps = chirps + excluded.chirps,
+                first_seen = MIN(first_seen, excluded.first_seen),
+                last_seen = MAX(COALESCE(last_seen, excluded.last_seen), excluded.last_seen)
+            ",
+        )
+        .bind(day)
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query(
+            "
+            INSERT INTO command_aggregate(day, command, uses)
+            SELECT
+                date(timestamp),
+                CASE WHEN instr(text, ' ') > 0 THEN substr(text, 1, instr(text, ' ') - 1)
+                ELSE text END AS command,
+                COUNT(*)
+            FROM message
+            WHERE date(timestamp) = ? AND text LIKE '!%'
+            GROUP BY command
+            ON CONFLICT(day, command) DO UPDATE SET uses = uses + excluded.uses
+            ",
+        )
+        .bind(day)
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query("DELETE FROM message WHERE date(timestamp) = ?")
+            .bind(day)
+            .execute(&mut *transaction)
+            .await?;
+        archive(&messages)?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Run an ad-hoc query on the read-only connection, returning at most `max_rows` rows with
+    /// every value formatted as text.
+    ///
+    /// SQLite itself is told to stop the query once it's run for longer than `timeout`. Just
+    /// dropping the future would leave it running and the read-only connection busy.
+    pub async fn read_only_query(
+        &self,
+        query: &str,
+        max_rows: usize,
+        timeout: std::time::Duration,
+    ) -> Result<QueryResults> {
+        let mut connection = self.read_only_connection.acquire().await?;
+        let deadline = std::time::Instant::now() + timeout;
+        // Checked every this many virtual machine instructions, returning `false` interrupts
+        // the query.
+        connection
+            .lock_handle()
+            .await?
+            .set_progress_handler(1000, move || std::time::Instant::now() < deadline);
+        let result = Self::query_as_text(&mut connection, query, max_rows).await;
+        connection.lock_handle().await?.remove_progress_handler();
+
+        if result.is_err() && std::time::Instant::now() >= deadline {
+            eyre::bail!("It took longer than {}s", timeout.as_secs());
+        }
+        result
+    }
+
+    async fn query_as_text(
+        connection: &mut sqlx::SqliteConnection,
+        query: &str,
+        max_rows: usize,
+    ) -> Result<QueryResults> {
+        use futures::TryStreamExt as _;
+        use sqlx::{Column as _, Row as _};
+
+        let mut stream = sqlx::query(query).fetch(connection);
+        let mut results = QueryResults {
+            columns: Vec::new(),
+            rows: Vec::new(),
+            is_truncated: false,
+        };
+        while let Some(row) = stream.try_next().await? {
+            if results.rows.len() == max_rows {
+                results.is_truncated = true;
+                break;
+            }
+            if results.columns.is_empty() {
+                results.columns = row
+                    .columns()
+                    .iter()
+                    .map(|column| column.name().to_owned())
+                    .collect();
+            }
+            let values = (0..row.len())
+                .map(|index| {
+                    if let Ok(value) = row.try_get::<Option<i64>, _>(index) {
+                        return value.map_or("NULL".to_owned(), |value| value.to_string());
+                    }
+                    if let Ok(value) = row.try_get::<Option<f64>, _>(index) {
+                        return value.map_or("NULL".to_owned(), |value| value.to_string());
+                    }
+                    if let Ok(value) = row.try_get::<Option<String>, _>(index) {
+                        return value.unwrap_or_else(|| "NULL".to_owned());
+                    }
+                    "<blob>".to_owned()
+                })
+                .collect();
+            results.rows.push(values);
+        }
+
+        Ok(results)
+    }
+
+    /// Write a consistent copy of the whole database to a new file.
+    pub async fn backup_to(&self, path: &std::path::Path) -> Result<()> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy())
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record that a mate arrived in the given stream, with their streak so far. Their best
+    /// streak is kept too.
+    pub async fn set_arrival_streak(
+        &self,
+        mate_id: i32,
+        stream_id: i64,
+        streak: i64,
+    ) -> Result<()> {
+        sqlx::query(
+            "
+            UPDATE mate SET
+                arrival_streak = ?1,
+                best_arrival_streak = MAX(best_arrival_streak, ?1),
+                last_arrival_stream = ?2
+            WHERE id = ?3
+            ",
+        )
+        .bind(streak)
+        .bind(stream_id)
+        .bind(mate_id)
+        .execute(self.pool())
+        .await?;
+
         Ok(())
     }
+
+    /// Record a tip, returning whether it's new.
+    pub async fn add_tip(&self, tip: &crate::tips::Tip) -> Result<bool> {
+        let result = sqlx::query(
+            "
+            INSERT INTO tip(source, external_id, username, amount, currency, message)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(source, external_id) DO NOTHING
+            ",
+        )
+        .bind(tip.source)
+        .bind(&tip.external_id)
+        .bind(&tip.username)
+        .bind(tip.amount)
+        .bind(&tip.currency)
+        .bind(&tip.message)
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn add_reminder(&self, reminder: Reminder) -> Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO reminder(author, target, text, message_id, is_onscreen, due_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(reminder.author)
+        .bind(reminder.target)
+        .bind(reminder.text)
+        .bind(reminder.message_id)
+        .bind(reminder.is_onscreen)
+        .bind(reminder.due_at)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// How many reminders someone has set that haven't been delivered yet.
+    pub async fn count_pending_reminders(&self, author: &str) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM reminder WHERE author = ? AND delivered_at IS NULL",
+        )
+        .bind(author)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Undelivered reminders that are due by the given time, oldest first.
+    pub async fn due_reminders(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<Reminder>> {
+        let reminders = sqlx::query_as(
+            "
+            SELECT id, author, target, text, message_id, is_onscreen, due_at FROM reminder
+            WHERE delivered_at IS NULL AND datetime(due_at) <= datetime(?)
+            ORDER BY due_at
+            ",
+        )
+        .bind(now)
+        .fetch_all(self.pool())
+        .await?;
+
+        Ok(reminders)
+    }
+
+    pub async fn mark_reminder_delivered(&self, id: i64) -> Result<()> {
+        sqlx::query("UPDATE reminder SET delivered_at = datetime('now', 'utc') WHERE id = ?")
+            .bind(id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Add to the todo list, returning the new todo's ID.
+    pub async fn add_todo(&self, text: &str) -> Result<i64> {
+        let result = sqlx::query("INSERT INTO todo(text) VALUES (?)")
+            .bind(text)
+            .execute(self.pool())
+            .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Tick off a todo, returning whether there was an open todo with the ID.
+    pub async fn complete_todo(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE todo SET done_at = datetime('now', 'utc') WHERE id = ? AND done_at IS NULL",
+        )
+        .bind(id)
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Todos that haven't been done yet, oldest first, as their ID and text.
+    pub async fn open_todos(&self) -> Result<Vec<(i64, String)>> {
+        let todos = sqlx::query_as("SELECT id, text FROM todo WHERE done_at IS NULL ORDER BY id")
+            .fetch_all(self.pool())
+            .await?;
+
+        Ok(todos)
+    }
+
+    /// Quote a message, returning the quote's ID, or `None` if it's already been quoted.
+    pub async fn add_quote(&self, message: &SavedMessage, added_by: &str) -> Result<Option<i64>> {
+        let result = sqlx::query(
+            "
+            INSERT INTO quote(message_id, twitch_user_id, username, text, added_by)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(message_id) DO NOTHING
+            ",
+        )
+        .bind(&message.message_id)
+        .bind(&message.twitch_user_id)
+        .bind(&message.username)
+        .bind(&message.text)
+        .bind(added_by)
+        .execute(self.pool())
+        .await?;
+
+        Ok((result.rows_affected() > 0).then(|| result.last_insert_rowid()))
+    }
+
+    /// A quote as its ID, who said it and what they said.
+    pub async fn get_quote(&self, id: i64) -> Result<Option<(i64, String, String)>> {
+        let quote = sqlx::query_as("SELECT id, username, text FROM quote WHERE id = ?")
+            .bind(id)
+            .fetch_optional(self.pool())
+            .await?;
+
+        Ok(quote)
+    }
+
+    pub async fn random_quote(&self) -> Result<Option<(i64, String, String)>> {
+        let quote =
+            sqlx::query_as("SELECT id, username, text FROM quote ORDER BY RANDOM() LIMIT 1")
+                .fetch_optional(self.pool())
+                .await?;
+
+        Ok(quote)
+    }
+
+    pub async fn add_chickens(
+        &self,
+        stream_id: Option<i64>,
+        user_id: &str,
+        username: &str,
+        chickens: u16,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO chicken(stream, twitch_user_id, username, chickens) VALUES (?, ?, ?, ?)",
+        )
+        .bind(stream_id)
+        .bind(user_id)
+        .bind(username)
+        .bind(chickens)
+        .execute(self.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Whoever got the most chickens in the stream, as their ID, name and chickens.
+    pub async fn top_chicken(&self, stream_id: i64) -> Result<Option<(String, String, i64)>> {
+        let top = sqlx::query_as(
+            "
+            SELECT twitch_user_id, username, SUM(chickens) AS total FROM chicken
+            WHERE stream = ?
+            GROUP BY twitch_user_id
+            ORDER BY total DESC
+            LIMIT 1
+            ",
+        )
+        .bind(stream_id)
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(top)
+    }
+
+    pub async fn add_timeout(&self, user_id: &str, username: &str, moderator: &str) -> Result<()> {
+        sqlx::query("INSERT INTO timeout(twitch_user_id, username, moderator) VALUES (?, ?, ?)")
+            .bind(user_id)
+            .bind(username)
+            .bind(moderator)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn count_timeouts_this_month(&self, user_id: &str) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "
+            SELECT COUNT(*) FROM timeout
+            WHERE twitch_user_id = ? AND datetime(timestamp) >= datetime('now', 'start of month')
+            ",
+        )
+        .bind(user_id)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Twitch doesn't say who's watching, so this is estimated from chat. For each stream the
+    /// mate chatted in, it's the time from their first message to their last. Archived days
+    /// only have the first and last message of each day, which is the same for streams that
+    /// don't cross midnight.
+    pub async fn estimated_watch_minutes(&self, user_id: &str) -> Result<f64> {
+        let (minutes,): (f64,) = sqlx::query_as(
+            "
+            SELECT COALESCE(SUM(minutes), 0.0) FROM (
+                SELECT
+                    (julianday(MAX(seen.timestamp)) - julianday(MIN(seen.timestamp)))
+                        * 24 * 60 AS minutes
+                FROM (
+                    SELECT timestamp FROM message WHERE CAST(twitch_user_id AS TEXT) = ?1
+                    UNION ALL
+                    SELECT first_seen FROM message_aggregate WHERE twitch_user_id = ?1
+                    UNION ALL
+                    SELECT last_seen FROM message_aggregate
+                    WHERE twitch_user_id = ?1 AND last_seen IS NOT NULL
+                ) AS seen
+                JOIN stream
+                    ON datetime(seen.timestamp) >= datetime(stream.started_at)
+                    AND datetime(seen.timestamp) <= datetime(COALESCE(stream.ended_at, 'now'))
+                GROUP BY stream.id
+            )
+            ",
+        )
+        .bind(user_id)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(minutes)
+    }
+
+    /// Save a bug report, returning its ID.
+    pub async fn add_bug_report(
+        &self,
+        user_id: &str,
+        username: &str,
+        description: &str,
+        logs: &str,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "
+            INSERT INTO bug_report(twitch_user_id, username, description, logs)
+            VALUES (?, ?, ?, ?)
+            ",
+        )
+        .bind(user_id)
+        .bind(username)
+        .bind(description)
+        .bind(logs)
+        .execute(self.pool())
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn set_bug_report_issue(&self, id: i64, issue_url: &str) -> Result<()> {
+        sqlx::query("UPDATE bug_report SET issue_url = ? WHERE id = ?")
+            .bind(issue_url)
+            .bind(id)
+            .execute(self.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// How many bug reports the mate has made in the last hour.
+    pub async fn count_recent_bug_reports(&self, user_id: &str) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "
+            SELECT COUNT(*) FROM bug_report
+            WHERE twitch_user_id = ? AND datetime(timestamp) >= datetime('now', '-1 hour')
+            ",
+        )
+        .bind(user_id)
+        .fetch_one(self.pool())
+        .await?;
+
+        Ok(count)
+    }
 }
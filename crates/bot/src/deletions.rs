@@ -0,0 +1,76 @@
+//! Flag deleted chat messages in the database. The deletion events don't say who did the
+//! deleting, so the moderator is filled in from the `channel.moderate` event, whichever of the
+//! two arrives first.
+
+use color_eyre::Result;
+use twitch_api::eventsub::channel::moderate::ActionV2;
+
+/// Clearing someone's messages, like when they're banned, only clears what's still visible in
+/// chat. So only flag their messages from this long ago.
+const CLEARED_MESSAGE_AGE: chrono::TimeDelta = chrono::TimeDelta::days(1);
+
+impl crate::bot::Bot {
+    pub async fn message_deleted(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageDeleteV1Payload,
+    ) -> Result<()> {
+        tracing::info!(
+            "Message from {} deleted: {}",
+            payload.target_user_name,
+            payload.message_id
+        );
+        self.db
+            .mark_message_deleted(payload.message_id.as_str(), None)
+            .await
+    }
+
+    pub async fn user_messages_cleared(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatClearUserMessagesV1Payload,
+    ) -> Result<()> {
+        tracing::info!("Messages from {} cleared", payload.target_user_name);
+        self.db
+            .mark_user_messages_deleted(
+                payload.target_user_id.as_str(),
+                chrono::Utc::now() - CLEARED_MESSAGE_AGE,
+                None,
+            )
+            .await
+    }
+
+    /// Used to find out which moderator deleted messages, and to record timeouts.
+    pub async fn channel_moderated(
+        &self,
+        payload: &crate::eventsub::channel::ChannelModerateV2Payload,
+    ) -> Result<()> {
+        let moderator = payload.moderator_user_name.as_str();
+        let since = chrono::Utc::now() - CLEARED_MESSAGE_AGE;
+        match &payload.action {
+            ActionV2::Delete(delete) => {
+                self.db
+                    .mark_message_deleted(delete.message_id.as_str(), Some(moderator))
+                    .await?;
+            }
+            ActionV2::Ban(ban) => {
+                self.db
+                    .mark_user_messages_deleted(ban.user_id.as_str(), since, Some(moderator))
+                    .await?;
+            }
+            ActionV2::Timeout(timeout) => {
+                self.db
+                    .mark_user_messages_deleted(timeout.user_id.as_str(), since, Some(moderator))
+                    .await?;
+                self.db
+                    .add_timeout(
+                        timeout.user_id.as_str(),
+                        timeout.user_name.as_str(),
+                        moderator,
+                    )
+                    .await?;
+            }
+            _ => tracing::debug!("Channel moderated: {payload:?}"),
+        }
+
+        Ok(())
+    }
+}
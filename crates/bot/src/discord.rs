@@ -0,0 +1,181 @@
+//! Post a go-live message to a Discord channel through a webhook, with the stream's title,
+//! category and box art. When the stream ends the message is edited, deleted or left alone.
+//!
+//! The message's ID is saved with the stream, so that it can still be found after a restart.
+//! Like cross-posts, the message isn't posted again within `[crosspost] min_gap_minutes`, so that
+//! a stream dropping and coming back doesn't spam the channel.
+
+use color_eyre::Result;
+
+/// When the last go-live message was posted.
+const LAST_GO_LIVE_SETTING: &str = "discord_go_live_at";
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct DiscordConfig {
+    pub enabled: bool,
+    /// The webhook's URL, from the channel's Integrations settings. Anyone with it can post to
+    /// the channel, so it's set with `TBHBOT_DISCORD__WEBHOOK_URL` rather than in the config file.
+    pub webhook_url: String,
+    /// The message. `{title}`, `{category}` and `{link}` are replaced with the stream's details.
+    pub template: String,
+    /// What to do with the message once the stream has ended.
+    pub on_offline: OnOffline,
+    /// What the message is edited to, when `on_offline = "edit"`. `{link}` is replaced with a
+    /// link to the channel.
+    pub offline_template: String,
+}
+
+impl Default for DiscordConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            webhook_url: String::new(),
+            template: "🔴 Live now: {title} {link}".to_owned(),
+            on_offline: OnOffline::Edit,
+            offline_template: "The stream has ended, thanks for coming! {link}".to_owned(),
+        }
+    }
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum OnOffline {
+    /// Replace the message with `offline_template`.
+    Edit,
+    Delete,
+    Keep,
+}
+
+/// The only part of Discord's reply that's needed.
+#[derive(serde_derive::Deserialize)]
+struct WebhookMessage {
+    id: String,
+}
+
+impl crate::bot::Bot {
+    /// Post the go-live message, and remember it for when the stream ends.
+    pub async fn discord_go_live(
+        &self,
+        payload: &crate::eventsub::stream::StreamOnlineV1Payload,
+    ) -> Result<()> {
+        let (config, min_gap_minutes) = {
+            let config = self.config.read().await;
+            (config.discord.clone(), config.crosspost.min_gap_minutes)
+        };
+        if !config.enabled {
+            return Ok(());
+        }
+        if crate::killswitch::stays_local() {
+            tracing::info!("Log-only, not posting the go-live message to Discord");
+            return Ok(());
+        }
+        if let Some(last_posted_at) = self
+            .posted_recently(LAST_GO_LIVE_SETTING, min_gap_minutes)
+            .await?
+        {
+            tracing::info!("Not posting to Discord, the last go-live was at {last_posted_at}");
+            return Ok(());
+        }
+
+        let token = self.token.lock().await.clone();
+        let channel = self
+            .client
+            .get_channel_from_id(&payload.broadcaster_user_id, &token)
+            .await?;
+        let (title, category, category_id) = channel
+            .map(|channel| {
+                (
+                    channel.title,
+                    channel.game_name.to_string(),
+                    channel.game_id.to_string(),
+                )
+            })
+            .unwrap_or_default();
+        let link = format!("https://twitch.tv/{}", payload.broadcaster_user_login);
+        let content = config
+            .template
+            .replace("{title}", &title)
+            .replace("{category}", &category)
+            .replace("{link}", &link);
+
+        let mut embed = serde_json::json!({
+            "title": title,
+            "url": link,
+            "description": category,
+        });
+        if !category_id.is_empty() {
+            embed["thumbnail"] = serde_json::json!({
+                "url": format!("https://static-cdn.jtvnw.net/ttv-boxart/{category_id}-285x380.jpg"),
+            });
+        }
+
+        // Saved before posting, so that a post that fails part way isn't retried over and over.
+        self.db
+            .set_setting(LAST_GO_LIVE_SETTING, &chrono::Utc::now().to_rfc3339())
+            .await?;
+        // `wait` makes Discord reply with the message, rather than nothing.
+        let message: WebhookMessage = reqwest::Client::new()
+            .post(&config.webhook_url)
+            .query(&[("wait", "true")])
+            .json(&serde_json::json!({ "content": content, "embeds": [embed] }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        tracing::info!("Posted go-live message to Discord: {content}");
+
+        if let Some(stream) = self.db.current_stream().await? {
+            self.db
+                .set_stream_discord_message(stream.id, &message.id)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Edit or delete the go-live message of a stream that's ended.
+    pub async fn discord_go_offline(
+        &self,
+        payload: &crate::eventsub::stream::StreamOfflineV1Payload,
+        stream: Option<&crate::database::Stream>,
+    ) -> Result<()> {
+        let config = self.config.read().await.discord.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+        let Some(message_id) = stream.and_then(|stream| stream.discord_message_id.as_ref()) else {
+            return Ok(());
+        };
+        if crate::killswitch::stays_local() {
+            tracing::info!("Log-only, not changing the Discord go-live message");
+            return Ok(());
+        }
+
+        let url = format!(
+            "{}/messages/{message_id}",
+            config.webhook_url.trim_end_matches('/')
+        );
+        let http = reqwest::Client::new();
+        match config.on_offline {
+            OnOffline::Edit => {
+                let link = format!("https://twitch.tv/{}", payload.broadcaster_user_login);
+                let content = config.offline_template.replace("{link}", &link);
+                http.patch(url)
+                    .json(&serde_json::json!({ "content": content, "embeds": [] }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                tracing::info!("Edited the Discord go-live message: {content}");
+            }
+            OnOffline::Delete => {
+                http.delete(url).send().await?.error_for_status()?;
+                tracing::info!("Deleted the Discord go-live message");
+            }
+            OnOffline::Keep => (),
+        }
+
+        Ok(())
+    }
+}
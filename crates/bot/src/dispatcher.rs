@@ -0,0 +1,121 @@
+//! Handle events concurrently, so that a burst of chat, like during a raid, doesn't queue up
+//! behind one slow command. Events from the same user are still handled in the order they
+//! arrived, as are events that aren't about anyone in particular, like the stream starting.
+
+use color_eyre::Result;
+use futures::StreamExt as _;
+use twitch_api::eventsub::{channel::moderate::ActionV2, Event, Message, Payload};
+
+/// How many events can be handled at the same time.
+const MAX_CONCURRENT_EVENTS: usize = 8;
+
+/// The lane for events that aren't about a particular user.
+const CHANNEL_LANE: &str = "channel";
+
+/// An event waiting to be handled.
+pub struct QueuedEvent {
+    pub event: Event,
+    pub timestamp: twitch_api::types::Timestamp,
+}
+
+/// Which lane the event goes in. Events in the same lane are handled one at a time, in order.
+/// Deletions go in the lane of whoever wrote the message, so that they're handled after the
+/// message itself has been saved.
+fn lane(event: &Event) -> String {
+    let user_id = match event {
+        Event::ChannelChatMessageV1(Payload {
+            message: Message::Notification(payload),
+            ..
+        }) => Some(payload.chatter_user_id.as_str()),
+        Event::ChannelChatMessageDeleteV1(Payload {
+            message: Message::Notification(payload),
+            ..
+        }) => Some(payload.target_user_id.as_str()),
+        Event::ChannelChatClearUserMessagesV1(Payload {
+            message: Message::Notification(payload),
+            ..
+        }) => Some(payload.target_user_id.as_str()),
+        Event::ChannelModerateV2(Payload {
+            message: Message::Notification(payload),
+            ..
+        }) => match &payload.action {
+            ActionV2::Delete(delete) => Some(delete.user_id.as_str()),
+            ActionV2::Ban(ban) => Some(ban.user_id.as_str()),
+            ActionV2::Timeout(timeout) => Some(timeout.user_id.as_str()),
+            _ => None,
+        },
+        Event::ChannelFollowV2(Payload {
+            message: Message::Notification(payload),
+            ..
+        }) => Some(payload.user_id.as_str()),
+        Event::ChannelPointsCustomRewardRedemptionAddV1(Payload {
+            message: Message::Notification(payload),
+            ..
+        }) => Some(payload.user_id.as_str()),
+        Event::UserWhisperMessageV1(Payload {
+            message: Message::Notification(payload),
+            ..
+        }) => Some(payload.from_user_id.as_str()),
+        _ => None,
+    };
+    user_id.unwrap_or(CHANNEL_LANE).to_owned()
+}
+
+impl crate::bot::Bot {
+    /// Handle queued events, up to `MAX_CONCURRENT_EVENTS` at a time.
+    pub async fn dispatcher(
+        &self,
+        mut queue: tokio::sync::mpsc::UnboundedReceiver<QueuedEvent>,
+    ) -> Result<()> {
+        let semaphore = tokio::sync::Semaphore::new(MAX_CONCURRENT_EVENTS);
+        // Lanes with an event being handled, and the events waiting behind it.
+        let mut lanes =
+            std::collections::HashMap::<String, std::collections::VecDeque<QueuedEvent>>::new();
+        let mut running = futures::stream::FuturesUnordered::new();
+
+        loop {
+            tokio::select! {
+                maybe_queued = queue.recv() => {
+                    let Some(queued) = maybe_queued else {
+                        break;
+                    };
+                    let lane = lane(&queued.event);
+                    if let Some(waiting) = lanes.get_mut(&lane) {
+                        waiting.push_back(queued);
+                    } else {
+                        lanes.insert(lane.clone(), std::collections::VecDeque::new());
+                        running.push(self.handle_queued_event(queued, lane, &semaphore));
+                    }
+                }
+                Some(lane) = running.next(), if !running.is_empty() => {
+                    match lanes.get_mut(&lane).and_then(std::collections::VecDeque::pop_front) {
+                        Some(next) => {
+                            running.push(self.handle_queued_event(next, lane, &semaphore));
+                        }
+                        None => {
+                            lanes.remove(&lane);
+                        }
+                    }
+                }
+            }
+        }
+
+        while running.next().await.is_some() {}
+        Ok(())
+    }
+
+    /// Handle the event once there's room, returning its lane so that the next event in the
+    /// lane can be started.
+    async fn handle_queued_event(
+        &self,
+        queued: QueuedEvent,
+        lane: String,
+        semaphore: &tokio::sync::Semaphore,
+    ) -> String {
+        let _permit = semaphore.acquire().await;
+        if let Err(error) = self.handle_event(queued.event, queued.timestamp).await {
+            tracing::error!("Handling event: {error:?}");
+        }
+        lane
+    }
+}
@@ -0,0 +1,84 @@
+//! Rubber duck debugging, by chat. Questions are queued up in an on-screen panel until they're
+//! answered.
+
+use color_eyre::Result;
+
+/// The longest question shown in the on-screen panel.
+const MAX_PANEL_QUESTION_LENGTH: usize = 60;
+
+impl crate::bot::Bot {
+    /// `!duck <question>` queues a question. `!duck done <id>` archives it once it's answered.
+    pub async fn duck(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let arguments = arguments.map(str::trim).unwrap_or_default();
+        if arguments.is_empty() {
+            self.send_message_reply(
+                &payload.message_id,
+                "Usage: !duck <question>, the broadcaster can then use !duck done <id>",
+            )
+            .await?;
+            return Ok(());
+        }
+
+        let stream_id = self.db.current_stream().await?.map(|stream| stream.id);
+        if let Some(id) = arguments.strip_prefix("done ") {
+            if !Self::is_broadcaster(payload) {
+                return Ok(());
+            }
+            let Ok(id) = id.trim().trim_start_matches('#').parse::<i64>() else {
+                self.send_message_reply(&payload.message_id, "Usage: !duck done <id>")
+                    .await?;
+                return Ok(());
+            };
+            if !self.db.answer_duck(id).await? {
+                let message = format!("There's no open duck #{id}");
+                self.send_message_reply(&payload.message_id, message.as_str())
+                    .await?;
+                return Ok(());
+            }
+            self.show_ducks(stream_id).await?;
+            return Ok(());
+        }
+
+        let id = self
+            .db
+            .add_duck(
+                stream_id,
+                payload.chatter_user_id.as_str(),
+                payload.chatter_user_name.as_str(),
+                arguments,
+            )
+            .await?;
+        let message = format!("🦆 Quack! Your question is duck #{id}");
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        self.show_ducks(stream_id).await
+    }
+
+    /// Show the stream's unanswered questions on screen.
+    async fn show_ducks(&self, stream_id: Option<i64>) -> Result<()> {
+        let ducks = self.db.open_ducks(stream_id).await?;
+        let panel = if ducks.is_empty() {
+            "🦆 All ducks answered".to_owned()
+        } else {
+            let questions = ducks
+                .iter()
+                .map(|duck| {
+                    let mut question = duck.question.clone();
+                    if question.chars().count() > MAX_PANEL_QUESTION_LENGTH {
+                        question = question.chars().take(MAX_PANEL_QUESTION_LENGTH).collect();
+                        question.push('…');
+                    }
+                    format!("#{} {}: {question}", duck.id, duck.username)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("🦆 Rubber ducks\n{questions}")
+        };
+
+        Self::onscreen_popup_replacing(panel, "twitch-duck", "ducks")
+    }
+}
@@ -0,0 +1,152 @@
+use color_eyre::Result;
+
+/// How long the emote of the stream is shown in the terminal for, in seconds.
+const CROWNING_DURATION: u64 = 10;
+
+/// How many past winners `!emotehistory` shows.
+const HISTORY_LENGTH: u32 = 5;
+
+/// How often the emotes that Tattoy knows about are refreshed, to pick up new channel emotes.
+const EMOTE_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Emote rain in Tattoy, for raids.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct EmoteRainConfig {
+    pub on_raid: bool,
+    /// The emote's code, eg: `GlitchCat`.
+    pub emote: String,
+    /// The most emotes that fall, there's one for each raider up to this many.
+    pub max_emotes: u16,
+    /// How long the rain lasts, in seconds.
+    pub duration: u64,
+}
+
+impl Default for EmoteRainConfig {
+    fn default() -> Self {
+        Self {
+            on_raid: true,
+            emote: "GlitchCat".to_owned(),
+            max_emotes: 30,
+            duration: 8,
+        }
+    }
+}
+
+impl crate::bot::Bot {
+    /// Make it rain emotes in Tattoy, one for each raider.
+    pub async fn raid_emote_rain(&self, raid: &crate::bus::Raid) -> Result<()> {
+        let config = self.config.read().await.emote_rain.clone();
+        if !config.on_raid {
+            return Ok(());
+        }
+        let count = u16::try_from(raid.viewers.max(1))
+            .unwrap_or(u16::MAX)
+            .min(config.max_emotes);
+        self.send_to_tattoy(&tattoy_twitch_tombh_plugin::BotMessage::EmoteRain {
+            emote: config.emote,
+            count,
+            duration: config.duration,
+        })
+        .await
+    }
+
+    /// Keep Tattoy's emotes up to date, so that it can render emotes by their codes.
+    pub async fn emote_syncer(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(EMOTE_SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.sync_emotes().await {
+                tracing::error!("Syncing emotes with Tattoy: {error:?}");
+            }
+        }
+    }
+
+    /// Send Tattoy the codes and IDs of the global emotes and the channel's own emotes.
+    pub async fn sync_emotes(&self) -> Result<()> {
+        let token = self.token.lock().await.clone();
+        let global = self
+            .client
+            .req_get(
+                twitch_api::helix::chat::GetGlobalEmotesRequest::new(),
+                &token,
+            )
+            .await?
+            .data;
+        let channel = self
+            .client
+            .req_get(
+                twitch_api::helix::chat::GetChannelEmotesRequest::broadcaster_id(
+                    crate::BROADCASTER_ID,
+                ),
+                &token,
+            )
+            .await?
+            .data;
+
+        let ids = global
+            .into_iter()
+            .map(|emote| (emote.name, emote.id.to_string()))
+            .chain(
+                channel
+                    .into_iter()
+                    .map(|emote| (emote.name, emote.id.to_string())),
+            )
+            .collect::<std::collections::HashMap<_, _>>();
+        tracing::info!("Sending {} emotes to Tattoy", ids.len());
+        self.send_to_tattoy(&tattoy_twitch_tombh_plugin::BotMessage::Emotes { ids })
+            .await
+    }
+
+    /// Crown the most used emote of the stream.
+    pub async fn elect_emote_of_the_stream(
+        &self,
+        stream: Option<&crate::database::Stream>,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        let Some((emote_id, name, uses)) = self.db.top_emote_since(since).await? else {
+            return Ok(());
+        };
+        tracing::info!("Emote of the stream: {name} ({uses} uses)");
+
+        self.db
+            .add_emote_of_the_stream(stream.map(|stream| stream.id), &emote_id, &name, uses)
+            .await?;
+        let message = format!("👑 The emote of the stream is... {name} with {uses} uses! 👑");
+        self.send_message(message.as_str()).await?;
+        self.send_to_tattoy(&tattoy_twitch_tombh_plugin::BotMessage::Spotlight {
+            emote_id,
+            duration: CROWNING_DURATION,
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// `!emotehistory`
+    pub async fn emote_history(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let winners = self.db.emote_history(HISTORY_LENGTH).await?;
+        if winners.is_empty() {
+            self.send_message_reply(&payload.message_id, "No emote has been crowned yet")
+                .await?;
+            return Ok(());
+        }
+
+        let message = winners
+            .iter()
+            .map(|(name, uses, timestamp)| {
+                format!("{} {name} ({uses})", timestamp.format("%Y-%m-%d"))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.send_message_reply(
+            &payload.message_id,
+            format!("👑 Past emotes of the stream: {message}").as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! Receiving EventSub events as webhooks, an alternative to the websocket for when a long-lived
+//! connection is flaky. Twitch POSTs each event to a callback URL, which has to be public and
+//! HTTPS on port 443. TLS is either served directly, or by a reverse proxy in front of the bot.
+//!
+//! Every request is signed with the secret that the subscriptions were made with, anything that
+//! isn't signed, or is too old, is rejected.
+
+use axum::response::IntoResponse as _;
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+use futures::TryStreamExt as _;
+use hmac::Mac as _;
+use twitch_api::{eventsub, types, HelixClient};
+
+const MESSAGE_ID_HEADER: &str = "Twitch-Eventsub-Message-Id";
+const MESSAGE_TIMESTAMP_HEADER: &str = "Twitch-Eventsub-Message-Timestamp";
+const MESSAGE_SIGNATURE_HEADER: &str = "Twitch-Eventsub-Message-Signature";
+const MESSAGE_TYPE_HEADER: &str = "Twitch-Eventsub-Message-Type";
+
+/// Messages older than this are rejected, so that old requests can't be replayed.
+const MAX_MESSAGE_AGE: chrono::TimeDelta = chrono::TimeDelta::minutes(10);
+
+/// How many message IDs to remember. Twitch can send the same message more than once.
+const SEEN_MESSAGES: usize = 100;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct EventsubWebhookConfig {
+    /// The public HTTPS address that Twitch sends events to.
+    pub callback_url: String,
+    /// The address to listen on.
+    pub listen: String,
+    /// Between 10 and 100 characters, for checking that requests really are from Twitch.
+    pub secret: String,
+    /// A certificate, in PEM format, to serve HTTPS directly. Without it plain HTTP is served,
+    /// for a reverse proxy that handles TLS.
+    pub tls_certificate: Option<std::path::PathBuf>,
+    /// The certificate's private key, in PEM format.
+    pub tls_key: Option<std::path::PathBuf>,
+}
+
+impl Default for EventsubWebhookConfig {
+    fn default() -> Self {
+        Self {
+            callback_url: String::new(),
+            listen: "127.0.0.1:8088".to_owned(),
+            secret: String::new(),
+            tls_certificate: None,
+            tls_key: None,
+        }
+    }
+}
+
+/// What the callback server passes on to be handled.
+enum Message {
+    Notification(eventsub::Event, types::Timestamp, serde_json::Value),
+    Revocation(String),
+}
+
+/// Shared by the callback server's requests.
+struct CallbackState {
+    secret: String,
+    messages: tokio::sync::mpsc::UnboundedSender<Message>,
+    seen: std::sync::Mutex<std::collections::VecDeque<String>>,
+}
+
+/// The body of Twitch's request to confirm a new subscription.
+#[derive(serde_derive::Deserialize)]
+struct Challenge {
+    challenge: String,
+}
+
+pub struct EventsubWebhookServer {
+    pub config: EventsubWebhookConfig,
+    /// The client used to make requests to the Twitch API
+    pub client: HelixClient<'static, reqwest::Client>,
+}
+
+impl EventsubWebhookServer {
+    /// Serve the callback, subscribe to the events and handle them as they come in.
+    pub async fn run<Fut>(
+        self,
+        mut event_fn: impl FnMut(eventsub::Event, types::Timestamp, serde_json::Value) -> Fut,
+    ) -> Result<()>
+    where
+        Fut: std::future::Future<Output = Result<()>>,
+    {
+        if !(10..=100).contains(&self.config.secret.len()) {
+            eyre::bail!("The EventSub webhook secret should be 10 to 100 characters long");
+        }
+
+        let (messages, mut queue) = tokio::sync::mpsc::unbounded_channel();
+        let state = std::sync::Arc::new(CallbackState {
+            secret: self.config.secret.clone(),
+            messages,
+            seen: std::sync::Mutex::default(),
+        });
+        let app = axum::Router::new().fallback(callback).with_state(state);
+
+        // Listening starts before subscribing, because Twitch checks the callback straight away.
+        let listener = std::net::TcpListener::bind(&self.config.listen)?;
+        listener.set_nonblocking(true)?;
+        tracing::info!("Listening for EventSub webhooks on {}", self.config.listen);
+        let server = async {
+            let service = app.into_make_service();
+            match (&self.config.tls_certificate, &self.config.tls_key) {
+                (Some(certificate), Some(key)) => {
+                    let tls =
+                        axum_server::tls_rustls::RustlsConfig::from_pem_file(certificate, key)
+                            .await?;
+                    axum_server::from_tcp_rustls(listener, tls)
+                        .serve(service)
+                        .await?;
+                }
+                _ => axum_server::from_tcp(listener).serve(service).await?,
+            }
+            Ok::<(), eyre::Report>(())
+        };
+
+        let handler = async {
+            self.subscribe().await?;
+            while let Some(message) = queue.recv().await {
+                match message {
+                    Message::Notification(event, timestamp, raw) => {
+                        event_fn(event, timestamp, raw).await?;
+                    }
+                    Message::Revocation(body) => eyre::bail!("got revocation event: {body}"),
+                }
+            }
+            Ok(())
+        };
+
+        tokio::try_join!(server, handler)?;
+        Ok(())
+    }
+
+    /// Replace any subscriptions left over from before with new ones. Webhook subscriptions
+    /// need an app access token, rather than the bot's user token.
+    async fn subscribe(&self) -> Result<()> {
+        let token = crate::tokens::app_access_token(&self.client).await?;
+
+        let callback_url = self.config.callback_url.as_str();
+        let existing: Vec<_> = self
+            .client
+            .get_eventsub_subscriptions(None, None, None, &token)
+            .map_ok(|response| {
+                futures::stream::iter(
+                    response
+                        .subscriptions
+                        .into_iter()
+                        .filter(|subscription| {
+                            subscription
+                                .transport
+                                .as_webhook()
+                                .is_some_and(|webhook| webhook.callback == callback_url)
+                        })
+                        .map(Ok::<_, eyre::Report>),
+                )
+            })
+            .try_flatten()
+            .try_collect()
+            .await?;
+        for subscription in existing {
+            self.client
+                .delete_eventsub_subscription(subscription.id, &token)
+                .await?;
+        }
+
+        let transport = eventsub::Transport::webhook(callback_url, self.config.secret.clone());
+        crate::subscriptions::create_subscriptions(
+            &self.client,
+            transport,
+            &types::UserId::new(crate::BOT_ID.to_owned()),
+            &token,
+        )
+        .await?;
+        tracing::info!("Subscribed to EventSub webhooks at {callback_url}");
+        Ok(())
+    }
+}
+
+async fn callback(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<CallbackState>>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    match handle_callback(&state, &headers, &body) {
+        Ok(response) => response,
+        Err(error) => {
+            tracing::warn!("EventSub webhook request: {error:?}");
+            axum::http::StatusCode::BAD_REQUEST.into_response()
+        }
+    }
+}
+
+fn handle_callback(
+    state: &CallbackState,
+    headers: &axum::http::HeaderMap,
+    body: &[u8],
+) -> Result<axum::response::Response> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .with_context(|| format!("No {name} header"))
+    };
+    let message_id = header(MESSAGE_ID_HEADER)?;
+    let timestamp = header(MESSAGE_TIMESTAMP_HEADER)?;
+
+    if !is_signed(
+        state,
+        message_id,
+        timestamp,
+        body,
+        header(MESSAGE_SIGNATURE_HEADER)?,
+    ) {
+        tracing::warn!("EventSub webhook request with a bad signature");
+        return Ok(axum::http::StatusCode::FORBIDDEN.into_response());
+    }
+    let sent_at = chrono::DateTime::parse_from_rfc3339(timestamp)?;
+    if chrono::Utc::now() - sent_at.to_utc() > MAX_MESSAGE_AGE {
+        tracing::warn!("EventSub webhook request from {sent_at} is too old");
+        return Ok(axum::http::StatusCode::FORBIDDEN.into_response());
+    }
+
+    {
+        let mut seen = state
+            .seen
+            .lock()
+            .map_err(|error| eyre::eyre!("Seen messages lock poisoned: {error}"))?;
+        if seen.iter().any(|seen_id| seen_id == message_id) {
+            return Ok(axum::http::StatusCode::NO_CONTENT.into_response());
+        }
+        seen.push_back(message_id.to_owned());
+        if seen.len() > SEEN_MESSAGES {
+            seen.pop_front();
+        }
+    }
+
+    let text = std::str::from_utf8(body)?;
+    match header(MESSAGE_TYPE_HEADER)? {
+        "webhook_callback_verification" => {
+            let challenge: Challenge = serde_json::from_str(text)?;
+            Ok((
+                [(axum::http::header::CONTENT_TYPE, "text/plain")],
+                challenge.challenge,
+            )
+                .into_response())
+        }
+        "notification" => {
+            let event = eventsub::Event::parse(text)?;
+            let raw: serde_json::Value = serde_json::from_str(text)?;
+            let timestamp = types::Timestamp::new(timestamp.to_owned())?;
+            state
+                .messages
+                .send(Message::Notification(event, timestamp, raw))
+                .map_err(|_| eyre::eyre!("Webhook message queue closed"))?;
+            Ok(axum::http::StatusCode::NO_CONTENT.into_response())
+        }
+        "revocation" => {
+            state
+                .messages
+                .send(Message::Revocation(text.to_owned()))
+                .map_err(|_| eyre::eyre!("Webhook message queue closed"))?;
+            Ok(axum::http::StatusCode::NO_CONTENT.into_response())
+        }
+        other => eyre::bail!("Unknown message type: {other}"),
+    }
+}
+
+/// Whether the request was signed with the secret. The signature is an HMAC of the message's
+/// ID, timestamp and body.
+fn is_signed(
+    state: &CallbackState,
+    message_id: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+) -> bool {
+    let Some(Ok(signature)) = signature.strip_prefix("sha256=").map(hex::decode) else {
+        return false;
+    };
+    let Ok(mut mac) = hmac::Hmac::<sha2::Sha256>::new_from_slice(state.secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(message_id.as_bytes());
+    mac.update(timestamp.as_bytes());
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
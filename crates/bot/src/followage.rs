@@ -0,0 +1,89 @@
+use color_eyre::Result;
+use twitch_api::helix;
+
+/// How long to remember that somebody isn't following, before asking Twitch again.
+const NOT_FOLLOWING_CACHE_TIME: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// A cached answer to "when did this user follow the channel?".
+#[derive(Debug, Clone)]
+pub struct CachedFollow {
+    pub followed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub fetched: std::time::Instant,
+}
+
+impl crate::bot::Bot {
+    /// When the user followed the channel, or `None` if they don't follow it. Lookups are
+    /// cached, because during a raid the same people tend to spam the same commands.
+    pub async fn followed_at(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        if let Some(cached) = self.follow_cache.lock().await.get(user_id) {
+            if cached.followed_at.is_some() || cached.fetched.elapsed() < NOT_FOLLOWING_CACHE_TIME {
+                return Ok(cached.followed_at);
+            }
+        }
+
+        let token = self.token.lock().await.clone();
+        let request =
+            helix::channels::GetChannelFollowersRequest::broadcaster_id(crate::BROADCASTER_ID)
+                .user_id(user_id);
+        let response = self.client.req_get(request, &token).await?;
+        let followed_at = match response.data.first() {
+            Some(follower) => Some(
+                chrono::DateTime::parse_from_rfc3339(follower.followed_at.as_str())?
+                    .with_timezone(&chrono::Utc),
+            ),
+            None => None,
+        };
+
+        self.follow_cache.lock().await.insert(
+            user_id.to_owned(),
+            CachedFollow {
+                followed_at,
+                fetched: std::time::Instant::now(),
+            },
+        );
+        Ok(followed_at)
+    }
+
+    /// Whether the chatter has followed for long enough to use the command. Commands without
+    /// a configured minimum follow age are always allowed, as are moderators.
+    pub async fn passes_follow_gate(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        command: &str,
+    ) -> Result<bool> {
+        let maybe_minimum_hours = self
+            .config
+            .read()
+            .await
+            .min_follow_hours
+            .get(command)
+            .copied();
+        let Some(minimum_hours) = maybe_minimum_hours else {
+            return Ok(true);
+        };
+        if self.is_moderator(payload).await? {
+            return Ok(true);
+        }
+
+        let Some(followed_at) = self.followed_at(payload.chatter_user_id.as_str()).await? else {
+            tracing::info!(
+                "{} isn't following, ignoring !{command}",
+                payload.chatter_user_name
+            );
+            return Ok(false);
+        };
+
+        let follow_age = chrono::Utc::now() - followed_at;
+        let is_old_enough = follow_age.num_hours() >= i64::try_from(minimum_hours)?;
+        if !is_old_enough {
+            tracing::info!(
+                "{} has only followed for {follow_age}, ignoring !{command}",
+                payload.chatter_user_name
+            );
+        }
+        Ok(is_old_enough)
+    }
+}
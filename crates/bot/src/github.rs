@@ -0,0 +1,267 @@
+//! Commands for the GitHub project being worked on: `!repo`, `!issue <n>`, `!pr <n>` and
+//! `!commit`.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+
+const GITHUB_API: &str = "https://api.github.com";
+
+/// GitHub rejects API requests without a user agent.
+const USER_AGENT: &str = "tbhbot";
+
+/// The setting that overrides the configured repo, so it can be changed on stream.
+const REPO_SETTING: &str = "github.repo";
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct GithubConfig {
+    /// The repo being worked on, eg: "tombh/tattoy". Can be changed with `!repo set`.
+    pub repo: String,
+    /// A personal access token. Public repos work without one, but with a lower rate limit.
+    pub token: Option<String>,
+    /// A local checkout for `!commit` to use, so it can show commits that haven't been pushed.
+    /// GitHub is asked when it's not set.
+    pub local_path: Option<std::path::PathBuf>,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct Issue {
+    title: String,
+    state: String,
+    html_url: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct PullRequest {
+    title: String,
+    state: String,
+    html_url: String,
+    #[serde(default)]
+    merged: bool,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct CreatedIssue {
+    html_url: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct Commit {
+    sha: String,
+    html_url: String,
+    commit: CommitDetails,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct CommitDetails {
+    message: String,
+}
+
+/// The short hash and subject of the latest commit in a local checkout.
+fn latest_local_commit(path: &std::path::Path) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["log", "-1", "--format=%h %s"])
+        .output()?;
+    if !output.status.success() {
+        eyre::bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
+
+impl crate::bot::Bot {
+    /// `!repo` links to the repo being worked on. The broadcaster can change it with
+    /// `!repo set owner/name`.
+    pub async fn repo(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let arguments = arguments
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        let message = match arguments.as_slice() {
+            ["set", repo] if Self::is_broadcaster(payload) => {
+                if repo.split('/').filter(|part| !part.is_empty()).count() == 2 {
+                    self.db.set_setting(REPO_SETTING, repo).await?;
+                    format!("Now working on https://github.com/{repo}")
+                } else {
+                    "Usage: !repo set <owner/name>".to_owned()
+                }
+            }
+            _ => match self.github_repo().await? {
+                Some(repo) => format!("Working on https://github.com/{repo}"),
+                None => "There's no repo set".to_owned(),
+            },
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+
+    /// `!issue <n>` shows an issue's title, state and link.
+    pub async fn issue(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let Some((repo, number)) = self.github_target(payload, arguments, "issue").await? else {
+            return Ok(());
+        };
+        let message = match self
+            .github_get::<Issue>(&format!("repos/{repo}/issues/{number}"))
+            .await?
+        {
+            Some(issue) => format!(
+                "#{number} {} [{}] {}",
+                issue.title, issue.state, issue.html_url
+            ),
+            None => format!("Couldn't find issue #{number} in {repo}"),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+
+    /// `!pr <n>` shows a pull request's title, state and link.
+    pub async fn pull_request(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let Some((repo, number)) = self.github_target(payload, arguments, "pr").await? else {
+            return Ok(());
+        };
+        let message = match self
+            .github_get::<PullRequest>(&format!("repos/{repo}/pulls/{number}"))
+            .await?
+        {
+            Some(pull) => {
+                let state = if pull.merged {
+                    "merged"
+                } else if pull.draft && pull.state == "open" {
+                    "draft"
+                } else {
+                    pull.state.as_str()
+                };
+                format!("#{number} {} [{state}] {}", pull.title, pull.html_url)
+            }
+            None => format!("Couldn't find PR #{number} in {repo}"),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+
+    /// `!commit` shows the latest commit, so that people joining can see what just changed.
+    pub async fn commit(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let local_path = self.config.read().await.github.local_path.clone();
+        let message = if let Some(path) = local_path {
+            format!("Latest commit: {}", latest_local_commit(&path)?)
+        } else if let Some(repo) = self.github_repo().await? {
+            match self
+                .github_get::<Commit>(&format!("repos/{repo}/commits/HEAD"))
+                .await?
+            {
+                Some(commit) => {
+                    let subject = commit.commit.message.lines().next().unwrap_or_default();
+                    let hash = commit.sha.get(..7).unwrap_or(&commit.sha);
+                    format!("Latest commit: {hash} {subject} {}", commit.html_url)
+                }
+                None => format!("Couldn't find any commits in {repo}"),
+            }
+        } else {
+            "There's no repo set".to_owned()
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+
+    /// Open an issue, returning its URL. Needs a token in the config.
+    pub async fn create_github_issue(&self, repo: &str, title: &str, body: &str) -> Result<String> {
+        let token = self
+            .config
+            .read()
+            .await
+            .github
+            .token
+            .clone()
+            .context("Opening GitHub issues needs a token in the `[github]` config")?;
+        let issue: CreatedIssue = reqwest::Client::new()
+            .post(format!("{GITHUB_API}/repos/{repo}/issues"))
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "title": title, "body": body }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(issue.html_url)
+    }
+
+    /// The repo being worked on, if there is one.
+    async fn github_repo(&self) -> Result<Option<String>> {
+        if let Some(repo) = self
+            .db
+            .get_setting(REPO_SETTING)
+            .await?
+            .filter(|repo| !repo.is_empty())
+        {
+            return Ok(Some(repo));
+        }
+        let repo = self.config.read().await.github.repo.clone();
+        Ok((!repo.is_empty()).then_some(repo))
+    }
+
+    /// The repo and number that an `!issue` or `!pr` command is about. Replies with the usage
+    /// and returns `None` if either is missing.
+    async fn github_target(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+        command: &str,
+    ) -> Result<Option<(String, u64)>> {
+        let number = arguments
+            .map(|argument| argument.trim().trim_start_matches('#'))
+            .and_then(|argument| argument.parse::<u64>().ok());
+        let Some(number) = number else {
+            let message = format!("Usage: !{command} <number>");
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(None);
+        };
+        let Some(repo) = self.github_repo().await? else {
+            self.send_message_reply(&payload.message_id, "There's no repo set")
+                .await?;
+            return Ok(None);
+        };
+        Ok(Some((repo, number)))
+    }
+
+    /// Get something from the GitHub API, `None` if it doesn't exist.
+    async fn github_get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        let token = self.config.read().await.github.token.clone();
+        let mut request = reqwest::Client::new()
+            .get(format!("{GITHUB_API}/{path}"))
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+}
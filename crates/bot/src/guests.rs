@@ -0,0 +1,88 @@
+use color_eyre::Result;
+
+const USAGE: &str = "Usage: !guest add|remove <user> or !guest list";
+
+impl crate::bot::Bot {
+    /// `!guest add <user>` makes somebody a guest for the rest of the stream. Guests get the
+    /// same bot permissions as moderators and skip cooldowns. `!guest remove <user>` and
+    /// `!guest list` do what they say.
+    pub async fn guest(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let mut parts = arguments.unwrap_or_default().split_whitespace();
+        let action = parts.next();
+        let target = parts.next().map(|user| user.trim_start_matches('@'));
+        match (action, target) {
+            (Some("list"), _) => {
+                let guests = self.db.users_with_role(crate::roles::Role::Guest).await?;
+                let message = if guests.is_empty() {
+                    "No guests today".to_owned()
+                } else {
+                    format!("Today's guests: {}", guests.join(", "))
+                };
+                self.send_message_reply(&payload.message_id, message.as_str())
+                    .await?;
+            }
+            (Some(action @ ("add" | "remove")), Some(target)) => {
+                let token = self.token.lock().await.clone();
+                let Some(user) = self.client.get_user_from_login(target, &token).await? else {
+                    let message = format!("Couldn't find {target} on Twitch");
+                    self.send_message_reply(&payload.message_id, message.as_str())
+                        .await?;
+                    return Ok(());
+                };
+
+                if action == "add" {
+                    self.db
+                        .add_role(
+                            user.id.as_str(),
+                            user.display_name.as_str(),
+                            crate::roles::Role::Guest,
+                        )
+                        .await?;
+                    let message = format!(
+                        "🌟 Please welcome our very special guest {}! 🌟 https://twitch.tv/{}",
+                        user.display_name, user.login
+                    );
+                    self.send_message(message.as_str()).await?;
+                } else {
+                    self.db
+                        .remove_role(user.id.as_str(), crate::roles::Role::Guest)
+                        .await?;
+                    self.send_message_reply(&payload.message_id, "Guest removed")
+                        .await?;
+                }
+
+                self.db
+                    .audit(
+                        payload.chatter_user_name.as_str(),
+                        &format!("guest {action}"),
+                        Some(user.display_name.as_str()),
+                        None,
+                    )
+                    .await?;
+            }
+            _ => {
+                self.send_message_reply(&payload.message_id, USAGE).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Guests only last for a single stream.
+    pub async fn expire_guests(&self) -> Result<()> {
+        for guest in self.db.users_with_role(crate::roles::Role::Guest).await? {
+            self.db
+                .audit("bot", "guest expired", Some(guest.as_str()), None)
+                .await?;
+        }
+        self.db.replace_roles(crate::roles::Role::Guest, &[]).await
+    }
+}
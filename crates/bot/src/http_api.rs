@@ -0,0 +1,199 @@
+//! A small HTTP API for local tools, like Stream Deck buttons, to show popups, play sounds and
+//! send chat messages in the same way that chat commands do:
+//!
+//! * `POST /osd` shows the body as a popup.
+//! * `POST /sound/<name>` plays a sound from the sounds directory, eg: `/sound/great_scott.mp3`.
+//! * `POST /say` sends the body to chat.
+//!
+//! Every request needs the configured token, as `Authorization: Bearer <token>`.
+
+use axum::response::IntoResponse as _;
+use color_eyre::Result;
+use hmac::Mac as _;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct HttpApiConfig {
+    pub enabled: bool,
+    /// The address to listen on.
+    pub listen: String,
+    /// What requests have to send to be allowed, at least 16 characters. It's a secret, so it's
+    /// set with `TBHBOT_HTTP_API__TOKEN` rather than in the config file.
+    pub token: String,
+}
+
+impl Default for HttpApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:8090".to_owned(),
+            token: String::new(),
+        }
+    }
+}
+
+/// Something that a request asks the bot to do.
+#[derive(Debug)]
+enum Action {
+    Osd(String),
+    Sound(String),
+    Say(String),
+}
+
+/// An action, with where to send whether it worked.
+struct Request {
+    action: Action,
+    reply: tokio::sync::oneshot::Sender<Result<()>>,
+}
+
+/// Shared by the API's requests.
+struct ApiState {
+    token: String,
+    requests: tokio::sync::mpsc::UnboundedSender<Request>,
+}
+
+impl crate::bot::Bot {
+    /// Serve the API, if it's enabled.
+    pub async fn http_api(&self) -> Result<()> {
+        let config = self.config.read().await.http_api.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+        if config.token.len() < 16 {
+            eyre::bail!("The HTTP API token should be at least 16 characters long");
+        }
+
+        let (requests, mut queue) = tokio::sync::mpsc::unbounded_channel();
+        let state = std::sync::Arc::new(ApiState {
+            token: config.token.clone(),
+            requests,
+        });
+        let app = axum::Router::new()
+            .route("/osd", axum::routing::post(osd))
+            .route("/sound/{name}", axum::routing::post(sound))
+            .route("/say", axum::routing::post(say))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind(&config.listen).await?;
+        tracing::info!("Serving the HTTP API on http://{}", config.listen);
+
+        let server = async {
+            axum::serve(listener, app).await?;
+            Ok::<(), eyre::Report>(())
+        };
+        let handler = async {
+            while let Some(request) = queue.recv().await {
+                tracing::info!("HTTP API: {:?}", request.action);
+                let result = self.run_api_action(request.action).await;
+                let _ = request.reply.send(result);
+            }
+            Ok(())
+        };
+
+        tokio::try_join!(server, handler)?;
+        Ok(())
+    }
+
+    async fn run_api_action(&self, action: Action) -> Result<()> {
+        match action {
+            Action::Osd(text) => {
+                if !self
+                    .is_osd_enabled
+                    .load(std::sync::atomic::Ordering::Relaxed)
+                {
+                    return Ok(());
+                }
+                Self::onscreen_popup(format!(" {}", text.trim()), "twitch-osd")
+            }
+            Action::Sound(name) => {
+                let is_in_sounds_directory = !name.is_empty()
+                    && !name.contains('/')
+                    && !name.contains('\\')
+                    && name != ".."
+                    && name != ".";
+                if !is_in_sounds_directory {
+                    eyre::bail!("Not a sound: {name}");
+                }
+                let path = crate::paths::sound(&name);
+                if !path.exists() {
+                    eyre::bail!("No such sound: {name}");
+                }
+                Self::play_sound(path)?;
+                Ok(())
+            }
+            Action::Say(text) => self.send_message(text.trim()).await,
+        }
+    }
+}
+
+async fn osd(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<ApiState>>,
+    headers: axum::http::HeaderMap,
+    body: String,
+) -> axum::response::Response {
+    run(&state, &headers, Action::Osd(body)).await
+}
+
+async fn sound(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<ApiState>>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    headers: axum::http::HeaderMap,
+) -> axum::response::Response {
+    run(&state, &headers, Action::Sound(name)).await
+}
+
+async fn say(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<ApiState>>,
+    headers: axum::http::HeaderMap,
+    body: String,
+) -> axum::response::Response {
+    run(&state, &headers, Action::Say(body)).await
+}
+
+/// Pass an authorised request on to the bot, and wait for it to be done.
+async fn run(
+    state: &ApiState,
+    headers: &axum::http::HeaderMap,
+    action: Action,
+) -> axum::response::Response {
+    if !has_bearer_token(headers, &state.token) {
+        tracing::warn!("HTTP API request without the right token");
+        return axum::http::StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let (reply, result) = tokio::sync::oneshot::channel();
+    if state.requests.send(Request { action, reply }).is_err() {
+        return axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    match result.await {
+        Ok(Ok(())) => axum::http::StatusCode::NO_CONTENT.into_response(),
+        Ok(Err(error)) => {
+            tracing::warn!("HTTP API request failed: {error:?}");
+            (axum::http::StatusCode::BAD_REQUEST, error.to_string()).into_response()
+        }
+        Err(_) => axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+/// Whether a request has `token` as its `Authorization: Bearer` header.
+pub fn has_bearer_token(headers: &axum::http::HeaderMap, token: &str) -> bool {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| is_same_token(given, token))
+}
+
+/// Compare tokens in constant time, so that how long it takes doesn't give away how much of the
+/// token was right. Both tokens are signed and the signatures verified, because verifying is
+/// constant time.
+pub fn is_same_token(given: &str, token: &str) -> bool {
+    let sign = |key: &str| {
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(key.as_bytes()).ok()?;
+        mac.update(b"tbhbot");
+        Some(mac)
+    };
+    let (Some(given), Some(token)) = (sign(given), sign(token)) else {
+        return false;
+    };
+    given.verify_slice(&token.finalize().into_bytes()).is_ok()
+}
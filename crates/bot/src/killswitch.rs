@@ -0,0 +1,154 @@
+//! A break-glass way to silence the bot while live, for when something is misbehaving and the
+//! terminal isn't to hand. Once shut down, the bot keeps running and logging but doesn't play
+//! sounds, show popups, send anything to Tattoy or send anything to chat, until it's re-enabled.
+//!
+//! It can be used either by the broadcaster over whispers, or by writing a line to the control
+//! socket, eg: `echo "shutdown now" | socat - UNIX-CONNECT:~/.local/state/tbhbot/control.sock`.
+//!
+//! Simulated and replayed events are handled as dry runs, which are like a shutdown that only
+//! applies to them, except that sounds, popups and the overlay still work so that alerts can be
+//! tested. See `dry_run()`.
+
+use color_eyre::Result;
+use rand::Rng as _;
+use std::os::unix::fs::PermissionsExt as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _};
+
+/// This is global rather than on `Bot`, because sounds and popups are played from places that
+/// don't have access to the bot.
+static IS_LOG_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// The length of the token needed to confirm a shutdown.
+const TOKEN_LENGTH: usize = 6;
+
+const HELP: &str = "Kill switch commands: shutdown now, enable";
+
+tokio::task_local! {
+    /// Set while a simulated or replayed event is being handled.
+    static IS_DRY_RUN: bool;
+}
+
+/// Whether the bot has been shut down and should only log what it would otherwise do.
+pub fn is_log_only() -> bool {
+    IS_LOG_ONLY.load(Ordering::Relaxed)
+}
+
+/// Whether nothing should leave this computer, not to chat, Twitch or anywhere else, because
+/// the bot's been shut down or it's a dry run.
+pub fn stays_local() -> bool {
+    is_log_only() || is_dry_run()
+}
+
+/// Whether a simulated or replayed event is being handled.
+pub fn is_dry_run() -> bool {
+    IS_DRY_RUN
+        .try_with(|is_dry_run| *is_dry_run)
+        .unwrap_or(false)
+}
+
+/// Handle a simulated or replayed event as a dry run. Nothing is sent anywhere, see
+/// `stays_local()`, and anything it saves goes to a scratch copy of the database, see
+/// `Database::prepare_dry_run()`. Only the event itself
+/// is affected, real events carry on as normal. Tasks that it spawns, like `!timer` countdowns,
+/// aren't part of the dry run.
+pub async fn dry_run<F: std::future::Future>(future: F) -> F::Output {
+    IS_DRY_RUN.scope(true, future).await
+}
+
+/// Where the control socket lives.
+pub fn socket_path() -> std::path::PathBuf {
+    crate::paths::state_directory().join("control.sock")
+}
+
+/// Send a line to the running bot's control socket, returning its reply.
+pub async fn send_control_line(line: &str) -> Result<String> {
+    let stream = tokio::net::UnixStream::connect(socket_path()).await?;
+    let (reader, mut writer) = stream.into_split();
+    writer.write_all(format!("{line}\n").as_bytes()).await?;
+    writer.shutdown().await?;
+
+    let mut reply = String::new();
+    tokio::io::BufReader::new(reader)
+        .read_line(&mut reply)
+        .await?;
+    Ok(reply.trim_end().to_owned())
+}
+
+impl crate::bot::Bot {
+    /// Handle a kill switch command, returning the reply to send back.
+    ///
+    /// `shutdown now` replies with a one-off token, then `shutdown now <token>` actually shuts
+    /// the bot down. The token protects against a stray or replayed command.
+    pub async fn kill_switch(&self, text: &str) -> String {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["shutdown", "now"] => {
+                let token = rand::rng()
+                    .sample_iter(&rand::distr::Alphanumeric)
+                    .take(TOKEN_LENGTH)
+                    .map(char::from)
+                    .collect::<String>();
+                let reply = format!("To confirm, send: shutdown now {token}");
+                *self.kill_switch_token.lock().await = Some(token);
+                reply
+            }
+            ["shutdown", "now", confirmation] => {
+                let mut token = self.kill_switch_token.lock().await;
+                if token.as_deref() != Some(*confirmation) {
+                    return "Wrong or expired token, send: shutdown now".to_owned();
+                }
+                *token = None;
+                IS_LOG_ONLY.store(true, Ordering::Relaxed);
+                tracing::warn!("Kill switch: shut down, only logging from now on");
+                "Shut down. No more sounds, popups or chat until: enable".to_owned()
+            }
+            ["enable"] => {
+                IS_LOG_ONLY.store(false, Ordering::Relaxed);
+                tracing::warn!("Kill switch: re-enabled");
+                "Re-enabled".to_owned()
+            }
+            _ => HELP.to_owned(),
+        }
+    }
+
+    /// Listen for kill switch commands on the control socket. Only the user running the bot
+    /// can write to it.
+    pub async fn controller(&self) -> Result<()> {
+        let path = socket_path();
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        tracing::info!("Listening for control commands on {path:?}");
+
+        loop {
+            let (stream, _) = listener.accept().await?;
+            if let Err(error) = self.control_connection(stream).await {
+                tracing::error!("Control socket connection: {error:?}");
+            }
+        }
+    }
+
+    async fn control_connection(&self, stream: tokio::net::UnixStream) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = tokio::io::BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            tracing::info!("Control command: {line}");
+            let mut reply = match line.split_once(' ') {
+                Some((crate::simulations::CONTROL_COMMAND, json)) => self.simulate(json).await,
+                Some((crate::recordings::CONTROL_COMMAND, json)) => self.replay_event(json).await,
+                Some((crate::admin::CONTROL_COMMAND, json)) => self.admin(json).await,
+                _ => self.kill_switch(&line).await,
+            };
+            reply.push('\n');
+            writer.write_all(reply.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+}
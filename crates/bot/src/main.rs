@@ -1,14 +1,85 @@
+pub mod achievements;
+pub mod admin;
+pub mod ads;
+pub mod archive;
+pub mod arrivals;
+pub mod automod;
+pub mod backfill;
+pub mod backups;
 pub mod bot;
+pub mod bug_reports;
+pub mod bus;
+pub mod captions;
+pub mod challenges;
+pub mod changelog;
+pub mod chat_commands;
+pub mod cheers;
+pub mod chickens;
+pub mod classifier;
 pub mod commands;
+pub mod conduits;
+pub mod config;
+pub mod crossposts;
+pub mod dashboard;
 pub mod database;
+pub mod deletions;
+pub mod discord;
+pub mod dispatcher;
+pub mod ducks;
+pub mod emotes;
+pub mod eventsub_webhook;
+pub mod followage;
+pub mod github;
+pub mod guests;
+pub mod http_api;
+pub mod killswitch;
+pub mod outbox;
+pub mod overlay;
+pub mod paths;
+pub mod plugins;
+pub mod polls;
+pub mod pomodoro;
+pub mod presence;
+pub mod previews;
+pub mod profiles;
+pub mod quiet_hours;
+pub mod quotes;
+pub mod raffles;
+pub mod raids;
+pub mod recordings;
+pub mod redemptions;
+pub mod reminders;
+pub mod replies;
+pub mod rewards;
+pub mod roles;
+pub mod shutdown;
+pub mod simulations;
+pub mod sql;
+pub mod stats;
+pub mod streams;
+pub mod subscriptions;
+pub mod supervisor;
+pub mod themes;
+pub mod timers;
+pub mod timezones;
+pub mod tips;
+pub mod titles;
+pub mod today;
+pub mod todos;
+pub mod tokens;
+pub mod utils;
+pub mod watchdog;
+pub mod webhooks;
 pub mod websocket;
+pub mod whispers;
+pub mod whois;
+pub mod youtube;
 
 use std::sync::Arc;
 
 use clap::Parser;
 use color_eyre::Result;
-use eyre::{ContextCompat as _, WrapErr as _};
-use std::io::Write as _;
+use eyre::WrapErr as _;
 use tokio::sync::Mutex;
 use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _};
 use twitch_api::{
@@ -16,11 +87,10 @@ use twitch_api::{
     eventsub::{self},
     HelixClient,
 };
-use twitch_oauth2::{Scope, TwitchToken as _};
+use twitch_oauth2::TwitchToken as _;
 
 pub const BROADCASTER_ID: &str = "630634223";
 const BOT_ID: &str = "630634223";
-const TWITCH_CLI_ENV_PATH: &str = "/home/streamer/.config/tbhbot/.env";
 
 #[derive(Parser, Debug, Clone)]
 #[clap(about, version)]
@@ -31,114 +101,216 @@ pub struct Cli {
     /// Mock websocket server for testing
     #[clap(long)]
     pub ws_server: Option<url::Url>,
+    /// How Twitch sends events to the bot. Webhooks need the `[eventsub_webhook]` config
+    #[clap(long, value_enum, default_value_t = Transport::Websocket)]
+    pub transport: Transport,
+    /// Path to the config file, defaults to `config.toml` in the workspace
+    #[clap(long)]
+    pub config: Option<std::path::PathBuf>,
+    /// Override a config value, eg: `--set 'min_follow_hours.chirp=0'`
+    #[clap(long, value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+    /// Where to keep the database, defaults to `$XDG_DATA_HOME/tbhbot`
+    #[clap(long)]
+    pub data_directory: Option<std::path::PathBuf>,
+    /// Where to keep tokens, archives and backups, defaults to `$XDG_STATE_HOME/tbhbot`
+    #[clap(long)]
+    pub state_directory: Option<std::path::PathBuf>,
+    /// Where to find sounds, defaults to `sounds` in the data directory
+    #[clap(long)]
+    pub sounds_directory: Option<std::path::PathBuf>,
+    /// Where to write logs, defaults to `logs` in the state directory
+    #[clap(long)]
+    pub log_directory: Option<std::path::PathBuf>,
+    #[clap(subcommand)]
+    pub command: Option<CliCommand>,
 }
 
-#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug)]
-pub struct Config {
-    command: Vec<Command>,
-}
-
-#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug)]
-pub struct Command {
-    pub trigger: Vec<String>,
-    pub response: String,
+/// How EventSub events are received.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// A websocket connection that the bot makes to Twitch.
+    Websocket,
+    /// Requests that Twitch makes to the bot, see `eventsub_webhook.rs`.
+    Webhook,
+    /// A websocket for each shard of a conduit, see `conduits.rs`.
+    Conduit,
 }
 
-impl Config {
-    pub fn load(path: &std::path::Path) -> Result<Self, eyre::Report> {
-        let config = std::fs::read_to_string(path)?;
-        toml::from_str(&config).wrap_err("Failed to parse config")
-    }
+/// One-off tasks, that run instead of the bot.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum CliCommand {
+    /// Show the saved Twitch token's user, expiry and scopes
+    TokenStatus,
+    /// Send a made up event to the running bot, to test alerts off-stream
+    Simulate {
+        #[clap(subcommand)]
+        event: simulations::SimulatedEvent,
+    },
+    /// Send recorded events to the running bot again, eg: from `events.jsonl`
+    Replay {
+        file: std::path::PathBuf,
+        /// How many times faster than real time to replay them
+        #[clap(long, default_value_t = 1.0)]
+        speed: f64,
+    },
+    /// Administer the running bot, for when chat isn't working
+    Ctl {
+        #[clap(subcommand)]
+        command: admin::AdminCommand,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), eyre::Report> {
     let mut is_restart = false;
+    let mut previous_crash = None;
     color_eyre::install()?;
+    let cli_args = Cli::parse();
+    paths::set_paths(&cli_args)?;
+
+    let log_directory = paths::log_directory();
+    std::fs::create_dir_all(&log_directory)?;
+    let log_file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_directory.join("tbhbot.log"))?;
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(std::sync::Mutex::new(log_file)),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| bug_reports::RecentLogs),
+        )
         .with(tracing_subscriber::EnvFilter::from_default_env())
         .init();
-    dotenvy::from_path(TWITCH_CLI_ENV_PATH).expect("Couldn't load .env file");
-    for _ in 0..100 {
-        let result = initialise(is_restart).await;
-        if let Err(error) = result {
-            tracing::error!("App crashed: {error:?}");
+    paths::log_paths();
+    dotenvy::from_path(paths::config_directory().join(".env")).expect("Couldn't load .env file");
+
+    match &cli_args.command {
+        Some(CliCommand::TokenStatus) => {
+            let client: HelixClient<reqwest::Client> = twitch_api::HelixClient::with_client(
+                ClientDefault::default_client_with_name(Some("tombh_chatbot".parse()?))?,
+            );
+            for line in tokens::token_status(&client).await? {
+                println!("{line}");
+            }
+            return Ok(());
+        }
+        Some(CliCommand::Simulate { event }) => {
+            println!("{}", simulations::send(event).await?);
+            return Ok(());
         }
+        Some(CliCommand::Replay { file, speed }) => {
+            recordings::replay(file, *speed).await?;
+            return Ok(());
+        }
+        Some(CliCommand::Ctl { command }) => {
+            println!("{}", admin::send(command).await?);
+            return Ok(());
+        }
+        None => (),
+    }
+
+    let mut backoff = supervisor::Backoff::default();
+    loop {
+        let started = std::time::Instant::now();
+        let result = initialise(is_restart, previous_crash).await;
+        previous_crash = match result {
+            Ok(()) => None,
+            Err(error) if supervisor::is_fatal(&error) => {
+                tracing::error!("App crashed, not restarting: {error:?}");
+                return Err(error);
+            }
+            Err(error) => {
+                tracing::error!("App crashed: {error:?}");
+                Some(Crash {
+                    category: error_category(&error),
+                    timestamp: chrono::Utc::now(),
+                })
+            }
+        };
         is_restart = true;
-        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-        tracing::info!("Restarting bot");
+
+        if previous_crash.is_none() || started.elapsed() >= supervisor::STABLE_AFTER {
+            backoff.reset();
+        }
+        let delay = backoff.next_delay();
+        tracing::info!("Restarting bot in {delay:?}");
+        tokio::time::sleep(delay).await;
     }
+}
 
-    Ok(())
+/// Details about the previous run of the bot crashing.
+#[derive(Debug, Clone, Copy)]
+pub struct Crash {
+    /// What kind of error caused the crash.
+    pub category: &'static str,
+    /// When the crash happened, events since then may have been missed.
+    pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
-async fn initialise(is_restart: bool) -> Result<(), eyre::Report> {
+/// A rough, human-friendly description of what kind of thing caused a crash.
+pub fn error_category(error: &eyre::Report) -> &'static str {
+    for cause in error.chain() {
+        if cause.is::<sqlx::Error>() {
+            return "database";
+        }
+        if cause.is::<tokio_tungstenite::tungstenite::Error>() {
+            return "websocket";
+        }
+        if cause.is::<reqwest::Error>() {
+            return "network";
+        }
+        if cause.is::<twitch_api::helix::ClientRequestError<reqwest::Error>>() {
+            return "Twitch API";
+        }
+        if cause.is::<std::io::Error>() {
+            return "IO";
+        }
+    }
+    "unknown"
+}
+
+async fn initialise(is_restart: bool, previous_crash: Option<Crash>) -> Result<(), eyre::Report> {
     let cli_args = Cli::parse();
-    let config = Config::load(&workspace_dir().join("config.toml"))?;
+    let config = config::Config::load(&cli_args)?;
+    quiet_hours::set_quiet_hours(&config.quiet_hours);
 
     let client: HelixClient<reqwest::Client> = twitch_api::HelixClient::with_client(
         ClientDefault::default_client_with_name(Some("tombh_chatbot".parse()?))?,
     );
 
-    let user = std::env::var("USER").expect("No value in `$USER` ENV var");
-    let state_directory = std::path::PathBuf::from(format!("/home/{user}/.local/state/tbhbot"));
-    let access_token_path = state_directory.join("access.token");
-    let refresh_token_path = state_directory.join("refresh.token");
-
     let client_secret_string =
         std::env::var("CLIENTSECRET").expect("Couldn't find CLIENTSECRET in the environment");
     let client_secret = twitch_oauth2::ClientSecret::new(client_secret_string);
 
     let token = if !cli_args.get_new_token || is_restart {
-        let mut access_token_string = std::fs::read_to_string(access_token_path)?;
-        access_token_string = access_token_string.trim().to_string();
-        let access_token = twitch_oauth2::AccessToken::from(access_token_string);
-
-        let mut refresh_token_string = std::fs::read_to_string(refresh_token_path)?;
-        refresh_token_string = refresh_token_string.trim().to_string();
-        let refresh_token = twitch_oauth2::RefreshToken::from(refresh_token_string);
-
+        let (access_token, refresh_token) = tokens::load_tokens().wrap_err(supervisor::Fatal)?;
         twitch_oauth2::UserToken::from_existing(
             &client,
             access_token,
             Some(refresh_token),
             Some(client_secret),
         )
-        .await?
+        .await
+        .map_err(supervisor::fatal_if_bad_credentials)?
     } else {
         let client_id_string =
             std::env::var("CLIENTID").expect("Couldn't find CLIENTID in the environment");
         let mut builder = twitch_oauth2::tokens::DeviceUserTokenBuilder::new(
             client_id_string,
-            [
-                Scope::UserReadChat,
-                Scope::UserWriteChat,
-                Scope::ModeratorReadFollowers,
-            ]
-            .to_vec(),
+            tokens::SCOPES.to_vec(),
         );
         let code = builder.start(&client).await?;
         println!("Please go to: {}", code.verification_uri);
         let mut token = builder.wait_for_code(&client, tokio::time::sleep).await?;
-
-        let mut access_token_file = std::fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(access_token_path)?;
-        writeln!(access_token_file, "{}", token.token().secret())?;
-
-        let refresh_token = token
-            .refresh_token
-            .clone()
-            .context("Couldn't get refresh token")?;
-        let mut refresh_token_file = std::fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(refresh_token_path)?;
-        writeln!(refresh_token_file, "{}", refresh_token.secret())?;
+        tokens::save_tokens(&token, &config.tokens)?;
 
         token.set_secret(Some(client_secret));
 
@@ -158,16 +330,45 @@ async fn initialise(is_restart: bool) -> Result<(), eyre::Report> {
         .unwrap();
     let tattoy_socket = Arc::new(Mutex::new(socket));
 
+    let plugins = plugins::load_plugins(&config.plugins);
+    let (outbox, outbox_queue) = tokio::sync::mpsc::unbounded_channel();
+    let config_path = config::Config::path(&cli_args);
+    let legacy_directory = config_path
+        .parent()
+        .unwrap_or_else(|| std::path::Path::new("."));
+    let db = database::Database::new(&config.database, &paths::data_directory(), legacy_directory)
+        .await?;
     let bot = bot::Bot {
-        db: database::Database::new().await?,
+        db,
         opts: cli_args,
         client,
         token,
-        config,
+        config: Arc::new(tokio::sync::RwLock::new(config)),
         broadcaster,
         tattoy_socket,
+        outbox,
+        noted_mates_seen: Mutex::default(),
+        held_automod_messages: Mutex::default(),
+        is_paused: false.into(),
+        is_osd_enabled: true.into(),
+        kill_switch_token: Mutex::default(),
+        pending_raid: Mutex::default(),
+        pomodoro: Mutex::default(),
+        timer: Mutex::default(),
+        raffle: Mutex::default(),
+        challenge: Mutex::default(),
+        title_vote: Mutex::default(),
+        chosen_theme: Mutex::default(),
+        follow_cache: Mutex::default(),
+        account_created_cache: Mutex::default(),
+        plugins,
+        bus: tokio::sync::broadcast::channel(bus::CAPACITY).0,
+        overlay: overlay::channel(),
+        command_cooldowns: Mutex::default(),
+        conduit_sessions: Arc::default(),
+        recovered_from: previous_crash,
     };
-    bot.start().await?;
+    bot.start(outbox_queue).await?;
     Ok(())
 }
 
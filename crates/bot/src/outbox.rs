@@ -0,0 +1,137 @@
+//! All chat messages go through a rate limited queue, so that bursts, like 20 people typing
+//! the same command, don't go over Twitch's message limits and get the bot's messages dropped.
+
+use color_eyre::Result;
+
+/// How many messages can be sent in one go.
+const BURST_SIZE: f64 = 20.0;
+
+/// How long it takes for a full burst of messages to be allowed again. Twitch allows 20
+/// messages every 30 seconds for normal chatters.
+const REFILL_PERIOD: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// A chat message waiting to be sent.
+#[derive(Debug)]
+pub enum Outgoing {
+    Message(String),
+    Reply {
+        parent_message_id: twitch_api::types::MsgId,
+        message: String,
+    },
+    Announcement {
+        message: String,
+        colour: twitch_api::helix::chat::AnnouncementColor,
+    },
+}
+
+/// A token bucket. Each message uses a token, and tokens slowly refill up to `BURST_SIZE`.
+struct TokenBucket {
+    tokens: f64,
+    last_refill: tokio::time::Instant,
+}
+
+impl TokenBucket {
+    fn new() -> Self {
+        Self {
+            tokens: BURST_SIZE,
+            last_refill: tokio::time::Instant::now(),
+        }
+    }
+
+    /// Wait until there's a token and then use it.
+    async fn take(&mut self) {
+        let refill_rate = BURST_SIZE / REFILL_PERIOD.as_secs_f64();
+        loop {
+            let now = tokio::time::Instant::now();
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * refill_rate).min(BURST_SIZE);
+            self.last_refill = now;
+
+            if self.tokens >= 1.0 {
+                self.tokens -= 1.0;
+                return;
+            }
+
+            let wait = (1.0 - self.tokens) / refill_rate;
+            tracing::debug!("Rate limiting chat messages for {wait:.1}s");
+            tokio::time::sleep(std::time::Duration::from_secs_f64(wait)).await;
+        }
+    }
+}
+
+impl crate::bot::Bot {
+    /// Send queued chat messages, no faster than the rate limit allows.
+    pub async fn outboxer(
+        &self,
+        queue: &mut tokio::sync::mpsc::UnboundedReceiver<Outgoing>,
+    ) -> Result<()> {
+        let mut bucket = TokenBucket::new();
+        while let Some(outgoing) = queue.recv().await {
+            if crate::killswitch::is_log_only() {
+                tracing::info!("Log-only, not sending: {outgoing:?}");
+                continue;
+            }
+            bucket.take().await;
+            if let Err(error) = self.deliver(&outgoing).await {
+                tracing::error!("Sending {outgoing:?}: {error:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn deliver(&self, outgoing: &Outgoing) -> Result<()> {
+        let token = self.token.lock().await.clone();
+        match outgoing {
+            Outgoing::Message(message) => {
+                self.client
+                    .send_chat_message(
+                        crate::BROADCASTER_ID,
+                        crate::BOT_ID,
+                        message.as_str(),
+                        &token,
+                    )
+                    .await?;
+            }
+            Outgoing::Reply {
+                parent_message_id,
+                message,
+            } => {
+                self.client
+                    .send_chat_message_reply(
+                        crate::BROADCASTER_ID,
+                        crate::BOT_ID,
+                        parent_message_id,
+                        message.as_str(),
+                        &token,
+                    )
+                    .await?;
+            }
+            Outgoing::Announcement { message, colour } => {
+                self.client
+                    .send_chat_announcement(
+                        crate::BROADCASTER_ID,
+                        crate::BOT_ID,
+                        message.as_str(),
+                        colour.clone(),
+                        &token,
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add a message to the queue of messages to send.
+    pub fn queue_message(&self, outgoing: Outgoing) -> Result<()> {
+        // The outboxer isn't part of the dry run, so it can't tell.
+        if crate::killswitch::is_dry_run() {
+            tracing::info!("Dry run, not sending: {outgoing:?}");
+            return Ok(());
+        }
+        self.outbox
+            .send(outgoing)
+            .map_err(|error| eyre::eyre!("Chat message queue closed: {error}"))
+    }
+}
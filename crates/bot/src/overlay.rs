@@ -0,0 +1,179 @@
+//! An alerts overlay for OBS. Add `http://127.0.0.1:8091` as a browser source and alerts for
+//! follows, raids, subscriptions, tips and cheers are animated on it, so that viewers actually
+//! see them. The page gets the alerts over a websocket, and reconnects if the bot restarts.
+
+use axum::response::IntoResponse as _;
+use color_eyre::Result;
+
+/// How many alerts a slow overlay can fall behind before it misses some.
+const CAPACITY: usize = 32;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct OverlayConfig {
+    pub enabled: bool,
+    /// The address to serve the overlay on.
+    pub listen: String,
+    /// Whether alerts are still shown as desktop popups as well.
+    pub popups: bool,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:8091".to_owned(),
+            popups: true,
+        }
+    }
+}
+
+/// An alert, as it's sent to the overlay.
+#[derive(serde_derive::Serialize, Debug, Clone)]
+pub struct OverlayAlert {
+    /// eg: `follow`, the page uses it as a CSS class.
+    pub kind: String,
+    pub message: String,
+    /// The colour of the current theme, if it has one.
+    pub colour: Option<String>,
+}
+
+/// Where alerts are sent, for every overlay that's connected.
+pub fn channel() -> tokio::sync::broadcast::Sender<OverlayAlert> {
+    tokio::sync::broadcast::channel(CAPACITY).0
+}
+
+impl crate::bot::Bot {
+    /// Serve the overlay, if it's enabled.
+    pub async fn overlay(&self) -> Result<()> {
+        let config = self.config.read().await.overlay.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(page))
+            .route("/alerts", axum::routing::get(alerts))
+            .with_state(self.overlay.clone());
+        let listener = tokio::net::TcpListener::bind(&config.listen).await?;
+        tracing::info!("Serving the overlay on http://{}", config.listen);
+        axum::serve(listener, app).await?;
+        Ok(())
+    }
+
+    /// Animate an alert on any connected overlays.
+    pub fn overlay_alert(&self, kind: &str, message: &str, colour: Option<&str>) {
+        if crate::killswitch::is_log_only() {
+            tracing::info!("Log-only, not sending alert to the overlay: {message}");
+            return;
+        }
+        // Sending only fails when no overlay is connected, which is fine.
+        let _ = self.overlay.send(OverlayAlert {
+            kind: kind.to_owned(),
+            message: message.to_owned(),
+            colour: colour.map(ToOwned::to_owned),
+        });
+    }
+
+    /// Whether alerts should be shown as desktop popups, rather than only on the overlay.
+    pub async fn wants_alert_popups(&self) -> bool {
+        let config = &self.config.read().await.overlay;
+        !config.enabled || config.popups
+    }
+}
+
+async fn page() -> axum::response::Html<&'static str> {
+    axum::response::Html(PAGE)
+}
+
+async fn alerts(
+    axum::extract::State(sender): axum::extract::State<
+        tokio::sync::broadcast::Sender<OverlayAlert>,
+    >,
+    upgrade: axum::extract::ws::WebSocketUpgrade,
+) -> axum::response::Response {
+    upgrade
+        .on_upgrade(move |socket| async move {
+            if let Err(error) = forward_alerts(socket, sender.subscribe()).await {
+                tracing::debug!("Overlay disconnected: {error:?}");
+            }
+        })
+        .into_response()
+}
+
+/// Send alerts to a connected overlay until it goes away.
+async fn forward_alerts(
+    mut socket: axum::extract::ws::WebSocket,
+    mut receiver: tokio::sync::broadcast::Receiver<OverlayAlert>,
+) -> Result<()> {
+    loop {
+        let alert = match receiver.recv().await {
+            Ok(alert) => alert,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!("Overlay fell behind and missed {missed} alerts");
+                continue;
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+        let json = serde_json::to_string(&alert)?;
+        socket
+            .send(axum::extract::ws::Message::Text(json.into()))
+            .await?;
+    }
+}
+
+/// The overlay itself. Alerts are queued, so that they don't cover each other up.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+  body { margin: 0; overflow: hidden; background: transparent; font-family: sans-serif; }
+  #alert {
+    position: absolute; top: 10%; left: 50%; transform: translate(-50%, -200%);
+    padding: 1em 2em; border-radius: 1em; background: #6441a5; color: white;
+    font-size: 3em; font-weight: bold; text-align: center; opacity: 0;
+    transition: transform 0.6s cubic-bezier(0.2, 1.6, 0.5, 1), opacity 0.6s;
+  }
+  #alert.showing { transform: translate(-50%, 0); opacity: 1; }
+  #alert.raid { font-size: 4em; }
+</style>
+</head>
+<body>
+<div id="alert"></div>
+<script>
+  const element = document.getElementById("alert");
+  const queue = [];
+  let isShowing = false;
+
+  function next() {
+    const alert = queue.shift();
+    if (!alert) {
+      isShowing = false;
+      return;
+    }
+    isShowing = true;
+    element.className = alert.kind;
+    element.textContent = alert.message;
+    element.style.background = alert.colour || "";
+    requestAnimationFrame(() => element.classList.add("showing"));
+    setTimeout(() => {
+      element.classList.remove("showing");
+      setTimeout(next, 1000);
+    }, 6000);
+  }
+
+  function connect() {
+    const socket = new WebSocket(`ws://${location.host}/alerts`);
+    socket.onmessage = (event) => {
+      queue.push(JSON.parse(event.data));
+      if (!isShowing) next();
+    };
+    socket.onclose = () => setTimeout(connect, 2000);
+  }
+
+  connect();
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,92 @@
+//! Where the bot keeps its files. They follow the XDG Base Directory spec: the database and
+//! sounds go in `$XDG_DATA_HOME/tbhbot`, eg: `~/.local/share/tbhbot`. Tokens, archives and
+//! logs go in `$XDG_STATE_HOME/tbhbot`, and the `.env` file is in `$XDG_CONFIG_HOME/tbhbot`.
+//! Each directory can be overridden from the CLI.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+
+/// This is global rather than on `Bot`, because sounds are played from places that don't have
+/// access to the bot.
+static PATHS: std::sync::OnceLock<Paths> = std::sync::OnceLock::new();
+
+#[derive(Debug)]
+struct Paths {
+    config: std::path::PathBuf,
+    data: std::path::PathBuf,
+    state: std::path::PathBuf,
+    sounds: std::path::PathBuf,
+    logs: std::path::PathBuf,
+}
+
+/// Work out the directories from the XDG defaults and any CLI overrides. This should be
+/// called once, before any of the directories are used.
+pub fn set_paths(cli: &crate::Cli) -> Result<()> {
+    let project = directories::ProjectDirs::from("", "", "tbhbot")
+        .context("Couldn't find the home directory")?;
+    let data = cli
+        .data_directory
+        .clone()
+        .unwrap_or_else(|| project.data_dir().to_path_buf());
+    let state = cli.state_directory.clone().unwrap_or_else(|| {
+        project
+            .state_dir()
+            .unwrap_or_else(|| project.data_local_dir())
+            .to_path_buf()
+    });
+    let paths = Paths {
+        config: project.config_dir().to_path_buf(),
+        sounds: cli
+            .sounds_directory
+            .clone()
+            .unwrap_or_else(|| data.join("sounds")),
+        logs: cli
+            .log_directory
+            .clone()
+            .unwrap_or_else(|| state.join("logs")),
+        data,
+        state,
+    };
+
+    // The bot restarts itself after crashes, the paths stay as they were first set.
+    let _already_set = PATHS.set(paths);
+    Ok(())
+}
+
+/// Log the directories being used. It's separate from `set_paths()`, because logging can't be
+/// set up until the log directory is known.
+pub fn log_paths() {
+    tracing::debug!("Using paths: {:?}", paths());
+}
+
+fn paths() -> &'static Paths {
+    PATHS
+        .get()
+        .expect("Paths should be set at startup, see `set_paths()`")
+}
+
+/// Where the `.env` file with the Twitch app's secrets lives.
+pub fn config_directory() -> std::path::PathBuf {
+    paths().config.clone()
+}
+
+/// Where the database lives, relative database paths in the config are relative to this.
+pub fn data_directory() -> std::path::PathBuf {
+    paths().data.clone()
+}
+
+/// Where the bot keeps its tokens, archives and backups.
+pub fn state_directory() -> std::path::PathBuf {
+    paths().state.clone()
+}
+
+/// Where the log files are written.
+pub fn log_directory() -> std::path::PathBuf {
+    paths().logs.clone()
+}
+
+/// The path of a sound. Relative paths are relative to the sounds directory, absolute paths
+/// are left as they are.
+pub fn sound<P: AsRef<std::path::Path>>(path: P) -> std::path::PathBuf {
+    paths().sounds.join(path)
+}
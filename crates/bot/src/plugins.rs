@@ -0,0 +1,516 @@
+//! Chat commands from WebAssembly plugins, so that new commands can be added without rebuilding
+//! the bot. Every `.wasm` file in the plugins directory is loaded when the bot starts. See
+//! `docs/PLUGINS.md` for how to write one.
+//!
+//! Plugins can't do anything directly. Replies, sounds and popups are collected while the plugin
+//! runs and only done after it's finished, and its stored values are loaded before it runs and
+//! saved afterwards. Each run gets a fresh instance of the plugin, with a limited amount of fuel
+//! and memory so that a plugin stuck in a loop or leaking memory can't hang the bot. Plugins run
+//! on a blocking thread, so that even the fuel that they do get doesn't hold up other events.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+use futures::future::LocalBoxFuture;
+
+/// The module that the bot's functions are imported from.
+const HOST_MODULE: &str = "tbhbot";
+
+/// Roughly how many instructions a plugin can run for each command.
+const FUEL: u64 = 100_000_000;
+
+/// The most memory a plugin can grow to, in bytes.
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// The longest string a plugin can pass to the bot, in bytes.
+const MAX_STRING_LENGTH: usize = 64 * 1024;
+
+/// The longest chat message a plugin can send, Twitch rejects anything longer.
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// How many replies, sounds and popups a plugin can ask for in one run.
+const MAX_ACTIONS: usize = 10;
+
+/// How much a plugin can store, in bytes of keys and values.
+const MAX_STORAGE: usize = 1024 * 1024;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct PluginsConfig {
+    /// Where to find the plugins, defaults to `plugins` in the data directory.
+    pub directory: Option<std::path::PathBuf>,
+    /// Plugins that aren't loaded, by file name without `.wasm`.
+    pub disabled: Vec<String>,
+}
+
+/// A command that a plugin provides, from its `commands` export.
+#[derive(serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CommandSpec {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub help: String,
+    #[serde(default)]
+    pub permission: crate::config::Permission,
+    /// Seconds before anybody can use the command again.
+    pub cooldown: Option<u64>,
+}
+
+/// What the plugin is given when one of its commands is run.
+#[derive(serde_derive::Serialize)]
+struct Invocation<'a> {
+    command: &'a str,
+    arguments: Option<&'a str>,
+    user: &'a str,
+    user_id: &'a str,
+}
+
+/// Something that a plugin asked the bot to do.
+#[derive(Debug)]
+enum Action {
+    Reply(String),
+    Message(String),
+    Sound(String),
+    Popup(String),
+}
+
+/// The state that the bot's functions share while a plugin runs.
+struct Host {
+    actions: Vec<Action>,
+    storage: std::collections::HashMap<String, String>,
+    /// Keys that the plugin has set.
+    changed: std::collections::HashSet<String>,
+    limits: wasmtime::StoreLimits,
+}
+
+impl Host {
+    fn new(storage: std::collections::HashMap<String, String>) -> Self {
+        Self {
+            actions: Vec::new(),
+            storage,
+            changed: std::collections::HashSet::new(),
+            limits: wasmtime::StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY)
+                .instances(1)
+                .build(),
+        }
+    }
+
+    /// Queue something for the bot to do once the plugin has finished.
+    fn push_action(&mut self, action: Action) -> wasmtime::Result<()> {
+        if self.actions.len() >= MAX_ACTIONS {
+            return Err(wasmtime::Error::msg(format!(
+                "Plugins can only do {MAX_ACTIONS} things per command"
+            )));
+        }
+        self.actions.push(action);
+        Ok(())
+    }
+
+    /// How many bytes the plugin has stored.
+    fn storage_size(&self) -> usize {
+        self.storage
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
+    }
+}
+
+/// A loaded plugin.
+pub struct Plugin {
+    /// The file name without `.wasm`. It's also what the plugin's stored values are kept under.
+    pub name: String,
+    pub commands: Vec<CommandSpec>,
+    engine: wasmtime::Engine,
+    module: wasmtime::Module,
+    linker: wasmtime::Linker<Host>,
+}
+
+impl Plugin {
+    fn load(path: &std::path::Path) -> Result<Self> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .context("Plugin has no file name")?
+            .to_owned();
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let engine = wasmtime::Engine::new(&config).map_err(wasm_error)?;
+        let module = wasmtime::Module::from_file(&engine, path).map_err(wasm_error)?;
+        let linker = host_functions(&engine).map_err(wasm_error)?;
+
+        let mut plugin = Self {
+            name,
+            commands: Vec::new(),
+            engine,
+            module,
+            linker,
+        };
+        let (mut store, instance) =
+            plugin.instantiate(Host::new(std::collections::HashMap::new()))?;
+        let commands = instance
+            .get_typed_func::<(), i64>(&mut store, "commands")
+            .map_err(wasm_error)?
+            .call(&mut store, ())
+            .map_err(wasm_error)?;
+        let json = read_packed(&mut store, &instance, commands)?;
+        plugin.commands = serde_json::from_str(&json)?;
+        Ok(plugin)
+    }
+
+    fn instantiate(&self, host: Host) -> Result<(wasmtime::Store<Host>, wasmtime::Instance)> {
+        let mut store = wasmtime::Store::new(&self.engine, host);
+        store.limiter(|host| &mut host.limits);
+        store.set_fuel(FUEL).map_err(wasm_error)?;
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .map_err(wasm_error)?;
+        Ok((store, instance))
+    }
+
+    /// Run one of the plugin's commands, returning what it asked to do and its stored values.
+    /// It runs until the plugin returns or runs out of fuel, so it blocks.
+    fn run(
+        &self,
+        invocation: &str,
+        storage: std::collections::HashMap<String, String>,
+    ) -> Result<Host> {
+        let (mut store, instance) = self.instantiate(Host::new(storage))?;
+        let (pointer, length) = write_string(&mut store, &instance, invocation)?;
+        let status = instance
+            .get_typed_func::<(i32, i32), i32>(&mut store, "execute")
+            .map_err(wasm_error)?
+            .call(&mut store, (pointer, length))
+            .map_err(wasm_error)?;
+        if status != 0 {
+            eyre::bail!("Plugin {} failed with status {status}", self.name);
+        }
+        Ok(store.into_data())
+    }
+}
+
+/// Load every plugin in the plugins directory. A plugin that can't be loaded is skipped.
+pub fn load_plugins(config: &PluginsConfig) -> Vec<std::sync::Arc<Plugin>> {
+    let directory = config
+        .directory
+        .clone()
+        .unwrap_or_else(|| crate::paths::data_directory().join("plugins"));
+    let Ok(entries) = std::fs::read_dir(&directory) else {
+        tracing::debug!("No plugins directory at {directory:?}");
+        return Vec::new();
+    };
+
+    let mut plugins = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        let is_wasm = path
+            .extension()
+            .is_some_and(|extension| extension == "wasm");
+        let is_disabled = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| config.disabled.iter().any(|name| name == stem));
+        if !is_wasm || is_disabled {
+            continue;
+        }
+        match Plugin::load(&path) {
+            Ok(plugin) => {
+                tracing::info!(
+                    "Loaded plugin {} with {} commands",
+                    plugin.name,
+                    plugin.commands.len()
+                );
+                plugins.push(std::sync::Arc::new(plugin));
+            }
+            Err(error) => tracing::error!("Loading plugin {path:?}: {error:?}"),
+        }
+    }
+    plugins
+}
+
+/// The functions that plugins can import from the bot.
+fn host_functions(engine: &wasmtime::Engine) -> wasmtime::Result<wasmtime::Linker<Host>> {
+    let mut linker = wasmtime::Linker::new(engine);
+    linker.func_wrap(
+        HOST_MODULE,
+        "reply",
+        |mut caller: wasmtime::Caller<'_, Host>,
+         pointer: i32,
+         length: i32|
+         -> wasmtime::Result<()> {
+            let text = read_string(&mut caller, pointer, length)?;
+            if text.len() > MAX_MESSAGE_LENGTH {
+                return Err(wasmtime::Error::msg("Chat messages can't be that long"));
+            }
+            caller.data_mut().push_action(Action::Reply(text))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "send_message",
+        |mut caller: wasmtime::Caller<'_, Host>,
+         pointer: i32,
+         length: i32|
+         -> wasmtime::Result<()> {
+            let text = read_string(&mut caller, pointer, length)?;
+            if text.len() > MAX_MESSAGE_LENGTH {
+                return Err(wasmtime::Error::msg("Chat messages can't be that long"));
+            }
+            caller.data_mut().push_action(Action::Message(text))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "play_sound",
+        |mut caller: wasmtime::Caller<'_, Host>,
+         pointer: i32,
+         length: i32|
+         -> wasmtime::Result<()> {
+            let path = read_string(&mut caller, pointer, length)?;
+            caller.data_mut().push_action(Action::Sound(path))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "popup",
+        |mut caller: wasmtime::Caller<'_, Host>,
+         pointer: i32,
+         length: i32|
+         -> wasmtime::Result<()> {
+            let text = read_string(&mut caller, pointer, length)?;
+            caller.data_mut().push_action(Action::Popup(text))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "storage_get",
+        |mut caller: wasmtime::Caller<'_, Host>,
+         pointer: i32,
+         length: i32|
+         -> wasmtime::Result<i64> {
+            let key = read_string(&mut caller, pointer, length)?;
+            let Some(value) = caller.data().storage.get(&key).cloned() else {
+                return Ok(-1);
+            };
+            let alloc = caller
+                .get_export("alloc")
+                .and_then(wasmtime::Extern::into_func)
+                .ok_or_else(|| wasmtime::Error::msg("Plugin doesn't export alloc"))?
+                .typed::<i32, i32>(&caller)?;
+            let value_length = i32::try_from(value.len())?;
+            let value_pointer = alloc.call(&mut caller, value_length)?;
+            memory(&mut caller)?.write(
+                &mut caller,
+                usize::try_from(value_pointer)?,
+                value.as_bytes(),
+            )?;
+            Ok(pack(value_pointer, value_length))
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "storage_set",
+        |mut caller: wasmtime::Caller<'_, Host>,
+         key_pointer: i32,
+         key_length: i32,
+         value_pointer: i32,
+         value_length: i32|
+         -> wasmtime::Result<()> {
+            let key = read_string(&mut caller, key_pointer, key_length)?;
+            let value = read_string(&mut caller, value_pointer, value_length)?;
+            let host = caller.data_mut();
+            let previous = host
+                .storage
+                .get(&key)
+                .map_or(0, |previous| key.len() + previous.len());
+            if host.storage_size() - previous + key.len() + value.len() > MAX_STORAGE {
+                return Err(wasmtime::Error::msg(format!(
+                    "Plugins can only store {MAX_STORAGE} bytes"
+                )));
+            }
+            host.storage.insert(key.clone(), value);
+            host.changed.insert(key);
+            Ok(())
+        },
+    )?;
+    Ok(linker)
+}
+
+/// Wasmtime's errors are `anyhow` errors, which have to be converted for `eyre`.
+fn wasm_error(error: wasmtime::Error) -> eyre::Report {
+    eyre::eyre!("{error:#}")
+}
+
+/// The plugin's memory, as seen from one of the bot's functions.
+fn memory(caller: &mut wasmtime::Caller<'_, Host>) -> wasmtime::Result<wasmtime::Memory> {
+    caller
+        .get_export("memory")
+        .and_then(wasmtime::Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("Plugin doesn't export its memory"))
+}
+
+/// Read a string that a plugin passed to one of the bot's functions.
+fn read_string(
+    caller: &mut wasmtime::Caller<'_, Host>,
+    pointer: i32,
+    length: i32,
+) -> wasmtime::Result<String> {
+    let memory = memory(caller)?;
+    let (pointer, length) = (usize::try_from(pointer)?, usize::try_from(length)?);
+    check_string_bounds(pointer, length, memory.data_size(&*caller))?;
+    let mut bytes = vec![0; length];
+    memory.read(&*caller, pointer, &mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Check that a string from a plugin is inside its memory and isn't too long, before any room
+/// is made for it.
+fn check_string_bounds(pointer: usize, length: usize, memory_size: usize) -> wasmtime::Result<()> {
+    if length > MAX_STRING_LENGTH {
+        return Err(wasmtime::Error::msg(format!(
+            "Plugin strings can't be longer than {MAX_STRING_LENGTH} bytes"
+        )));
+    }
+    if !matches!(pointer.checked_add(length), Some(end) if end <= memory_size) {
+        return Err(wasmtime::Error::msg(
+            "Plugin string is outside of its memory",
+        ));
+    }
+    Ok(())
+}
+
+/// A pointer and length in one number, for returning a string to a plugin.
+fn pack(pointer: i32, length: i32) -> i64 {
+    (i64::from(pointer) << 32) | i64::from(length)
+}
+
+/// Read a string that a plugin returned as a packed pointer and length.
+fn read_packed(
+    store: &mut wasmtime::Store<Host>,
+    instance: &wasmtime::Instance,
+    packed: i64,
+) -> Result<String> {
+    let pointer = usize::try_from(packed >> 32)?;
+    let length = usize::try_from(packed & 0xFFFF_FFFF)?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .context("Plugin doesn't export its memory")?;
+    check_string_bounds(pointer, length, memory.data_size(&*store)).map_err(wasm_error)?;
+    let mut bytes = vec![0; length];
+    memory.read(&*store, pointer, &mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Copy a string into the plugin's memory, returning where it is.
+fn write_string(
+    store: &mut wasmtime::Store<Host>,
+    instance: &wasmtime::Instance,
+    text: &str,
+) -> Result<(i32, i32)> {
+    let length = i32::try_from(text.len())?;
+    let pointer = instance
+        .get_typed_func::<i32, i32>(&mut *store, "alloc")
+        .map_err(wasm_error)?
+        .call(&mut *store, length)
+        .map_err(wasm_error)?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .context("Plugin doesn't export its memory")?;
+    memory.write(&mut *store, usize::try_from(pointer)?, text.as_bytes())?;
+    Ok((pointer, length))
+}
+
+/// One of a plugin's commands, for the command registry.
+pub struct PluginCommand {
+    pub plugin: std::sync::Arc<Plugin>,
+    pub spec: CommandSpec,
+}
+
+impl crate::chat_commands::ChatCommand for PluginCommand {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        self.spec.aliases.iter().map(String::as_str).collect()
+    }
+
+    fn help(&self) -> &str {
+        &self.spec.help
+    }
+
+    fn permission(&self) -> crate::config::Permission {
+        self.spec.permission
+    }
+
+    fn cooldown(&self) -> Option<std::time::Duration> {
+        self.spec.cooldown.map(std::time::Duration::from_secs)
+    }
+
+    fn execute<'a>(
+        &'a self,
+        bot: &'a crate::bot::Bot,
+        payload: &'a crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&'a str>,
+    ) -> LocalBoxFuture<'a, Result<()>> {
+        Box::pin(bot.run_plugin(
+            std::sync::Arc::clone(&self.plugin),
+            &self.spec.name,
+            payload,
+            arguments,
+        ))
+    }
+}
+
+impl crate::bot::Bot {
+    /// Run a plugin's command, then do what it asked and save what it stored.
+    async fn run_plugin(
+        &self,
+        plugin: std::sync::Arc<Plugin>,
+        command: &str,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let storage = self.db.get_plugin_storage(&plugin.name).await?;
+        let invocation = Invocation {
+            command,
+            arguments,
+            user: payload.chatter_user_name.as_str(),
+            user_id: payload.chatter_user_id.as_str(),
+        };
+        let invocation = serde_json::to_string(&invocation)?;
+        let runner = std::sync::Arc::clone(&plugin);
+        let host = tokio::task::spawn_blocking(move || runner.run(&invocation, storage)).await??;
+
+        for key in &host.changed {
+            if let Some(value) = host.storage.get(key) {
+                self.db.set_plugin_value(&plugin.name, key, value).await?;
+            }
+        }
+        for action in host.actions {
+            match action {
+                Action::Reply(text) => {
+                    self.send_message_reply(&payload.message_id, text.as_str())
+                        .await?;
+                }
+                Action::Message(text) => self.send_message(text.as_str()).await?,
+                Action::Sound(path) => {
+                    let path = std::path::PathBuf::from(path);
+                    if path.is_absolute()
+                        || path
+                            .components()
+                            .any(|component| component == std::path::Component::ParentDir)
+                    {
+                        eyre::bail!("Plugins can only play sounds from the sounds directory");
+                    }
+                    if !self.are_sounds_muted().await {
+                        Self::play_sound(path)?;
+                    }
+                }
+                Action::Popup(text) => Self::onscreen_popup(format!(" \n{text}"), "twitch-plugin")?,
+            }
+        }
+        Ok(())
+    }
+}
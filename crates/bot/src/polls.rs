@@ -0,0 +1,100 @@
+use color_eyre::Result;
+use twitch_api::helix;
+
+/// The number of choices that Twitch allows in a poll.
+const CHOICES_RANGE: std::ops::RangeInclusive<usize> = 2..=5;
+
+/// The poll durations, in seconds, that Twitch allows.
+const DURATION_RANGE: std::ops::RangeInclusive<u64> = 15..=1800;
+
+const USAGE: &str = "Usage: !poll \"question\" option1 option2 ... <duration, eg 2m>";
+
+impl crate::bot::Bot {
+    /// `!poll "What next?" Rust Zig "More Rust" 2m` starts a native Twitch poll.
+    pub async fn poll(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !self.is_moderator(payload).await? {
+            return Ok(());
+        }
+
+        let mut parts = crate::utils::split_arguments(arguments.unwrap_or_default());
+        let maybe_duration = parts
+            .pop()
+            .and_then(|duration| crate::utils::parse_duration(&duration));
+        let Some(duration) = maybe_duration else {
+            self.send_message_reply(&payload.message_id, USAGE).await?;
+            return Ok(());
+        };
+        if parts.is_empty() {
+            self.send_message_reply(&payload.message_id, USAGE).await?;
+            return Ok(());
+        }
+        let question = parts.remove(0);
+
+        if !CHOICES_RANGE.contains(&parts.len()) {
+            self.send_message_reply(&payload.message_id, "Polls need 2 to 5 options")
+                .await?;
+            return Ok(());
+        }
+        if !DURATION_RANGE.contains(&duration.as_secs()) {
+            self.send_message_reply(
+                &payload.message_id,
+                "Polls can last between 15 seconds and 30 minutes",
+            )
+            .await?;
+            return Ok(());
+        }
+
+        let choices = parts
+            .iter()
+            .map(|choice| helix::polls::NewPollChoice::new(choice.as_str()))
+            .collect::<Vec<_>>();
+        let body = helix::polls::CreatePollBody::new(
+            crate::BROADCASTER_ID,
+            question.as_str(),
+            i64::try_from(duration.as_secs())?,
+            choices,
+        );
+        let token = self.token.lock().await.clone();
+        self.client
+            .req_post(helix::polls::CreatePollRequest::new(), body, &token)
+            .await?;
+        tracing::info!("Started poll: {question}");
+
+        Ok(())
+    }
+
+    /// Announce the winner when a poll finishes.
+    pub async fn poll_ended(
+        &self,
+        payload: &crate::eventsub::channel::ChannelPollEndV1Payload,
+    ) -> Result<()> {
+        tracing::info!("Poll ended: {payload:?}");
+        if !matches!(
+            payload.status,
+            crate::eventsub::channel::poll::PollEndStatus::Completed
+        ) {
+            return Ok(());
+        }
+
+        let Some(winner) = payload
+            .choices
+            .iter()
+            .max_by_key(|choice| choice.votes.unwrap_or_default())
+        else {
+            return Ok(());
+        };
+        let message = format!(
+            "📊 \"{}\" — the winner is: {} ({} votes)",
+            payload.title,
+            winner.title,
+            winner.votes.unwrap_or_default()
+        );
+        self.send_message(message.as_str()).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+//! A Pomodoro focus timer. The time left is shown on screen, breaks are announced in chat and
+//! sound commands can be muted whilst focusing.
+
+use color_eyre::Result;
+
+const USAGE: &str = "Usage: !pomo start [focus minutes] [break minutes], !pomo stop or !pomo";
+
+/// How long focus blocks are when no time is given.
+const DEFAULT_FOCUS_MINUTES: u64 = 25;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct PomodoroConfig {
+    /// How long breaks are when no time is given.
+    pub break_minutes: u64,
+    /// Stop chat from playing sounds, like `!chirp`, whilst focusing.
+    pub mute_sounds: bool,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            break_minutes: 5,
+            mute_sounds: true,
+        }
+    }
+}
+
+/// A Pomodoro that's running, either focusing or on a break.
+pub struct Pomodoro {
+    started_at: std::time::Instant,
+    focus: std::time::Duration,
+    rest: std::time::Duration,
+    countdown: tokio::task::JoinHandle<()>,
+}
+
+impl Pomodoro {
+    fn is_focusing(&self) -> bool {
+        self.started_at.elapsed() < self.focus
+    }
+
+    fn is_finished(&self) -> bool {
+        self.started_at.elapsed() >= self.focus + self.rest
+    }
+}
+
+/// Show the time left on screen, every second until it runs out. `text` makes what's shown
+/// from the seconds left. Each popup replaces the last one with the same `tag`.
+pub async fn countdown(
+    duration: std::time::Duration,
+    category: &str,
+    tag: &str,
+    text: impl Fn(u64) -> String,
+) -> Result<()> {
+    for remaining in (1..=duration.as_secs()).rev() {
+        crate::bot::Bot::onscreen_popup_replacing(text(remaining), category, tag)?;
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    Ok(())
+}
+
+/// Count down the focus time or the break.
+async fn pomodoro_countdown(label: &str, duration: std::time::Duration) -> Result<()> {
+    countdown(duration, "twitch-pomodoro", "pomodoro", |remaining| {
+        format!(" \n🍅 {label} {:02}:{:02}", remaining / 60, remaining % 60)
+    })
+    .await
+}
+
+impl crate::bot::Bot {
+    /// `!pomo start [focus minutes] [break minutes]` and `!pomo stop` (broadcaster only).
+    /// `!pomo` shows how long is left.
+    pub async fn pomodoro(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let arguments = arguments
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        match arguments.as_slice() {
+            [] => self.pomodoro_status(payload).await,
+            ["stop"] if Self::is_broadcaster(payload) => {
+                let message = match self.pomodoro.lock().await.take() {
+                    Some(pomodoro) => {
+                        pomodoro.countdown.abort();
+                        "Pomodoro stopped"
+                    }
+                    None => "There's no Pomodoro running",
+                };
+                self.send_message_reply(&payload.message_id, message).await
+            }
+            ["start", minutes @ ..] if Self::is_broadcaster(payload) => {
+                let parse = |index: usize, default: u64| match minutes.get(index) {
+                    Some(text) => text.parse::<u64>().ok(),
+                    None => Some(default),
+                };
+                let break_minutes = self.config.read().await.pomodoro.break_minutes;
+                let (Some(focus), Some(rest)) =
+                    (parse(0, DEFAULT_FOCUS_MINUTES), parse(1, break_minutes))
+                else {
+                    return self.send_message_reply(&payload.message_id, USAGE).await;
+                };
+                self.start_pomodoro(focus, rest).await
+            }
+            _ => self.send_message_reply(&payload.message_id, USAGE).await,
+        }
+    }
+
+    async fn start_pomodoro(&self, focus_minutes: u64, break_minutes: u64) -> Result<()> {
+        let focus = std::time::Duration::from_secs(focus_minutes * 60);
+        let rest = std::time::Duration::from_secs(break_minutes * 60);
+
+        // The countdown outlives this command, so it queues its chat messages directly.
+        let outbox = self.outbox.clone();
+        let countdown = tokio::spawn(async move {
+            let result = async {
+                pomodoro_countdown("Focus", focus).await?;
+                outbox.send(crate::outbox::Outgoing::Message(format!(
+                    "🍅 Focus time's over, break for {break_minutes} minutes!"
+                )))?;
+                pomodoro_countdown("Break", rest).await?;
+                outbox.send(crate::outbox::Outgoing::Message(
+                    "🍅 Break's over, back to it!".to_owned(),
+                ))?;
+                Ok::<(), eyre::Report>(())
+            };
+            if let Err(error) = result.await {
+                tracing::error!("Pomodoro countdown: {error:?}");
+            }
+        });
+
+        let pomodoro = Pomodoro {
+            started_at: std::time::Instant::now(),
+            focus,
+            rest,
+            countdown,
+        };
+        if let Some(previous) = self.pomodoro.lock().await.replace(pomodoro) {
+            previous.countdown.abort();
+        }
+
+        let message = format!("🍅 Focusing for {focus_minutes} minutes, see you on the other side");
+        self.send_message(message.as_str()).await
+    }
+
+    async fn pomodoro_status(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let message = match self.pomodoro.lock().await.as_ref() {
+            Some(pomodoro) if !pomodoro.is_finished() => {
+                let elapsed = pomodoro.started_at.elapsed();
+                let (phase, left) = if pomodoro.is_focusing() {
+                    ("Focusing", pomodoro.focus - elapsed)
+                } else {
+                    ("On a break", pomodoro.focus + pomodoro.rest - elapsed)
+                };
+                format!("🍅 {phase}, {} minutes left", left.as_secs().div_ceil(60))
+            }
+            _ => "There's no Pomodoro running".to_owned(),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+
+    /// Whether chat shouldn't be able to play sounds right now, because of a focus block.
+    pub async fn are_sounds_muted(&self) -> bool {
+        self.config.read().await.pomodoro.mute_sounds
+            && self
+                .pomodoro
+                .lock()
+                .await
+                .as_ref()
+                .is_some_and(Pomodoro::is_focusing)
+    }
+}
@@ -0,0 +1,58 @@
+//! Let chat know whether the bot is around, and so whether commands will work. The bot says
+//! hello when the stream starts, or when it starts up during a stream, and says goodbye when
+//! the stream ends, or when it's stopped during a stream.
+
+use color_eyre::Result;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct PresenceConfig {
+    pub enabled: bool,
+    /// Sent when the bot comes online.
+    pub online: String,
+    /// Sent when the bot goes offline.
+    pub offline: String,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            online: "🤖 Bot online, commands are working".to_owned(),
+            offline: "🤖 Bot signing off, commands won't work until I'm back".to_owned(),
+        }
+    }
+}
+
+/// Whether the bot is arriving or leaving.
+#[derive(Debug, Clone, Copy)]
+pub enum Presence {
+    Online,
+    Offline,
+}
+
+impl crate::bot::Bot {
+    /// Tell chat and the streamer that the bot is arriving or leaving.
+    pub async fn announce_presence(&self, presence: Presence) -> Result<()> {
+        let config = self.config.read().await.presence.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+
+        let message = match presence {
+            Presence::Online => config.online,
+            Presence::Offline => config.offline,
+        };
+        Self::onscreen_popup(format!(" \n{message}"), "twitch-bot-presence")?;
+        self.send_message(message.as_str()).await
+    }
+
+    /// Say hello if the bot is starting in the middle of a stream. Crash recoveries have their
+    /// own message.
+    pub async fn announce_startup(&self) -> Result<()> {
+        if self.recovered_from.is_some() || self.db.current_stream().await?.is_none() {
+            return Ok(());
+        }
+        self.announce_presence(Presence::Online).await
+    }
+}
@@ -0,0 +1,86 @@
+//! Broadcaster-only commands that run the alerts with made up events. Handy for checking
+//! volumes and popups before a stream.
+
+use color_eyre::Result;
+
+/// The name used for the made up viewers in the previews.
+const TEST_USER: &str = "test_mate";
+
+/// The user ID of the made up viewers.
+const TEST_USER_ID: &str = "1";
+
+/// A made up follow.
+pub fn made_up_follow() -> crate::bus::Follow {
+    crate::bus::Follow {
+        user_id: TEST_USER_ID.to_owned(),
+        username: TEST_USER.to_owned(),
+    }
+}
+
+/// A made up raid.
+pub fn made_up_raid(viewers: i64) -> crate::bus::Raid {
+    crate::bus::Raid {
+        user_id: TEST_USER_ID.to_owned(),
+        username: TEST_USER.to_owned(),
+        login: TEST_USER.to_owned(),
+        viewers,
+    }
+}
+
+/// A made up subscription, at tier 1, 2 or 3.
+pub fn made_up_subscription(tier: u8) -> crate::bus::Subscription {
+    crate::bus::Subscription {
+        user_id: TEST_USER_ID.to_owned(),
+        username: TEST_USER.to_owned(),
+        tier,
+        is_gift: false,
+    }
+}
+
+impl crate::bot::Bot {
+    /// `!testfollow`
+    pub async fn test_follow(
+        &self,
+        message: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(message) {
+            return Ok(());
+        }
+
+        self.new_follower(&made_up_follow()).await
+    }
+
+    /// `!testraid [viewers]`
+    pub async fn test_raid(
+        &self,
+        message: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(message) {
+            return Ok(());
+        }
+
+        let viewers = arguments
+            .and_then(|args| args.trim().parse::<i64>().ok())
+            .unwrap_or(10);
+        self.incoming_raid(&made_up_raid(viewers)).await
+    }
+
+    /// `!testsub [tier1|tier2|tier3]`
+    pub async fn test_sub(
+        &self,
+        message: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(message) {
+            return Ok(());
+        }
+
+        let tier = match arguments.map(str::trim) {
+            Some("tier2" | "2") => 2,
+            Some("tier3" | "3") => 3,
+            _ => 1,
+        };
+        self.new_subscriber(&made_up_subscription(tier)).await
+    }
+}
@@ -0,0 +1,119 @@
+//! Things that mates can tell the bot about themselves, like their pronouns and a short intro.
+//! They're used when greeting them, for example when they `!arrive`.
+
+use color_eyre::Result;
+
+/// Pronouns are usually short, like "she/her" or "they/them".
+const MAX_PRONOUNS_LENGTH: usize = 20;
+
+/// Intros are included in chat replies, so they have to leave room for the rest of the message.
+const MAX_INTRO_LENGTH: usize = 100;
+
+/// Which of the profile fields is being set.
+#[derive(Clone, Copy)]
+enum Field {
+    Pronouns,
+    Intro,
+}
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Pronouns => "pronouns",
+            Self::Intro => "intro",
+        }
+    }
+
+    fn max_length(self) -> usize {
+        match self {
+            Self::Pronouns => MAX_PRONOUNS_LENGTH,
+            Self::Intro => MAX_INTRO_LENGTH,
+        }
+    }
+}
+
+impl crate::bot::Bot {
+    /// `!pronouns she/her` sets your pronouns, `!pronouns clear` removes them.
+    pub async fn pronouns(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        self.set_profile_field(payload, arguments, Field::Pronouns)
+            .await
+    }
+
+    /// `!intro <text>` sets a short intro about you, `!intro clear` removes it.
+    pub async fn intro(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        self.set_profile_field(payload, arguments, Field::Intro)
+            .await
+    }
+
+    async fn set_profile_field(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+        field: Field,
+    ) -> Result<()> {
+        let mate = self
+            .db
+            .get_mate(
+                payload.chatter_user_id.as_str(),
+                payload.chatter_user_name.as_str(),
+            )
+            .await?;
+        let name = field.name();
+        let value = arguments.map(str::trim).unwrap_or_default();
+        if value.is_empty() {
+            let current = match field {
+                Field::Pronouns => mate.pronouns,
+                Field::Intro => mate.intro,
+            };
+            let message = match current {
+                Some(current) => format!("Your {name}: {current}. Use \"!{name} clear\" to remove"),
+                None => format!("You haven't set your {name}, use \"!{name} <text>\""),
+            };
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        }
+
+        if value.chars().count() > field.max_length() {
+            let message = format!(
+                "Sorry, your {name} can be at most {} characters",
+                field.max_length()
+            );
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        }
+
+        let value = (value != "clear").then_some(value);
+        match field {
+            Field::Pronouns => self.db.set_pronouns(mate.id, value).await?,
+            Field::Intro => self.db.set_intro(mate.id, value).await?,
+        }
+
+        let message = match value {
+            Some(_) => format!("Saved your {name} 👍"),
+            None => format!("Cleared your {name}"),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+}
+
+impl crate::database::Mate {
+    /// Their name, followed by their pronouns if they've set any, eg: "tombh (he/him)".
+    pub fn display_name(&self) -> String {
+        match &self.pronouns {
+            Some(pronouns) => format!("{} ({pronouns})", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
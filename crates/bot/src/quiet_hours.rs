@@ -0,0 +1,75 @@
+//! Times of day when sounds aren't played, like late at night when flatmates are asleep.
+//! Popups and chat replies still work as normal.
+
+/// This is global rather than on `Bot`, because sounds are played from places that don't have
+/// access to the bot.
+static WINDOWS: std::sync::RwLock<Vec<Window>> = std::sync::RwLock::new(Vec::new());
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct QuietHoursConfig {
+    /// Windows of local time, like `"23:00-08:00"`. They can cross midnight.
+    pub windows: Vec<Window>,
+}
+
+/// A daily window of local time.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct Window {
+    pub start: chrono::NaiveTime,
+    pub end: chrono::NaiveTime,
+}
+
+impl Window {
+    fn contains(&self, time: chrono::NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl TryFrom<String> for Window {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let parse = |time: &str| {
+            chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|error| format!("Bad time `{time}` in quiet hours `{text}`: {error}"))
+        };
+        let Some((start, end)) = text.split_once('-') else {
+            return Err(format!("Quiet hours look like `23:00-08:00`, not `{text}`"));
+        };
+        Ok(Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
+
+impl From<Window> for String {
+    fn from(window: Window) -> Self {
+        format!(
+            "{}-{}",
+            window.start.format("%H:%M"),
+            window.end.format("%H:%M")
+        )
+    }
+}
+
+/// Use the quiet hours from the config. Needs calling whenever the config is loaded.
+pub fn set_quiet_hours(config: &QuietHoursConfig) {
+    match WINDOWS.write() {
+        Ok(mut windows) => windows.clone_from(&config.windows),
+        Err(error) => tracing::error!("Setting quiet hours: {error:?}"),
+    }
+}
+
+/// Whether it's currently quiet hours.
+pub fn is_quiet_now() -> bool {
+    let now = chrono::Local::now().time();
+    WINDOWS
+        .read()
+        .is_ok_and(|windows| windows.iter().any(|window| window.contains(now)))
+}
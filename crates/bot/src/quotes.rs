@@ -0,0 +1,69 @@
+//! Quotes from chat. Mods add them by replying to a message with `!quote this`.
+
+use color_eyre::Result;
+
+impl crate::bot::Bot {
+    /// `!quote this` (mods only, as a reply) quotes the replied-to message. `!quote [id]` shows
+    /// a quote, a random one if no ID is given.
+    pub async fn quote(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let arguments = arguments.map(str::trim).unwrap_or_default();
+        if arguments == "this" {
+            return self.quote_this(payload).await;
+        }
+
+        let quote = match arguments.trim_start_matches('#') {
+            "" => self.db.random_quote().await?,
+            id => match id.parse::<i64>() {
+                Ok(id) => self.db.get_quote(id).await?,
+                Err(_) => {
+                    self.send_message_reply(
+                        &payload.message_id,
+                        "Usage: !quote [id], or reply to a message with !quote this",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            },
+        };
+        let message = match quote {
+            Some((id, username, text)) => format!("#{id}: \"{text}\" — {username}"),
+            None => "No quote found".to_owned(),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    async fn quote_this(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        if !self.is_moderator(payload).await? {
+            return Ok(());
+        }
+        let Some(parent) = self.replied_to(payload).await? else {
+            self.send_message_reply(
+                &payload.message_id,
+                "Reply to the message that you want to quote with !quote this",
+            )
+            .await?;
+            return Ok(());
+        };
+
+        let message = match self
+            .db
+            .add_quote(&parent, payload.chatter_user_name.as_str())
+            .await?
+        {
+            Some(id) => format!("Quote #{id} added 📜"),
+            None => "That's already been quoted".to_owned(),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+//! Raffles. Mods start one with `!raffle start <prize>`, chat enters with `!join` and mods pick
+//! a winner with `!raffle draw`.
+//!
+//! Who can enter is decided by the eligibility rules in the config, like `follow_days >= 7`.
+//! Every rule has to pass, and they're checked when someone tries to enter.
+
+use color_eyre::Result;
+use rand::seq::IndexedRandom as _;
+
+const USAGE: &str = "Usage: !raffle start <prize>, !raffle draw or !raffle cancel";
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct RaffleConfig {
+    /// Rules that everyone entering has to pass, eg: `["follow_days >= 7", "watch_hours >= 1"]`.
+    pub eligibility: Vec<Rule>,
+}
+
+/// Something about a mate that eligibility rules can check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    /// How long they've followed the channel. Rules using it fail for non-followers.
+    FollowDays,
+    /// How old their Twitch account is.
+    AccountDays,
+    /// An estimate from chat: each stream counts from their first to their last message in it.
+    WatchHours,
+    /// How many times they've been timed out since the start of the month.
+    TimeoutsThisMonth,
+    /// How many messages they've sent, ever.
+    Messages,
+}
+
+impl Quantity {
+    const ALL: [Self; 5] = [
+        Self::FollowDays,
+        Self::AccountDays,
+        Self::WatchHours,
+        Self::TimeoutsThisMonth,
+        Self::Messages,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::FollowDays => "follow_days",
+            Self::AccountDays => "account_days",
+            Self::WatchHours => "watch_hours",
+            Self::TimeoutsThisMonth => "timeouts_this_month",
+            Self::Messages => "messages",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    AtLeast,
+    AtMost,
+    MoreThan,
+    LessThan,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    const ALL: [Self; 6] = [
+        Self::AtLeast,
+        Self::AtMost,
+        Self::MoreThan,
+        Self::LessThan,
+        Self::Equal,
+        Self::NotEqual,
+    ];
+
+    const fn symbol(self) -> &'static str {
+        match self {
+            Self::AtLeast => ">=",
+            Self::AtMost => "<=",
+            Self::MoreThan => ">",
+            Self::LessThan => "<",
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+        }
+    }
+
+    fn compare(self, left: f64, right: f64) -> bool {
+        match self {
+            Self::AtLeast => left >= right,
+            Self::AtMost => left <= right,
+            Self::MoreThan => left > right,
+            Self::LessThan => left < right,
+            Self::Equal => (left - right).abs() < f64::EPSILON,
+            Self::NotEqual => (left - right).abs() >= f64::EPSILON,
+        }
+    }
+}
+
+/// An eligibility rule, written in the config like `follow_days >= 7`.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    pub quantity: Quantity,
+    pub comparison: Comparison,
+    pub value: f64,
+}
+
+impl TryFrom<String> for Rule {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let [quantity, comparison, value] = words.as_slice() else {
+            return Err(format!(
+                "Eligibility rules look like `follow_days >= 7`, not `{text}`"
+            ));
+        };
+        let quantity = Quantity::ALL
+            .into_iter()
+            .find(|candidate| candidate.name() == *quantity)
+            .ok_or_else(|| {
+                let names = Quantity::ALL.map(Quantity::name).join(", ");
+                format!("Unknown eligibility quantity `{quantity}`, use one of: {names}")
+            })?;
+        let comparison = match *comparison {
+            "==" => Comparison::Equal,
+            symbol => Comparison::ALL
+                .into_iter()
+                .find(|candidate| candidate.symbol() == symbol)
+                .ok_or_else(|| format!("Unknown comparison `{symbol}` in `{text}`"))?,
+        };
+        let value = value
+            .parse()
+            .map_err(|_| format!("`{value}` isn't a number in `{text}`"))?;
+        Ok(Self {
+            quantity,
+            comparison,
+            value,
+        })
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> Self {
+        rule.to_string()
+    }
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{} {} {}",
+            self.quantity.name(),
+            self.comparison.symbol(),
+            self.value
+        )
+    }
+}
+
+/// A raffle that's taking entries.
+#[derive(Debug)]
+pub struct Raffle {
+    prize: String,
+    /// User IDs and names of everyone who's entered.
+    entrants: Vec<(String, String)>,
+}
+
+impl crate::bot::Bot {
+    /// `!raffle start <prize>`, `!raffle draw` and `!raffle cancel`. Moderators only.
+    pub async fn raffle(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !self.is_moderator(payload).await? {
+            return Ok(());
+        }
+
+        let arguments = arguments.map(str::trim).unwrap_or_default();
+        let (action, prize) = arguments
+            .split_once(char::is_whitespace)
+            .unwrap_or((arguments, ""));
+        let mut raffle = self.raffle.lock().await;
+        let message = match (action, raffle.take()) {
+            ("start", previous) if !prize.trim().is_empty() => {
+                if previous.is_some() {
+                    tracing::info!("Replacing the raffle that was running");
+                }
+                *raffle = Some(Raffle {
+                    prize: prize.trim().to_owned(),
+                    entrants: Vec::new(),
+                });
+                let rules = self.config.read().await.raffle.eligibility.clone();
+                let mut message = format!("🎟️ Raffle for {}! Type !join to enter", prize.trim());
+                if !rules.is_empty() {
+                    let rules = rules.iter().map(Rule::to_string).collect::<Vec<_>>();
+                    message.push_str(&format!(". Entry needs: {}", rules.join(", ")));
+                }
+                message
+            }
+            ("draw", Some(finished)) => {
+                let winner = finished.entrants.choose(&mut rand::rng()).cloned();
+                match winner {
+                    Some((_, username)) => format!(
+                        "🎉 {username} won {}, out of {} entries! 🎉",
+                        finished.prize,
+                        finished.entrants.len()
+                    ),
+                    None => format!("Nobody entered the raffle for {} 😢", finished.prize),
+                }
+            }
+            ("cancel", Some(cancelled)) => format!("Raffle for {} cancelled", cancelled.prize),
+            ("draw" | "cancel", None) => "There's no raffle running".to_owned(),
+            (_, running) => {
+                *raffle = running;
+                USAGE.to_owned()
+            }
+        };
+        drop(raffle);
+
+        self.send_message(message.as_str()).await
+    }
+
+    /// `!join` enters the running raffle, if the rules allow it.
+    pub async fn join_raffle(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let user_id = payload.chatter_user_id.as_str();
+        if self.raffle.lock().await.is_none() {
+            return Ok(());
+        }
+
+        let rules = self.config.read().await.raffle.eligibility.clone();
+        for rule in rules {
+            let value = self.eligibility_quantity(rule.quantity, payload).await?;
+            if !value.is_some_and(|value| rule.comparison.compare(value, rule.value)) {
+                tracing::info!(
+                    "{} isn't eligible for the raffle: {rule}",
+                    payload.chatter_user_name
+                );
+                let message = format!("Sorry, entering needs {rule}");
+                return self
+                    .send_message_reply(&payload.message_id, message.as_str())
+                    .await;
+            }
+        }
+
+        // Checking the rules can take a while, so the raffle could have been drawn since.
+        let mut raffle = self.raffle.lock().await;
+        let Some(raffle) = raffle.as_mut() else {
+            return Ok(());
+        };
+        if raffle.entrants.iter().any(|(id, _)| id == user_id) {
+            return Ok(());
+        }
+        raffle
+            .entrants
+            .push((user_id.to_owned(), payload.chatter_user_name.to_string()));
+        tracing::info!("{} entered the raffle", payload.chatter_user_name);
+        Ok(())
+    }
+
+    /// The mate's value for an eligibility rule, `None` when it doesn't apply, like how long a
+    /// non-follower has followed for.
+    async fn eligibility_quantity(
+        &self,
+        quantity: Quantity,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<Option<f64>> {
+        let user_id = payload.chatter_user_id.as_str();
+        let days_since = |date: chrono::DateTime<chrono::Utc>| {
+            (chrono::Utc::now() - date).num_seconds() as f64 / (24.0 * 60.0 * 60.0)
+        };
+
+        let value = match quantity {
+            Quantity::FollowDays => self.followed_at(user_id).await?.map(days_since),
+            Quantity::AccountDays => self.account_created_at(user_id).await?.map(days_since),
+            Quantity::WatchHours => Some(self.db.estimated_watch_minutes(user_id).await? / 60.0),
+            Quantity::TimeoutsThisMonth => {
+                Some(self.db.count_timeouts_this_month(user_id).await? as f64)
+            }
+            Quantity::Messages => {
+                let mate = self
+                    .db
+                    .get_mate(user_id, payload.chatter_user_name.as_str())
+                    .await?;
+                let messages = self
+                    .db
+                    .metric(crate::achievements::Metric::Messages, user_id, mate.id)
+                    .await?;
+                Some(messages as f64)
+            }
+        };
+        Ok(value)
+    }
+
+    /// When a Twitch account was made. It never changes, so it's cached, rather than asking
+    /// Twitch every time someone tries to `!join`.
+    async fn account_created_at(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        if let Some(created_at) = self.account_created_cache.lock().await.get(user_id) {
+            return Ok(Some(*created_at));
+        }
+
+        let token = self.token.lock().await.clone();
+        let Some(user) = self.client.get_user_from_id(user_id, &token).await? else {
+            return Ok(None);
+        };
+        let created_at = chrono::DateTime::parse_from_rfc3339(user.created_at.as_str())?.to_utc();
+        self.account_created_cache
+            .lock()
+            .await
+            .insert(user_id.to_owned(), created_at);
+        Ok(Some(created_at))
+    }
+}
@@ -0,0 +1,158 @@
+use color_eyre::Result;
+use twitch_api::helix;
+
+/// How long to count down on screen before actually starting a raid.
+const RAID_COUNTDOWN: u64 = 10;
+
+/// How far back to look for a raider's clip to show off.
+const RECENT_CLIP_AGE: chrono::TimeDelta = chrono::TimeDelta::days(30);
+
+impl crate::bot::Bot {
+    /// `!raid <channel>` counts down and then raids the channel. `!raid cancel` stops it, either
+    /// during the countdown or whilst Twitch is getting the raid ready.
+    pub async fn raid(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let Some(target) = arguments
+            .map(|args| args.trim().trim_start_matches('@'))
+            .filter(|target| !target.is_empty())
+        else {
+            self.send_message_reply(&payload.message_id, "Usage: !raid <channel>|cancel")
+                .await?;
+            return Ok(());
+        };
+
+        if target == "cancel" {
+            return self.cancel_raid(payload).await;
+        }
+
+        let token = self.token.lock().await.clone();
+        let Some(user) = self.client.get_user_from_login(target, &token).await? else {
+            let message = format!("Couldn't find a channel called {target}");
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        };
+
+        let message = format!("Getting ready to raid {} 🚀", user.display_name);
+        self.send_announcement(message.as_str(), helix::chat::AnnouncementColor::Purple)
+            .await?;
+
+        let client = self.client.clone();
+        let token = self.token.clone();
+        let countdown = tokio::spawn(async move {
+            let result = async {
+                for remaining in (1..=RAID_COUNTDOWN).rev() {
+                    Self::onscreen_popup_replacing(
+                        format!(" \nRaiding {} in {remaining}s", user.display_name),
+                        "twitch-raid-out",
+                        "raid-out",
+                    )?;
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                }
+
+                let token = token.lock().await.clone();
+                let request = helix::raids::StartARaidRequest::new(crate::BROADCASTER_ID, user.id);
+                client.req_post(request, helix::EmptyBody, &token).await?;
+                tracing::info!("Raid started");
+                Ok::<(), eyre::Report>(())
+            };
+            if let Err(error) = result.await {
+                tracing::error!("Starting raid: {error:?}");
+            }
+        });
+
+        if let Some(previous) = self.pending_raid.lock().await.replace(countdown) {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    async fn cancel_raid(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<()> {
+        let Some(countdown) = self.pending_raid.lock().await.take() else {
+            self.send_message_reply(&payload.message_id, "There's no raid to cancel")
+                .await?;
+            return Ok(());
+        };
+
+        if countdown.is_finished() {
+            let token = self.token.lock().await.clone();
+            let request = helix::raids::CancelARaidRequest::broadcaster_id(crate::BROADCASTER_ID);
+            self.client.req_delete(request, &token).await?;
+        } else {
+            countdown.abort();
+        }
+
+        Self::onscreen_popup_replacing(
+            " \nRaid cancelled".to_owned(),
+            "twitch-raid-out",
+            "raid-out",
+        )?;
+        self.send_message_reply(&payload.message_id, "Raid cancelled 🛑")
+            .await?;
+        Ok(())
+    }
+
+    /// Give an incoming raider a proper shoutout in chat, with what they were streaming and one
+    /// of their recent clips. Also does Twitch's own shoutout.
+    pub async fn raid_shoutout(&self, raid: &crate::bus::Raid) -> Result<()> {
+        let raider = &twitch_api::types::UserId::new(raid.user_id.clone());
+        let token = self.token.lock().await.clone();
+
+        let category = self
+            .client
+            .get_channel_from_id(raider, &token)
+            .await?
+            .map(|channel| channel.game_name.to_string())
+            .filter(|category| !category.is_empty());
+
+        let mut clips_request = helix::clips::GetClipsRequest::broadcaster_id(raider).first(1);
+        let since = (chrono::Utc::now() - RECENT_CLIP_AGE)
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        clips_request.started_at = Some(twitch_api::types::Timestamp::new(since)?.into());
+        let clip = self
+            .client
+            .req_get(clips_request, &token)
+            .await?
+            .data
+            .into_iter()
+            .next();
+
+        let mut message = format!(
+            "📣 Go and follow {} at https://twitch.tv/{}",
+            raid.username, raid.login
+        );
+        if let Some(category) = category {
+            message.push_str(&format!(", they were just streaming {category}"));
+        }
+        if let Some(clip) = clip {
+            message.push_str(&format!(". Check out this clip: {}", clip.url));
+        }
+        self.send_announcement(message.as_str(), helix::chat::AnnouncementColor::Purple)
+            .await?;
+
+        if crate::killswitch::stays_local() {
+            tracing::info!("Log-only, not shouting out {}", raid.username);
+            return Ok(());
+        }
+        let request = helix::chat::SendAShoutoutRequest::new(
+            crate::BROADCASTER_ID,
+            raider.clone(),
+            crate::BOT_ID,
+        );
+        self.client
+            .req_post(request, helix::EmptyBody, &token)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+//! A log of every EventSub notification, exactly as it was received, for debugging alerts after
+//! the fact. `tbhbot replay <file>` sends the notifications to the running bot again, over the
+//! control socket, at the pace that they first arrived or faster. Replayed events are handled
+//! like new ones, but as dry runs, so nothing goes to chat and the real database isn't changed,
+//! see `killswitch::dry_run()`.
+//!
+//! The log is off by default. Once it's bigger than `max_megabytes` it's moved to
+//! `events.jsonl.old`, replacing the previous old one, so it never takes more than twice that.
+
+use color_eyre::Result;
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _};
+
+/// What a replayed event is called on the control socket.
+pub const CONTROL_COMMAND: &str = "replay";
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct EventLogConfig {
+    pub enabled: bool,
+    /// Where to append the notifications, defaults to `events.jsonl` in the state directory.
+    pub path: Option<std::path::PathBuf>,
+    /// How big the log can get before it's rotated.
+    pub max_megabytes: u64,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            max_megabytes: 50,
+        }
+    }
+}
+
+/// A line in the log.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+struct Recorded {
+    /// When Twitch sent the notification.
+    timestamp: String,
+    /// The notification's subscription and event.
+    notification: serde_json::Value,
+}
+
+/// Send the notifications in a log to the running bot. `speed` is how many times faster than
+/// real time to send them.
+pub async fn replay(path: &std::path::Path, speed: f64) -> Result<()> {
+    if speed <= 0.0 {
+        eyre::bail!("The replay speed should be more than 0");
+    }
+
+    let log = tokio::fs::read_to_string(path).await?;
+    let stream = tokio::net::UnixStream::connect(crate::killswitch::socket_path()).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut replies = tokio::io::BufReader::new(reader).lines();
+
+    let mut previous_sent_at = None;
+    for line in log.lines().filter(|line| !line.trim().is_empty()) {
+        let recorded: Recorded = serde_json::from_str(line)?;
+        let sent_at = chrono::DateTime::parse_from_rfc3339(&recorded.timestamp)?;
+        if let Some(previous) = previous_sent_at {
+            let gap = (sent_at - previous).to_std().unwrap_or_default();
+            tokio::time::sleep(gap.div_f64(speed)).await;
+        }
+        previous_sent_at = Some(sent_at);
+
+        writer
+            .write_all(format!("{CONTROL_COMMAND} {line}\n").as_bytes())
+            .await?;
+        let reply = replies.next_line().await?.unwrap_or_default();
+        let kind = recorded.notification["subscription"]["type"]
+            .as_str()
+            .unwrap_or("unknown");
+        println!("[{}] {kind}: {reply}", recorded.timestamp);
+    }
+
+    Ok(())
+}
+
+impl crate::bot::Bot {
+    /// Append a notification to the log. Failing to is only logged, so that it doesn't stop the
+    /// event being handled.
+    pub async fn record_event(
+        &self,
+        notification: &serde_json::Value,
+        timestamp: &twitch_api::types::Timestamp,
+    ) {
+        let config = self.config.read().await.event_log.clone();
+        if !config.enabled {
+            return;
+        }
+        let path = config
+            .path
+            .unwrap_or_else(|| crate::paths::state_directory().join("events.jsonl"));
+        let recorded = Recorded {
+            timestamp: timestamp.as_str().to_owned(),
+            notification: notification.clone(),
+        };
+        if let Err(error) = Self::append_recorded(&path, &recorded, config.max_megabytes) {
+            tracing::error!("Recording event to {path:?}: {error:?}");
+        }
+    }
+
+    fn append_recorded(
+        path: &std::path::Path,
+        recorded: &Recorded,
+        max_megabytes: u64,
+    ) -> Result<()> {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let size = std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+        if size > max_megabytes.saturating_mul(1024 * 1024) {
+            std::fs::rename(path, path.with_extension("jsonl.old"))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let mut line = serde_json::to_string(recorded)?;
+        line.push('\n');
+        std::io::Write::write_all(&mut file, line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Handle a replayed notification from the control socket, returning the reply to send
+    /// back.
+    pub async fn replay_event(&self, json: &str) -> String {
+        let result = async {
+            let recorded: Recorded = serde_json::from_str(json)?;
+            let event = twitch_api::eventsub::Event::parse(&recorded.notification.to_string())?;
+            let timestamp = twitch_api::types::Timestamp::new(recorded.timestamp)?;
+            self.db.prepare_dry_run().await?;
+            crate::killswitch::dry_run(self.handle_event(event, timestamp)).await
+        }
+        .await;
+        match result {
+            Ok(()) => "Replayed".to_owned(),
+            Err(error) => {
+                tracing::error!("Replaying event: {error:?}");
+                format!("Replay failed: {error}")
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use color_eyre::Result;
+use twitch_api::helix::{self, points::CustomRewardRedemptionStatus};
+
+/// Maps a channel point reward to what the bot should do when it's redeemed.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Redemption {
+    /// The title of the reward, exactly as it appears on Twitch.
+    pub reward: String,
+    pub action: Action,
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Play the sound file at the given path.
+    Sound(String),
+    /// Show an onscreen popup. `{user}` and `{input}` are replaced in the message.
+    Popup(String),
+    /// Render the given emote in Tattoy behind the text that the viewer entered.
+    Tattoy(String),
+    /// Pin an emote in Tattoy at the given anchor until the end of the stream, eg:
+    /// `{ pinned_tattoy = { emote = "LUL", anchor = "prompt" } }`.
+    PinnedTattoy {
+        emote: String,
+        anchor: tattoy_twitch_tombh_plugin::Anchor,
+    },
+    /// Make what the viewer entered their default `!tty` emote, rendered bigger and for longer.
+    PremiumTtyDefault,
+    /// Run a shell command. The viewer's name and input are available in the `TBHBOT_USER` and
+    /// `TBHBOT_INPUT` environment variables. A failing command cancels the redemption.
+    Command(String),
+}
+
+impl crate::bot::Bot {
+    /// Run the configured action for a channel point redemption, then mark the redemption as
+    /// fulfilled, or cancelled (refunding the points) if the action failed.
+    pub async fn redemption(&self, redeemed: &crate::bus::Redemption) -> Result<()> {
+        tracing::info!("Redemption: {redeemed:?}");
+        let Some(redemption) = self
+            .config
+            .read()
+            .await
+            .redemption
+            .iter()
+            .find(|redemption| redemption.reward == redeemed.reward)
+            .cloned()
+        else {
+            tracing::debug!("No action for reward: {}", redeemed.reward);
+            return Ok(());
+        };
+
+        let result = self
+            .run_redemption_action(&redemption.action, redeemed)
+            .await;
+        let status = match &result {
+            Ok(()) => CustomRewardRedemptionStatus::Fulfilled,
+            Err(error) => {
+                tracing::error!("Redemption action failed: {error:?}");
+                CustomRewardRedemptionStatus::Canceled
+            }
+        };
+        self.set_redemption_status(redeemed, status).await
+    }
+
+    async fn run_redemption_action(
+        &self,
+        action: &Action,
+        redeemed: &crate::bus::Redemption,
+    ) -> Result<()> {
+        let username = redeemed.username.as_str();
+        match action {
+            Action::Sound(path) => {
+                Self::play_sound(path)?;
+            }
+            Action::Popup(message) => {
+                let text = message
+                    .replace("{user}", username)
+                    .replace("{input}", &redeemed.input);
+                Self::onscreen_popup(format!(" \n{text}"), "twitch-redemption")?;
+            }
+            Action::Tattoy(emote) => {
+                self.send_tattoy_emote(username, &redeemed.input, emote, false, None)
+                    .await?;
+            }
+            Action::PinnedTattoy { emote, anchor } => {
+                self.send_to_tattoy(&tattoy_twitch_tombh_plugin::BotMessage::Emote {
+                    username: username.to_owned(),
+                    regexish: String::new(),
+                    emote: emote.clone(),
+                    scale: None,
+                    duration: None,
+                    anchor: Some(anchor.clone()),
+                    pinned: true,
+                    every_match: None,
+                    pop_in: false,
+                })
+                .await?;
+            }
+            Action::PremiumTtyDefault => {
+                let Some(emote) = redeemed.input.split_whitespace().next() else {
+                    eyre::bail!("No emote given for the premium !tty default");
+                };
+                let mate = self.db.get_mate(&redeemed.user_id, username).await?;
+                self.db.set_tty_default(mate.id, Some(emote), true).await?;
+                let message = format!("{username}'s premium !tty emote is now {emote} ✨");
+                self.send_message(message.as_str()).await?;
+            }
+            Action::Command(command) => {
+                let status = tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .env("TBHBOT_USER", username)
+                    .env("TBHBOT_INPUT", &redeemed.input)
+                    .status()
+                    .await?;
+                if !status.success() {
+                    eyre::bail!("`{command}` exited with: {status}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn set_redemption_status(
+        &self,
+        redeemed: &crate::bus::Redemption,
+        status: CustomRewardRedemptionStatus,
+    ) -> Result<()> {
+        let token = self.token.lock().await.clone();
+        let request = helix::points::UpdateRedemptionStatusRequest::new(
+            crate::BROADCASTER_ID,
+            twitch_api::types::RewardId::new(redeemed.reward_id.clone()),
+            twitch_api::types::RedemptionId::new(redeemed.id.clone()),
+        );
+        let body = helix::points::UpdateRedemptionStatusBody::status(status);
+        self.client.req_patch(request, body, &token).await?;
+
+        Ok(())
+    }
+}
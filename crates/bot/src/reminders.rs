@@ -0,0 +1,126 @@
+//! `!remind`. Reminders are kept in the database so that they still fire if the bot restarts.
+//! Reminders for the broadcaster are shown on screen, everyone else gets a reply in chat.
+
+use color_eyre::Result;
+
+const USAGE: &str = "Usage: !remind <duration> <text>, eg: !remind 10m stretch";
+
+/// How often to check for reminders that are due.
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// The furthest in the future that a reminder can be set for.
+const MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How many reminders each mate can have waiting, so that chat can't flood the table.
+const MAX_PENDING_PER_AUTHOR: i64 = 5;
+
+impl crate::bot::Bot {
+    /// `!remind 10m text` reminds you. Moderators can remind others with
+    /// `!remind @user 10m text`.
+    pub async fn remind(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let author = payload.chatter_user_name.as_str();
+        let mut arguments = arguments.map(str::trim).unwrap_or_default();
+
+        let mut target = author.to_owned();
+        if let Some(rest) = arguments.strip_prefix('@') {
+            if !self.is_moderator(payload).await? {
+                self.send_message_reply(&payload.message_id, "Only mods can remind other people")
+                    .await?;
+                return Ok(());
+            }
+            let (user, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            target = user.to_owned();
+            arguments = rest.trim();
+        }
+
+        let (duration, text) = arguments
+            .split_once(char::is_whitespace)
+            .unwrap_or((arguments, ""));
+        let (Some(delay), false) = (
+            crate::utils::parse_duration(duration),
+            text.trim().is_empty(),
+        ) else {
+            self.send_message_reply(&payload.message_id, USAGE).await?;
+            return Ok(());
+        };
+        if delay > MAX_DELAY {
+            self.send_message_reply(
+                &payload.message_id,
+                "Reminders can only be up to a week away",
+            )
+            .await?;
+            return Ok(());
+        }
+        if self.db.count_pending_reminders(author).await? >= MAX_PENDING_PER_AUTHOR {
+            let message = format!(
+                "You already have {MAX_PENDING_PER_AUTHOR} reminders waiting, that's plenty!"
+            );
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        }
+
+        let due_at = chrono::Utc::now() + delay;
+        let is_onscreen = target.eq_ignore_ascii_case(payload.broadcaster_user_name.as_str());
+        self.db
+            .add_reminder(crate::database::Reminder {
+                id: 0,
+                author: author.to_owned(),
+                target: target.clone(),
+                text: text.trim().to_owned(),
+                message_id: Some(payload.message_id.to_string()),
+                is_onscreen,
+                due_at,
+            })
+            .await?;
+        let who = if target == author {
+            "you".to_owned()
+        } else {
+            target.clone()
+        };
+        let message = format!("⏰ OK, I'll remind {who} at {} UTC", due_at.format("%H:%M"));
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// Deliver reminders as they become due.
+    pub async fn reminderer(&self) -> Result<()> {
+        loop {
+            if let Err(error) = self.deliver_due_reminders().await {
+                tracing::error!("Delivering reminders: {error:?}");
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    async fn deliver_due_reminders(&self) -> Result<()> {
+        for reminder in self.db.due_reminders(chrono::Utc::now()).await? {
+            tracing::info!("Reminder is due: {reminder:?}");
+            if reminder.is_onscreen {
+                Self::onscreen_popup(format!(" \n⏰ {}", reminder.text), "twitch-reminder")?;
+            } else {
+                let from = if reminder.author == reminder.target {
+                    String::new()
+                } else {
+                    format!(" (from {})", reminder.author)
+                };
+                let message = format!("⏰ @{} {}{from}", reminder.target, reminder.text);
+                match reminder.message_id {
+                    Some(message_id) => {
+                        self.send_message_reply(&message_id.into(), message.as_str())
+                            .await?;
+                    }
+                    None => self.send_message(message.as_str()).await?,
+                }
+            }
+            self.db.mark_reminder_delivered(reminder.id).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Working out which message a reply in chat refers to, for features like quoting by replying.
+
+use color_eyre::Result;
+
+/// The most of a replied-to message that's shown in the terminal.
+const MAX_CONTEXT_LENGTH: usize = 50;
+
+impl crate::bot::Bot {
+    /// Twitch starts replies with a mention of who's being replied to, eg "@tombh !quote this".
+    /// This is the text without that mention, so that commands can be used in replies.
+    pub fn without_reply_mention(
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> &str {
+        let text = payload.message.text.as_str();
+        let Some(reply) = &payload.reply else {
+            return text;
+        };
+        let Some((mention, rest)) = text.split_once(char::is_whitespace) else {
+            return text;
+        };
+        if mention
+            .trim_start_matches('@')
+            .eq_ignore_ascii_case(reply.parent_user_login.as_str())
+        {
+            rest.trim_start()
+        } else {
+            text
+        }
+    }
+
+    /// The message that the given message is a reply to, if it is one. Saved messages are
+    /// preferred, but messages from before the bot started still come from the reply's
+    /// metadata.
+    pub async fn replied_to(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<Option<crate::database::SavedMessage>> {
+        let Some(reply) = &payload.reply else {
+            return Ok(None);
+        };
+
+        if let Some(saved) = self
+            .db
+            .get_message(reply.parent_message_id.as_str())
+            .await?
+        {
+            return Ok(Some(saved));
+        }
+
+        Ok(Some(crate::database::SavedMessage {
+            message_id: reply.parent_message_id.to_string(),
+            twitch_user_id: reply.parent_user_id.to_string(),
+            username: reply.parent_user_name.to_string(),
+            text: reply.parent_message_body.clone(),
+            parent_message_id: None,
+        }))
+    }
+
+    /// A short description of the message being replied to, for the terminal chat log.
+    pub async fn reply_context(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+    ) -> Result<Option<String>> {
+        let Some(parent) = self.replied_to(payload).await? else {
+            return Ok(None);
+        };
+
+        let mut excerpt = parent
+            .text
+            .chars()
+            .take(MAX_CONTEXT_LENGTH)
+            .collect::<String>();
+        if excerpt.len() < parent.text.len() {
+            excerpt.push('…');
+        }
+        Ok(Some(format!("↪ {}: {excerpt}", parent.username)))
+    }
+}
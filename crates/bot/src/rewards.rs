@@ -0,0 +1,95 @@
+use color_eyre::Result;
+use twitch_api::helix::{self, points};
+
+/// A channel point reward that the bot owns. The rewards in the config are the source of
+/// truth, so rewards made by the bot that are no longer in the config get deleted.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Reward {
+    pub title: String,
+    pub cost: usize,
+    #[serde(default)]
+    pub prompt: String,
+    /// Global cooldown in seconds.
+    pub cooldown: Option<usize>,
+    /// Whether the viewer has to enter some text when redeeming.
+    #[serde(default)]
+    pub user_input: bool,
+}
+
+impl Reward {
+    /// Whether the reward on Twitch already matches the config.
+    fn matches(&self, existing: &points::CustomReward) -> bool {
+        let cooldown = existing
+            .global_cooldown_setting
+            .is_enabled
+            .then_some(existing.global_cooldown_setting.global_cooldown_seconds);
+
+        existing.cost == self.cost
+            && existing.prompt == self.prompt
+            && existing.is_user_input_required == self.user_input
+            && cooldown == self.cooldown
+    }
+}
+
+impl crate::bot::Bot {
+    /// Create, update and delete the channel's rewards so that they match the config. Twitch
+    /// only lets us manage rewards that were created by the bot, so rewards made through the
+    /// Twitch dashboard are left alone.
+    pub async fn sync_rewards(&self) -> Result<()> {
+        let token = self.token.lock().await.clone();
+        let request = points::GetCustomRewardRequest::broadcaster_id(crate::BROADCASTER_ID)
+            .only_manageable_rewards(true);
+        let existing_rewards = self.client.req_get(request, &token).await?.data;
+        let rewards = self.config.read().await.reward.clone();
+
+        for reward in &rewards {
+            match existing_rewards
+                .iter()
+                .find(|existing| existing.title == reward.title)
+            {
+                Some(existing) if reward.matches(existing) => {}
+                Some(existing) => {
+                    tracing::info!("Updating reward: {}", reward.title);
+                    let request = points::UpdateCustomRewardRequest::new(
+                        crate::BROADCASTER_ID,
+                        existing.id.clone(),
+                    );
+                    let mut body = points::UpdateCustomRewardBody::default();
+                    body.cost = Some(reward.cost);
+                    body.prompt = Some(reward.prompt.clone().into());
+                    body.is_user_input_required = Some(reward.user_input);
+                    body.is_global_cooldown_enabled = Some(reward.cooldown.is_some());
+                    body.global_cooldown_seconds = reward.cooldown;
+                    self.client.req_patch(request, body, &token).await?;
+                }
+                None => {
+                    tracing::info!("Creating reward: {}", reward.title);
+                    let request =
+                        points::CreateCustomRewardRequest::broadcaster_id(crate::BROADCASTER_ID);
+                    let mut body =
+                        points::CreateCustomRewardBody::new(reward.title.clone(), reward.cost);
+                    body.prompt = Some(reward.prompt.clone().into());
+                    body.is_user_input_required = Some(reward.user_input);
+                    body.is_global_cooldown_enabled = Some(reward.cooldown.is_some());
+                    body.global_cooldown_seconds = reward.cooldown;
+                    self.client.req_post(request, body, &token).await?;
+                }
+            }
+        }
+
+        for existing in existing_rewards {
+            if rewards.iter().any(|reward| reward.title == existing.title) {
+                continue;
+            }
+            tracing::info!("Deleting reward: {}", existing.title);
+            let request = helix::points::DeleteCustomRewardRequest::new(
+                crate::BROADCASTER_ID,
+                existing.id.clone(),
+            );
+            self.client.req_delete(request, &token).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use color_eyre::Result;
+use futures::TryStreamExt as _;
+
+/// How often the moderator and VIP lists are fetched from Twitch.
+const SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// The channel roles that we cache from Helix, and the bot's own roles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(rename_all = "lowercase")]
+pub enum Role {
+    Moderator,
+    Vip,
+    /// A co-host for a collab stream, see `guests.rs`.
+    Guest,
+}
+
+impl crate::bot::Bot {
+    /// Periodically cache the channel's moderators and VIPs in the database. Badges are only
+    /// available on chat messages, so this is what lets permission checks work for things like
+    /// whispers.
+    pub async fn role_syncer(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.sync_roles().await {
+                tracing::error!("Syncing moderators and VIPs: {error:?}");
+            }
+        }
+    }
+
+    async fn sync_roles(&self) -> Result<()> {
+        let token = self.token.lock().await.clone();
+
+        let moderators: Vec<(String, String)> = self
+            .client
+            .get_moderators_in_channel_from_id(crate::BROADCASTER_ID, &token)
+            .map_ok(|moderator| {
+                (
+                    moderator.user_id.to_string(),
+                    moderator.user_name.to_string(),
+                )
+            })
+            .try_collect()
+            .await?;
+        self.db.replace_roles(Role::Moderator, &moderators).await?;
+
+        let vips: Vec<(String, String)> = self
+            .client
+            .get_vips_in_channel(crate::BROADCASTER_ID, &token)
+            .map_ok(|vip| (vip.user_id.to_string(), vip.user_name.to_string()))
+            .try_collect()
+            .await?;
+        self.db.replace_roles(Role::Vip, &vips).await?;
+
+        tracing::info!(
+            "Synced {} moderators and {} VIPs",
+            moderators.len(),
+            vips.len()
+        );
+        Ok(())
+    }
+
+    /// Whether the user is the broadcaster, a cached moderator or a guest. Works without badge
+    /// data.
+    pub async fn user_is_moderator(&self, user_id: &str) -> Result<bool> {
+        if user_id == crate::BROADCASTER_ID {
+            return Ok(true);
+        }
+        Ok(self.db.has_role(user_id, Role::Moderator).await?
+            || self.db.has_role(user_id, Role::Guest).await?)
+    }
+
+    /// Whether the user is a cached VIP. Works without badge data.
+    pub async fn user_is_vip(&self, user_id: &str) -> Result<bool> {
+        self.db.has_role(user_id, Role::Vip).await
+    }
+}
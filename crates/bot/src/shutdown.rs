@@ -0,0 +1,67 @@
+//! Stopping cleanly on SIGINT or SIGTERM. Event sources and background tasks are cancelled, so no
+//! new events arrive, then the events that have already arrived are finished, including saving
+//! them in the database. Then chat messages that are still queued are sent, the database is
+//! closed and the Tattoy socket is shut down. Sounds that are already playing finish on their
+//! own, because they're separate processes.
+
+use color_eyre::Result;
+use tokio::io::AsyncWriteExt as _;
+
+/// The longest that sending the queued chat messages can take, so that a Twitch outage can't
+/// stop the bot from stopping.
+const FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// The longest that finishing the events that have already arrived can take, so that a stuck
+/// handler can't stop the bot from stopping.
+pub const DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Wait for a signal to stop.
+pub async fn signal() -> Result<()> {
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => (),
+    }
+    Ok(())
+}
+
+impl crate::bot::Bot {
+    /// Tidy up after everything else has been cancelled and queued events have been finished,
+    /// saying goodbye first if it's during a stream.
+    pub async fn shut_down(
+        &self,
+        outbox: &mut tokio::sync::mpsc::UnboundedReceiver<crate::outbox::Outgoing>,
+    ) {
+        tracing::info!("Shutting down");
+        crate::watchdog::notify_stopping();
+
+        match self.db.current_stream().await {
+            Ok(Some(_)) => {
+                if let Err(error) = self
+                    .announce_presence(crate::presence::Presence::Offline)
+                    .await
+                {
+                    tracing::error!("Saying goodbye: {error:?}");
+                }
+            }
+            Ok(None) => (),
+            Err(error) => tracing::error!("Checking for a stream to say goodbye to: {error:?}"),
+        }
+
+        outbox.close();
+        if tokio::time::timeout(FLUSH_TIMEOUT, self.outboxer(outbox))
+            .await
+            .is_err()
+        {
+            tracing::warn!("Gave up sending queued chat messages");
+        }
+
+        self.db.close().await;
+
+        if let Err(error) = self.tattoy_socket.lock().await.shutdown().await {
+            tracing::warn!("Closing the Tattoy socket: {error:?}");
+        }
+
+        tracing::info!("Shut down");
+    }
+}
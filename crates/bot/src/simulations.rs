@@ -0,0 +1,154 @@
+//! Made up events, for testing alerts and commands off-stream, eg: `tbhbot simulate raid
+//! --viewers 40`. The CLI sends the event to the running bot over the control socket, where it's
+//! handled just like a real one from Twitch, but as a dry run. So alerts are shown and played,
+//! but nothing is sent to chat or Twitch, and anything saved goes to a scratch copy of the
+//! database, see `killswitch::dry_run()`.
+
+use color_eyre::Result;
+
+/// What a simulation is called on the control socket.
+pub const CONTROL_COMMAND: &str = "simulate";
+
+/// The user ID given to simulated users. It's not a real account.
+const SIMULATED_USER_ID: &str = "0";
+
+#[derive(clap::Subcommand, serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+pub enum SimulatedEvent {
+    /// A new follower
+    Follow {
+        #[clap(long, default_value = "alice")]
+        user: String,
+    },
+    /// An incoming raid
+    Raid {
+        #[clap(long, default_value = "alice")]
+        user: String,
+        #[clap(long, default_value_t = 10)]
+        viewers: u64,
+    },
+    /// A chat message, eg: "!chirp"
+    Chat {
+        message: String,
+        #[clap(long, default_value = "alice")]
+        user: String,
+    },
+}
+
+impl SimulatedEvent {
+    /// The event as Twitch would send it, with its subscription.
+    fn to_notification(&self) -> serde_json::Value {
+        let (kind, version, condition, event) = match self {
+            Self::Follow { user } => (
+                "channel.follow",
+                "2",
+                serde_json::json!({
+                    "broadcaster_user_id": crate::BROADCASTER_ID,
+                    "moderator_user_id": crate::BOT_ID,
+                }),
+                serde_json::json!({
+                    "broadcaster_user_id": crate::BROADCASTER_ID,
+                    "broadcaster_user_login": "broadcaster",
+                    "broadcaster_user_name": "broadcaster",
+                    "user_id": SIMULATED_USER_ID,
+                    "user_login": user.to_lowercase(),
+                    "user_name": user,
+                    "followed_at": chrono::Utc::now().to_rfc3339(),
+                }),
+            ),
+            Self::Raid { user, viewers } => (
+                "channel.raid",
+                "1",
+                serde_json::json!({
+                    "from_broadcaster_user_id": "",
+                    "to_broadcaster_user_id": crate::BROADCASTER_ID,
+                }),
+                serde_json::json!({
+                    "from_broadcaster_user_id": SIMULATED_USER_ID,
+                    "from_broadcaster_user_login": user.to_lowercase(),
+                    "from_broadcaster_user_name": user,
+                    "to_broadcaster_user_id": crate::BROADCASTER_ID,
+                    "to_broadcaster_user_login": "broadcaster",
+                    "to_broadcaster_user_name": "broadcaster",
+                    "viewers": viewers,
+                }),
+            ),
+            Self::Chat { message, user } => (
+                "channel.chat.message",
+                "1",
+                serde_json::json!({
+                    "broadcaster_user_id": crate::BROADCASTER_ID,
+                    "user_id": crate::BOT_ID,
+                }),
+                serde_json::json!({
+                    "broadcaster_user_id": crate::BROADCASTER_ID,
+                    "broadcaster_user_login": "broadcaster",
+                    "broadcaster_user_name": "broadcaster",
+                    "chatter_user_id": SIMULATED_USER_ID,
+                    "chatter_user_login": user.to_lowercase(),
+                    "chatter_user_name": user,
+                    "message_id": format!("simulated-{}", chrono::Utc::now().timestamp_millis()),
+                    "message": {
+                        "text": message,
+                        "fragments": [{
+                            "type": "text",
+                            "text": message,
+                            "cheermote": null,
+                            "emote": null,
+                            "mention": null,
+                        }],
+                    },
+                    "color": "",
+                    "badges": [],
+                    "message_type": "text",
+                    "cheer": null,
+                    "reply": null,
+                    "channel_points_custom_reward_id": null,
+                }),
+            ),
+        };
+        serde_json::json!({
+            "subscription": {
+                "id": "simulated",
+                "status": "enabled",
+                "type": kind,
+                "version": version,
+                "cost": 0,
+                "condition": condition,
+                "transport": { "method": "websocket", "session_id": "simulated" },
+                "created_at": chrono::Utc::now().to_rfc3339(),
+            },
+            "event": event,
+        })
+    }
+}
+
+/// Send a simulated event to the running bot, returning its reply.
+pub async fn send(event: &SimulatedEvent) -> Result<String> {
+    let line = format!("{CONTROL_COMMAND} {}", serde_json::to_string(event)?);
+    crate::killswitch::send_control_line(&line).await
+}
+
+impl crate::bot::Bot {
+    /// Handle a simulated event from the control socket, returning the reply to send back.
+    pub async fn simulate(&self, json: &str) -> String {
+        match self.handle_simulated_event(json).await {
+            Ok(()) => "Simulated".to_owned(),
+            Err(error) => {
+                tracing::error!("Simulating event: {error:?}");
+                format!("Simulation failed: {error}")
+            }
+        }
+    }
+
+    async fn handle_simulated_event(&self, json: &str) -> Result<()> {
+        let simulated: SimulatedEvent = serde_json::from_str(json)?;
+        tracing::info!("Simulating: {simulated:?}");
+        let notification = simulated.to_notification().to_string();
+        let event = twitch_api::eventsub::Event::parse(&notification)?;
+        let timestamp = twitch_api::types::Timestamp::new(
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        )?;
+        self.db.prepare_dry_run().await?;
+        crate::killswitch::dry_run(self.handle_event(event, timestamp)).await
+    }
+}
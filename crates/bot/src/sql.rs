@@ -0,0 +1,65 @@
+//! `!sql` lets the broadcaster query the bot's database live, eg to answer "how many times has
+//! X said Y?". Queries run on a read-only connection.
+
+use color_eyre::Result;
+
+/// The most rows to reply with.
+const MAX_ROWS: usize = 5;
+
+/// Replies are cut down to this many characters, Twitch's limit is 500.
+const MAX_REPLY_LENGTH: usize = 450;
+
+/// How long a query can run for before it's stopped.
+const QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+impl crate::bot::Bot {
+    /// `!sql SELECT ...`
+    pub async fn sql(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let query = arguments.unwrap_or_default().trim().trim_end_matches(';');
+        let keyword = query
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if !["select", "with"].contains(&keyword.as_str()) || query.contains(';') {
+            self.send_message_reply(&payload.message_id, "Usage: !sql SELECT ...")
+                .await?;
+            return Ok(());
+        }
+
+        let result = self
+            .db
+            .read_only_query(query, MAX_ROWS, QUERY_TIMEOUT)
+            .await;
+        let mut message = match result {
+            Err(error) => format!("Query failed: {error}"),
+            Ok(results) if results.rows.is_empty() => "No rows".to_owned(),
+            Ok(results) => {
+                let rows = results
+                    .rows
+                    .iter()
+                    .map(|row| row.join(", "))
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                let more = if results.is_truncated { " | …" } else { "" };
+                format!("[{}] {rows}{more}", results.columns.join(", "))
+            }
+        };
+        if message.chars().count() > MAX_REPLY_LENGTH {
+            message = message.chars().take(MAX_REPLY_LENGTH).collect();
+            message.push('…');
+        }
+
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+}
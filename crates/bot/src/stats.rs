@@ -0,0 +1,110 @@
+//! Chat stats. `!stats` for individual chatters, and a fun stats line posted to chat every
+//! hour whilst live.
+
+use color_eyre::Result;
+
+/// How often stats are posted, and how far back they look.
+const STATS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct HourlyStatsConfig {
+    pub enabled: bool,
+    /// The stats line. `{messages}`, `{chatters}`, `{chirps}`, `{chicken_runs}`, `{follows}`
+    /// and `{top_emote}` are replaced with the past hour's stats.
+    pub template: String,
+}
+
+impl Default for HourlyStatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: "📈 Past hour: {messages} messages, {chirps} chirps, {chicken_runs} chicken \
+                runs, {follows} new followers"
+                .to_owned(),
+        }
+    }
+}
+
+impl crate::bot::Bot {
+    /// `!stats [user]`, defaults to the chatter.
+    pub async fn stats(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let username = arguments
+            .map(|args| args.trim().trim_start_matches('@'))
+            .filter(|user| !user.is_empty())
+            .unwrap_or(payload.chatter_user_name.as_str());
+
+        let Some(stats) = self.db.user_stats(username).await? else {
+            let message = format!("I haven't seen {username} chat yet");
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        };
+
+        let mut message = format!(
+            "📊 {}: {} messages since {}",
+            stats.username,
+            stats.messages,
+            stats.first_seen.format("%Y-%m-%d")
+        );
+        if let Some(emote) = stats.favourite_emote {
+            message.push_str(&format!(", favourite emote {emote}"));
+        }
+        if let Some(chickens) = stats.best_chicken_run {
+            message.push_str(&format!(", best chicken run {chickens} 🐔"));
+        }
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// Post stats to chat every hour whilst the stream is live.
+    pub async fn hourly_stats(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(STATS_INTERVAL);
+        // The first tick is immediate, and there aren't any stats yet.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.post_hourly_stats().await {
+                tracing::error!("Posting hourly stats: {error:?}");
+            }
+        }
+    }
+
+    async fn post_hourly_stats(&self) -> Result<()> {
+        let config = self.config.read().await.hourly_stats.clone();
+        if !config.enabled || self.db.current_stream().await?.is_none() {
+            return Ok(());
+        }
+
+        let since = chrono::Utc::now() - STATS_INTERVAL;
+        let stats = self.db.summarise_since(since).await?;
+        tracing::info!("Hourly stats: {stats:?}");
+        if stats.messages == 0 {
+            return Ok(());
+        }
+
+        let top_emote = stats
+            .top_emotes
+            .first()
+            .map(|(emote, _)| emote.clone())
+            .unwrap_or_default();
+        let message = config
+            .template
+            .replace("{messages}", &stats.messages.to_string())
+            .replace("{chatters}", &stats.unique_chatters.to_string())
+            .replace("{chirps}", &stats.chirps.to_string())
+            .replace("{chicken_runs}", &stats.chicken_runs.to_string())
+            .replace("{follows}", &stats.follows.to_string())
+            .replace("{top_emote}", &top_emote);
+        self.send_announcement(
+            message.as_str(),
+            twitch_api::helix::chat::AnnouncementColor::Primary,
+        )
+        .await
+    }
+}
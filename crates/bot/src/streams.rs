@@ -0,0 +1,134 @@
+use color_eyre::Result;
+
+/// If the bot didn't see the stream start, summarise this far back.
+const FALLBACK_STREAM_LENGTH: chrono::TimeDelta = chrono::TimeDelta::hours(12);
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct SummaryConfig {
+    /// Where to write a Markdown report of each stream. No reports are written if unset.
+    pub report_directory: Option<std::path::PathBuf>,
+}
+
+impl crate::database::StreamSummary {
+    fn to_markdown(&self, started_at: chrono::DateTime<chrono::Utc>) -> String {
+        let emotes = self
+            .top_emotes
+            .iter()
+            .map(|(emote, uses)| format!("* {emote} ({uses})"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "# Stream on {}\n\n\
+            * Messages: {}\n\
+            * Unique chatters: {}\n\
+            * New follows: {}\n\
+            * Chicken runs: {}\n\n\
+            ## Top emotes\n\n\
+            {emotes}\n",
+            started_at.format("%Y-%m-%d"),
+            self.messages,
+            self.unique_chatters,
+            self.follows,
+            self.chicken_runs,
+        )
+    }
+}
+
+impl crate::bot::Bot {
+    pub async fn stream_online(
+        &self,
+        payload: &crate::eventsub::stream::StreamOnlineV1Payload,
+    ) -> Result<()> {
+        tracing::info!("Stream online: {payload:?}");
+        let started_at = chrono::DateTime::parse_from_rfc3339(payload.started_at.as_str())?;
+        self.db.start_stream(started_at.to_utc()).await?;
+        if let Err(error) = self
+            .announce_presence(crate::presence::Presence::Online)
+            .await
+        {
+            tracing::error!("Announcing bot presence: {error:?}");
+        }
+        if let Err(error) = self.announce_today().await {
+            tracing::error!("Announcing today's plan: {error:?}");
+        }
+        if let Err(error) = self.crosspost_go_live(payload).await {
+            tracing::error!("Cross-posting go-live: {error:?}");
+        }
+        if let Err(error) = self.discord_go_live(payload).await {
+            tracing::error!("Posting go-live to Discord: {error:?}");
+        }
+        self.crown_earliest_bird().await
+    }
+
+    pub async fn stream_offline(
+        &self,
+        payload: &crate::eventsub::stream::StreamOfflineV1Payload,
+    ) -> Result<()> {
+        tracing::info!("Stream offline: {payload:?}");
+        self.expire_guests().await?;
+        if let Err(error) = self
+            .send_to_tattoy(&tattoy_twitch_tombh_plugin::BotMessage::ClearPinned)
+            .await
+        {
+            tracing::error!("Clearing pinned emotes: {error:?}");
+        }
+        let stream = self.db.end_stream().await?;
+        let started_at = match &stream {
+            Some(stream) => stream.started_at,
+            None => chrono::Utc::now() - FALLBACK_STREAM_LENGTH,
+        };
+        self.stream_summary(started_at).await?;
+        if let Err(error) = self.discord_go_offline(payload, stream.as_ref()).await {
+            tracing::error!("Updating the Discord go-live message: {error:?}");
+        }
+        if let Err(error) = self.crown_last_chicken_standing(stream.as_ref()).await {
+            tracing::error!("Crowning the last chicken standing: {error:?}");
+        }
+        if let Err(error) = self
+            .elect_emote_of_the_stream(stream.as_ref(), started_at)
+            .await
+        {
+            tracing::error!("Electing the emote of the stream: {error:?}");
+        }
+        self.announce_presence(crate::presence::Presence::Offline)
+            .await
+    }
+
+    /// Show a summary of the stream on screen, and optionally save it as a Markdown report.
+    async fn stream_summary(&self, started_at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        let summary = self.db.summarise_since(started_at).await?;
+        tracing::info!("Stream summary: {summary:?}");
+
+        let emotes = summary
+            .top_emotes
+            .iter()
+            .map(|(emote, _)| emote.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let message = format!(
+            " \nThat's a wrap!\n{} messages from {} mates\n{} new follows, {} chicken runs\n{emotes}",
+            summary.messages, summary.unique_chatters, summary.follows, summary.chicken_runs,
+        );
+        Self::onscreen_popup(message, "twitch-stream-summary")?;
+        if let Err(error) = self.crosspost_summary(&summary).await {
+            tracing::error!("Cross-posting the stream summary: {error:?}");
+        }
+
+        let maybe_directory = self
+            .config
+            .read()
+            .await
+            .stream_summary
+            .report_directory
+            .clone();
+        if let Some(directory) = maybe_directory {
+            std::fs::create_dir_all(&directory)?;
+            let path = directory.join(format!("stream-{}.md", started_at.format("%Y-%m-%d")));
+            std::fs::write(&path, summary.to_markdown(started_at))?;
+            tracing::info!("Wrote stream report to: {path:?}");
+        }
+
+        Ok(())
+    }
+}
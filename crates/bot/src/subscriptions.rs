@@ -0,0 +1,149 @@
+//! The EventSub subscriptions for all the events that the bot handles. They're the same whichever
+//! transport they're delivered over.
+
+use twitch_api::{eventsub, types, HelixClient};
+
+/// Subscribe to every event, delivered over the given transport. Chat events are read as
+/// `user_id`, which is the bot's account.
+pub async fn create_subscriptions<T>(
+    client: &HelixClient<'static, reqwest::Client>,
+    transport: eventsub::Transport,
+    user_id: &types::UserId,
+    token: &T,
+) -> Result<(), eyre::Report>
+where
+    T: twitch_oauth2::TwitchToken + Send + Sync + ?Sized,
+{
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::chat::ChannelChatMessageV1::new(
+                crate::BROADCASTER_ID,
+                user_id.clone(),
+            ),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelChatMessageDeleteV1::new(
+                crate::BROADCASTER_ID,
+                user_id.clone(),
+            ),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelChatClearUserMessagesV1::new(
+                crate::BROADCASTER_ID,
+                user_id.clone(),
+            ),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelModerateV2::new(crate::BROADCASTER_ID, user_id.clone()),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelFollowV2::new(crate::BROADCASTER_ID, user_id.clone()),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelRaidV1::to_broadcaster_user_id(crate::BROADCASTER_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelPointsCustomRewardRedemptionAddV1::broadcaster_user_id(
+                crate::BROADCASTER_ID,
+            ),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelCheerV1::broadcaster_user_id(crate::BROADCASTER_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelPollEndV1::broadcaster_user_id(crate::BROADCASTER_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelAdBreakBeginV1::broadcaster_user_id(crate::BROADCASTER_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::channel::ChannelSubscribeV1::broadcaster_user_id(crate::BROADCASTER_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::stream::StreamOnlineV1::broadcaster_user_id(crate::BROADCASTER_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::stream::StreamOfflineV1::broadcaster_user_id(crate::BROADCASTER_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::user::UserWhisperMessageV1::to_user_id(crate::BOT_ID),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    client
+        .create_eventsub_subscription(
+            eventsub::automod::AutomodMessageHoldV2::new(crate::BROADCASTER_ID, user_id.clone()),
+            transport.clone(),
+            token,
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Keep the bot running. When it crashes it's restarted, waiting longer after each crash in a row
+//! so that an outage on Twitch's side isn't hammered. The waits are jittered so that restarts
+//! don't fall into step with anything else that's retrying. Fatal errors, like bad credentials,
+//! stop the bot instead, because restarting can't fix them.
+
+use rand::Rng as _;
+
+/// The wait before the first restart, it doubles with each crash in a row after that.
+const FIRST_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The longest wait between restarts.
+const MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// A run that lasts this long is considered stable, so the wait starts from the beginning again.
+pub const STABLE_AFTER: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Marks an error that restarting can't fix.
+#[derive(Debug, Clone, Copy)]
+pub struct Fatal;
+
+impl std::fmt::Display for Fatal {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Fatal error, restarting won't help")
+    }
+}
+
+/// Whether an error was marked as fatal.
+pub fn is_fatal(error: &eyre::Report) -> bool {
+    error.downcast_ref::<Fatal>().is_some()
+}
+
+/// Mark an error from a token request as fatal, but only when Twitch said that the token itself
+/// is bad. Anything else, like the network or Twitch being down or rate limiting, might go away
+/// on its own, so it's worth restarting for.
+pub fn fatal_if_bad_credentials<E>(error: E) -> eyre::Report
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let report = eyre::Report::new(error);
+    if report.chain().any(is_bad_credentials) {
+        report.wrap_err(Fatal)
+    } else {
+        report
+    }
+}
+
+/// Whether the error is Twitch refusing the token, rather than failing to answer.
+fn is_bad_credentials(error: &(dyn std::error::Error + 'static)) -> bool {
+    use twitch_oauth2::tokens::errors::{RefreshTokenError, ValidationError};
+
+    if let Some(error) = error.downcast_ref::<ValidationError<reqwest::Error>>() {
+        return matches!(
+            error,
+            ValidationError::NotAuthorized | ValidationError::InvalidToken(_)
+        );
+    }
+    if let Some(error) = error.downcast_ref::<RefreshTokenError<reqwest::Error>>() {
+        return matches!(
+            error,
+            RefreshTokenError::NoClientSecretFound | RefreshTokenError::NoRefreshToken
+        );
+    }
+    // Twitch replies to a refresh token that's been revoked with a 400.
+    if let Some(twitch_oauth2::RequestParseError::TwitchError(response)) =
+        error.downcast_ref::<twitch_oauth2::RequestParseError>()
+    {
+        return matches!(response.status.as_u16(), 400 | 401 | 403);
+    }
+    false
+}
+
+/// How long to wait before restarting.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    /// Crashes since the bot was last stable.
+    crashes: u32,
+}
+
+impl Backoff {
+    /// The wait before the next restart. It's somewhere between half and all of the
+    /// exponential backoff.
+    pub fn next_delay(&mut self) -> std::time::Duration {
+        let exponential = FIRST_DELAY
+            .saturating_mul(2_u32.saturating_pow(self.crashes))
+            .min(MAX_DELAY);
+        self.crashes = self.crashes.saturating_add(1);
+        exponential.mul_f64(rand::rng().random_range(0.5..=1.0))
+    }
+
+    /// Start from the shortest wait again.
+    pub const fn reset(&mut self) {
+        self.crashes = 0;
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::Datelike as _;
+use color_eyre::Result;
+
+/// A named pack of alert sounds, popup messages and colours, eg for seasonal events. Anything
+/// left out of a theme falls back to the bot's built-in alerts.
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Theme {
+    /// Months (1-12) in which this theme is automatically used, eg `[10]` for October.
+    pub months: Vec<u32>,
+    /// The popup colour, eg `"#ff7518"`.
+    pub colour: Option<String>,
+    /// `{user}` is replaced in the message.
+    pub follow: Alert,
+    /// `{user}` and `{viewers}` are replaced in the message.
+    pub raid: Alert,
+    /// `{user}` and `{tier}` are replaced in the message.
+    pub subscribe: Alert,
+    /// `{user}` and `{amount}` are replaced in the message.
+    pub tip: Alert,
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Alert {
+    pub message: Option<String>,
+    pub sound: Option<String>,
+}
+
+/// The kinds of alerts that can be themed.
+#[derive(Debug, Clone, Copy)]
+pub enum AlertKind {
+    Follow,
+    Raid,
+    Subscribe,
+    Tip,
+}
+
+impl AlertKind {
+    const fn category(self) -> &'static str {
+        match self {
+            Self::Follow => "twitch-new-follower",
+            Self::Raid => "twitch-raid",
+            Self::Subscribe => "twitch-new-subscriber",
+            Self::Tip => "twitch-tip",
+        }
+    }
+
+    /// What the overlay calls the alert.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Follow => "follow",
+            Self::Raid => "raid",
+            Self::Subscribe => "subscribe",
+            Self::Tip => "tip",
+        }
+    }
+
+    const fn default_sound(self) -> &'static str {
+        match self {
+            Self::Follow | Self::Subscribe | Self::Tip => "great_scott.mp3",
+            Self::Raid => "hand_of_god.mp3",
+        }
+    }
+}
+
+impl Theme {
+    const fn alert(&self, kind: AlertKind) -> &Alert {
+        match kind {
+            AlertKind::Follow => &self.follow,
+            AlertKind::Raid => &self.raid,
+            AlertKind::Subscribe => &self.subscribe,
+            AlertKind::Tip => &self.tip,
+        }
+    }
+}
+
+impl crate::bot::Bot {
+    /// The name of the theme in use. A theme chosen with `!theme` wins, then a theme for the
+    /// current month, then the theme called "default".
+    pub async fn current_theme_name(&self) -> Option<String> {
+        if let Some(name) = self.chosen_theme.lock().await.clone() {
+            return Some(name);
+        }
+
+        let config = self.config.read().await;
+        let month = chrono::Local::now().month();
+        config
+            .theme
+            .iter()
+            .find(|(_, theme)| theme.months.contains(&month))
+            .map(|(name, _)| name.clone())
+            .or_else(|| {
+                config
+                    .theme
+                    .contains_key("default")
+                    .then(|| "default".to_owned())
+            })
+    }
+
+    async fn current_theme(&self) -> Theme {
+        let Some(name) = self.current_theme_name().await else {
+            return Theme::default();
+        };
+        self.config
+            .read()
+            .await
+            .theme
+            .get(&name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Show an alert's popup and play its sound, using the current theme.
+    pub async fn play_alert(
+        &self,
+        kind: AlertKind,
+        default_message: String,
+        replacements: &[(&str, &str)],
+    ) -> Result<()> {
+        let theme = self.current_theme().await;
+        let alert = theme.alert(kind);
+
+        let message = match &alert.message {
+            Some(template) => replacements
+                .iter()
+                .fold(template.clone(), |message, (from, to)| {
+                    message.replace(from, to)
+                }),
+            None => default_message,
+        };
+        self.overlay_alert(kind.name(), &message, theme.colour.as_deref());
+        if self.wants_alert_popups().await {
+            Self::onscreen_popup_coloured(
+                format!(" \n{message}"),
+                kind.category(),
+                theme.colour.as_deref(),
+            )?;
+        }
+
+        let sound = alert.sound.as_deref().unwrap_or(kind.default_sound());
+        Self::play_sound(sound)?;
+        Ok(())
+    }
+
+    /// `!theme [name|auto]`
+    pub async fn theme_command(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let Some(name) = arguments.map(str::trim).filter(|name| !name.is_empty()) else {
+            let current = self
+                .current_theme_name()
+                .await
+                .unwrap_or_else(|| "the classic".to_owned());
+            let message = format!("We're using {current} theme");
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        };
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        if name == "auto" {
+            *self.chosen_theme.lock().await = None;
+        } else if self.config.read().await.theme.contains_key(name) {
+            *self.chosen_theme.lock().await = Some(name.to_owned());
+        } else {
+            let message = format!("There's no theme called {name}");
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        }
+
+        self.send_message_reply(&payload.message_id, "Theme changed 🎨")
+            .await?;
+        Ok(())
+    }
+}
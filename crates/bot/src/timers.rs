@@ -0,0 +1,106 @@
+//! `!timer`, a countdown that's shown on screen. When it runs out a sound is played and chat is
+//! told.
+
+use color_eyre::Result;
+
+const USAGE: &str = "Usage: !timer <duration> [label], eg: !timer 5m pizza, or !timer stop";
+
+/// The longest a timer can run for.
+const MAX_DURATION: std::time::Duration = std::time::Duration::from_secs(12 * 60 * 60);
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct TimerConfig {
+    /// The sound played when a timer runs out.
+    pub sound: std::path::PathBuf,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            sound: "timer.mp3".into(),
+        }
+    }
+}
+
+/// Format the time left like a clock, eg: `4:59` or `1:04:59`.
+fn clock(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+impl crate::bot::Bot {
+    /// `!timer 5m pizza` starts a countdown on screen, replacing any timer that's already
+    /// running. `!timer stop` cancels it. Moderators only.
+    pub async fn timer(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !self.is_moderator(payload).await? {
+            return Ok(());
+        }
+
+        let arguments = arguments.map(str::trim).unwrap_or_default();
+        if arguments == "stop" {
+            let message = match self.timer.lock().await.take() {
+                Some(countdown) => {
+                    countdown.abort();
+                    "Timer stopped"
+                }
+                None => "There's no timer running",
+            };
+            return self.send_message_reply(&payload.message_id, message).await;
+        }
+
+        let (duration, label) = arguments
+            .split_once(char::is_whitespace)
+            .unwrap_or((arguments, ""));
+        let Some(duration) = crate::utils::parse_duration(duration)
+            .filter(|duration| !duration.is_zero() && *duration <= MAX_DURATION)
+        else {
+            return self.send_message_reply(&payload.message_id, USAGE).await;
+        };
+        let label = match label.trim() {
+            "" => "Timer".to_owned(),
+            label => label.to_owned(),
+        };
+
+        let sound = self.config.read().await.timer.sound.clone();
+        // The countdown outlives this command, so it queues its chat message directly.
+        let outbox = self.outbox.clone();
+        let countdown_label = label.clone();
+        let countdown = tokio::spawn(async move {
+            let result = async {
+                crate::pomodoro::countdown(duration, "twitch-timer", "timer", |remaining| {
+                    format!(" \n⏲️ {countdown_label} {}", clock(remaining))
+                })
+                .await?;
+                Self::onscreen_popup_replacing(
+                    format!(" \n⏲️ {countdown_label} is done!"),
+                    "twitch-timer",
+                    "timer",
+                )?;
+                Self::play_sound(&sound)?;
+                outbox.send(crate::outbox::Outgoing::Message(format!(
+                    "⏲️ Time's up: {countdown_label}!"
+                )))?;
+                Ok::<(), eyre::Report>(())
+            };
+            if let Err(error) = result.await {
+                tracing::error!("Timer countdown: {error:?}");
+            }
+        });
+        if let Some(previous) = self.timer.lock().await.replace(countdown) {
+            previous.abort();
+        }
+
+        let message = format!("⏲️ {label}: {} on the clock", clock(duration.as_secs()));
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+}
@@ -0,0 +1,84 @@
+//! `!tz` lets mates say what timezone they're in, and `!time` tells you what time it is for
+//! the streamer, another mate or anywhere else.
+
+use color_eyre::Result;
+
+const TZ_USAGE: &str = "Usage: !tz <timezone>, eg: !tz Europe/Warsaw, or !tz clear";
+
+/// The local time in the timezone, eg: "14:05 on Tuesday".
+fn local_time(timezone: chrono_tz::Tz) -> String {
+    chrono::Utc::now()
+        .with_timezone(&timezone)
+        .format("%H:%M on %A")
+        .to_string()
+}
+
+impl crate::bot::Bot {
+    /// `!tz Europe/Warsaw` sets your timezone, `!tz clear` removes it and `!tz` shows it.
+    pub async fn timezone(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let mate = self
+            .db
+            .get_mate(
+                payload.chatter_user_id.as_str(),
+                payload.chatter_user_name.as_str(),
+            )
+            .await?;
+        let message = match arguments.map(str::trim).unwrap_or_default() {
+            "" => match mate.timezone {
+                Some(timezone) => format!("Your timezone is {timezone}"),
+                None => TZ_USAGE.to_owned(),
+            },
+            "clear" => {
+                self.db.set_timezone(mate.id, None).await?;
+                "Cleared your timezone".to_owned()
+            }
+            name => match name.parse::<chrono_tz::Tz>() {
+                Ok(timezone) => {
+                    self.db.set_timezone(mate.id, Some(timezone.name())).await?;
+                    format!("Saved, it's {} for you 👍", local_time(timezone))
+                }
+                Err(_) => format!("\"{name}\" isn't a timezone I know. {TZ_USAGE}"),
+            },
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+
+    /// `!time` shows the streamer's local time, `!time @user` someone else's and
+    /// `!time Asia/Tokyo` the time in a timezone.
+    pub async fn time(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let argument = arguments
+            .map(str::trim)
+            .filter(|argument| !argument.is_empty());
+        if let Some(timezone) = argument.and_then(|name| name.parse::<chrono_tz::Tz>().ok()) {
+            let message = format!("It's {} in {}", local_time(timezone), timezone.name());
+            return self
+                .send_message_reply(&payload.message_id, message.as_str())
+                .await;
+        }
+
+        let username = argument
+            .map(|name| name.trim_start_matches('@'))
+            .unwrap_or(payload.broadcaster_user_name.as_str());
+        let timezone = self
+            .db
+            .find_mate(username)
+            .await?
+            .and_then(|mate| mate.timezone)
+            .and_then(|name| name.parse::<chrono_tz::Tz>().ok());
+        let message = match timezone {
+            Some(timezone) => format!("It's {} for {username}", local_time(timezone)),
+            None => format!("{username} hasn't set their timezone, they can with !tz"),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await
+    }
+}
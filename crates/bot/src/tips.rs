@@ -0,0 +1,348 @@
+//! Tips from outside of Twitch. Ko-fi sends each donation to a webhook that the bot listens on,
+//! StreamElements is polled. Tips get an alert like follows and subs do, and a thank you in
+//! chat.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _};
+
+/// Where the StreamElements API lives.
+const STREAMELEMENTS_API: &str = "https://api.streamelements.com/kappa/v2";
+
+/// How many of the latest StreamElements tips to fetch each time.
+const STREAMELEMENTS_PAGE_SIZE: &str = "10";
+
+/// Polled tips older than this aren't alerted, so that starting the bot doesn't replay old tips.
+const MAX_TIP_AGE: chrono::TimeDelta = chrono::TimeDelta::hours(1);
+
+/// The most characters of a tipper's message that are shown in chat, so that a long message
+/// can't take over the thank you.
+const MAX_TIP_MESSAGE_LENGTH: usize = 200;
+
+/// Ko-fi's requests are small, anything bigger than this isn't from Ko-fi.
+const MAX_WEBHOOK_BODY: usize = 64 * 1024;
+
+/// How long a connection to the Ko-fi listener has to send its request, so that idle
+/// connections don't pile up.
+const WEBHOOK_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct TipsConfig {
+    /// The thank you in chat. `{user}`, `{amount}` and `{message}` are replaced.
+    pub template: String,
+    pub kofi: KofiConfig,
+    pub streamelements: StreamElementsConfig,
+}
+
+impl Default for TipsConfig {
+    fn default() -> Self {
+        Self {
+            template: "Thank you so much for the {amount} tip {user}! ❤️".to_owned(),
+            kofi: KofiConfig::default(),
+            streamelements: StreamElementsConfig::default(),
+        }
+    }
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct KofiConfig {
+    pub enabled: bool,
+    /// The address to listen for Ko-fi's webhook on. Ko-fi needs to be able to reach it, eg
+    /// through a tunnel or reverse proxy.
+    pub listen: String,
+    /// The token from Ko-fi's webhook settings, so that other requests are ignored. The
+    /// listener doesn't start without it.
+    pub verification_token: String,
+}
+
+impl Default for KofiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:8087".to_owned(),
+            verification_token: String::new(),
+        }
+    }
+}
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct StreamElementsConfig {
+    pub enabled: bool,
+    /// The account ID from StreamElements' channel settings.
+    pub channel_id: String,
+    /// The JWT token from StreamElements' channel settings.
+    pub token: String,
+    pub poll_seconds: u64,
+}
+
+impl Default for StreamElementsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel_id: String::new(),
+            token: String::new(),
+            poll_seconds: 60,
+        }
+    }
+}
+
+/// A tip, from any platform.
+#[derive(Debug)]
+pub struct Tip {
+    /// Where the tip came from, eg: "kofi".
+    pub source: &'static str,
+    /// The platform's ID for the tip, so that it's only recorded once.
+    pub external_id: String,
+    pub username: String,
+    pub amount: f64,
+    pub currency: String,
+    pub message: Option<String>,
+}
+
+/// The JSON that Ko-fi sends in the `data` field of its webhook.
+#[derive(serde_derive::Deserialize)]
+struct KofiDonation {
+    verification_token: String,
+    message_id: String,
+    is_public: bool,
+    from_name: String,
+    message: Option<String>,
+    amount: String,
+    currency: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct StreamElementsTips {
+    docs: Vec<StreamElementsTip>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamElementsTip {
+    #[serde(rename = "_id")]
+    id: String,
+    donation: StreamElementsDonation,
+    created_at: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct StreamElementsDonation {
+    user: StreamElementsUser,
+    message: Option<String>,
+    amount: f64,
+    currency: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct StreamElementsUser {
+    username: String,
+}
+
+impl crate::bot::Bot {
+    /// Listen for tips from all the enabled platforms.
+    pub async fn tipper(&self) -> Result<()> {
+        let config = self.config.read().await.tips.clone();
+        tokio::try_join!(
+            self.kofi_listener(&config.kofi),
+            self.streamelements_poller(&config.streamelements)
+        )?;
+        Ok(())
+    }
+
+    /// Alert, record and give thanks for a tip. Tips that have already been seen are ignored.
+    pub async fn new_tip(&self, tip: Tip) -> Result<()> {
+        tracing::info!("New tip: {tip:?}");
+        if !self.db.add_tip(&tip).await? {
+            return Ok(());
+        }
+
+        let amount = format!("{:.2} {}", tip.amount, tip.currency);
+        let user = tip.username.as_str();
+        self.play_alert(
+            crate::themes::AlertKind::Tip,
+            format!("{user} tipped {amount}! 💸"),
+            &[("{user}", user), ("{amount}", &amount)],
+        )
+        .await?;
+
+        let tip_message = tip
+            .message
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .take(MAX_TIP_MESSAGE_LENGTH)
+            .collect::<String>();
+        let message = self
+            .config
+            .read()
+            .await
+            .tips
+            .template
+            .replace("{user}", user)
+            .replace("{amount}", &amount)
+            .replace("{message}", &tip_message);
+        self.send_message(message.trim()).await
+    }
+
+    /// Handle Ko-fi's requests concurrently, so that one slow connection doesn't hold up the
+    /// others.
+    async fn kofi_listener(&self, config: &KofiConfig) -> Result<()> {
+        if !config.enabled {
+            return Ok(());
+        }
+        // Without a token anybody could send fake tips. It's not an error, because that would
+        // stop StreamElements tips too.
+        if config.verification_token.is_empty() {
+            tracing::error!("Not listening for Ko-fi tips without a `verification_token`");
+            return Ok(());
+        }
+
+        let listener = tokio::net::TcpListener::bind(&config.listen).await?;
+        tracing::info!("Listening for Ko-fi tips on {}", config.listen);
+        let mut requests = futures::stream::FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, address) = accepted?;
+                    requests.push(async move {
+                        if let Err(error) = self.kofi_request(stream, config).await {
+                            tracing::error!("Ko-fi webhook request from {address}: {error:?}");
+                        }
+                    });
+                }
+                Some(()) = futures::StreamExt::next(&mut requests), if !requests.is_empty() => (),
+            }
+        }
+    }
+
+    /// Ko-fi sends a form-encoded POST, with the donation as JSON in its `data` field.
+    async fn kofi_request(
+        &self,
+        mut stream: tokio::net::TcpStream,
+        config: &KofiConfig,
+    ) -> Result<()> {
+        let donation = tokio::time::timeout(
+            WEBHOOK_READ_TIMEOUT,
+            Self::read_kofi_request(&mut stream, config),
+        )
+        .await
+        .map_err(|_| eyre::eyre!("Timed out waiting for the request"))??;
+
+        // Private donations shouldn't have their name or message shown on stream.
+        let (username, message) = if donation.is_public {
+            (donation.from_name, donation.message)
+        } else {
+            ("Someone".to_owned(), None)
+        };
+        self.new_tip(Tip {
+            source: "kofi",
+            external_id: donation.message_id,
+            username,
+            amount: donation.amount.parse()?,
+            currency: donation.currency,
+            message,
+        })
+        .await
+    }
+
+    /// Read and verify a donation, replying to Ko-fi with whether it was accepted.
+    async fn read_kofi_request(
+        stream: &mut tokio::net::TcpStream,
+        config: &KofiConfig,
+    ) -> Result<KofiDonation> {
+        let (reader, mut writer) = stream.split();
+        let mut reader = tokio::io::BufReader::new(reader);
+
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                eyre::bail!("Connection closed before the end of the headers");
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse()?;
+                }
+            }
+        }
+        if content_length > MAX_WEBHOOK_BODY {
+            eyre::bail!("Request body is too big: {content_length} bytes");
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+        let data = url::form_urlencoded::parse(&body)
+            .find(|(key, _)| key == "data")
+            .map(|(_, value)| value.into_owned())
+            .context("No `data` field in the request")?;
+        let donation: KofiDonation = serde_json::from_str(&data)?;
+
+        if !crate::http_api::is_same_token(&donation.verification_token, &config.verification_token)
+        {
+            writer
+                .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")
+                .await?;
+            eyre::bail!("Wrong verification token");
+        }
+        writer
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .await?;
+        Ok(donation)
+    }
+
+    async fn streamelements_poller(&self, config: &StreamElementsConfig) -> Result<()> {
+        if !config.enabled {
+            return Ok(());
+        }
+
+        let client = reqwest::Client::new();
+        loop {
+            if let Err(error) = self.poll_streamelements(&client, config).await {
+                tracing::error!("Polling StreamElements tips: {error:?}");
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(config.poll_seconds.max(1))).await;
+        }
+    }
+
+    async fn poll_streamelements(
+        &self,
+        client: &reqwest::Client,
+        config: &StreamElementsConfig,
+    ) -> Result<()> {
+        let tips: StreamElementsTips = client
+            .get(format!("{STREAMELEMENTS_API}/tips/{}", config.channel_id))
+            .bearer_auth(&config.token)
+            .query(&[("limit", STREAMELEMENTS_PAGE_SIZE), ("sort", "-createdAt")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Oldest first, so that the alerts are in the order that the tips were made.
+        for tip in tips.docs.into_iter().rev() {
+            let created_at = chrono::DateTime::parse_from_rfc3339(&tip.created_at)?;
+            if chrono::Utc::now() - created_at.to_utc() > MAX_TIP_AGE {
+                continue;
+            }
+            self.new_tip(Tip {
+                source: "streamelements",
+                external_id: tip.id,
+                username: tip.donation.user.username,
+                amount: tip.donation.amount,
+                currency: tip.donation.currency,
+                message: tip.donation.message,
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+}
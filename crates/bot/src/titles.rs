@@ -0,0 +1,199 @@
+//! Chat suggests stream titles with `!suggesttitle`, votes on them with `!titlevote`, and the
+//! broadcaster can then apply the winner.
+
+use color_eyre::Result;
+
+/// The most suggestions that can go into a vote.
+const MAX_SUGGESTIONS: usize = 9;
+
+/// Twitch's limit on stream titles.
+const MAX_TITLE_LENGTH: usize = 140;
+
+const USAGE: &str = "Usage: !titlevote <number> to vote. The broadcaster can use !titlevote \
+    start|end|apply|clear";
+
+/// A title suggested by a viewer.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    username: String,
+    title: String,
+}
+
+/// Title suggestions and the vote on them.
+#[derive(Debug, Default)]
+pub struct TitleVote {
+    suggestions: Vec<Suggestion>,
+    /// The index of the suggestion each viewer voted for, keyed by user ID.
+    votes: std::collections::HashMap<String, usize>,
+    is_open: bool,
+    /// The winning title, once voting has ended, waiting to be applied.
+    winner: Option<String>,
+}
+
+impl TitleVote {
+    fn tally(&self, index: usize) -> usize {
+        self.votes.values().filter(|vote| **vote == index).count()
+    }
+
+    fn overlay(&self) -> String {
+        let lines = self
+            .suggestions
+            .iter()
+            .enumerate()
+            .map(|(index, suggestion)| {
+                format!(
+                    "{}. {} ({})",
+                    index + 1,
+                    suggestion.title,
+                    self.tally(index)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("🗳️ Title vote, type !titlevote <number>\n{lines}")
+    }
+}
+
+impl crate::bot::Bot {
+    /// `!suggesttitle <text>`. Each viewer gets one suggestion, suggesting again replaces it.
+    pub async fn suggest_title(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let Some(title) = arguments.map(str::trim).filter(|title| !title.is_empty()) else {
+            self.send_message_reply(&payload.message_id, "Usage: !suggesttitle <title>")
+                .await?;
+            return Ok(());
+        };
+        if title.chars().count() > MAX_TITLE_LENGTH {
+            self.send_message_reply(&payload.message_id, "That title is too long for Twitch")
+                .await?;
+            return Ok(());
+        }
+
+        let username = payload.chatter_user_name.to_string();
+        let message = {
+            let mut vote = self.title_vote.lock().await;
+            if vote.is_open {
+                "Voting has already started, vote with !titlevote <number>"
+            } else if let Some(existing) = vote
+                .suggestions
+                .iter_mut()
+                .find(|suggestion| suggestion.username == username)
+            {
+                existing.title = title.to_owned();
+                "Your title suggestion has been updated"
+            } else if vote.suggestions.len() >= MAX_SUGGESTIONS {
+                "There are already enough suggestions, thanks!"
+            } else {
+                vote.suggestions.push(Suggestion {
+                    username,
+                    title: title.to_owned(),
+                });
+                "Thanks for the title suggestion!"
+            }
+        };
+        self.send_message_reply(&payload.message_id, message)
+            .await?;
+        Ok(())
+    }
+
+    /// `!titlevote <number>` votes. The broadcaster uses `!titlevote start|end|apply|clear`.
+    pub async fn title_vote(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let argument = arguments.map(str::trim).unwrap_or_default();
+        if let Ok(number) = argument.parse::<usize>() {
+            return self.vote_for_title(payload, number).await;
+        }
+        if !Self::is_broadcaster(payload) {
+            self.send_message_reply(&payload.message_id, USAGE).await?;
+            return Ok(());
+        }
+
+        let mut vote = self.title_vote.lock().await;
+        let message = match argument {
+            "start" if vote.suggestions.len() < 2 => {
+                "There need to be at least 2 suggestions, use !suggesttitle".to_owned()
+            }
+            "start" => {
+                vote.is_open = true;
+                vote.votes.clear();
+                vote.winner = None;
+                Self::onscreen_popup_replacing(vote.overlay(), "twitch-title-vote", "title-vote")?;
+                "🗳️ Title vote started! Vote with !titlevote <number>".to_owned()
+            }
+            "end" => {
+                vote.is_open = false;
+                let winner = (0..vote.suggestions.len())
+                    .max_by_key(|index| vote.tally(*index))
+                    .and_then(|index| vote.suggestions.get(index).cloned());
+                match winner {
+                    Some(winner) => {
+                        vote.winner = Some(winner.title.clone());
+                        Self::onscreen_popup_replacing(
+                            format!(" \nNew title:\n{}", winner.title),
+                            "twitch-title-vote",
+                            "title-vote",
+                        )?;
+                        format!(
+                            "🗳️ The winner is \"{}\" by {}! Use !titlevote apply to use it",
+                            winner.title, winner.username
+                        )
+                    }
+                    None => "There wasn't anything to vote on".to_owned(),
+                }
+            }
+            "apply" => match vote.winner.take() {
+                Some(title) => {
+                    self.set_title(&title).await?;
+                    vote.suggestions.clear();
+                    vote.votes.clear();
+                    format!("📝 The stream title is now: {title}")
+                }
+                None => "There's no winning title yet, use !titlevote end".to_owned(),
+            },
+            "clear" => {
+                *vote = TitleVote::default();
+                "Title suggestions cleared".to_owned()
+            }
+            _ => USAGE.to_owned(),
+        };
+        drop(vote);
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    async fn vote_for_title(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        number: usize,
+    ) -> Result<()> {
+        let mut vote = self.title_vote.lock().await;
+        if !vote.is_open {
+            drop(vote);
+            self.send_message_reply(&payload.message_id, "There's no title vote running")
+                .await?;
+            return Ok(());
+        }
+        let Some(index) = number
+            .checked_sub(1)
+            .filter(|index| *index < vote.suggestions.len())
+        else {
+            let message = format!("Vote for a number from 1 to {}", vote.suggestions.len());
+            drop(vote);
+            self.send_message_reply(&payload.message_id, message.as_str())
+                .await?;
+            return Ok(());
+        };
+
+        vote.votes
+            .insert(payload.chatter_user_id.to_string(), index);
+        Self::onscreen_popup_replacing(vote.overlay(), "twitch-title-vote", "title-vote")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! `!today`, what the stream is about. It's saved with the stream, and when it's set before
+//! going live it's kept for the next stream and announced when that stream starts.
+
+use color_eyre::Result;
+
+/// What the next stream is about, when `!today` is set whilst offline.
+const NEXT_STREAM_SETTING: &str = "today.next";
+
+impl crate::bot::Bot {
+    /// `!today` shows what the stream is about. The broadcaster sets it with
+    /// `!today set <text>`.
+    pub async fn today(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        let arguments = arguments.map(str::trim).unwrap_or_default();
+        let stream = self.db.current_stream().await?;
+
+        if let Some(text) = arguments.strip_prefix("set") {
+            if !Self::is_broadcaster(payload) {
+                return Ok(());
+            }
+            let text = text.trim();
+            if text.is_empty() {
+                self.send_message_reply(&payload.message_id, "Usage: !today set <text>")
+                    .await?;
+                return Ok(());
+            }
+            let message = match &stream {
+                Some(stream) => {
+                    self.db.set_stream_today(stream.id, text).await?;
+                    "Today's plan is set 📋"
+                }
+                None => {
+                    self.db.set_setting(NEXT_STREAM_SETTING, text).await?;
+                    "Saved for the next stream 📋"
+                }
+            };
+            self.send_message_reply(&payload.message_id, message)
+                .await?;
+            return Ok(());
+        }
+
+        let message = match stream.and_then(|stream| stream.today) {
+            Some(today) => format!("📋 Today: {today}"),
+            None => match self.next_stream_today().await? {
+                Some(next) => format!("📋 Next stream: {next}"),
+                None => "Nothing planned, we'll see where the day takes us".to_owned(),
+            },
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// When a stream starts, give it the plan that was set whilst offline and announce it.
+    pub async fn announce_today(&self) -> Result<()> {
+        let Some(today) = self.next_stream_today().await? else {
+            return Ok(());
+        };
+        let Some(stream) = self.db.current_stream().await? else {
+            return Ok(());
+        };
+        self.db.set_stream_today(stream.id, &today).await?;
+        self.db.set_setting(NEXT_STREAM_SETTING, "").await?;
+        self.send_announcement(
+            format!("📋 Today: {today}").as_str(),
+            twitch_api::helix::chat::AnnouncementColor::Green,
+        )
+        .await
+    }
+
+    async fn next_stream_today(&self) -> Result<Option<String>> {
+        Ok(self
+            .db
+            .get_setting(NEXT_STREAM_SETTING)
+            .await?
+            .filter(|next| !next.is_empty()))
+    }
+}
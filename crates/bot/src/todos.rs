@@ -0,0 +1,73 @@
+//! The streamer's todo list, managed from chat and shown in a panel in the Tattoy overlay.
+
+use color_eyre::Result;
+
+const USAGE: &str = "Usage: !todo add <text>, !todo done <id> or !todo list";
+
+impl crate::bot::Bot {
+    /// `!todo add <text>`, `!todo done <id>` and `!todo list`. Mods only.
+    pub async fn todo(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !self.is_moderator(payload).await? {
+            return Ok(());
+        }
+
+        let arguments = arguments.map(str::trim).unwrap_or_default();
+        let (subcommand, rest) = arguments
+            .split_once(char::is_whitespace)
+            .unwrap_or((arguments, ""));
+        let rest = rest.trim();
+
+        let message = match subcommand {
+            "add" if !rest.is_empty() => {
+                let id = self.db.add_todo(rest).await?;
+                self.show_todos().await?;
+                format!("Added todo #{id}")
+            }
+            "done" => {
+                let Ok(id) = rest.trim_start_matches('#').parse::<i64>() else {
+                    self.send_message_reply(&payload.message_id, USAGE).await?;
+                    return Ok(());
+                };
+                if self.db.complete_todo(id).await? {
+                    self.show_todos().await?;
+                    format!("Done with todo #{id} ✅")
+                } else {
+                    format!("There's no open todo #{id}")
+                }
+            }
+            "list" | "" => {
+                let todos = self.db.open_todos().await?;
+                if todos.is_empty() {
+                    "Nothing to do 🏖️".to_owned()
+                } else {
+                    todos
+                        .iter()
+                        .map(|(id, text)| format!("#{id} {text}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }
+            }
+            _ => USAGE.to_owned(),
+        };
+        self.send_message_reply(&payload.message_id, message.as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// Send the open todos to the Tattoy overlay.
+    pub async fn show_todos(&self) -> Result<()> {
+        let items = self
+            .db
+            .open_todos()
+            .await?
+            .into_iter()
+            .map(|(id, text)| format!("#{id} {text}"))
+            .collect();
+        self.send_to_tattoy(&tattoy_twitch_tombh_plugin::BotMessage::Todos { items })
+            .await
+    }
+}
@@ -0,0 +1,248 @@
+//! Saving and loading the Twitch tokens, so that the bot doesn't need authorising again after a
+//! restart. They're kept in the state directory, either as plain text or encrypted with
+//! [age](https://age-encryption.org). Encrypted tokens are decrypted whenever they're found,
+//! whatever the config says, so that turning encryption on or off doesn't need a new token.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+use rand::Rng as _;
+use std::io::Write as _;
+use std::os::unix::fs::OpenOptionsExt as _;
+use twitch_oauth2::Scope;
+
+const ACCESS_TOKEN_FILE: &str = "access.token";
+const REFRESH_TOKEN_FILE: &str = "refresh.token";
+
+/// Added to the name of a token file when it's encrypted.
+const ENCRYPTED_EXTENSION: &str = "age";
+
+/// The ENV var with the passphrase for encrypting the tokens.
+const PASSPHRASE_ENV: &str = "TOKEN_PASSPHRASE";
+
+/// Where the passphrase is kept in the system keyring, when it's not in the ENV.
+const KEYRING_SERVICE: &str = "tbhbot";
+const KEYRING_USER: &str = "token-passphrase";
+
+/// The length of a passphrase made for the keyring.
+const GENERATED_PASSPHRASE_LENGTH: usize = 64;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct TokensConfig {
+    /// Encrypt the tokens, with the passphrase from the `TOKEN_PASSPHRASE` ENV var, or one kept
+    /// in the system keyring.
+    pub encrypted: bool,
+}
+
+/// Load the saved tokens.
+pub fn load_tokens() -> Result<(twitch_oauth2::AccessToken, twitch_oauth2::RefreshToken)> {
+    let access_token = read_token(ACCESS_TOKEN_FILE)?;
+    let refresh_token = read_token(REFRESH_TOKEN_FILE)?;
+    Ok((access_token.into(), refresh_token.into()))
+}
+
+/// Save the tokens, so that they're used again after a restart.
+pub fn save_tokens(token: &twitch_oauth2::UserToken, config: &TokensConfig) -> Result<()> {
+    let refresh_token = token
+        .refresh_token
+        .as_ref()
+        .context("Couldn't get refresh token")?;
+    write_token(ACCESS_TOKEN_FILE, token.token().secret(), config)?;
+    write_token(REFRESH_TOKEN_FILE, refresh_token.secret(), config)?;
+    Ok(())
+}
+
+/// A token for the bot's Twitch app, rather than for its user. Some things, like webhook and
+/// conduit subscriptions, can only be done with one.
+pub async fn app_access_token(
+    client: &twitch_api::HelixClient<'static, reqwest::Client>,
+) -> Result<twitch_oauth2::AppAccessToken> {
+    let client_id =
+        std::env::var("CLIENTID").context("Couldn't find CLIENTID in the environment")?;
+    let client_secret =
+        std::env::var("CLIENTSECRET").context("Couldn't find CLIENTSECRET in the environment")?;
+    let token = twitch_oauth2::AppAccessToken::get_app_access_token(
+        client,
+        client_id.into(),
+        client_secret.into(),
+        vec![],
+    )
+    .await?;
+    Ok(token)
+}
+
+/// The paths of a token file, in plain text and encrypted.
+fn token_paths(name: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+    let state_directory = crate::paths::state_directory();
+    (
+        state_directory.join(name),
+        state_directory.join(format!("{name}.{ENCRYPTED_EXTENSION}")),
+    )
+}
+
+fn read_token(name: &str) -> Result<String> {
+    let (path, encrypted_path) = token_paths(name);
+    let token = if encrypted_path.exists() {
+        let identity = age::scrypt::Identity::new(passphrase()?);
+        let decrypted = age::decrypt(&identity, &std::fs::read(&encrypted_path)?)?;
+        String::from_utf8(decrypted)?
+    } else {
+        std::fs::read_to_string(&path)?
+    };
+    Ok(token.trim().to_owned())
+}
+
+/// Write the token, removing the copy in the other format so that there's only ever one.
+fn write_token(name: &str, token: &str, config: &TokensConfig) -> Result<()> {
+    let (path, encrypted_path) = token_paths(name);
+    let contents = format!("{token}\n");
+    let (new_path, old_path) = if config.encrypted {
+        let recipient = age::scrypt::Recipient::new(passphrase()?);
+        let encrypted = age::encrypt(&recipient, contents.as_bytes())?;
+        write_secret(&encrypted_path, &encrypted)?;
+        (encrypted_path, path)
+    } else {
+        write_secret(&path, contents.as_bytes())?;
+        (path, encrypted_path)
+    };
+    tracing::debug!("Saved token to {new_path:?}");
+
+    if old_path.exists() {
+        std::fs::remove_file(old_path)?;
+    }
+    Ok(())
+}
+
+/// The passphrase for encrypting the tokens. Without one in the ENV, a random one is made and
+/// kept in the system keyring.
+fn passphrase() -> Result<age::secrecy::SecretString> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase.into());
+    }
+
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+    match entry.get_password() {
+        Ok(passphrase) => Ok(passphrase.into()),
+        Err(keyring::Error::NoEntry) => {
+            tracing::info!("Adding a new token passphrase to the system keyring");
+            let passphrase = rand::rng()
+                .sample_iter(&rand::distr::Alphanumeric)
+                .take(GENERATED_PASSPHRASE_LENGTH)
+                .map(char::from)
+                .collect::<String>();
+            entry.set_password(&passphrase)?;
+            Ok(passphrase.into())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Write a file that only the user running the bot can read. It's written to a temporary file
+/// first and then renamed, so that a crash part way through can't leave it half written.
+fn write_secret(path: &std::path::Path, secret: &[u8]) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    let temporary_path = path.with_extension("tmp");
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&temporary_path)?;
+    file.write_all(secret)?;
+    file.sync_all()?;
+    std::fs::rename(&temporary_path, path)?;
+    Ok(())
+}
+
+/// The scopes that the bot asks for when it gets a new token.
+pub const SCOPES: &[Scope] = &[
+    Scope::UserReadChat,
+    Scope::UserWriteChat,
+    Scope::ModeratorReadFollowers,
+    Scope::UserManageWhispers,
+    Scope::UserReadWhispers,
+    Scope::ModeratorManageAutoMod,
+    Scope::ModerationRead,
+    // All of these are needed for the `channel.moderate` event.
+    Scope::ModeratorReadBlockedTerms,
+    Scope::ModeratorReadChatSettings,
+    Scope::ModeratorReadUnbanRequests,
+    Scope::ModeratorReadBannedUsers,
+    Scope::ModeratorReadChatMessages,
+    Scope::ModeratorReadWarnings,
+    Scope::ModeratorReadModerators,
+    Scope::ModeratorReadVips,
+    Scope::ChannelReadVips,
+    Scope::ChannelManageRedemptions,
+    Scope::BitsRead,
+    Scope::ChannelManagePolls,
+    Scope::ChannelReadAds,
+    Scope::ChannelManageAds,
+    Scope::ChannelReadSubscriptions,
+    Scope::ChannelManageRaids,
+    Scope::ModeratorManageShoutouts,
+    Scope::ModeratorManageAnnouncements,
+    Scope::ChannelManageBroadcast,
+    Scope::ChannelManagePredictions,
+];
+
+/// A summary of the saved access token: who it's for, when it expires and what it can do.
+pub async fn token_status(
+    client: &twitch_api::HelixClient<'static, reqwest::Client>,
+) -> Result<Vec<String>> {
+    let (access_token, _) = load_tokens()?;
+    let validated = match access_token.validate_token(client).await {
+        Ok(validated) => validated,
+        Err(error) => return Ok(vec![format!("Access token isn't valid: {error}")]),
+    };
+
+    let user = match (validated.login, validated.user_id) {
+        (Some(login), Some(user_id)) => format!("{login} ({user_id})"),
+        _ => "nobody, it's an app token".to_owned(),
+    };
+    let expiry = validated.expires_in.map_or_else(
+        || "never".to_owned(),
+        |expires_in| {
+            let minutes = expires_in.as_secs() / 60;
+            format!("in {}h {}m", minutes / 60, minutes % 60)
+        },
+    );
+    // Listing every scope would be too long for a chat message, so only missing ones are named.
+    let scopes = validated.scopes.unwrap_or_default();
+    let missing = SCOPES
+        .iter()
+        .filter(|scope| !scopes.contains(scope))
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    let missing = if missing.is_empty() {
+        "none missing".to_owned()
+    } else {
+        format!("missing: {}", missing.join(", "))
+    };
+    Ok(vec![
+        format!("Authorised user: {user}"),
+        format!("Expires: {expiry}"),
+        format!("Scopes: {}, {missing}", scopes.len()),
+    ])
+}
+
+impl crate::bot::Bot {
+    /// `!bot token`, the health of the bot's Twitch token. Only for the broadcaster.
+    pub async fn bot_command(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !Self::is_broadcaster(payload) {
+            return Ok(());
+        }
+
+        let message = match arguments.map(str::trim) {
+            Some("token") => token_status(&self.client).await?.join(" | "),
+            _ => "Usage: !bot token".to_owned(),
+        };
+        self.send_message_reply(&payload.message_id, &message).await
+    }
+}
@@ -0,0 +1,55 @@
+/// Split command arguments on whitespace, keeping "double quoted phrases" together.
+pub fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut is_quoted = false;
+    for character in arguments.chars() {
+        match character {
+            '"' => {
+                if is_quoted {
+                    parts.push(std::mem::take(&mut current));
+                }
+                is_quoted = !is_quoted;
+            }
+            _ if character.is_whitespace() && !is_quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(character),
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Parse durations like `90`, `90s`, `5m`, `1h` or `1h30m`. A bare number is in seconds.
+pub fn parse_duration(text: &str) -> Option<std::time::Duration> {
+    if let Ok(seconds) = text.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for character in text.chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+        let value = number.parse::<u64>().ok()?;
+        number.clear();
+        total += match character {
+            's' => value,
+            'm' => value * 60,
+            'h' => value * 60 * 60,
+            'd' => value * 60 * 60 * 24,
+            _ => return None,
+        };
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(std::time::Duration::from_secs(total))
+}
@@ -0,0 +1,88 @@
+//! Running as a systemd `Type=notify` service. systemd is told when the bot is ready and when
+//! it's stopping. With `WatchdogSec=` set, the bot keeps telling systemd that it's alive, but only
+//! whilst it's healthy: Twitch has been heard from recently and the token isn't stuck locked. So a
+//! hung websocket or a deadlock gets the bot restarted by systemd. Without systemd none of this
+//! does anything.
+
+use color_eyre::Result;
+
+/// How long the token can be locked before it counts as stuck.
+const TOKEN_LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// When anything last came in from Twitch.
+static LAST_ACTIVITY: std::sync::Mutex<Option<std::time::Instant>> = std::sync::Mutex::new(None);
+
+/// Note that something came in from Twitch, even just a keepalive.
+pub fn record_activity() {
+    if let Ok(mut last_activity) = LAST_ACTIVITY.lock() {
+        *last_activity = Some(std::time::Instant::now());
+    }
+}
+
+fn notify(state: sd_notify::NotifyState<'_>) {
+    if let Err(error) = sd_notify::notify(false, &[state]) {
+        tracing::warn!("Notifying systemd: {error:?}");
+    }
+}
+
+pub fn notify_ready() {
+    notify(sd_notify::NotifyState::Ready);
+}
+
+pub fn notify_stopping() {
+    notify(sd_notify::NotifyState::Stopping);
+}
+
+impl crate::bot::Bot {
+    /// Tell systemd that the bot is alive, twice per watchdog timeout, for as long as it's
+    /// healthy.
+    pub async fn watchdog(&self) -> Result<()> {
+        let mut microseconds = 0;
+        if !sd_notify::watchdog_enabled(false, &mut microseconds) {
+            return Ok(());
+        }
+        let timeout = std::time::Duration::from_micros(microseconds);
+        tracing::info!("Using the systemd watchdog, with a {timeout:?} timeout");
+        record_activity();
+
+        let mut interval = tokio::time::interval(timeout / 2);
+        loop {
+            interval.tick().await;
+            match self.unhealthy_because(timeout).await {
+                None => notify(sd_notify::NotifyState::Watchdog),
+                Some(reason) => {
+                    tracing::warn!("Not telling systemd that the bot is alive: {reason}")
+                }
+            }
+        }
+    }
+
+    /// Why the bot isn't healthy, if it isn't.
+    async fn unhealthy_because(&self, timeout: std::time::Duration) -> Option<String> {
+        if tokio::time::timeout(TOKEN_LOCK_TIMEOUT, self.token.lock())
+            .await
+            .is_err()
+        {
+            return Some(format!(
+                "the token has been locked for over {TOKEN_LOCK_TIMEOUT:?}"
+            ));
+        }
+
+        // Webhooks can go quiet for as long as nothing happens on the channel.
+        if matches!(self.opts.transport, crate::Transport::Webhook) {
+            return None;
+        }
+        let last_activity = LAST_ACTIVITY
+            .lock()
+            .ok()
+            .and_then(|last_activity| *last_activity);
+        match last_activity {
+            Some(last_activity) if last_activity.elapsed() < timeout => None,
+            Some(last_activity) => Some(format!(
+                "nothing from Twitch for {:?}",
+                last_activity.elapsed()
+            )),
+            None => Some("nothing from Twitch yet".to_owned()),
+        }
+    }
+}
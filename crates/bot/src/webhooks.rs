@@ -0,0 +1,112 @@
+//! Forward Twitch events to other services as JSON POSTs, so that one-off automations can be
+//! set up in the config rather than needing new code in the bot.
+
+/// How long to wait before the first retry, it doubles with each retry after that.
+const FIRST_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    /// The EventSub subscription type to forward, eg: `channel.follow`, or `*` for everything.
+    pub event: String,
+    pub url: String,
+    /// The body to send. `{field}` is replaced with the value of that field in the event, eg:
+    /// `{user_name}`. Without a template the whole event is sent.
+    pub template: Option<String>,
+    /// How many times to retry when the request fails.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+const fn default_retries() -> u32 {
+    3
+}
+
+impl Webhook {
+    fn matches(&self, event_type: &str) -> bool {
+        self.event == "*" || self.event == event_type
+    }
+
+    /// The body of the request for the given event.
+    fn body(&self, event: &serde_json::Value) -> String {
+        let Some(template) = &self.template else {
+            return event.to_string();
+        };
+
+        let mut body = template.clone();
+        if let Some(fields) = event.as_object() {
+            for (key, value) in fields {
+                // Strings are escaped so that they can be safely put inside quotes in the
+                // template.
+                let value = match value {
+                    serde_json::Value::String(string) => {
+                        let quoted = serde_json::Value::String(string.clone()).to_string();
+                        quoted[1..quoted.len() - 1].to_owned()
+                    }
+                    other => other.to_string(),
+                };
+                body = body.replace(&format!("{{{key}}}"), &value);
+            }
+        }
+        body
+    }
+
+    /// Send the body, retrying with a backoff when it fails.
+    async fn post(self, body: String) {
+        let client = reqwest::Client::new();
+        let mut delay = FIRST_RETRY_DELAY;
+        for attempt in 0..=self.retries {
+            let result = client
+                .post(&self.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status);
+            match result {
+                Ok(_) => {
+                    tracing::debug!("Forwarded `{}` event to {}", self.event, self.url);
+                    return;
+                }
+                Err(error) => {
+                    tracing::warn!(
+                        "Forwarding `{}` event to {} (attempt {}): {error:?}",
+                        self.event,
+                        self.url,
+                        attempt + 1
+                    );
+                }
+            }
+            if attempt < self.retries {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+        tracing::error!("Gave up forwarding `{}` event to {}", self.event, self.url);
+    }
+}
+
+impl crate::bot::Bot {
+    /// Send the event to any webhooks that want it. `raw` is the notification's payload, as
+    /// received from Twitch, with its `subscription` and `event`.
+    ///
+    /// Requests are made in the background so that slow or failing webhooks don't hold up
+    /// the handling of other events.
+    pub async fn forward_to_webhooks(&self, raw: serde_json::Value) {
+        let Some(event_type) = raw["subscription"]["type"].as_str() else {
+            return;
+        };
+        if crate::killswitch::is_log_only() {
+            return;
+        }
+
+        let webhooks = self.config.read().await.webhook.clone();
+        for webhook in webhooks
+            .into_iter()
+            .filter(|webhook| webhook.matches(event_type))
+        {
+            let body = webhook.body(&raw["event"]);
+            tokio::spawn(webhook.post(body));
+        }
+    }
+}
@@ -16,23 +16,44 @@ use twitch_api::{
 };
 use twitch_oauth2::{TwitchToken, UserToken};
 
+/// How long to wait for a message before Twitch has said how often it sends keepalives.
+const DEFAULT_KEEPALIVE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Extra time on top of the keepalive timeout, for messages that are slow to arrive.
+const KEEPALIVE_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
 pub struct ChatWebsocketClient {
     /// The session id of the websocket connection
     pub session_id: Option<String>,
+    /// The longest that Twitch goes without sending a message, keepalive or otherwise. Any
+    /// longer and the connection is dead.
+    pub keepalive_timeout: Option<std::time::Duration>,
     /// The token used to authenticate with the Twitch API
     pub token: Arc<Mutex<UserToken>>,
     /// The client used to make requests to the Twitch API
     pub client: HelixClient<'static, reqwest::Client>,
     /// The url to use for websocket
     pub connect_url: url::Url,
+    /// Where Twitch asked to move the session to with a `session_reconnect`. It can only be
+    /// used once, so reconnecting for any other reason uses `connect_url`.
+    pub reconnect_url: Option<url::Url>,
     /// Chats to connect to.
     pub chats: Vec<twitch_api::types::UserId>,
+    /// The conduit shard that this websocket receives events for, see `conduits.rs`. Without
+    /// one, the websocket makes its own subscriptions.
+    pub shard: Option<crate::conduits::ConduitShard>,
+    /// When the last message arrived before the connection was lost, until it's back.
+    pub dropped_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Told when the connection was lost once it's back, so that missed events can be
+    /// backfilled, see `backfill.rs`.
+    pub reconnections: Option<tokio::sync::mpsc::UnboundedSender<chrono::DateTime<chrono::Utc>>>,
 }
 
 impl ChatWebsocketClient {
     /// Connect to the websocket and return the stream
     async fn connect(
         &self,
+        url: &url::Url,
     ) -> Result<
         tokio_tungstenite::WebSocketStream<
             tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
@@ -41,10 +62,9 @@ impl ChatWebsocketClient {
     > {
         tracing::info!("connecting to twitch");
         let config = tungstenite::protocol::WebSocketConfig::default();
-        let (socket, _) =
-            tokio_tungstenite::connect_async_with_config(&self.connect_url, Some(config), false)
-                .await
-                .wrap_err("Can't connect")?;
+        let (socket, _) = tokio_tungstenite::connect_async_with_config(url, Some(config), false)
+            .await
+            .wrap_err("Can't connect")?;
 
         Ok(socket)
     }
@@ -53,18 +73,40 @@ impl ChatWebsocketClient {
     #[tracing::instrument(name = "subscriber", skip_all, fields())]
     pub async fn run<Fut>(
         mut self,
-        mut event_fn: impl FnMut(Event, types::Timestamp) -> Fut,
+        mut event_fn: impl FnMut(Event, types::Timestamp, serde_json::Value) -> Fut,
     ) -> Result<(), eyre::Report>
     where
         Fut: std::future::Future<Output = Result<(), eyre::Report>>,
     {
         // Establish the stream
         let mut s = self
-            .connect()
+            .connect(&self.connect_url)
             .await
             .context("when establishing connection")?;
+        let mut last_message_at = chrono::Utc::now();
         // Loop over the stream, processing messages as they come in.
-        while let Some(msg) = futures::StreamExt::next(&mut s).await {
+        loop {
+            // A connection can die without being closed, so if Twitch goes quiet for longer
+            // than it said it would, assume that it's gone and connect again.
+            let window =
+                self.keepalive_timeout.unwrap_or(DEFAULT_KEEPALIVE_TIMEOUT) + KEEPALIVE_GRACE;
+            let Ok(maybe_msg) =
+                tokio::time::timeout(window, futures::StreamExt::next(&mut s)).await
+            else {
+                tracing::warn!("nothing received for {window:?}, reestablishing connection");
+                self.keepalive_timeout = None;
+                self.dropped_at.get_or_insert(last_message_at);
+                s = self
+                    .connect(&self.connect_url)
+                    .await
+                    .context("when reestablishing connection after keepalive timeout")?;
+                continue;
+            };
+            let Some(msg) = maybe_msg else {
+                break;
+            };
+            crate::watchdog::record_activity();
+            last_message_at = chrono::Utc::now();
             let span = tracing::debug_span!("message received", raw_message = ?msg);
             let msg = match msg {
                 Err(tungstenite::Error::Protocol(
@@ -73,8 +115,9 @@ impl ChatWebsocketClient {
                     tracing::warn!(
                         "connection was sent an unexpected frame or was reset, reestablishing it"
                     );
+                    self.dropped_at.get_or_insert(last_message_at);
                     s = self
-                        .connect()
+                        .connect(&self.connect_url)
                         .instrument(span)
                         .await
                         .context("when reestablishing connection")?;
@@ -83,8 +126,17 @@ impl ChatWebsocketClient {
                 _ => msg.context("when getting message")?,
             };
             self.process_message(msg, &mut event_fn)
-                .instrument(span)
-                .await?
+                .instrument(span.clone())
+                .await?;
+            // Twitch moves the session over once the new connection is made, and then closes
+            // the old one.
+            if let Some(url) = self.reconnect_url.take() {
+                s = self
+                    .connect(&url)
+                    .instrument(span)
+                    .await
+                    .context("when following a session reconnect")?;
+            }
         }
         Ok(())
     }
@@ -93,7 +145,7 @@ impl ChatWebsocketClient {
     async fn process_message<Fut>(
         &mut self,
         msg: tungstenite::Message,
-        event_fn: &mut impl FnMut(Event, types::Timestamp) -> Fut,
+        event_fn: &mut impl FnMut(Event, types::Timestamp, serde_json::Value) -> Fut,
     ) -> Result<(), eyre::Report>
     where
         Fut: std::future::Future<Output = Result<(), eyre::Report>>,
@@ -112,10 +164,23 @@ impl ChatWebsocketClient {
                         ..
                     } => {
                         self.process_welcome_message(session).await?;
+                        if let (Some(since), Some(reconnections)) =
+                            (self.dropped_at.take(), &self.reconnections)
+                        {
+                            // Sending only fails when the bot is stopping, which is fine.
+                            let _ = reconnections.send(since);
+                        }
                         Ok(())
                     }
                     EventsubWebsocketData::Notification { metadata, payload } => {
-                        event_fn(payload, metadata.message_timestamp.into_owned()).await?;
+                        // The raw payload is also passed on, for forwarding to webhooks.
+                        let mut raw: serde_json::Value = serde_json::from_str(&s)?;
+                        event_fn(
+                            payload,
+                            metadata.message_timestamp.into_owned(),
+                            raw["payload"].take(),
+                        )
+                        .await?;
                         Ok(())
                     }
                     re @ EventsubWebsocketData::Revocation { .. } => {
@@ -138,8 +203,15 @@ impl ChatWebsocketClient {
     async fn process_welcome_message(&mut self, data: SessionData<'_>) -> Result<(), eyre::Report> {
         tracing::info!("connected to twitch chat");
         self.session_id = Some(data.id.to_string());
+        // Reconnect messages don't include the timeout, it stays as it was.
+        if let Some(seconds) = data.keepalive_timeout_seconds {
+            self.keepalive_timeout = Some(std::time::Duration::from_secs(u64::try_from(seconds)?));
+        }
         if let Some(url) = data.reconnect_url {
-            self.connect_url = url.parse()?;
+            self.reconnect_url = Some(url.parse()?);
+        }
+        if let Some(shard) = &self.shard {
+            return shard.assign(&self.client, &data.id).await;
         }
         let token = self.token.lock().await;
         let transport = eventsub::Transport::websocket(data.id.clone());
@@ -172,32 +244,13 @@ impl ChatWebsocketClient {
                 continue;
             }
 
-            self.client
-                .create_eventsub_subscription(
-                    eventsub::channel::chat::ChannelChatMessageV1::new(
-                        crate::BROADCASTER_ID,
-                        user_id.clone(),
-                    ),
-                    transport.clone(),
-                    &*token,
-                )
-                .await?;
-
-            self.client
-                .create_eventsub_subscription(
-                    eventsub::channel::ChannelFollowV2::new(crate::BROADCASTER_ID, user_id.clone()),
-                    transport.clone(),
-                    &*token,
-                )
-                .await?;
-
-            self.client
-                .create_eventsub_subscription(
-                    eventsub::channel::ChannelRaidV1::to_broadcaster_user_id(crate::BROADCASTER_ID),
-                    transport.clone(),
-                    &*token,
-                )
-                .await?;
+            crate::subscriptions::create_subscriptions(
+                &self.client,
+                transport.clone(),
+                &user_id,
+                &*token,
+            )
+            .await?;
         }
         Ok(())
     }
@@ -0,0 +1,80 @@
+//! Private control of the bot over whispers. Whispered commands are separate from the public
+//! chat commands and only moderators can use them.
+
+use color_eyre::Result;
+use std::sync::atomic::Ordering;
+
+const HELP: &str = "Whisper commands: !pause, !resume, !osd on|off, !status";
+
+impl crate::bot::Bot {
+    pub async fn whisper(
+        &self,
+        payload: &crate::eventsub::user::UserWhisperMessageV1Payload,
+    ) -> Result<()> {
+        let from = payload.from_user_id.as_str();
+        tracing::info!(
+            "Whisper from {}: {}",
+            payload.from_user_name,
+            payload.whisper.text
+        );
+        let text = payload.whisper.text.trim();
+        if from == crate::BROADCASTER_ID
+            && (text.starts_with("shutdown") || text.starts_with("enable"))
+        {
+            let reply = self.kill_switch(text).await;
+            self.db
+                .audit(
+                    payload.from_user_name.as_str(),
+                    "kill switch",
+                    None,
+                    Some(text),
+                )
+                .await?;
+            self.send_whisper(from, reply.as_str()).await?;
+            return Ok(());
+        }
+
+        if !self.user_is_moderator(from).await? {
+            return Ok(());
+        }
+
+        let text = text.strip_prefix('!').unwrap_or(text);
+        let (command, arguments) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+
+        let reply = match (command, arguments.trim()) {
+            ("pause", _) => {
+                self.is_paused.store(true, Ordering::Relaxed);
+                "Paused, chat commands will be ignored"
+            }
+            ("resume", _) => {
+                self.is_paused.store(false, Ordering::Relaxed);
+                "Resumed"
+            }
+            ("osd", "off") => {
+                self.is_osd_enabled.store(false, Ordering::Relaxed);
+                "!osd is off"
+            }
+            ("osd", "on") => {
+                self.is_osd_enabled.store(true, Ordering::Relaxed);
+                "!osd is on"
+            }
+            ("status", _) => {
+                let status = format!(
+                    "Paused: {}, !osd: {}, log-only: {}",
+                    self.is_paused.load(Ordering::Relaxed),
+                    self.is_osd_enabled.load(Ordering::Relaxed),
+                    crate::killswitch::is_log_only()
+                );
+                self.send_whisper(from, status.as_str()).await?;
+                return Ok(());
+            }
+            _ => HELP,
+        };
+
+        self.db
+            .audit(payload.from_user_name.as_str(), "whisper", None, Some(text))
+            .await?;
+        self.send_whisper(from, reply).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! `!whois` for mods, a quick look at an account to help decide whether it's up to no good.
+//! It combines what Twitch knows about the account with what the bot has seen of it.
+
+use color_eyre::Result;
+
+/// A date and how long ago it was, eg: "2024-03-01 (212 days ago)".
+fn date_with_age(date: chrono::DateTime<chrono::Utc>) -> String {
+    let days = (chrono::Utc::now() - date).num_days();
+    format!("{} ({days} days ago)", date.format("%Y-%m-%d"))
+}
+
+impl crate::bot::Bot {
+    /// `!whois <user>` whispers when the account was made, when it followed, how much it's
+    /// chatted here and how many notes mods have about it. Moderators only, and it's whispered
+    /// so that none of it is shown to the rest of chat.
+    pub async fn whois(
+        &self,
+        payload: &crate::eventsub::channel::ChannelChatMessageV1Payload,
+        arguments: Option<&str>,
+    ) -> Result<()> {
+        if !self.is_moderator(payload).await? {
+            return Ok(());
+        }
+        let Some(target) = arguments
+            .map(|argument| argument.trim().trim_start_matches('@'))
+            .filter(|target| !target.is_empty())
+        else {
+            return self
+                .send_message_reply(&payload.message_id, "Usage: !whois <user>")
+                .await;
+        };
+
+        let token = self.token.lock().await.clone();
+        let Some(user) = self.client.get_user_from_login(target, &token).await? else {
+            let message = format!("Couldn't find {target} on Twitch");
+            return self
+                .send_message_reply(&payload.message_id, message.as_str())
+                .await;
+        };
+
+        let created_at = chrono::DateTime::parse_from_rfc3339(user.created_at.as_str())?.to_utc();
+        let followed = match self.followed_at(user.id.as_str()).await? {
+            Some(followed_at) => date_with_age(followed_at),
+            None => "not following".to_owned(),
+        };
+        let (messages, notes) = match self.db.find_mate(user.login.as_str()).await? {
+            Some(mate) => (
+                self.db
+                    .metric(
+                        crate::achievements::Metric::Messages,
+                        user.id.as_str(),
+                        mate.id,
+                    )
+                    .await?,
+                self.db.get_notes(mate.id).await?.len(),
+            ),
+            None => (0, 0),
+        };
+
+        let message = format!(
+            "🔎 {}: created {}, followed {followed}, {messages} messages here, {notes} notes",
+            user.display_name,
+            date_with_age(created_at),
+        );
+        self.send_whisper(payload.chatter_user_id.as_str(), message.as_str())
+            .await
+    }
+}
@@ -0,0 +1,281 @@
+//! Mirror the chat of a simultaneous YouTube live stream. Messages are shown and saved alongside
+//! Twitch's, tagged with their platform. Super Chats and new members get the same alerts as their
+//! Twitch equivalents.
+//!
+//! An API key can only read YouTube's chat, so replies to commands are sent to Twitch chat, which
+//! is on the stream for everyone to see.
+
+use color_eyre::Result;
+use eyre::ContextCompat as _;
+
+const YOUTUBE_API: &str = "https://www.googleapis.com/youtube/v3";
+
+/// How often to look for a YouTube live stream whilst live on Twitch. Searching uses a lot of
+/// YouTube's daily API quota, so this shouldn't be too often.
+const LIVE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// The shortest time between fetching chat messages, whatever YouTube says. Each fetch costs 5
+/// of the 10,000 units of daily API quota, so polling as often as YouTube suggests would use it
+/// all up within hours. At this rate a six hour stream uses 3,600 units.
+const MIN_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct YoutubeConfig {
+    pub enabled: bool,
+    /// An API key from the Google Cloud console, with the YouTube Data API enabled.
+    pub api_key: String,
+    /// The ID of the YouTube channel that's streaming, it starts with "UC".
+    pub channel_id: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct SearchResults {
+    items: Vec<SearchResult>,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct SearchResult {
+    id: SearchResultId,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResultId {
+    video_id: String,
+}
+
+#[derive(serde_derive::Deserialize)]
+struct Videos {
+    items: Vec<Video>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Video {
+    live_streaming_details: Option<LiveStreamingDetails>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LiveStreamingDetails {
+    active_live_chat_id: Option<String>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatMessages {
+    next_page_token: Option<String>,
+    polling_interval_millis: u64,
+    /// Set once the stream has ended.
+    offline_at: Option<String>,
+    items: Vec<ChatMessage>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatMessage {
+    id: String,
+    snippet: ChatMessageSnippet,
+    author_details: ChatMessageAuthor,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatMessageSnippet {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    display_message: String,
+    super_chat_details: Option<SuperChatDetails>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SuperChatDetails {
+    /// The amount in millionths of the currency. It's a string because it's a 64 bit number.
+    amount_micros: String,
+    currency: String,
+    user_comment: Option<String>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatMessageAuthor {
+    channel_id: String,
+    display_name: String,
+}
+
+impl crate::bot::Bot {
+    /// Whilst live on Twitch, look for a YouTube live stream and mirror its chat.
+    pub async fn youtube_mirror(&self) -> Result<()> {
+        let config = self.config.read().await.youtube.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+
+        let client = reqwest::Client::new();
+        loop {
+            if self.db.current_stream().await?.is_some() {
+                match Self::find_youtube_live_chat(&client, &config).await {
+                    Ok(Some(live_chat_id)) => {
+                        tracing::info!("Mirroring YouTube live chat: {live_chat_id}");
+                        if let Err(error) = self
+                            .mirror_youtube_chat(&client, &config, &live_chat_id)
+                            .await
+                        {
+                            tracing::error!("Mirroring YouTube chat: {error:?}");
+                        }
+                    }
+                    Ok(None) => tracing::debug!("No YouTube live stream found"),
+                    Err(error) => tracing::error!("Finding YouTube live stream: {error:?}"),
+                }
+            }
+            tokio::time::sleep(LIVE_CHECK_INTERVAL).await;
+        }
+    }
+
+    async fn find_youtube_live_chat(
+        client: &reqwest::Client,
+        config: &YoutubeConfig,
+    ) -> Result<Option<String>> {
+        let search: SearchResults = client
+            .get(format!("{YOUTUBE_API}/search"))
+            .query(&[
+                ("part", "id"),
+                ("channelId", config.channel_id.as_str()),
+                ("eventType", "live"),
+                ("type", "video"),
+                ("key", config.api_key.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let Some(live) = search.items.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let videos: Videos = client
+            .get(format!("{YOUTUBE_API}/videos"))
+            .query(&[
+                ("part", "liveStreamingDetails"),
+                ("id", live.id.video_id.as_str()),
+                ("key", config.api_key.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let live_chat_id = videos
+            .items
+            .into_iter()
+            .next()
+            .and_then(|video| video.live_streaming_details)
+            .and_then(|details| details.active_live_chat_id);
+        Ok(live_chat_id)
+    }
+
+    /// Keep fetching new chat messages until the YouTube stream ends.
+    async fn mirror_youtube_chat(
+        &self,
+        client: &reqwest::Client,
+        config: &YoutubeConfig,
+        live_chat_id: &str,
+    ) -> Result<()> {
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("liveChatId", live_chat_id),
+                ("part", "snippet,authorDetails"),
+                ("key", config.api_key.as_str()),
+            ];
+            if let Some(token) = &page_token {
+                query.push(("pageToken", token.as_str()));
+            }
+            let messages: ChatMessages = client
+                .get(format!("{YOUTUBE_API}/liveChat/messages"))
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            // The first page is the chat's recent history, which has already been seen.
+            if page_token.is_some() {
+                for message in messages.items {
+                    if let Err(error) = self.youtube_chat_message(message).await {
+                        tracing::error!("Handling YouTube chat message: {error:?}");
+                    }
+                }
+            }
+
+            if messages.offline_at.is_some() {
+                tracing::info!("YouTube live stream has ended");
+                return Ok(());
+            }
+            page_token = Some(
+                messages
+                    .next_page_token
+                    .context("No next page token for YouTube chat")?,
+            );
+            let interval = std::time::Duration::from_millis(messages.polling_interval_millis);
+            tokio::time::sleep(interval.max(MIN_POLL_INTERVAL)).await;
+        }
+    }
+
+    async fn youtube_chat_message(&self, message: ChatMessage) -> Result<()> {
+        let user_id = message.author_details.channel_id.as_str();
+        let username = message.author_details.display_name.as_str();
+        let text = message.snippet.display_message.as_str();
+
+        match message.snippet.kind.as_str() {
+            "superChatEvent" => {
+                let details = message
+                    .snippet
+                    .super_chat_details
+                    .context("Super Chat without any details")?;
+                let micros: f64 = details.amount_micros.parse()?;
+                self.new_tip(crate::tips::Tip {
+                    source: "youtube",
+                    external_id: message.id.clone(),
+                    username: username.to_owned(),
+                    amount: micros / 1_000_000.0,
+                    currency: details.currency,
+                    message: details.user_comment,
+                })
+                .await?;
+            }
+            "newSponsorEvent" => {
+                self.play_alert(
+                    crate::themes::AlertKind::Subscribe,
+                    format!("{username} became a member on YouTube! 🎉"),
+                    &[("{user}", username), ("{tier}", "YouTube")],
+                )
+                .await?;
+            }
+            _ => (),
+        }
+
+        if text.is_empty() {
+            return Ok(());
+        }
+        println!("[YouTube] {username}: {text}");
+        self.db
+            .save_platform_message("youtube", user_id, username, text, &message.id)
+            .await?;
+
+        if let Some(original) = text.strip_prefix('!') {
+            let command = original.split_whitespace().next().unwrap_or_default();
+            if let Some(response) = self.command_response(command, username).await {
+                let reply = format!("[YouTube] @{username} {response}");
+                self.send_message(reply.as_str()).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! Benchmarks for the renderer's hot path, so that performance work has a baseline to compare
+//! against. They use a realistic 200x60 terminal full of text, with 20 emotes showing.
+//!
+//! Run with: `cargo bench -p tattoy_twitch_tombh_plugin`
+
+// The renderer lives in the plugin's binary, so it's included directly.
+#[allow(dead_code)]
+#[path = "../src/rain.rs"]
+mod rain;
+#[allow(dead_code)]
+#[path = "../src/renderer.rs"]
+mod renderer;
+#[allow(dead_code)]
+#[path = "../src/utils.rs"]
+mod utils;
+
+/// The size of the terminal, in cells.
+const TERMINAL_SIZE: (u16, u16) = (200, 60);
+
+/// How many emotes are showing at once.
+const ACTIVE_EMOTES: usize = 20;
+
+/// Twitch's biggest emote images are 112 pixels square.
+const EMOTE_IMAGE_SIZE: u32 = 112;
+
+/// The line of text on each row of the terminal, like a busy shell session.
+fn terminal_line(row: u16) -> String {
+    format!("{row:>4} | cargo build --release && ./target/release/tbhbot --config config.toml # word{row}")
+}
+
+/// A terminal full of text, with the cursor on the last row.
+fn pty_update() -> tattoy_protocol::PluginInputMessages {
+    let mut cells = Vec::new();
+    for row in 0..TERMINAL_SIZE.1 {
+        for (col, character) in terminal_line(row)
+            .chars()
+            .take(usize::from(TERMINAL_SIZE.0))
+            .enumerate()
+        {
+            let col = u32::try_from(col).expect("Terminals aren't that wide");
+            cells.push(
+                tattoy_protocol::Cell::builder()
+                    .character(character)
+                    .coordinates((col, u32::from(row)))
+                    .build(),
+            );
+        }
+    }
+    tattoy_protocol::PluginInputMessages::PTYUpdate {
+        size: TERMINAL_SIZE,
+        cells,
+        cursor: (0, TERMINAL_SIZE.1 - 1),
+    }
+}
+
+/// A made up emote image, so that nothing needs downloading.
+fn emote_image() -> image::DynamicImage {
+    image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(
+        EMOTE_IMAGE_SIZE,
+        EMOTE_IMAGE_SIZE,
+        |x, y| {
+            let byte = |value: u32| u8::try_from(value % 256).unwrap_or(u8::MAX);
+            image::Rgba([byte(x), byte(y), byte(x ^ y), u8::MAX])
+        },
+    ))
+}
+
+/// A plugin showing the terminal and emotes. Emotes are spread down the terminal, so that
+/// finding their text means searching most of it.
+fn plugin() -> renderer::Plugin {
+    let mut plugin = renderer::Plugin::new().expect("Couldn't create plugin");
+    plugin.handle_tattoy_message(pty_update());
+    let image = emote_image();
+    for index in 0..ACTIVE_EMOTES {
+        let row = TERMINAL_SIZE.1 - 1 - u16::try_from(index).expect("Not that many emotes");
+        plugin.push_active_emote(
+            image.clone(),
+            renderer::Placement::Text(format!("word{row}")),
+            1.0,
+            std::time::Duration::from_secs(60),
+            false,
+            false,
+        );
+    }
+    plugin
+}
+
+fn find_text_coordinates(criterion: &mut criterion::Criterion) {
+    let mut plugin = plugin();
+    let last_row = format!("word{}", TERMINAL_SIZE.1 - 1);
+    criterion.bench_function("find_text_coordinates", |bencher| {
+        bencher.iter(|| plugin.find_text_coordinates(std::hint::black_box(&last_row)));
+    });
+}
+
+fn frame_composition(criterion: &mut criterion::Criterion) {
+    let runtime = tokio::runtime::Runtime::new().expect("Couldn't start Tokio");
+    let mut plugin = plugin();
+    let mut group = criterion.benchmark_group("frame_composition");
+    group.sample_size(10);
+    group.bench_function("render_emotes", |bencher| {
+        bencher.iter(|| runtime.block_on(plugin.render_emotes()));
+    });
+    group.finish();
+}
+
+fn json_serialisation(criterion: &mut criterion::Criterion) {
+    let runtime = tokio::runtime::Runtime::new().expect("Couldn't start Tokio");
+    let mut plugin = plugin();
+    runtime
+        .block_on(plugin.render_emotes())
+        .expect("Couldn't render a frame");
+    criterion.bench_function("output_json", |bencher| {
+        bencher.iter(|| plugin.output_json());
+    });
+}
+
+criterion::criterion_group!(
+    benches,
+    find_text_coordinates,
+    frame_composition,
+    json_serialisation
+);
+criterion::criterion_main!(benches);
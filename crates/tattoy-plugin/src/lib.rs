@@ -1,8 +1,104 @@
 pub const SOCKET_PATH: &str = "/tmp/tattoy-twitch.sock";
 
+/// A fixed place in the terminal to render an emote, rather than behind some matching text.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    /// The bottom row of the terminal.
+    StatusBar,
+    /// The end of the line that the cursor is on.
+    Prompt,
+    /// A specific terminal cell, counting from 0.
+    Cell {
+        row: u16,
+        col: u16,
+    },
+}
+
+impl std::str::FromStr for Anchor {
+    type Err = String;
+
+    /// Parse anchors as they're written in chat: `top-left`, `status-bar`, `prompt` or
+    /// `<row>,<col>`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "top-left" => Ok(Self::TopLeft),
+            "status-bar" => Ok(Self::StatusBar),
+            "prompt" => Ok(Self::Prompt),
+            _ => {
+                let parse = |number: &str| number.trim().parse::<u16>().ok();
+                text.split_once(',')
+                    .and_then(|(row, col)| {
+                        Some(Self::Cell {
+                            row: parse(row)?,
+                            col: parse(col)?,
+                        })
+                    })
+                    .ok_or_else(|| format!("Unknown anchor: {text}"))
+            }
+        }
+    }
+}
+
+/// Messages sent from the bot to the plugin over the Unix socket, one JSON object per line.
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
-pub struct BotMessage {
-    pub username: String,
-    pub regexish: String,
-    pub emote: String,
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BotMessage {
+    /// Render an emote behind some text in the terminal.
+    Emote {
+        username: String,
+        regexish: String,
+        emote: String,
+        /// How much bigger than normal to render the emote.
+        scale: Option<f32>,
+        /// How many seconds to show the emote for, instead of the default.
+        duration: Option<u64>,
+        /// Render the emote here instead of behind `regexish`.
+        anchor: Option<Anchor>,
+        /// Keep the emote until it's cleared with `ClearPinned`, rather than it timing out.
+        #[serde(default)]
+        pinned: bool,
+        /// Render the emote behind every match of `regexish`, up to this many, rather than only
+        /// the first.
+        #[serde(default)]
+        every_match: Option<usize>,
+        /// Grow the emote into place when it appears.
+        #[serde(default)]
+        pop_in: bool,
+    },
+    /// Remove all pinned emotes.
+    ClearPinned,
+    /// Render an emote big in the middle of the terminal.
+    Spotlight {
+        /// The Twitch ID of the emote, so that non-global emotes work too.
+        emote_id: String,
+        /// How many seconds to show the emote for.
+        duration: u64,
+    },
+    /// Show a banner across the top of the terminal for an unlocked achievement.
+    Achievement {
+        username: String,
+        /// The name of the achievement.
+        name: String,
+        /// How many seconds to show the banner for.
+        duration: u64,
+    },
+    /// Show the streamer's todo list in a panel, replacing any previous list. An empty list
+    /// hides the panel.
+    Todos { items: Vec<String> },
+    /// The codes of every emote that can be rendered, with their Twitch IDs. Includes the
+    /// channel's own emotes, and replaces any previous emotes.
+    Emotes {
+        ids: std::collections::HashMap<String, String>,
+    },
+    /// Make it rain an emote, for hype moments like raids. The emotes fall from the top of the
+    /// terminal and bounce along the bottom.
+    EmoteRain {
+        emote: String,
+        /// How many of the emote fall.
+        count: u16,
+        /// How many seconds the rain lasts for.
+        duration: u64,
+    },
 }
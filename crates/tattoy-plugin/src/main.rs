@@ -1,3 +1,4 @@
+pub mod rain;
 pub mod renderer;
 pub mod utils;
 
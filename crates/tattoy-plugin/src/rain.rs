@@ -0,0 +1,165 @@
+//! Emote rain, for hype moments like raids. Emotes start above the top of the terminal, fall
+//! with gravity and bounce along the bottom until the rain is over.
+
+use rand::Rng as _;
+
+/// How quickly emotes fall faster, in pixels per second per second. Each terminal cell is 1
+/// pixel wide and 2 pixels high.
+const GRAVITY: f32 = 80.0;
+
+/// How much of its speed an emote keeps after bouncing.
+const BOUNCINESS: f32 = 0.6;
+
+/// Bounces slower than this just stop, rather than jittering forever.
+const MIN_BOUNCE_SPEED: f32 = 4.0;
+
+/// How fast emotes can drift sideways, in pixels per second.
+const MAX_DRIFT: f32 = 8.0;
+
+/// How big each falling emote is, in pixels.
+const EMOTE_SIZE: u32 = 6;
+
+/// An emote that's falling, or bouncing along the bottom.
+#[derive(Clone, Debug)]
+struct FallingEmote {
+    x: f32,
+    y: f32,
+    velocity: (f32, f32),
+}
+
+/// One rain of the same emote.
+#[derive(Clone, Debug)]
+pub(crate) struct Rain {
+    /// The emote's image, already resized.
+    image: crate::renderer::EmoteImage,
+    emotes: Vec<FallingEmote>,
+    started_at: std::time::Instant,
+    /// When the emotes were last moved.
+    updated_at: std::time::Instant,
+    duration: std::time::Duration,
+}
+
+impl Rain {
+    /// Start a rain of `count` emotes in a terminal of `size` cells. The emotes start at
+    /// different heights above the terminal, so that they don't all land at once.
+    pub(crate) fn new(
+        image: &crate::renderer::EmoteImage,
+        count: u16,
+        duration: std::time::Duration,
+        size: (u16, u16),
+    ) -> Self {
+        let (width, height) = pixel_size(size);
+        let mut rng = rand::rng();
+        #[expect(
+            clippy::cast_precision_loss,
+            reason = "Emotes are only ever a few pixels big"
+        )]
+        let emote_size = EMOTE_SIZE as f32;
+        let emotes = (0..count)
+            .map(|_| FallingEmote {
+                x: rng.random_range(0.0..(width - emote_size).max(1.0)),
+                y: rng.random_range(-height.max(1.0)..0.0) - emote_size,
+                velocity: (rng.random_range(-MAX_DRIFT..MAX_DRIFT), 0.0),
+            })
+            .collect();
+
+        let now = std::time::Instant::now();
+        Self {
+            image: image.resized(EMOTE_SIZE, EMOTE_SIZE),
+            emotes,
+            started_at: now,
+            updated_at: now,
+            duration,
+        }
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.started_at.elapsed() >= self.duration
+    }
+
+    /// Move every emote on by the time since they were last moved, bouncing them off the
+    /// bottom and sides of a terminal of `size` cells.
+    pub(crate) fn step(&mut self, size: (u16, u16)) {
+        let now = std::time::Instant::now();
+        let seconds = (now - self.updated_at).as_secs_f32();
+        self.updated_at = now;
+
+        let (width, height) = pixel_size(size);
+        #[expect(
+            clippy::cast_precision_loss,
+            reason = "Emotes are only ever a few pixels big"
+        )]
+        let emote_size = EMOTE_SIZE as f32;
+        let floor = height - emote_size;
+        let right_wall = (width - emote_size).max(0.0);
+        for emote in &mut self.emotes {
+            emote.velocity.1 += GRAVITY * seconds;
+            emote.x += emote.velocity.0 * seconds;
+            emote.y += emote.velocity.1 * seconds;
+
+            if emote.y > floor {
+                emote.y = floor;
+                emote.velocity.1 = -emote.velocity.1 * BOUNCINESS;
+                if emote.velocity.1.abs() < MIN_BOUNCE_SPEED {
+                    emote.velocity.1 = 0.0;
+                }
+            }
+            if emote.x < 0.0 || emote.x > right_wall {
+                emote.x = emote.x.clamp(0.0, right_wall);
+                emote.velocity.0 = -emote.velocity.0;
+            }
+        }
+    }
+
+    /// The pixels of every emote that's on screen, in a terminal of `size` cells.
+    pub(crate) fn pixels(&self, size: (u16, u16)) -> Vec<tattoy_protocol::Pixel> {
+        let elapsed = self.started_at.elapsed();
+        let frame = self.image.frame_at(elapsed);
+        let remaining = self.duration.saturating_sub(elapsed);
+        let opacity =
+            (remaining.as_secs_f32() / crate::renderer::FADE_OUT_DURATION.as_secs_f32()).min(1.0);
+        let width = i64::from(size.0);
+        let height = i64::from(size.1) * 2;
+
+        let mut pixels = Vec::new();
+        for emote in &self.emotes {
+            #[expect(
+                clippy::cast_possible_truncation,
+                reason = "Terminals are never anywhere near big enough to overflow"
+            )]
+            let (emote_x, emote_y) = (emote.x.round() as i64, emote.y.round() as i64);
+            for (pixel_x, pixel_y, colour) in image::GenericImageView::pixels(frame) {
+                let x = emote_x + i64::from(pixel_x);
+                let y = emote_y + i64::from(pixel_y);
+                let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+                    continue;
+                };
+                // Transparent pixels are skipped, so that emotes aren't boxes.
+                if colour.0[3] == 0 {
+                    continue;
+                }
+                if i64::from(x) >= width || i64::from(y) >= height {
+                    continue;
+                }
+                pixels.push(
+                    tattoy_protocol::Pixel::builder()
+                        .coordinates((x, y))
+                        .color((
+                            f32::from(colour.0[0]) / 255.0,
+                            f32::from(colour.0[1]) / 255.0,
+                            f32::from(colour.0[2]) / 255.0,
+                            opacity,
+                        ))
+                        .build(),
+                );
+            }
+        }
+
+        pixels
+    }
+}
+
+/// The size of a terminal of `size` cells, in pixels.
+fn pixel_size(size: (u16, u16)) -> (f32, f32) {
+    (f32::from(size.0), f32::from(size.1) * 2.0)
+}
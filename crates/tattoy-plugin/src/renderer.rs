@@ -1,4 +1,5 @@
 use color_eyre::eyre::Result;
+use image::AnimationDecoder as _;
 use image::GenericImageView as _;
 use std::io::Write as _;
 use tokio::io::AsyncBufReadExt;
@@ -19,21 +20,216 @@ struct TTY {
     cells: Vec<tattoy_protocol::Cell>,
 }
 
+/// How long emotes are shown for by default.
+const DEFAULT_EMOTE_DURATION: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Emotes fade out over the last of their time on screen, rather than vanishing.
+pub(crate) const FADE_OUT_DURATION: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long emotes that pop in take to grow to their full size.
+const POP_IN_DURATION: std::time::Duration = std::time::Duration::from_millis(400);
+
+/// How big emotes that pop in start, compared to their full size.
+const POP_IN_START: f32 = 0.3;
+
+/// The biggest that a compiled text pattern can be, so that chat can't use up all the memory.
+const PATTERN_SIZE_LIMIT: usize = 1024 * 1024;
+
+/// Where an emote is rendered in the terminal.
+#[derive(Clone, Debug)]
+pub(crate) enum Placement {
+    /// Behind the first bit of text on screen that matches the pattern, a regex.
+    Text(String),
+    /// Behind every bit of text on screen that matches the pattern, up to `limit` of them.
+    EveryMatch { regexish: String, limit: usize },
+    /// Big, in the middle of the terminal.
+    Centre,
+    /// At a fixed place in the terminal.
+    Anchor(tattoy_twitch_tombh_plugin::Anchor),
+}
+
+/// The size of anchored emotes, in terminal cells.
+const ANCHORED_EMOTE_SIZE: (u32, u32) = (6, 3);
+
+/// How long achievement banners take to slide in.
+const BANNER_SLIDE_IN: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The colour of achievement banners.
+const BANNER_COLOUR: (f32, f32, f32, f32) = (1.0, 0.84, 0.0, 1.0);
+
+/// The height of achievement banners, in terminal rows.
+const BANNER_ROWS: u32 = 2;
+
+/// The colour of the todo list panel.
+const TODO_PANEL_COLOUR: (f32, f32, f32, f32) = (0.15, 0.15, 0.2, 1.0);
+
+/// The longest todo item shown, longer ones are cut short.
+const MAX_TODO_WIDTH: usize = 40;
+
+/// GIF frames with shorter delays than this are shown for `DEFAULT_FRAME_DELAY` instead, like
+/// browsers do.
+const MIN_FRAME_DELAY: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// How long GIF frames without a sensible delay are shown for.
+const DEFAULT_FRAME_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// An emote's image. Animated emotes have a frame for each step of the animation, static
+/// emotes just have the one. There's always at least one frame.
+#[derive(Clone, Debug)]
+pub(crate) struct EmoteImage {
+    /// Each frame, with how long it's shown for.
+    frames: Vec<(image::DynamicImage, std::time::Duration)>,
+    /// How long the whole animation takes.
+    loop_duration: std::time::Duration,
+}
+
+impl From<image::DynamicImage> for EmoteImage {
+    fn from(image: image::DynamicImage) -> Self {
+        Self {
+            frames: vec![(image, std::time::Duration::ZERO)],
+            loop_duration: std::time::Duration::ZERO,
+        }
+    }
+}
+
+impl EmoteImage {
+    /// Decode every frame of an animated GIF.
+    fn from_gif(bytes: &[u8]) -> Result<Self> {
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(bytes))?;
+        let frames = decoder
+            .into_frames()
+            .collect_frames()?
+            .into_iter()
+            .map(|frame| {
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                let delay = std::time::Duration::from_millis(u64::from(
+                    numerator.checked_div(denominator).unwrap_or_default(),
+                ));
+                let delay = if delay < MIN_FRAME_DELAY {
+                    DEFAULT_FRAME_DELAY
+                } else {
+                    delay
+                };
+                (image::DynamicImage::ImageRgba8(frame.into_buffer()), delay)
+            })
+            .collect::<Vec<_>>();
+        if frames.is_empty() {
+            color_eyre::eyre::bail!("GIF emote has no frames");
+        }
+
+        let loop_duration = frames.iter().map(|(_, delay)| *delay).sum();
+        Ok(Self {
+            frames,
+            loop_duration,
+        })
+    }
+
+    /// Every frame resized to `width` by `height` pixels, keeping the aspect ratio.
+    pub(crate) fn resized(&self, width: u32, height: u32) -> Self {
+        Self {
+            frames: self
+                .frames
+                .iter()
+                .map(|(frame, delay)| {
+                    let resized =
+                        frame.resize(width, height, image::imageops::FilterType::Lanczos3);
+                    (resized, *delay)
+                })
+                .collect(),
+            loop_duration: self.loop_duration,
+        }
+    }
+
+    /// The frame to show after the emote has been showing for `elapsed`.
+    pub(crate) fn frame_at(&self, elapsed: std::time::Duration) -> &image::DynamicImage {
+        let loop_micros = self.loop_duration.as_micros().max(1);
+        let mut position = elapsed.as_micros() % loop_micros;
+        for (frame, delay) in &self.frames {
+            if position < delay.as_micros() {
+                return frame;
+            }
+            position -= delay.as_micros();
+        }
+        &self.frames[0].0
+    }
+}
+
+/// Some text on screen that matched a pattern, in terminal cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TextMatch {
+    pub x: usize,
+    pub y: usize,
+    /// How many characters matched.
+    pub width: usize,
+}
+
+/// An achievement banner. Banners are shown one after the other, rather than on top of each
+/// other.
+#[derive(Clone, Debug)]
+struct ActiveBanner {
+    text: String,
+    starts_at: std::time::Instant,
+    duration: std::time::Duration,
+}
+
 #[derive(Clone, Debug)]
 struct ActiveEmote {
-    regexish: String,
+    placement: Placement,
     timestamp: std::time::Instant,
+    /// How long the emote is shown for.
+    duration: std::time::Duration,
+    /// How much bigger than normal the emote is rendered.
+    scale: f32,
+    /// Pinned emotes don't time out.
+    is_pinned: bool,
+    /// Whether the emote grows into place when it appears.
+    pops_in: bool,
     /// The emote's cached image data.
-    image: image::DynamicImage,
+    image: EmoteImage,
+}
+
+impl ActiveEmote {
+    /// How opaque the emote is, from 0 to 1. It fades out over its last `FADE_OUT_DURATION`.
+    fn opacity(&self) -> f32 {
+        if self.is_pinned {
+            return 1.0;
+        }
+        let remaining = self.duration.saturating_sub(self.timestamp.elapsed());
+        (remaining.as_secs_f32() / FADE_OUT_DURATION.as_secs_f32()).min(1.0)
+    }
+
+    /// How big the emote is compared to its full size. Emotes that pop in overshoot a little
+    /// before settling, like a bounce.
+    fn pop(&self) -> f32 {
+        if !self.pops_in {
+            return 1.0;
+        }
+        let progress =
+            (self.timestamp.elapsed().as_secs_f32() / POP_IN_DURATION.as_secs_f32()).min(1.0);
+        // An "ease out back" curve.
+        let overshoot = 1.70158;
+        let eased = 1.0
+            + (overshoot + 1.0) * (progress - 1.0).powi(3)
+            + overshoot * (progress - 1.0).powi(2);
+        POP_IN_START + (1.0 - POP_IN_START) * eased
+    }
 }
 
 pub struct Plugin {
     /// Details about the user's terminal.
     tty: TTY,
-    /// A mapping of all the global Twitch emote IDs with their actual text codes.
-    global_emotes: crate::utils::EmoteIDs,
+    /// Emote codes with their Twitch IDs, sent by the bot. Global and channel emotes.
+    emotes: crate::utils::EmoteIDs,
+    /// Compiled text patterns, so they aren't compiled again every frame.
+    patterns: std::collections::HashMap<String, regex::Regex>,
     /// The currently rendered emotes from Twitch chat.
     active_emotes: Vec<ActiveEmote>,
+    /// Achievement banners, currently showing or waiting their turn.
+    active_banners: Vec<ActiveBanner>,
+    /// Emote rains that are falling.
+    rains: Vec<crate::rain::Rain>,
+    /// The streamer's todo list.
+    todos: Vec<String>,
     /// The current output of all emotes to be sent to Tattoy.
     output: Vec<tattoy_protocol::Pixel>,
     /// The time at which the previous frame was rendererd.
@@ -42,15 +238,19 @@ pub struct Plugin {
 
 impl Plugin {
     /// Instatiate
-    fn new() -> Result<Self> {
+    pub(crate) fn new() -> Result<Self> {
         Ok(Self {
             tty: TTY {
                 size: (0, 0),
                 cursor_position: (0, 0),
                 cells: Vec::new(),
             },
-            global_emotes: crate::utils::load_emotes()?,
+            emotes: crate::utils::EmoteIDs::default(),
+            patterns: std::collections::HashMap::default(),
             active_emotes: Vec::default(),
+            active_banners: Vec::default(),
+            rains: Vec::default(),
+            todos: Vec::default(),
             output: Vec::default(),
             last_frame_tick: tokio::time::Instant::now(),
         })
@@ -134,13 +334,103 @@ impl Plugin {
         Ok(())
     }
 
-    // {"username": "tom", "regexish": "nightly", "emote": "LUL"}
+    // {"kind": "emote", "username": "tom", "regexish": "nightly", "emote": "LUL"}
     async fn handle_bot_message(
         &mut self,
         message: tattoy_twitch_tombh_plugin::BotMessage,
     ) -> Result<()> {
         tracing::info!("Adding active emote: {message:?}");
-        self.add_active_emote(message.emote, message.regexish).await
+        match message {
+            tattoy_twitch_tombh_plugin::BotMessage::Emote {
+                emote,
+                regexish,
+                scale,
+                duration,
+                anchor,
+                pinned,
+                every_match,
+                pop_in,
+                ..
+            } => {
+                let placement = match (anchor, every_match) {
+                    (Some(anchor), _) => Placement::Anchor(anchor),
+                    (None, Some(limit)) => Placement::EveryMatch { regexish, limit },
+                    (None, None) => Placement::Text(regexish),
+                };
+                let duration =
+                    duration.map_or(DEFAULT_EMOTE_DURATION, std::time::Duration::from_secs);
+                self.add_active_emote(
+                    emote,
+                    placement,
+                    scale.unwrap_or(1.0),
+                    duration,
+                    pinned,
+                    pop_in,
+                )
+                .await
+            }
+            tattoy_twitch_tombh_plugin::BotMessage::ClearPinned => {
+                self.active_emotes.retain(|emote| !emote.is_pinned);
+                Ok(())
+            }
+            tattoy_twitch_tombh_plugin::BotMessage::Spotlight { emote_id, duration } => {
+                let image = self.get_emote_image(&emote_id).await?;
+                self.push_active_emote(
+                    image,
+                    Placement::Centre,
+                    1.0,
+                    std::time::Duration::from_secs(duration),
+                    false,
+                    true,
+                );
+                Ok(())
+            }
+            tattoy_twitch_tombh_plugin::BotMessage::Achievement {
+                username,
+                name,
+                duration,
+            } => {
+                let starts_at = self
+                    .active_banners
+                    .iter()
+                    .map(|banner| banner.starts_at + banner.duration)
+                    .max()
+                    .unwrap_or_else(std::time::Instant::now)
+                    .max(std::time::Instant::now());
+                self.active_banners.push(ActiveBanner {
+                    text: format!("🏆 {username} unlocked {name} 🏆"),
+                    starts_at,
+                    duration: std::time::Duration::from_secs(duration),
+                });
+                Ok(())
+            }
+            tattoy_twitch_tombh_plugin::BotMessage::Todos { items } => {
+                self.todos = items;
+                Ok(())
+            }
+            tattoy_twitch_tombh_plugin::BotMessage::Emotes { ids } => {
+                self.emotes = ids;
+                Ok(())
+            }
+            tattoy_twitch_tombh_plugin::BotMessage::EmoteRain {
+                emote,
+                count,
+                duration,
+            } => {
+                let Some(id) = self.emotes.get(&emote) else {
+                    tracing::warn!("Couldn't find ID for emote code: {emote}");
+                    return Ok(());
+                };
+                let image = self.get_emote_image(id).await?;
+                self.rains.push(crate::rain::Rain::new(
+                    &image,
+                    count,
+                    std::time::Duration::from_secs(duration),
+                    self.tty.size,
+                ));
+                Ok(())
+            }
+        }
     }
 
     /// Sleep until the next frame render is due.
@@ -155,7 +445,7 @@ impl Plugin {
 
     /// Handle a protocol message from Tattoy.
     #[expect(clippy::todo, reason = "TODO: support terminal resizing")]
-    fn handle_tattoy_message(&mut self, message: tattoy_protocol::PluginInputMessages) {
+    pub(crate) fn handle_tattoy_message(&mut self, message: tattoy_protocol::PluginInputMessages) {
         match message {
             tattoy_protocol::PluginInputMessages::PTYUpdate {
                 size,
@@ -185,17 +475,19 @@ impl Plugin {
     // |   ^^^    |
     // — @YourInty April 23rd 2025
     //
-    async fn add_active_emote(&mut self, code: String, regexish: String) -> Result<()> {
-        match self.global_emotes.get(&code) {
+    async fn add_active_emote(
+        &mut self,
+        code: String,
+        placement: Placement,
+        scale: f32,
+        duration: std::time::Duration,
+        is_pinned: bool,
+        pops_in: bool,
+    ) -> Result<()> {
+        match self.emotes.get(&code) {
             Some(id) => {
                 let image = self.get_emote_image(id).await?;
-                let active_emote = ActiveEmote {
-                    regexish,
-                    image,
-                    timestamp: std::time::Instant::now(),
-                };
-                tracing::debug!("Generated active emote: {active_emote:?}");
-                self.active_emotes.push(active_emote);
+                self.push_active_emote(image, placement, scale, duration, is_pinned, pops_in);
             }
             None => {
                 tracing::warn!("Couldn't find ID for emote code: {code}");
@@ -205,14 +497,40 @@ impl Plugin {
         Ok(())
     }
 
-    async fn get_emote_image(&self, emote_id: &str) -> Result<image::DynamicImage> {
-        let url = format!("https://static-cdn.jtvnw.net/emoticons/v2/{emote_id}/static/light/3.0");
-        let resp = reqwest::get(url).await?;
+    /// Start showing an emote whose image has already been fetched.
+    pub(crate) fn push_active_emote(
+        &mut self,
+        image: impl Into<EmoteImage>,
+        placement: Placement,
+        scale: f32,
+        duration: std::time::Duration,
+        is_pinned: bool,
+        pops_in: bool,
+    ) {
+        let active_emote = ActiveEmote {
+            placement,
+            image: image.into(),
+            timestamp: std::time::Instant::now(),
+            duration,
+            scale,
+            is_pinned,
+            pops_in,
+        };
+        tracing::debug!("Generated active emote: {active_emote:?}");
+        self.active_emotes.push(active_emote);
+    }
 
-        let emote_big =
-            image::load_from_memory_with_format(&resp.bytes().await?, image::ImageFormat::Png)?;
+    /// Fetch an emote's image. The `default` format is an animated GIF for animated emotes,
+    /// and a static PNG for the rest.
+    async fn get_emote_image(&self, emote_id: &str) -> Result<EmoteImage> {
+        let url = format!("https://static-cdn.jtvnw.net/emoticons/v2/{emote_id}/default/light/3.0");
+        let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
 
-        Ok(emote_big)
+        if image::guess_format(&bytes)? == image::ImageFormat::Gif {
+            return EmoteImage::from_gif(&bytes);
+        }
+        let emote_big = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)?;
+        Ok(emote_big.into())
     }
 
     /// Send a frame to Tattoy.
@@ -223,38 +541,147 @@ impl Plugin {
 
         self.cleanup().await?;
         self.render_emotes().await?;
+        let banner_text = self.render_banner()?;
         self.send_output()?;
+        for line in self.todo_panel_lines()? {
+            self.send_text(line, TODO_PANEL_COLOUR, (1.0, 1.0, 1.0, 1.0))?;
+        }
+        if let Some(text) = banner_text {
+            self.send_text(text, BANNER_COLOUR, (0.0, 0.0, 0.0, 1.0))?;
+        }
 
         Ok(())
     }
 
-    async fn render_emotes(&mut self) -> Result<()> {
+    pub(crate) async fn render_emotes(&mut self) -> Result<()> {
         self.output = Vec::default();
         for emote in self.active_emotes.clone() {
             self.render_emote(emote).await?;
         }
+        for rain in &mut self.rains {
+            rain.step(self.tty.size);
+            self.output.extend(rain.pixels(self.tty.size));
+        }
 
         Ok(())
     }
 
     async fn render_emote(&mut self, emote: ActiveEmote) -> Result<()> {
-        let maybe_match = self.find_text_coordinates(emote.regexish.clone())?;
+        let image = emote.image.frame_at(emote.timestamp.elapsed());
+        let pop = emote.pop();
+        let scale = emote.scale * pop;
+        let placed = match &emote.placement {
+            Placement::Text(regexish) | Placement::EveryMatch { regexish, .. } => {
+                let limit = match &emote.placement {
+                    Placement::EveryMatch { limit, .. } => *limit,
+                    _ => 1,
+                };
+                let matches = self.find_text_coordinates(regexish)?;
+                if matches.is_empty() {
+                    tracing::debug!("Couldn't find '{regexish}' in TTY");
+                    return Ok(());
+                }
 
-        let Some((match_x, match_y)) = maybe_match else {
-            tracing::debug!("Couldn't find '{}' in TTY", emote.regexish);
-            return Ok(());
+                matches
+                    .iter()
+                    .take(limit)
+                    .map(|text_match| self.behind_text(image, *text_match, scale))
+                    .collect::<Result<Vec<_>>>()?
+            }
+            Placement::Centre => {
+                // Each terminal cell is 2 pixels high.
+                let width = u32::from(self.tty.size.0);
+                let height = u32::from(self.tty.size.1) * 2;
+                #[expect(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss,
+                    clippy::cast_precision_loss,
+                    reason = "Terminals are never anywhere near big enough to lose precision"
+                )]
+                let emote_resized = image.resize(
+                    ((width * 2 / 3) as f32 * pop).max(1.0) as u32,
+                    ((height * 2 / 3) as f32 * pop).max(1.0) as u32,
+                    image::imageops::FilterType::Lanczos3,
+                );
+                let emote_x = width.saturating_sub(emote_resized.width()) / 2;
+                let emote_y = height.saturating_sub(emote_resized.height()) / 2;
+                vec![(emote_resized, emote_x, emote_y)]
+            }
+            Placement::Anchor(anchor) => {
+                // Each terminal cell is 2 pixels high.
+                let width = u32::from(self.tty.size.0);
+                let height = u32::from(self.tty.size.1) * 2;
+                #[expect(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss,
+                    clippy::cast_precision_loss,
+                    reason = "Emotes are only ever a few cells big"
+                )]
+                let emote_resized = image.resize(
+                    (ANCHORED_EMOTE_SIZE.0 as f32 * scale).max(1.0) as u32,
+                    (ANCHORED_EMOTE_SIZE.1 as f32 * 2.0 * scale).max(1.0) as u32,
+                    image::imageops::FilterType::Lanczos3,
+                );
+                let (emote_x, emote_y) = match anchor {
+                    tattoy_twitch_tombh_plugin::Anchor::TopLeft => (0, 0),
+                    tattoy_twitch_tombh_plugin::Anchor::StatusBar => {
+                        (0, height.saturating_sub(emote_resized.height()))
+                    }
+                    tattoy_twitch_tombh_plugin::Anchor::Prompt => (
+                        width.saturating_sub(emote_resized.width()),
+                        (u32::from(self.tty.cursor_position.1) * 2)
+                            .saturating_sub(emote_resized.height() / 2),
+                    ),
+                    tattoy_twitch_tombh_plugin::Anchor::Cell { row, col } => {
+                        (u32::from(*col), u32::from(*row) * 2)
+                    }
+                };
+                vec![(emote_resized, emote_x, emote_y)]
+            }
         };
 
-        let emote_resized = emote.image.resize(
-            emote.regexish.len().try_into()?,
+        let opacity = emote.opacity();
+        for (emote_resized, emote_x, emote_y) in placed {
+            self.push_emote_pixels(&emote_resized, emote_x, emote_y, opacity);
+        }
+
+        Ok(())
+    }
+
+    /// An emote resized to cover some matched text, and where to put it.
+    fn behind_text(
+        &self,
+        image: &image::DynamicImage,
+        text_match: TextMatch,
+        scale: f32,
+    ) -> Result<(image::DynamicImage, u32, u32)> {
+        #[expect(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss,
+            reason = "Emotes are only ever a few hundred pixels wide"
+        )]
+        let width = (text_match.width as f32 * scale).max(1.0) as u32;
+        let emote_resized = image.resize(
+            width,
             self.tty.size.1.into(),
             image::imageops::FilterType::Lanczos3,
         );
 
         let half_the_emote_height = emote_resized.height() / 2;
-        let emote_x = u32::try_from(match_x)?;
-        let emote_y = (u32::try_from(match_y)? * 2) - half_the_emote_height;
+        let emote_x = u32::try_from(text_match.x)?;
+        let emote_y = (u32::try_from(text_match.y)? * 2).saturating_sub(half_the_emote_height);
+        Ok((emote_resized, emote_x, emote_y))
+    }
 
+    /// Add a resized emote's pixels to the frame, as opaque as `opacity`.
+    fn push_emote_pixels(
+        &mut self,
+        emote_resized: &image::DynamicImage,
+        emote_x: u32,
+        emote_y: u32,
+        opacity: f32,
+    ) {
         for pixel_y in 0..emote_resized.height() {
             for pixel_x in 0..emote_resized.width() {
                 let image_pixel_u8 = emote_resized.get_pixel(pixel_x, pixel_y).0;
@@ -262,7 +689,7 @@ impl Plugin {
                     f32::from(image_pixel_u8[0]) / 255.0,
                     f32::from(image_pixel_u8[1]) / 255.0,
                     f32::from(image_pixel_u8[2]) / 255.0,
-                    1.0,
+                    opacity,
                 );
                 let pixel = tattoy_protocol::Pixel::builder()
                     .coordinates((emote_x + pixel_x, emote_y + pixel_y))
@@ -271,11 +698,107 @@ impl Plugin {
                 self.output.push(pixel);
             }
         }
+    }
 
-        Ok(())
+    /// Render the pixels of the current achievement banner, sliding in from the left. Returns
+    /// the banner's text, and where to put it, once it has fully slid in.
+    fn render_banner(&mut self) -> Result<Option<(String, (u32, u32))>> {
+        let now = std::time::Instant::now();
+        let Some(banner) = self
+            .active_banners
+            .iter()
+            .find(|banner| banner.starts_at <= now && now < banner.starts_at + banner.duration)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+
+        let width = u32::from(self.tty.size.0);
+        let progress = (now - banner.starts_at).as_secs_f32() / BANNER_SLIDE_IN.as_secs_f32();
+        #[expect(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss,
+            reason = "Terminals are never anywhere near big enough to lose precision"
+        )]
+        let visible_width = (width as f32 * progress.min(1.0)) as u32;
+
+        // Each terminal cell is 2 pixels high.
+        for pixel_y in 0..BANNER_ROWS * 2 {
+            for pixel_x in 0..visible_width {
+                let pixel = tattoy_protocol::Pixel::builder()
+                    .coordinates((pixel_x, pixel_y))
+                    .color(BANNER_COLOUR)
+                    .build();
+                self.output.push(pixel);
+            }
+        }
+
+        if visible_width < width {
+            return Ok(None);
+        }
+        let text_width = u32::try_from(banner.text.chars().count())?;
+        let text_x = width.saturating_sub(text_width) / 2;
+        Ok(Some((banner.text, (text_x, BANNER_ROWS / 2))))
     }
 
-    fn find_text_coordinates(&self, regexish: String) -> Result<Option<(usize, usize)>> {
+    /// The lines of the todo list panel, and where to put them. The panel is in the top
+    /// right, below where achievement banners go.
+    fn todo_panel_lines(&self) -> Result<Vec<(String, (u32, u32))>> {
+        if self.todos.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut lines = vec!["TODO".to_owned()];
+        for item in &self.todos {
+            let mut line = format!("☐ {item}");
+            if line.chars().count() > MAX_TODO_WIDTH {
+                line = line.chars().take(MAX_TODO_WIDTH - 1).collect();
+                line.push('…');
+            }
+            lines.push(line);
+        }
+        let panel_width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or_default();
+        let x = u32::from(self.tty.size.0).saturating_sub(u32::try_from(panel_width)? + 1);
+
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, line)| {
+                // Pad every line to the same width so the panel's background is a rectangle.
+                let padded = format!(" {line:<panel_width$}");
+                Ok((padded, (x, BANNER_ROWS + u32::try_from(index)?)))
+            })
+            .collect()
+    }
+
+    /// A compiled pattern, from the cache if it's been used before. Patterns that aren't valid
+    /// regexes are matched literally.
+    fn pattern(&mut self, regexish: &str) -> Result<&regex::Regex> {
+        if !self.patterns.contains_key(regexish) {
+            let compile = |pattern: &str| {
+                regex::RegexBuilder::new(pattern)
+                    .size_limit(PATTERN_SIZE_LIMIT)
+                    .build()
+            };
+            let pattern = compile(regexish).or_else(|error| {
+                tracing::debug!("Matching '{regexish}' literally: {error}");
+                compile(&regex::escape(regexish))
+            })?;
+            self.patterns.insert(regexish.to_owned(), pattern);
+        }
+
+        self.patterns
+            .get(regexish)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Pattern wasn't cached: {regexish}"))
+    }
+
+    /// Everywhere on screen that matches the pattern, top to bottom and left to right.
+    pub(crate) fn find_text_coordinates(&mut self, regexish: &str) -> Result<Vec<TextMatch>> {
         let mut lines = Vec::<String>::new();
         for y in 0..self.tty.size.1 {
             let mut line = String::new();
@@ -293,39 +816,71 @@ impl Plugin {
             lines.push(line);
         }
 
-        let mut maybe_x: Option<usize> = None;
-        let mut y = 0;
-        for (line_number, line) in lines.iter().enumerate() {
-            y = line_number;
-            let maybe_byte_offset = line.find(&regexish);
-            if let Some(byte_offset) = maybe_byte_offset {
-                maybe_x = Some(line[..byte_offset].chars().count());
-                break;
+        let pattern = self.pattern(regexish)?;
+        let mut matches = Vec::new();
+        for (y, line) in lines.iter().enumerate() {
+            // Empty matches would put an emote on every cell.
+            for found in pattern.find_iter(line).filter(|found| !found.is_empty()) {
+                matches.push(TextMatch {
+                    x: line[..found.start()].chars().count(),
+                    y,
+                    width: found.as_str().chars().count(),
+                });
             }
         }
 
-        if let Some(x) = maybe_x {
-            tracing::debug!("regexish matched to coordinates: {x}x{y}");
-            return Ok(Some((x, y)));
-        };
-
-        Ok(None)
+        tracing::debug!("regexish '{regexish}' matched at: {matches:?}");
+        Ok(matches)
     }
 
     async fn cleanup(&mut self) -> Result<()> {
-        let duration = std::time::Duration::from_secs(10);
         let now = std::time::Instant::now();
         self.active_emotes
-            .retain(|emote| now - emote.timestamp < duration);
+            .retain(|emote| emote.is_pinned || now - emote.timestamp < emote.duration);
+        self.active_banners
+            .retain(|banner| now < banner.starts_at + banner.duration);
+        self.rains.retain(|rain| !rain.is_finished());
+        let active_emotes = &self.active_emotes;
+        self.patterns.retain(|pattern, _| {
+            active_emotes.iter().any(|emote| match &emote.placement {
+                Placement::Text(regexish) | Placement::EveryMatch { regexish, .. } => {
+                    regexish == pattern
+                }
+                Placement::Centre | Placement::Anchor(_) => false,
+            })
+        });
 
         Ok(())
     }
 
+    /// The current frame as the JSON message that's sent to Tattoy.
+    pub(crate) fn output_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(
+            &tattoy_protocol::PluginOutputMessages::OutputPixels(self.output.clone()),
+        )?)
+    }
+
     /// Send a frame to Tattoy.
     fn send_output(&self) -> Result<()> {
-        let json = serde_json::to_string(&tattoy_protocol::PluginOutputMessages::OutputPixels(
-            self.output.clone(),
-        ))?;
+        let json = self.output_json()?;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Send some text to Tattoy, drawn over the top of the terminal.
+    fn send_text(
+        &self,
+        (text, coordinates): (String, (u32, u32)),
+        bg: (f32, f32, f32, f32),
+        fg: (f32, f32, f32, f32),
+    ) -> Result<()> {
+        let json = serde_json::to_string(&tattoy_protocol::PluginOutputMessages::OutputText {
+            text,
+            coordinates,
+            bg: Some(bg),
+            fg: Some(fg),
+        })?;
         let mut stdout = std::io::stdout().lock();
         stdout.write_all(json.as_bytes())?;
         Ok(())
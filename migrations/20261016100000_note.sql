@@ -0,0 +1,11 @@
+-- Private notes that moderators keep about mates
+
+CREATE TABLE IF NOT EXISTS note (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		mate INTEGER NOT NULL,
+		author_id TEXT NOT NULL,
+		author_name TEXT NOT NULL,
+		text TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		FOREIGN KEY(mate) REFERENCES mate(id)
+);
@@ -0,0 +1,9 @@
+-- Cached moderator and VIP lists, synced from Helix
+
+CREATE TABLE IF NOT EXISTS role (
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		role TEXT NOT NULL,
+		synced DATETIME DEFAULT (datetime('now', 'utc')),
+		PRIMARY KEY(twitch_user_id, role)
+);
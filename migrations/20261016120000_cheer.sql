@@ -0,0 +1,9 @@
+-- Every cheer, so that we can keep per-user bits totals
+
+CREATE TABLE IF NOT EXISTS cheer (
+		twitch_user_id TEXT,
+		username TEXT,
+		bits INTEGER NOT NULL,
+		message TEXT,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
@@ -0,0 +1,20 @@
+-- Stream sessions, follows and emote usage, for end-of-stream summaries
+
+CREATE TABLE IF NOT EXISTS stream (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		started_at DATETIME NOT NULL,
+		ended_at DATETIME
+);
+
+CREATE TABLE IF NOT EXISTS follow (
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
+
+CREATE TABLE IF NOT EXISTS emote (
+		emote_id TEXT NOT NULL,
+		name TEXT NOT NULL,
+		twitch_user_id TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
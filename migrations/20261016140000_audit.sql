@@ -0,0 +1,9 @@
+-- A record of privileged actions taken through the bot
+
+CREATE TABLE IF NOT EXISTS audit (
+		actor TEXT NOT NULL,
+		action TEXT NOT NULL,
+		target TEXT,
+		details TEXT,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
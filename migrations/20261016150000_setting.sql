@@ -0,0 +1,6 @@
+-- Key-value settings that the bot needs to remember between runs
+
+CREATE TABLE IF NOT EXISTS setting (
+		key TEXT PRIMARY KEY,
+		value TEXT NOT NULL
+);
@@ -0,0 +1,10 @@
+-- The most used emote of each stream
+
+CREATE TABLE IF NOT EXISTS emote_of_the_stream (
+		stream INTEGER,
+		emote_id TEXT NOT NULL,
+		name TEXT NOT NULL,
+		uses INTEGER NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		FOREIGN KEY(stream) REFERENCES stream(id)
+);
@@ -0,0 +1,12 @@
+-- The order mates arrive in each stream, for the earliest bird leaderboard
+
+CREATE TABLE IF NOT EXISTS arrival (
+		stream INTEGER NOT NULL,
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		position INTEGER NOT NULL,
+		points INTEGER NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		UNIQUE(stream, twitch_user_id),
+		FOREIGN KEY(stream) REFERENCES stream(id)
+);
@@ -0,0 +1,12 @@
+-- Rubber duck debugging questions from chat
+
+CREATE TABLE IF NOT EXISTS duck (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		stream INTEGER,
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		question TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		answered_at DATETIME,
+		FOREIGN KEY(stream) REFERENCES stream(id)
+);
@@ -0,0 +1,7 @@
+-- Keep track of messages that were deleted by moderators
+
+ALTER TABLE message ADD COLUMN message_id TEXT;
+ALTER TABLE message ADD COLUMN deleted_at DATETIME;
+ALTER TABLE message ADD COLUMN deleted_by TEXT;
+
+CREATE INDEX IF NOT EXISTS message_message_id ON message(message_id);
@@ -0,0 +1,4 @@
+-- Each mate's default emote for !tty
+
+ALTER TABLE mate ADD COLUMN tty_emote TEXT;
+ALTER TABLE mate ADD COLUMN tty_premium BOOLEAN NOT NULL DEFAULT FALSE;
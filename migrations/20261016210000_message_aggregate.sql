@@ -0,0 +1,11 @@
+-- Daily message counts per chatter, kept after the messages themselves are archived
+
+CREATE TABLE IF NOT EXISTS message_aggregate (
+		day DATE NOT NULL,
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		messages INTEGER NOT NULL,
+		chirps INTEGER NOT NULL,
+		first_seen DATETIME NOT NULL,
+		PRIMARY KEY(day, twitch_user_id)
+);
@@ -0,0 +1,4 @@
+-- Profile fields that mates set about themselves
+
+ALTER TABLE mate ADD COLUMN pronouns TEXT;
+ALTER TABLE mate ADD COLUMN intro TEXT;
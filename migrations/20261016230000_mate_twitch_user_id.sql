@@ -0,0 +1,77 @@
+-- Mates are identified by their Twitch user ID, so that they keep everything when they rename
+
+ALTER TABLE mate ADD COLUMN twitch_user_id TEXT;
+
+-- Existing mates get the ID that most recently chatted with their name.
+UPDATE mate SET twitch_user_id = (
+		SELECT CAST(message.twitch_user_id AS TEXT) FROM message
+		WHERE message.username = mate.name
+		ORDER BY message.timestamp DESC
+		LIMIT 1
+);
+
+-- If an ID was found for more than one name then they're the same mate, who renamed. They're
+-- merged into the newest mate, so that nothing they had is lost.
+CREATE TEMPORARY TABLE mate_merge AS
+SELECT mate.id AS old, newest.id AS new FROM mate
+JOIN (
+		SELECT MAX(id) AS id, twitch_user_id FROM mate
+		WHERE twitch_user_id IS NOT NULL
+		GROUP BY twitch_user_id
+) AS newest ON newest.twitch_user_id = mate.twitch_user_id
+WHERE mate.id != newest.id;
+
+UPDATE achievement SET achiever = (SELECT new FROM mate_merge WHERE old = achiever)
+WHERE achiever IN (SELECT old FROM mate_merge);
+
+UPDATE note SET mate = (SELECT new FROM mate_merge WHERE old = note.mate)
+WHERE mate IN (SELECT old FROM mate_merge);
+
+-- Anything the newest mate hasn't set is taken from the most recent of the old ones that has.
+UPDATE mate SET
+		last_played = (
+				SELECT MAX(merged.last_played) FROM mate AS merged
+				WHERE merged.twitch_user_id = mate.twitch_user_id
+		),
+		tty_premium = (
+				SELECT MAX(merged.tty_premium) FROM mate AS merged
+				WHERE merged.twitch_user_id = mate.twitch_user_id
+		),
+		tty_emote = COALESCE(tty_emote, (
+				SELECT merged.tty_emote FROM mate AS merged
+				WHERE merged.twitch_user_id = mate.twitch_user_id AND merged.tty_emote IS NOT NULL
+				ORDER BY merged.id DESC
+				LIMIT 1
+		)),
+		pronouns = COALESCE(pronouns, (
+				SELECT merged.pronouns FROM mate AS merged
+				WHERE merged.twitch_user_id = mate.twitch_user_id AND merged.pronouns IS NOT NULL
+				ORDER BY merged.id DESC
+				LIMIT 1
+		)),
+		intro = COALESCE(intro, (
+				SELECT merged.intro FROM mate AS merged
+				WHERE merged.twitch_user_id = mate.twitch_user_id AND merged.intro IS NOT NULL
+				ORDER BY merged.id DESC
+				LIMIT 1
+		))
+WHERE id IN (SELECT new FROM mate_merge);
+
+-- Every name that each mate has been seen with
+CREATE TABLE IF NOT EXISTS mate_name (
+		mate INTEGER NOT NULL,
+		name TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		FOREIGN KEY(mate) REFERENCES mate(id)
+);
+
+-- The old names of merged mates are kept as names of the mate they were merged into.
+INSERT INTO mate_name(mate, name)
+SELECT COALESCE(mate_merge.new, mate.id), mate.name FROM mate
+LEFT JOIN mate_merge ON mate_merge.old = mate.id
+ORDER BY mate.id;
+
+DELETE FROM mate WHERE id IN (SELECT old FROM mate_merge);
+DROP TABLE mate_merge;
+
+CREATE UNIQUE INDEX IF NOT EXISTS mate_twitch_user_id ON mate(twitch_user_id);
@@ -0,0 +1,5 @@
+-- How many streams in a row each mate has arrived for
+
+ALTER TABLE mate ADD COLUMN arrival_streak INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE mate ADD COLUMN best_arrival_streak INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE mate ADD COLUMN last_arrival_stream INTEGER REFERENCES stream(id);
@@ -0,0 +1,12 @@
+-- Tips from outside of Twitch, eg Ko-fi
+
+CREATE TABLE IF NOT EXISTS tip (
+		source TEXT NOT NULL,
+		external_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		amount REAL NOT NULL,
+		currency TEXT NOT NULL,
+		message TEXT,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		UNIQUE(source, external_id)
+);
@@ -0,0 +1,13 @@
+-- Reminders set with !remind, kept in the database so that they survive restarts
+
+CREATE TABLE IF NOT EXISTS reminder (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		author TEXT NOT NULL,
+		target TEXT NOT NULL,
+		text TEXT NOT NULL,
+		message_id TEXT,
+		is_onscreen BOOLEAN NOT NULL DEFAULT FALSE,
+		due_at DATETIME NOT NULL,
+		delivered_at DATETIME,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
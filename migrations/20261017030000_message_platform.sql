@@ -0,0 +1,3 @@
+-- Messages can be mirrored from other platforms' chats, eg YouTube
+
+ALTER TABLE message ADD COLUMN platform TEXT NOT NULL DEFAULT 'twitch';
@@ -0,0 +1,6 @@
+-- Which message each reply was replying to, and the thread it's in
+
+ALTER TABLE message ADD COLUMN parent_message_id TEXT;
+ALTER TABLE message ADD COLUMN thread_message_id TEXT;
+
+CREATE INDEX IF NOT EXISTS message_message_id ON message(message_id);
@@ -0,0 +1,8 @@
+-- The streamer's todo list, shown on stream
+
+CREATE TABLE IF NOT EXISTS todo (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		text TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		done_at DATETIME
+);
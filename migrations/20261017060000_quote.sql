@@ -0,0 +1,11 @@
+-- Memorable things said in chat, quoted with !quote this
+
+CREATE TABLE IF NOT EXISTS quote (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		message_id TEXT UNIQUE,
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		text TEXT NOT NULL,
+		added_by TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
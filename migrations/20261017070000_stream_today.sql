@@ -0,0 +1,3 @@
+-- What each stream is about, set with !today
+
+ALTER TABLE stream ADD COLUMN today TEXT;
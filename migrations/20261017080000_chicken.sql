@@ -0,0 +1,10 @@
+-- Every run of rubber chickens, so that each stream's top chicken can be found
+
+CREATE TABLE IF NOT EXISTS chicken (
+		stream INTEGER,
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		chickens INTEGER NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc')),
+		FOREIGN KEY(stream) REFERENCES stream(id)
+);
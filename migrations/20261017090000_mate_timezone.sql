@@ -0,0 +1,3 @@
+-- The timezone that a mate lives in, eg: "Europe/Warsaw"
+
+ALTER TABLE mate ADD COLUMN timezone TEXT;
@@ -0,0 +1,10 @@
+-- Timeouts given by moderators, so that raffles can exclude recently timed out mates
+
+CREATE TABLE IF NOT EXISTS timeout (
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		moderator TEXT NOT NULL,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
+
+CREATE INDEX IF NOT EXISTS timeout_twitch_user_id ON timeout(twitch_user_id);
@@ -0,0 +1,11 @@
+-- Reports from chat about the bot misbehaving, with the bot's logs from the time
+
+CREATE TABLE IF NOT EXISTS bug_report (
+		id INTEGER PRIMARY KEY AUTOINCREMENT,
+		twitch_user_id TEXT NOT NULL,
+		username TEXT NOT NULL,
+		description TEXT NOT NULL,
+		logs TEXT NOT NULL,
+		issue_url TEXT,
+		timestamp DATETIME DEFAULT (datetime('now', 'utc'))
+);
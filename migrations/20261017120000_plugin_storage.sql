@@ -0,0 +1,8 @@
+-- Values that command plugins store between runs, each plugin has its own keys
+
+CREATE TABLE IF NOT EXISTS plugin_storage (
+		plugin TEXT NOT NULL,
+		key TEXT NOT NULL,
+		value TEXT NOT NULL,
+		PRIMARY KEY (plugin, key)
+);
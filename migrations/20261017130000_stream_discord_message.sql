@@ -0,0 +1,3 @@
+-- The go-live message posted to Discord for each stream, so it can be edited when it ends
+
+ALTER TABLE stream ADD COLUMN discord_message_id TEXT;
@@ -0,0 +1,8 @@
+-- Daily counts of each `!command`, kept after the messages themselves are archived
+
+CREATE TABLE IF NOT EXISTS command_aggregate (
+		day DATE NOT NULL,
+		command TEXT NOT NULL,
+		uses INTEGER NOT NULL,
+		PRIMARY KEY(day, command)
+);
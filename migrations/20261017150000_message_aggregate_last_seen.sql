@@ -0,0 +1,4 @@
+-- Each chatter's last message of the day, so that how long they watched can still be estimated
+-- after the messages themselves are archived
+
+ALTER TABLE message_aggregate ADD COLUMN last_seen DATETIME;